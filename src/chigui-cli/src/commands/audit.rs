@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat};
+use clap::{Args, Subcommand};
+
+use chigui_core::audit::{AuditLog, Source};
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommand {
+    /// List rejected submissions, oldest first.
+    List(ListArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// Only submissions from this source: cli, rpc or p2p.
+    #[arg(long)]
+    source: Option<Source>,
+    /// Only the most recent entries, at most this many.
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+pub fn run(db_dir: &Path, command: AuditCommand) -> Result<()> {
+    match command {
+        AuditCommand::List(args) => list(db_dir, args),
+    }
+}
+
+fn list(db_dir: &Path, args: ListArgs) -> Result<()> {
+    let entries = AuditLog::open(db_dir)
+        .entries()?
+        .into_iter()
+        .filter(|entry| args.source.is_none_or(|source| entry.source == source))
+        .collect::<Vec<_>>();
+    let skip = entries
+        .len()
+        .saturating_sub(args.limit.unwrap_or(usize::MAX));
+
+    for entry in entries.into_iter().skip(skip) {
+        let time = DateTime::from_timestamp(entry.time as i64, 0)
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .unwrap_or_else(|| entry.time.to_string());
+
+        println!(
+            "{} [{}] {} {}",
+            time, entry.source, entry.hash, entry.reason
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+
+use chigui_core::Account;
+use chigui_core::state::State;
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum Format {
+    /// Aligned columns sorted by account, for humans.
+    #[default]
+    Table,
+    /// A JSON object mapping accounts to balances, for scripts.
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct BalancesArgs {
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+    /// Only show the balance of this account.
+    #[arg(long)]
+    account: Option<String>,
+    /// Show the balance right after the block at this height, `0` being the genesis.
+    #[arg(long, requires = "account", conflicts_with = "denom")]
+    at: Option<u64>,
+    /// Show the balances of this asset, declared in the genesis, instead of the native coin.
+    #[arg(long)]
+    denom: Option<String>,
+}
+
+pub fn run(db_dir: &Path, args: BalancesArgs) -> Result<()> {
+    let state = State::open(db_dir)?;
+    let balances = match args.account {
+        Some(account) => {
+            let account = Account::new(account)?;
+            let balance = match args.at {
+                Some(height) => state.balance_at(&account, height)?,
+                None => state.get_asset_balance(&account, args.denom.as_deref()),
+            }
+            .with_context(|| format!("Account \"{}\" not found.", account))?;
+
+            BTreeMap::from([(account.to_string(), balance)])
+        }
+        None => {
+            let balances = match &args.denom {
+                Some(denom) => state
+                    .asset_balances(denom)
+                    .with_context(|| format!("Unknown asset \"{}\".", denom))?,
+                None => state.balances(),
+            };
+
+            balances
+                .iter()
+                .map(|(account, balance)| (account.to_string(), *balance))
+                .collect()
+        }
+    };
+
+    match args.format {
+        Format::Table => print_table(&balances),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&balances)?),
+    }
+
+    Ok(())
+}
+
+fn print_table(balances: &BTreeMap<String, u64>) {
+    let width = balances
+        .keys()
+        .map(String::len)
+        .max()
+        .unwrap_or_default()
+        .max("ACCOUNT".len());
+
+    println!("{:<width$}  {:>20}", "ACCOUNT", "BALANCE");
+
+    for (account, balance) in balances.iter() {
+        println!("{:<width$}  {:>20}", account, balance);
+    }
+}
@@ -0,0 +1,45 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+
+use crate::Cli;
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// Shell to complete `chigui` commands in.
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+pub fn run(args: CompletionsArgs) -> Result<()> {
+    generate(args.shell, &mut io::stdout())
+}
+
+fn generate(shell: Shell, out: &mut dyn Write) -> Result<()> {
+    clap_complete::generate(shell, &mut Cli::command(), "chigui", out);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_subcommands() -> Result<()> {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let mut script = Vec::new();
+
+            generate(shell, &mut script)?;
+
+            let script = String::from_utf8(script)?;
+
+            assert!(script.contains("chigui"), "{}", shell);
+            assert!(script.contains("transfer-multi"), "{}", shell);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use anyhow::Result;
+use clap::Subcommand;
+
+use crate::config::Config;
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration: the configuration file, overridden by the `CHIGUI_*`
+    /// environment variables and then the global flags.
+    Show,
+}
+
+pub fn run(config: &Config, command: ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Show => show(config),
+    }
+}
+
+fn show(config: &Config) -> Result<()> {
+    match &config.source {
+        Some(path) => println!("# Loaded from {}", path.display()),
+        None => println!("# No configuration file, defaults"),
+    }
+
+    print!("{}", config.to_toml()?);
+
+    Ok(())
+}
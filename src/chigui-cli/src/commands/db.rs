@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use clap::Subcommand;
+
+use chigui_core::migrate::{SCHEMA_VERSION, schema_version};
+use chigui_core::state::State;
+use chigui_core::storage::binary::BinaryStorage;
+use chigui_core::storage::{FileStorage, copy};
+
+use super::node::Format;
+
+#[derive(Debug, Subcommand)]
+pub enum DbCommand {
+    /// Fold the block history into a snapshot and empty `block.db`, reclaiming disk space.
+    Compact {
+        /// Number of recent blocks to keep in `block.db`.
+        #[arg(long, default_value_t = 0)]
+        keep: u64,
+    },
+    /// Convert the blocks and latest snapshot between the JSONL and binary formats, keeping the
+    /// original files.
+    Convert {
+        /// Format to convert to.
+        #[arg(long, value_enum)]
+        to: Format,
+    },
+    /// Upgrade the database directory written by an older release to the current layout.
+    Migrate,
+    /// Rewrite `block.db` with its valid records, backing up the damaged file first.
+    Repair {
+        /// Skip invalid records instead of dropping everything past the first one.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Undo the last transactions along with their blocks, on dev chains.
+    Rollback {
+        /// Number of transactions to undo, covering whole blocks.
+        n: usize,
+    },
+}
+
+pub fn run(db_dir: &Path, command: DbCommand) -> Result<()> {
+    match command {
+        DbCommand::Compact { keep } => compact(db_dir, keep),
+        DbCommand::Convert { to } => convert(db_dir, to),
+        DbCommand::Migrate => migrate(db_dir),
+        DbCommand::Repair { force } => repair(db_dir, force),
+        DbCommand::Rollback { n } => rollback(db_dir, n),
+    }
+}
+
+fn compact(db_dir: &Path, keep: u64) -> Result<()> {
+    let block_db = db_dir.join("block.db");
+    let size_before = std::fs::metadata(&block_db)?.len();
+    let mut state = State::open(db_dir)?;
+    let blocks_before = state.blocks().len();
+
+    state.prune(keep)?;
+
+    let size_after = std::fs::metadata(&block_db)?.len();
+
+    println!(
+        "Folded {} blocks into a snapshot at height {}",
+        blocks_before - state.blocks().len(),
+        state.height()
+    );
+    println!("block.db: {} -> {} bytes", size_before, size_after);
+
+    Ok(())
+}
+
+fn rollback(db_dir: &Path, n: usize) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+
+    for tx in state.rollback(n)? {
+        println!("Undone {} {}", tx.hash(), tx);
+    }
+
+    println!("Height: {}", state.height());
+    println!("Total supply: {}", state.total_supply());
+
+    Ok(())
+}
+
+fn convert(db_dir: &Path, to: Format) -> Result<()> {
+    let (from_path, to_path) = match to {
+        Format::Binary => ("block.db", "block.bin"),
+        Format::Jsonl => ("block.bin", "block.db"),
+    };
+
+    if !db_dir.join(from_path).exists() {
+        bail!("No {} to convert in {}", from_path, db_dir.display());
+    }
+
+    let mut jsonl = FileStorage::new(db_dir);
+    let mut binary = BinaryStorage::open(db_dir)?;
+    let blocks = match to {
+        Format::Binary => copy(&jsonl, &mut binary)?,
+        Format::Jsonl => copy(&binary, &mut jsonl)?,
+    };
+    let size_before = std::fs::metadata(db_dir.join(from_path))?.len();
+    let size_after = std::fs::metadata(db_dir.join(to_path))?.len();
+
+    println!("Converted {} blocks", blocks);
+    println!(
+        "{}: {} bytes -> {}: {} bytes",
+        from_path, size_before, to_path, size_after
+    );
+
+    Ok(())
+}
+
+fn migrate(db_dir: &Path) -> Result<()> {
+    let version = schema_version(db_dir)?;
+    let applied = chigui_core::migrate::migrate(db_dir)?;
+
+    if applied.is_empty() {
+        println!("Schema version {} is up to date", version);
+
+        return Ok(());
+    }
+
+    for migration in applied {
+        println!("Applied migration: {}", migration);
+    }
+
+    println!("Upgraded schema version {} to {}", version, SCHEMA_VERSION);
+
+    Ok(())
+}
+
+fn repair(db_dir: &Path, force: bool) -> Result<()> {
+    let report = chigui_core::verify::repair(db_dir, force)?;
+
+    let Some(backup) = report.backup else {
+        println!("block.db is valid, nothing to repair");
+
+        return Ok(());
+    };
+
+    for offense in report.offenses.iter() {
+        println!("Line {}: {}", offense.line, offense.error);
+    }
+
+    println!(
+        "Kept {} records up to height {}, dropped {}",
+        report.kept, report.height, report.dropped
+    );
+    println!("Damaged block.db backed up to {}", backup.display());
+
+    Ok(())
+}
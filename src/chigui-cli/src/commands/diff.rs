@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use anyhow::Result;
+use clap::Args;
+
+use chigui_core::diff::StateDiff;
+use chigui_core::state::State;
+
+use super::balances::Format;
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Height to diff from, `0` being the genesis.
+    from: u64,
+    /// Height to diff to, the latest block when omitted.
+    to: Option<u64>,
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+pub fn run(db_dir: &Path, args: DiffArgs) -> Result<()> {
+    let state = State::open(db_dir)?;
+    let diff = state.diff(args.from, args.to.unwrap_or(state.height()))?;
+
+    match args.format {
+        Format::Table => print_table(&diff),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+    }
+
+    Ok(())
+}
+
+fn print_table(diff: &StateDiff) {
+    let width = diff
+        .accounts
+        .keys()
+        .map(|account| account.to_string().len())
+        .max()
+        .unwrap_or_default()
+        .max("ACCOUNT".len());
+
+    println!(
+        "{:<width$}  {:>20}  {:>20}  {:>21}",
+        "ACCOUNT", "BEFORE", "AFTER", "DELTA"
+    );
+
+    for (account, change) in diff.accounts.iter() {
+        println!(
+            "{:<width$}  {:>20}  {:>20}  {:>+21}",
+            account.to_string(),
+            change.before,
+            change.after,
+            change.delta()
+        );
+
+        for hash in change.txs.iter() {
+            println!("  {}", hash);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+
+use chigui_core::state::State;
+use chigui_core::{Account, Hash, Tx};
+
+use super::tx::{append, sign};
+use crate::config::FeePolicy;
+
+#[derive(Debug, Subcommand)]
+pub enum EscrowCommand {
+    /// List the escrows not released or refunded yet.
+    List,
+    /// Hold coins until they're released to the recipient or refunded.
+    Create(CreateArgs),
+    /// Pay an escrow to its recipient, as its sender or arbiter.
+    Release(CloseArgs),
+    /// Return an escrow to its sender, as its recipient or arbiter.
+    Refund(CloseArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CreateArgs {
+    #[arg(long)]
+    from: String,
+    #[arg(long)]
+    to: String,
+    #[arg(long)]
+    value: u64,
+    /// Account allowed to both release and refund the escrow.
+    #[arg(long)]
+    arbiter: Option<String>,
+    /// Fee paid by the sender, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct CloseArgs {
+    #[arg(long)]
+    account: String,
+    /// Hash of the transaction creating the escrow.
+    #[arg(long)]
+    escrow: Hash,
+    /// Fee paid by the account, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+pub fn run(db_dir: &Path, fees: &FeePolicy, command: EscrowCommand) -> Result<()> {
+    match command {
+        EscrowCommand::List => list(db_dir),
+        EscrowCommand::Create(args) => create(db_dir, fees, args),
+        EscrowCommand::Release(args) => close(db_dir, fees, args, true),
+        EscrowCommand::Refund(args) => close(db_dir, fees, args, false),
+    }
+}
+
+fn list(db_dir: &Path) -> Result<()> {
+    let state = State::open(db_dir)?;
+
+    for (id, escrow) in state.escrows().iter() {
+        print!(
+            "{} \"{}\" -> \"{}\" {} coins",
+            id, escrow.from, escrow.to, escrow.value
+        );
+
+        match &escrow.arbiter {
+            Some(arbiter) => println!(", arbitrated by \"{}\"", arbiter),
+            None => println!(),
+        }
+    }
+
+    Ok(())
+}
+
+fn create(db_dir: &Path, fees: &FeePolicy, args: CreateArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let from = Account::new(args.from)?;
+    let tx = Tx::EscrowCreate {
+        from: from.clone(),
+        to: Account::new(args.to)?,
+        value: args.value,
+        arbiter: args.arbiter.map(Account::new).transpose()?,
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&from),
+    };
+    let signed = sign(db_dir, state.chain_id(), &from, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Escrow {} created", hash);
+
+    Ok(())
+}
+
+fn close(db_dir: &Path, fees: &FeePolicy, args: CloseArgs, release: bool) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let account = Account::new(args.account)?;
+    let fee = fees.fee(args.fee, state.fee_schedule().min_fee)?;
+    let nonce = state.next_nonce(&account);
+    let tx = if release {
+        Tx::EscrowRelease {
+            account: account.clone(),
+            escrow: args.escrow,
+            fee,
+            nonce,
+        }
+    } else {
+        Tx::EscrowRefund {
+            account: account.clone(),
+            escrow: args.escrow,
+            fee,
+            nonce,
+        }
+    };
+    let signed = sign(db_dir, state.chain_id(), &account, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+
+    Ok(())
+}
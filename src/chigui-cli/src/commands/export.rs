@@ -0,0 +1,528 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat};
+use clap::{Args, Subcommand, ValueEnum};
+
+use chigui_core::Account;
+use chigui_core::signed::SignedTx;
+use chigui_core::state::State;
+
+#[derive(Debug, Subcommand)]
+pub enum ExportCommand {
+    /// Write balances, transactions or an account statement as CSV, with a header row.
+    Csv(CsvArgs),
+    /// Write the transaction history to an Apache Parquet file, for analytics tools.
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetArgs),
+    /// Write the genesis and every block to a single JSON file, restored by `chigui import state`.
+    State(StateArgs),
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CsvData {
+    /// Native balance of every account, by account.
+    Balances,
+    /// Every transaction, oldest first.
+    Txs,
+    /// Transactions of an account with the balance each left it with.
+    Statement,
+}
+
+#[derive(Debug, Args)]
+pub struct CsvArgs {
+    #[arg(long, value_enum)]
+    what: CsvData,
+    /// Only this account: its balance, the transactions naming it or its statement.
+    #[arg(long, required_if_eq("what", "statement"))]
+    account: Option<String>,
+    /// File to write, stdout when omitted.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+#[cfg(feature = "parquet")]
+#[derive(Debug, Args)]
+pub struct ParquetArgs {
+    /// Only the transactions naming this account.
+    #[arg(long)]
+    account: Option<String>,
+    /// File to write.
+    #[arg(long, short)]
+    output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct StateArgs {
+    /// File to write, stdout when omitted.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// A transaction flattened into the columns of the exported files.
+struct TxRow {
+    block: u64,
+    /// Unix timestamp, in seconds, of the block.
+    time: u64,
+    hash: String,
+    kind: String,
+    from: Option<String>,
+    /// Every other account the transaction touches, separated by `;`.
+    to: String,
+    value: u64,
+    fee: u64,
+    nonce: Option<u64>,
+    memo: Option<String>,
+}
+
+impl TxRow {
+    fn new(state: &State, number: u64, tx: &SignedTx) -> Self {
+        let sender = tx.tx.sender();
+        // The sender, if any, comes first among the accounts a transaction touches.
+        let recipients = tx
+            .tx
+            .accounts()
+            .into_iter()
+            .skip(usize::from(sender.is_some()))
+            .map(Account::to_string)
+            .collect::<Vec<String>>();
+
+        Self {
+            block: number,
+            time: state
+                .block_by_number(number)
+                .map_or(0, |block| block.header.time),
+            hash: tx.hash().to_string(),
+            kind: tx.tx.kind().to_string(),
+            from: sender.map(Account::to_string),
+            to: recipients.join(";"),
+            value: tx.tx.value(),
+            fee: tx.tx.fee(),
+            nonce: tx.tx.nonce(),
+            memo: tx.tx.memo().map(str::to_string),
+        }
+    }
+}
+
+pub fn run(db_dir: &Path, command: ExportCommand) -> Result<()> {
+    match command {
+        ExportCommand::Csv(args) => csv(db_dir, args),
+        #[cfg(feature = "parquet")]
+        ExportCommand::Parquet(args) => parquet(db_dir, args),
+        ExportCommand::State(args) => state(db_dir, args),
+    }
+}
+
+/// Open the file to write, or stdout.
+fn output(path: Option<&Path>) -> Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) => {
+            Box::new(BufWriter::new(File::create(path).with_context(|| {
+                format!("Failed to create {}.", path.display())
+            })?))
+        }
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+fn csv(db_dir: &Path, args: CsvArgs) -> Result<()> {
+    let state = State::open(db_dir)?;
+    let account = args.account.map(Account::new).transpose()?;
+    let mut writer = csv::Writer::from_writer(output(args.output.as_deref())?);
+
+    match args.what {
+        CsvData::Balances => write_balances(&mut writer, &state, account.as_ref())?,
+        CsvData::Txs => write_txs(&mut writer, &state, account.as_ref())?,
+        CsvData::Statement => {
+            let account = account.context("--account is required for statements.")?;
+
+            write_statement(&mut writer, &state, &account)?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_balances<W: Write>(
+    writer: &mut csv::Writer<W>,
+    state: &State,
+    account: Option<&Account>,
+) -> Result<()> {
+    let mut balances = state
+        .balances()
+        .iter()
+        .filter(|(acct, _)| account.is_none_or(|account| account == *acct))
+        .collect::<Vec<_>>();
+
+    balances.sort();
+    writer.write_record(["account", "balance"])?;
+
+    for (account, balance) in balances {
+        writer.write_record([account.to_string(), balance.to_string()])?;
+    }
+
+    Ok(())
+}
+
+fn write_txs<W: Write>(
+    writer: &mut csv::Writer<W>,
+    state: &State,
+    account: Option<&Account>,
+) -> Result<()> {
+    writer.write_record([
+        "block", "time", "hash", "type", "from", "to", "value", "fee", "nonce", "memo",
+    ])?;
+
+    for (number, tx) in history(state, account) {
+        let row = TxRow::new(state, number, tx);
+
+        writer.write_record([
+            row.block.to_string(),
+            format_time(row.time),
+            row.hash,
+            row.kind,
+            row.from.unwrap_or_default(),
+            row.to,
+            row.value.to_string(),
+            row.fee.to_string(),
+            row.nonce.map(|nonce| nonce.to_string()).unwrap_or_default(),
+            row.memo.unwrap_or_default(),
+        ])?;
+    }
+
+    Ok(())
+}
+
+fn write_statement<W: Write>(
+    writer: &mut csv::Writer<W>,
+    state: &State,
+    account: &Account,
+) -> Result<()> {
+    writer.write_record([
+        "block",
+        "time",
+        "hash",
+        "type",
+        "direction",
+        "amount",
+        "balance",
+    ])?;
+
+    for entry in state.statement(account)? {
+        writer.write_record([
+            entry.block.to_string(),
+            format_time(entry.time),
+            entry.hash.to_string(),
+            entry.tx.tx.kind().to_string(),
+            entry.direction.to_string(),
+            entry.amount.to_string(),
+            entry.balance.to_string(),
+        ])?;
+    }
+
+    Ok(())
+}
+
+fn state(db_dir: &Path, args: StateArgs) -> Result<()> {
+    let backup = State::open(db_dir)?.backup()?;
+    let mut output = output(args.output.as_deref())?;
+
+    serde_json::to_writer(&mut output, &backup)?;
+    writeln!(output)?;
+    output.flush()?;
+
+    if let Some(path) = args.output {
+        println!(
+            "Exported chain \"{}\" up to block #{} to {}",
+            backup.chain_id,
+            backup.height,
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Transactions naming the account if any, every transaction otherwise, oldest first.
+fn history<'a>(
+    state: &'a State,
+    account: Option<&'a Account>,
+) -> Box<dyn Iterator<Item = (u64, &'a SignedTx)> + 'a> {
+    match account {
+        Some(account) => Box::new(state.txs_for(account)),
+        None => Box::new(state.txs_range(0, usize::MAX)),
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn parquet(db_dir: &Path, args: ParquetArgs) -> Result<()> {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, RecordBatch, StringArray, TimestampSecondArray, UInt64Array};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+
+    /// Rows buffered before being written out as a row group.
+    const BATCH_ROWS: usize = 8192;
+
+    let state = State::open(db_dir)?;
+    let account = args.account.map(Account::new).transpose()?;
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("block", DataType::UInt64, false),
+        Field::new(
+            "time",
+            DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            false,
+        ),
+        Field::new("hash", DataType::Utf8, false),
+        Field::new("type", DataType::Utf8, false),
+        Field::new("from", DataType::Utf8, true),
+        Field::new("to", DataType::Utf8, false),
+        Field::new("value", DataType::UInt64, false),
+        Field::new("fee", DataType::UInt64, false),
+        Field::new("nonce", DataType::UInt64, true),
+        Field::new("memo", DataType::Utf8, true),
+    ]));
+    let file = File::create(&args.output)
+        .with_context(|| format!("Failed to create {}.", args.output.display()))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+    let mut rows = history(&state, account.as_ref())
+        .map(|(number, tx)| TxRow::new(&state, number, tx))
+        .peekable();
+
+    while rows.peek().is_some() {
+        let batch = rows.by_ref().take(BATCH_ROWS).collect::<Vec<TxRow>>();
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from_iter_values(
+                batch.iter().map(|row| row.block),
+            )),
+            Arc::new(
+                TimestampSecondArray::from_iter_values(batch.iter().map(|row| row.time as i64))
+                    .with_timezone("UTC"),
+            ),
+            Arc::new(StringArray::from_iter_values(
+                batch.iter().map(|row| &row.hash),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                batch.iter().map(|row| &row.kind),
+            )),
+            Arc::new(StringArray::from_iter(
+                batch.iter().map(|row| row.from.as_ref()),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                batch.iter().map(|row| &row.to),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                batch.iter().map(|row| row.value),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                batch.iter().map(|row| row.fee),
+            )),
+            Arc::new(UInt64Array::from_iter(batch.iter().map(|row| row.nonce))),
+            Arc::new(StringArray::from_iter(
+                batch.iter().map(|row| row.memo.as_ref()),
+            )),
+        ];
+
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+
+    let metadata = writer.close()?;
+
+    println!(
+        "Wrote {} transactions to {}.",
+        metadata.file_metadata().num_rows(),
+        args.output.display()
+    );
+
+    Ok(())
+}
+
+/// Format a Unix timestamp as RFC 3339, which spreadsheets parse as a date.
+fn format_time(time: u64) -> String {
+    i64::try_from(time)
+        .ok()
+        .and_then(|time| DateTime::from_timestamp(time, 0))
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_else(|| time.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chigui_core::Tx;
+    use chigui_core::state::{Genesis, GenesisBuilder};
+
+    use super::*;
+
+    fn genesis() -> Result<Genesis> {
+        Ok(GenesisBuilder::new("testnet")
+            .balance("alice", 100)
+            .balance("bob", 0)
+            .permissive(true)
+            .build()?)
+    }
+
+    /// Send bob 30 coins with a memo, then generate 5 coins for alice.
+    fn add_txs(state: &mut State) -> Result<()> {
+        state.add_tx(Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 30,
+            fee: 0,
+            nonce: 0,
+            memo: Some("rent, march".into()),
+            denom: None,
+        })?;
+        state.add_tx(Tx::Generate {
+            to: Account::new("alice")?,
+            value: 5,
+            denom: None,
+        })?;
+
+        Ok(())
+    }
+
+    fn csv_of(
+        write: impl FnOnce(&mut csv::Writer<Vec<u8>>, &State) -> Result<()>,
+    ) -> Result<Vec<String>> {
+        let mut state = State::in_memory(genesis()?)?;
+
+        add_txs(&mut state)?;
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+
+        write(&mut writer, &state)?;
+
+        Ok(String::from_utf8(writer.into_inner()?)?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    #[test]
+    fn exports_csv() -> Result<()> {
+        let bob = Account::new("bob")?;
+
+        assert_eq!(
+            csv_of(|writer, state| write_balances(writer, state, None))?,
+            ["account,balance", "alice,75", "bob,30"]
+        );
+        assert_eq!(
+            csv_of(|writer, state| write_balances(writer, state, Some(&bob)))?,
+            ["account,balance", "bob,30"]
+        );
+
+        let txs = csv_of(|writer, state| write_txs(writer, state, None))?;
+
+        assert_eq!(txs[0], "block,time,hash,type,from,to,value,fee,nonce,memo");
+        assert_eq!(txs.len(), 3);
+        assert!(txs[1].starts_with("1,"));
+        assert!(txs[1].ends_with(",transfer,alice,bob,30,0,0,\"rent, march\""));
+        assert!(txs[2].ends_with(",generate,,alice,5,0,,"));
+        assert_eq!(
+            csv_of(|writer, state| write_txs(writer, state, Some(&bob)))?.len(),
+            2
+        );
+
+        let statement = csv_of(|writer, state| write_statement(writer, state, &bob))?;
+
+        assert_eq!(
+            statement[0],
+            "block,time,hash,type,direction,amount,balance"
+        );
+        assert_eq!(statement.len(), 2);
+        assert!(statement[1].ends_with(",transfer,credit,30,30"));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "parquet")]
+    fn exports_parquet() -> Result<()> {
+        use arrow_array::{Array, RecordBatch, StringArray, UInt64Array};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let dir = tempfile::TempDir::new()?;
+        let db_dir = dir.path().join("db");
+        let output = dir.path().join("txs.parquet");
+
+        genesis()?.write_to(&db_dir)?;
+        add_txs(&mut State::open(&db_dir)?)?;
+        parquet(
+            &db_dir,
+            ParquetArgs {
+                account: None,
+                output: output.clone(),
+            },
+        )?;
+
+        let batches = ParquetRecordBatchReaderBuilder::try_new(File::open(&output)?)?
+            .build()?
+            .collect::<Result<Vec<RecordBatch>, _>>()?;
+        let batch = &batches[0];
+        let column = |name| batch.column_by_name(name).unwrap();
+        let strings = |name| {
+            column(name)
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .iter()
+                .map(|value| value.map(str::to_string))
+                .collect::<Vec<Option<String>>>()
+        };
+        let numbers = |name| {
+            column(name)
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .unwrap()
+                .iter()
+                .collect::<Vec<Option<u64>>>()
+        };
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(numbers("block"), [Some(1), Some(2)]);
+        assert_eq!(
+            strings("type"),
+            [Some("transfer".into()), Some("generate".into())]
+        );
+        assert_eq!(strings("from"), [Some("alice".into()), None]);
+        assert_eq!(strings("to"), [Some("bob".into()), Some("alice".into())]);
+        assert_eq!(numbers("value"), [Some(30), Some(5)]);
+        assert_eq!(numbers("nonce"), [Some(0), None]);
+        assert_eq!(strings("memo"), [Some("rent, march".into()), None]);
+        assert_eq!(column("time").null_count(), 0);
+
+        parquet(
+            &db_dir,
+            ParquetArgs {
+                account: Some("bob".into()),
+                output: output.clone(),
+            },
+        )?;
+
+        let rows = ParquetRecordBatchReaderBuilder::try_new(File::open(&output)?)?
+            .metadata()
+            .file_metadata()
+            .num_rows();
+
+        assert_eq!(rows, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn formats_times_as_rfc3339() {
+        assert_eq!(format_time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_time(u64::MAX), u64::MAX.to_string());
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use anyhow::Result;
+use clap::{ArgGroup, Args, Subcommand};
+
+use chigui_core::governance::ParamChange;
+use chigui_core::state::State;
+use chigui_core::{Account, Hash, Tx};
+
+use super::tx::{append, sign};
+use crate::config::FeePolicy;
+
+#[derive(Debug, Subcommand)]
+pub enum GovernanceCommand {
+    /// List the proposals open to votes, along with the current parameters.
+    List,
+    /// Propose a change of chain parameter.
+    Propose(ProposeArgs),
+    /// Vote on a proposal with the stake of an account.
+    Vote(VoteArgs),
+}
+
+#[derive(Debug, Args)]
+#[command(group = ArgGroup::new("change").required(true))]
+pub struct ProposeArgs {
+    #[arg(long)]
+    account: String,
+    /// New smallest fee transactions may carry.
+    #[arg(long, group = "change")]
+    min_fee: Option<u64>,
+    /// New number of blocks unstaked coins stay locked.
+    #[arg(long, group = "change")]
+    unbonding_period: Option<u64>,
+    /// Fee paid by the account, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct VoteArgs {
+    #[arg(long)]
+    account: String,
+    /// Hash of the transaction proposing the change.
+    #[arg(long)]
+    proposal: Hash,
+    /// Vote against the proposal instead of for it.
+    #[arg(long)]
+    reject: bool,
+    /// Fee paid by the account, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+pub fn run(db_dir: &Path, fees: &FeePolicy, command: GovernanceCommand) -> Result<()> {
+    match command {
+        GovernanceCommand::List => list(db_dir),
+        GovernanceCommand::Propose(args) => {
+            let change = match (args.min_fee, args.unbonding_period) {
+                (Some(min_fee), _) => ParamChange::MinFee(min_fee),
+                (_, Some(period)) => ParamChange::UnbondingPeriod(period),
+                _ => unreachable!("clap requires a parameter change"),
+            };
+
+            submit(
+                db_dir,
+                fees,
+                &args.account,
+                args.fee,
+                |account, fee, nonce| Tx::Propose {
+                    account,
+                    change,
+                    fee,
+                    nonce,
+                },
+            )
+        }
+        GovernanceCommand::Vote(args) => submit(
+            db_dir,
+            fees,
+            &args.account,
+            args.fee,
+            |account, fee, nonce| Tx::Vote {
+                account,
+                proposal: args.proposal,
+                approve: !args.reject,
+                fee,
+                nonce,
+            },
+        ),
+    }
+}
+
+fn list(db_dir: &Path) -> Result<()> {
+    let state = State::open(db_dir)?;
+
+    println!("Minimum fee: {}", state.fee_schedule().min_fee);
+    println!("Unbonding period: {} blocks", state.unbonding_period());
+
+    for (id, proposal) in state.governance().iter() {
+        let approving = proposal.votes.values().filter(|approve| **approve).count();
+
+        println!(
+            "{} {:?} by \"{}\", voting until block {}, {} for, {} against",
+            id,
+            proposal.change,
+            proposal.proposer,
+            proposal.ends_at,
+            approving,
+            proposal.votes.len() - approving
+        );
+    }
+
+    Ok(())
+}
+
+fn submit(
+    db_dir: &Path,
+    fees: &FeePolicy,
+    account: &str,
+    fee: Option<u64>,
+    build: impl FnOnce(Account, u64, u64) -> Tx,
+) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let account = Account::new(account)?;
+    let tx = build(
+        account.clone(),
+        fees.fee(fee, state.fee_schedule().min_fee)?,
+        state.next_nonce(&account),
+    );
+    let signed = sign(db_dir, state.chain_id(), &account, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+
+use chigui_core::backup::Backup;
+use chigui_core::miner::Miner;
+use chigui_core::signed::SignedTx;
+use chigui_core::state::State;
+use chigui_core::{Account, Tx};
+use chigui_wallet::Keystore;
+
+use super::read_password;
+use super::tx::resolve;
+use crate::config::FeePolicy;
+
+#[derive(Debug, Subcommand)]
+pub enum ImportCommand {
+    /// Append the transfers listed in a CSV file, all in a single block or none at all.
+    ///
+    /// The header row names the columns: `from`, `to` and `value`, then optionally `fee`, `memo`
+    /// and `denom`. Senders with a keystore wallet sign their transfers.
+    Csv(CsvArgs),
+    /// Restore a chain written by `chigui export state` into an empty db dir, replaying and
+    /// checking every block first.
+    State(StateArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CsvArgs {
+    file: PathBuf,
+    /// Check every transfer and print them without appending anything.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct StateArgs {
+    file: PathBuf,
+}
+
+pub fn run(db_dir: &Path, fees: &FeePolicy, command: ImportCommand) -> Result<()> {
+    match command {
+        ImportCommand::Csv(args) => csv(db_dir, fees, args),
+        ImportCommand::State(args) => state(db_dir, args),
+    }
+}
+
+/// Positions of the columns of a transfers CSV file.
+struct Columns {
+    from: usize,
+    to: usize,
+    value: usize,
+    fee: Option<usize>,
+    memo: Option<usize>,
+    denom: Option<usize>,
+}
+
+impl Columns {
+    fn new(headers: &csv::StringRecord) -> Result<Self> {
+        let find = |name: &str| headers.iter().position(|header| header.trim() == name);
+        let require = |name: &str| find(name).with_context(|| format!("Missing {} column.", name));
+
+        Ok(Self {
+            from: require("from")?,
+            to: require("to")?,
+            value: require("value")?,
+            fee: find("fee"),
+            memo: find("memo"),
+            denom: find("denom"),
+        })
+    }
+
+    /// Build the transfer of a row, with the next nonce of its sender.
+    fn transfer(
+        &self,
+        state: &State,
+        fees: &FeePolicy,
+        record: &csv::StringRecord,
+        nonces: &mut HashMap<Account, u64>,
+    ) -> Result<Tx> {
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .map(str::trim)
+                .filter(|field| !field.is_empty())
+        };
+        let from = Account::new(field(Some(self.from)).context("Missing sender.")?)?;
+        let to = resolve(state, field(Some(self.to)).context("Missing recipient.")?)?;
+        let value = field(Some(self.value))
+            .context("Missing value.")?
+            .parse::<u64>()
+            .context("Invalid value.")?;
+        let fee = field(self.fee)
+            .map(|fee| fee.parse::<u64>().context("Invalid fee."))
+            .transpose()?;
+        let nonce = nonces
+            .entry(from.clone())
+            .or_insert_with(|| state.next_nonce(&from));
+        let tx = Tx::Transfer {
+            from,
+            to,
+            value,
+            fee: fees.fee(fee, state.fee_schedule().min_fee)?,
+            nonce: *nonce,
+            memo: field(self.memo).map(str::to_string),
+            denom: field(self.denom).map(str::to_string),
+        };
+
+        tx.check_memo()?;
+        *nonce += 1;
+
+        Ok(tx)
+    }
+}
+
+fn csv(db_dir: &Path, fees: &FeePolicy, args: CsvArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let mut reader = csv::Reader::from_path(&args.file)
+        .with_context(|| format!("Failed to read {}.", args.file.display()))?;
+    let columns = Columns::new(reader.headers()?)?;
+    let mut nonces = HashMap::new();
+    let mut txs = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        // The header is the first line.
+        let line = index + 2;
+        let tx = record
+            .map_err(anyhow::Error::from)
+            .and_then(|record| columns.transfer(&state, fees, &record, &mut nonces))
+            .with_context(|| format!("Invalid transfer on line {}.", line))?;
+
+        txs.push(tx);
+    }
+
+    if txs.is_empty() {
+        bail!("No transfers in {}.", args.file.display());
+    }
+
+    let signed = sign_all(db_dir, state.chain_id(), txs)?;
+    let block = Miner::new(&state).mine(&signed)?;
+
+    for tx in signed.iter() {
+        println!("{} {}", tx.hash(), tx);
+    }
+
+    if args.dry_run {
+        state.check_block(block)?;
+        println!("{} transfers are valid, none appended", signed.len());
+    } else {
+        state.add_block(block)?;
+        println!(
+            "{} transfers appended in block #{}",
+            signed.len(),
+            state.height()
+        );
+    }
+
+    Ok(())
+}
+
+/// Sign the transfers of senders holding a keystore wallet, loading each wallet once.
+fn sign_all(db_dir: &Path, chain_id: &str, txs: Vec<Tx>) -> Result<Vec<SignedTx>> {
+    let keystore = Keystore::open(db_dir);
+    let owned = keystore.accounts()?;
+    let mut wallets = HashMap::new();
+
+    txs.into_iter()
+        .map(|tx| {
+            let Some(from) = tx.sender().filter(|from| owned.contains(from)) else {
+                return Ok(SignedTx::unsigned(tx));
+            };
+            let wallet = match wallets.entry(from.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let wallet = keystore.load(entry.key(), &read_password()?)?;
+
+                    entry.insert(wallet)
+                }
+            };
+
+            Ok(wallet.sign(&tx, chain_id))
+        })
+        .collect()
+}
+
+fn state(db_dir: &Path, args: StateArgs) -> Result<()> {
+    if db_dir.join("genesis.json").exists() {
+        bail!("A chain already exists in {}.", db_dir.display());
+    }
+
+    let file = File::open(&args.file)
+        .with_context(|| format!("Failed to read {}.", args.file.display()))?;
+    let backup = serde_json::from_reader::<_, Backup>(BufReader::new(file))
+        .context("Invalid chain export.")?;
+    let state = backup.restore(db_dir)?;
+
+    println!(
+        "Imported chain \"{}\" up to block #{} into {}",
+        state.chain_id(),
+        state.height(),
+        db_dir.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use clap::Args;
+
+use chigui_core::Account;
+use chigui_core::state::GenesisBuilder;
+
+use super::prompt_from;
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Identifier of the new chain. Prompted for, along with balances, when omitted.
+    #[arg(long)]
+    chain_id: Option<String>,
+    /// RFC 3339 genesis time, defaults to now.
+    #[arg(long)]
+    genesis_time: Option<DateTime<Utc>>,
+    /// Initial balance of an account, as `<account>=<value>`. May be repeated.
+    #[arg(long = "balance", value_name = "ACCOUNT=VALUE", value_parser = parse_balance)]
+    balances: Vec<(Account, u64)>,
+    /// Accept unsigned transfers, for dev chains.
+    #[arg(long)]
+    permissive: bool,
+}
+
+/// Chain id of a new chain when none is given.
+const DEFAULT_CHAIN_ID: &str = "chigui";
+
+impl InitArgs {
+    pub fn chain_id(&self) -> Option<&str> {
+        self.chain_id.as_deref()
+    }
+
+    /// Prompt for the chain id and balances on `input` when no chain id was given, so that the
+    /// chain id is known before picking the db dir of the new chain.
+    pub fn prompt_missing(mut self, input: &mut impl BufRead) -> Result<Self> {
+        if self.chain_id.is_none() {
+            let chain_id = prompt_from(input, &format!("Chain id [{}]: ", DEFAULT_CHAIN_ID))?;
+
+            self.balances.extend(prompt_balances(input)?);
+            self.chain_id = Some(if chain_id.is_empty() {
+                String::from(DEFAULT_CHAIN_ID)
+            } else {
+                chain_id
+            });
+        }
+
+        Ok(self)
+    }
+}
+
+pub fn run(db_dir: &Path, args: InitArgs) -> Result<()> {
+    let genesis_path = db_dir.join("genesis.json");
+
+    if genesis_path.exists() {
+        bail!("A chain already exists in {}.", db_dir.display());
+    }
+
+    let chain_id = args
+        .chain_id
+        .unwrap_or_else(|| String::from(DEFAULT_CHAIN_ID));
+    let balances = args
+        .balances
+        .into_iter()
+        .map(|(account, value)| (account.to_string(), value))
+        .collect::<BTreeMap<String, u64>>();
+    let genesis = balances
+        .iter()
+        .fold(
+            GenesisBuilder::new(&chain_id)
+                .time(args.genesis_time.unwrap_or_else(Utc::now))
+                .permissive(args.permissive),
+            |builder, (account, value)| builder.balance(account, *value),
+        )
+        .build()?;
+
+    genesis.write_to(db_dir)?;
+
+    println!("Initialized chain \"{}\" in {}", chain_id, db_dir.display());
+
+    for (account, value) in balances.iter() {
+        println!("  {}: {}", account, value);
+    }
+
+    Ok(())
+}
+
+/// Read `<account>=<value>` lines from `input` until an empty line.
+fn prompt_balances(input: &mut impl BufRead) -> Result<Vec<(Account, u64)>> {
+    let mut balances = Vec::new();
+
+    println!("Initial balances as <account>=<value>, one per line, empty line to finish:");
+
+    loop {
+        let line = prompt_from(input, "> ")?;
+
+        if line.trim().is_empty() {
+            return Ok(balances);
+        }
+
+        match parse_balance(&line) {
+            Ok(balance) => balances.push(balance),
+            Err(err) => println!("{}", err),
+        }
+    }
+}
+
+fn parse_balance(s: &str) -> Result<(Account, u64)> {
+    let (account, value) = s.split_once('=').context("Expected <account>=<value>.")?;
+    let account = Account::new(account.trim())?;
+    let value = value
+        .trim()
+        .parse::<u64>()
+        .context("Balance must be a non-negative integer.")?;
+
+    Ok((account, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_balances() -> Result<()> {
+        assert_eq!(
+            parse_balance("alice = 1000")?,
+            (Account::new("alice")?, 1000)
+        );
+        assert!(parse_balance("alice").is_err());
+        assert!(parse_balance("alice=-1").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+pub mod audit;
+pub mod balances;
+pub mod completions;
+pub mod config;
+pub mod db;
+pub mod diff;
+pub mod escrow;
+pub mod export;
+pub mod governance;
+pub mod import;
+pub mod init;
+pub mod multisig;
+pub mod node;
+pub mod script;
+pub mod tx;
+pub mod verify;
+pub mod wallet;
+
+use std::env;
+use std::io::{self, BufRead, Write};
+
+use anyhow::Result;
+
+/// Print the given label and read a single line from stdin.
+pub fn prompt(label: &str) -> Result<String> {
+    prompt_from(&mut io::stdin().lock(), label)
+}
+
+/// Print the given label and read a single line from `input`.
+pub fn prompt_from(input: &mut impl BufRead, label: &str) -> Result<String> {
+    print!("{}", label);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+
+    input.read_line(&mut line)?;
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Read the wallet password from `CHIGUI_WALLET_PASSWORD`, or prompt for it on stdin.
+pub fn read_password() -> Result<String> {
+    if let Ok(password) = env::var("CHIGUI_WALLET_PASSWORD") {
+        return Ok(password);
+    }
+
+    prompt("Password: ")
+}
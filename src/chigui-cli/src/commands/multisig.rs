@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+
+use chigui_core::multisig::MultisigAction;
+use chigui_core::state::State;
+use chigui_core::{Account, Hash, Tx};
+
+use super::tx::{append, sign};
+use crate::config::FeePolicy;
+
+#[derive(Debug, Subcommand)]
+pub enum MultisigCommand {
+    /// List the transfers waiting for approvals.
+    List,
+    /// Propose a transfer out of a multisig account, approved by the proposer.
+    Propose(ProposeArgs),
+    /// Approve a pending transfer.
+    Approve(ProposalArgs),
+    /// Carry out a transfer approved by enough members.
+    Execute(ProposalArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ProposeArgs {
+    /// Member proposing the transfer.
+    #[arg(long)]
+    account: String,
+    #[arg(long)]
+    multisig: String,
+    #[arg(long)]
+    to: String,
+    #[arg(long)]
+    value: u64,
+    /// Fee paid by the member, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct ProposalArgs {
+    /// Member approving or executing the transfer.
+    #[arg(long)]
+    account: String,
+    /// Hash of the transaction proposing the transfer.
+    #[arg(long)]
+    proposal: Hash,
+    /// Fee paid by the member, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+pub fn run(db_dir: &Path, fees: &FeePolicy, command: MultisigCommand) -> Result<()> {
+    match command {
+        MultisigCommand::List => list(db_dir),
+        MultisigCommand::Propose(args) => {
+            let action = MultisigAction::Propose {
+                multisig: Account::new(args.multisig)?,
+                to: Account::new(args.to)?,
+                value: args.value,
+            };
+
+            submit(db_dir, fees, &args.account, action, args.fee)
+        }
+        MultisigCommand::Approve(args) => {
+            let action = MultisigAction::Approve {
+                proposal: args.proposal,
+            };
+
+            submit(db_dir, fees, &args.account, action, args.fee)
+        }
+        MultisigCommand::Execute(args) => {
+            let action = MultisigAction::Execute {
+                proposal: args.proposal,
+            };
+
+            submit(db_dir, fees, &args.account, action, args.fee)
+        }
+    }
+}
+
+fn list(db_dir: &Path) -> Result<()> {
+    let state = State::open(db_dir)?;
+
+    for (id, proposal) in state.proposals().iter() {
+        let approvals = proposal
+            .approvals
+            .iter()
+            .map(Account::to_string)
+            .collect::<Vec<String>>();
+
+        println!(
+            "{} \"{}\" -> \"{}\" {} coins, approved by {}",
+            id,
+            proposal.multisig,
+            proposal.to,
+            proposal.value,
+            approvals.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+fn submit(
+    db_dir: &Path,
+    fees: &FeePolicy,
+    account: &str,
+    action: MultisigAction,
+    fee: Option<u64>,
+) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let account = Account::new(account)?;
+    let tx = Tx::Multisig {
+        account: account.clone(),
+        action,
+        fee: fees.fee(fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&account),
+    };
+    let signed = sign(db_dir, state.chain_id(), &account, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand, ValueEnum};
+use tokio::runtime::Runtime;
+
+use chigui_core::Account;
+use chigui_core::audit::AuditLog;
+use chigui_core::migrate::migrate;
+use chigui_core::peers::KnownPeers;
+use chigui_core::state::State;
+use chigui_core::storage::binary::BinaryStorage;
+#[cfg(feature = "rocksdb")]
+use chigui_core::storage::rocksdb::RocksStorage;
+#[cfg(feature = "sled")]
+use chigui_core::storage::sled::SledStorage;
+use chigui_core::storage::{FileStorage, Storage};
+use chigui_node::ApiConfig;
+use chigui_node::admin::AdminToken;
+use chigui_node::limit::Limits;
+use chigui_node::tls::TlsFiles;
+use chigui_wallet::Keystore;
+
+use super::read_password;
+use crate::config::Config;
+
+#[derive(Debug, Subcommand)]
+pub enum NodeCommand {
+    /// Print the chain height, tip and consensus parameters.
+    Info,
+    /// Serve the HTTP API and sync with peers.
+    #[command(alias = "serve")]
+    Start(Box<StartArgs>),
+}
+
+/// Where the node keeps its blocks and snapshots.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum Backend {
+    /// JSONL `block.db` and `snapshot-<height>.json` files.
+    #[default]
+    File,
+    /// A sled database under `sled/`, with crash-safe writes.
+    #[cfg(feature = "sled")]
+    Sled,
+    /// A RocksDB database under `rocksdb/`, for very large histories.
+    #[cfg(feature = "rocksdb")]
+    Rocksdb,
+}
+
+/// How the file backend encodes blocks and snapshots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// JSONL `block.db` and `snapshot-<height>.json` files.
+    #[default]
+    Jsonl,
+    /// Compact binary `block.bin` and `snapshot-<height>.bin` files, in a tagged encoding of the
+    /// JSON records.
+    Binary,
+}
+
+#[derive(Debug, Args)]
+pub struct StartArgs {
+    /// Address the HTTP API listens on [default: 127.0.0.1:8080].
+    #[arg(long)]
+    addr: Option<SocketAddr>,
+    /// Address the peer-to-peer sync protocol listens on [default: 127.0.0.1:9090].
+    #[arg(long)]
+    p2p_addr: Option<SocketAddr>,
+    /// Bootstrap peer to sync with, repeatable, on top of the configured `peers`. Peers are
+    /// remembered in `known_peers.json`.
+    #[arg(long = "peer")]
+    peers: Vec<SocketAddr>,
+    /// Storage backend holding blocks and snapshots.
+    #[arg(long, value_enum, default_value_t)]
+    backend: Backend,
+    /// Encoding of the blocks and snapshots kept by the file backend.
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+    /// Only keep this many recent blocks in storage, on top of the latest snapshot.
+    #[arg(long, conflicts_with = "archive")]
+    prune: Option<u64>,
+    /// Index the balances of every account at every height, to serve `chigui_getBalanceAt`
+    /// without replaying the chain.
+    #[arg(long)]
+    archive: bool,
+    /// Keystore account sealing blocks on proof-of-authority and proof-of-stake chains.
+    #[arg(long)]
+    validator: Option<String>,
+    /// Requests each client IP may make per second to the HTTP API, `0` disabling the limit
+    /// [default: 50].
+    #[arg(long)]
+    rate_limit: Option<f64>,
+    /// Requests each client IP may make at once before being held to `--rate-limit` [default:
+    /// 100].
+    #[arg(long)]
+    burst: Option<u32>,
+    /// Largest HTTP request body accepted, in bytes [default: 1048576].
+    #[arg(long)]
+    max_body_size: Option<usize>,
+    /// PEM certificate chain to serve the HTTP API over HTTPS with.
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key of `--tls-cert`.
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    /// File holding the bearer token of the `admin_*` JSON-RPC methods, served on `/admin/rpc`.
+    /// They're disabled without it.
+    #[arg(long)]
+    admin_token_file: Option<PathBuf>,
+}
+
+pub fn run(db_dir: &Path, config: &Config, command: NodeCommand) -> Result<()> {
+    match command {
+        NodeCommand::Info => info(db_dir),
+        NodeCommand::Start(args) => start(db_dir, config, *args),
+    }
+}
+
+fn info(db_dir: &Path) -> Result<()> {
+    let state = State::open(db_dir)?;
+
+    println!("Chain: {}", state.chain_id());
+    println!("Genesis time: {}", state.genesis_time());
+
+    match state.latest_block() {
+        Some(block) => {
+            println!("Height: {}", block.header.number);
+            println!("Latest block: {}", block.hash());
+        }
+        None => println!("Height: 0"),
+    }
+
+    println!("State root: {}", state.state_root());
+
+    if !state.validators().is_empty() {
+        println!("Consensus: proof-of-authority");
+
+        for validator in state.validators().iter() {
+            println!("Validator: {}", validator);
+        }
+    } else if let Some(proposer) = state.next_proposer() {
+        println!("Consensus: proof-of-stake");
+        println!("Next proposer: {}", proposer);
+
+        for (account, stake) in state.stakes().validators() {
+            println!("Validator: {} (stake {})", account, stake);
+        }
+    } else {
+        println!("Consensus: proof-of-work");
+        println!("Difficulty: {}", state.difficulty());
+    }
+
+    println!("Total supply: {}", state.total_supply());
+
+    if let Some(max_supply) = state.max_supply() {
+        println!("Maximum supply: {}", max_supply);
+    }
+
+    println!("Minimum fee: {}", state.fee_schedule().min_fee);
+
+    Ok(())
+}
+
+fn start(db_dir: &Path, config: &Config, args: StartArgs) -> Result<()> {
+    let rpc = &config.rpc;
+    let tls_cert = args.tls_cert.or_else(|| rpc.tls_cert.clone());
+    let tls_key = args.tls_key.or_else(|| rpc.tls_key.clone());
+
+    if tls_cert.is_some() != tls_key.is_some() {
+        bail!("The TLS certificate and key must be configured together");
+    }
+
+    migrate(db_dir)?;
+
+    if args.format == Format::Binary && !matches!(args.backend, Backend::File) {
+        bail!("--format only applies to the file backend");
+    }
+
+    let storage: Box<dyn Storage> = match args.backend {
+        Backend::File if args.format == Format::Binary => Box::new(BinaryStorage::open(db_dir)?),
+        Backend::File => Box::new(FileStorage::new(db_dir)),
+        #[cfg(feature = "sled")]
+        Backend::Sled => Box::new(SledStorage::open(db_dir)?),
+        #[cfg(feature = "rocksdb")]
+        Backend::Rocksdb => Box::new(RocksStorage::open(db_dir)?),
+    };
+    let state = match args.prune {
+        Some(window) => State::with_storage_pruned(storage, window)?,
+        None if args.archive => State::with_storage_archival(storage)?,
+        None => State::with_storage(storage)?,
+    };
+    let validator = match args.validator {
+        Some(account) => {
+            let account = Account::new(account)?;
+            let password = read_password()?;
+
+            Some(Keystore::open(db_dir).load(&account, &password)?)
+        }
+        None => None,
+    };
+    let mut known_peers = KnownPeers::open(db_dir)?;
+
+    for peer in config.network.peers.iter().copied().chain(args.peers) {
+        known_peers.insert(peer)?;
+    }
+
+    let admin_token = match args
+        .admin_token_file
+        .as_ref()
+        .or(rpc.admin_token_file.as_ref())
+    {
+        Some(path) => Some(read_admin_token(path)?),
+        None => None,
+    };
+    let api = ApiConfig {
+        addr: args.addr.unwrap_or(rpc.addr),
+        limits: Limits {
+            requests_per_second: args.rate_limit.unwrap_or(rpc.rate_limit),
+            burst: args.burst.unwrap_or(rpc.burst),
+            max_body_bytes: args.max_body_size.unwrap_or(rpc.max_body_size),
+        },
+        tls: tls_cert
+            .zip(tls_key)
+            .map(|(cert, key)| TlsFiles { cert, key }),
+        admin_token,
+    };
+    let scheme = match api.tls {
+        Some(_) => "https",
+        None => "http",
+    };
+
+    let p2p_addr = args.p2p_addr.unwrap_or(config.network.p2p_addr);
+
+    println!("Serving {} on {}://{}", db_dir.display(), scheme, api.addr);
+    println!("Syncing with peers on {}", p2p_addr);
+
+    Runtime::new()?.block_on(chigui_node::start(
+        state,
+        api,
+        p2p_addr,
+        known_peers,
+        validator,
+        AuditLog::open(db_dir),
+    ))?;
+
+    Ok(())
+}
+
+/// Read the admin token, the whole file but surrounding whitespace.
+fn read_admin_token(path: &Path) -> Result<AdminToken> {
+    let token = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}.", path.display()))?;
+    let token = token.trim();
+
+    if token.is_empty() {
+        bail!("The admin token in {} is empty.", path.display());
+    }
+
+    Ok(AdminToken::new(token))
+}
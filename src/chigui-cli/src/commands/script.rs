@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+
+use chigui_core::script::Op;
+use chigui_core::state::State;
+use chigui_core::{Account, Tx};
+
+use super::tx::{append, sign};
+use crate::config::FeePolicy;
+
+#[derive(Debug, Subcommand)]
+pub enum ScriptCommand {
+    /// Run a script against the storage of an account (experimental).
+    Run(RunArgs),
+    /// Print the storage written by scripts of an account.
+    Storage { account: String },
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    #[arg(long)]
+    account: String,
+    /// The instructions as JSON, e.g. `[{"load":"n"},{"push":1},"add",{"store":"n"}]`.
+    #[arg(long)]
+    code: String,
+    /// Gas the script may use before failing.
+    #[arg(long, default_value_t = 10_000)]
+    gas_limit: u64,
+    /// Fee paid by the account, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+pub fn run(db_dir: &Path, fees: &FeePolicy, command: ScriptCommand) -> Result<()> {
+    match command {
+        ScriptCommand::Run(args) => run_script(db_dir, fees, args),
+        ScriptCommand::Storage { account } => storage(db_dir, &account),
+    }
+}
+
+fn run_script(db_dir: &Path, fees: &FeePolicy, args: RunArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let account = Account::new(args.account)?;
+    let code = serde_json::from_str::<Vec<Op>>(&args.code).context("Invalid script JSON.")?;
+    let tx = Tx::Script {
+        account: account.clone(),
+        code,
+        gas_limit: args.gas_limit,
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&account),
+    };
+    let signed = sign(db_dir, state.chain_id(), &account, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+
+    Ok(())
+}
+
+fn storage(db_dir: &Path, account: &str) -> Result<()> {
+    let state = State::open(db_dir)?;
+    let account = Account::new(account)?;
+
+    for (key, value) in state.contracts().storage_of(&account).into_iter().flatten() {
+        println!("{}: {}", key, value);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,592 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+
+use chigui_core::audit::{AuditEntry, AuditLog, Source};
+use chigui_core::query::TxFilter;
+use chigui_core::signed::SignedTx;
+use chigui_core::state::State;
+use chigui_core::timelock::Unlock;
+use chigui_core::{Account, ChiguiError, Payment, Tx, TxKind};
+use chigui_wallet::Keystore;
+
+use super::read_password;
+use crate::config::FeePolicy;
+
+#[derive(Debug, Subcommand)]
+pub enum TxCommand {
+    /// List transactions, oldest first, with their block number.
+    List(ListArgs),
+    /// Validate and append a transaction given as JSON, signed or not.
+    Add {
+        /// The transaction, e.g. `{"tx":{"type":"generate","to":"bob","value":10}}`.
+        json: String,
+    },
+    /// Build a transfer, sign it with the sender's keystore wallet if any, and append it.
+    Transfer(TransferArgs),
+    /// Pay several recipients from one account in a single transaction.
+    TransferMulti(TransferMultiArgs),
+    /// Send coins the recipient can only spend after a block height or time.
+    TransferLocked(TransferLockedArgs),
+    /// Send coins that vest for the recipient over a number of blocks.
+    TransferVesting(TransferVestingArgs),
+    /// Generate new coins, signed with the minter's keystore wallet when given.
+    Generate(GenerateArgs),
+    /// Replace the accounts allowed to generate coins, signed by the minter admin.
+    SetMinters(SetMintersArgs),
+    /// Register a name others can send coins to as `@<name>`.
+    RegisterAlias(RegisterAliasArgs),
+    /// Lock coins of an account as validator stake.
+    Stake(StakeArgs),
+    /// Start unbonding staked coins, released after the chain unbonding period.
+    Unstake(StakeArgs),
+    /// Destroy coins of an account, removing them from the total supply.
+    Burn(StakeArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// Only transactions touching this account.
+    #[arg(long)]
+    account: Option<String>,
+    /// Only transactions of this type, e.g. transfer, generate, stake or burn.
+    #[arg(long = "type")]
+    kind: Option<TxKind>,
+    /// Only transactions moving at least this many coins.
+    #[arg(long)]
+    min_value: Option<u64>,
+    /// Only transfers whose memo contains this text.
+    #[arg(long)]
+    memo: Option<String>,
+    /// Only transactions of blocks from this height on.
+    #[arg(long)]
+    from_height: Option<u64>,
+    /// Only transactions of blocks up to this height, included.
+    #[arg(long)]
+    to_height: Option<u64>,
+    /// Maximum number of transactions to print.
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Number of matching transactions to skip.
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct TransferArgs {
+    #[arg(long)]
+    from: String,
+    /// Recipient account, or `@<alias>`.
+    #[arg(long)]
+    to: String,
+    #[arg(long)]
+    value: u64,
+    /// Fee paid by the sender, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+    /// Reference attached to the transfer, e.g. an invoice ID.
+    #[arg(long)]
+    memo: Option<String>,
+    /// Asset to transfer, declared in the genesis. Defaults to the native coin.
+    #[arg(long)]
+    denom: Option<String>,
+    /// Check the transfer and print the native balances it would leave, without signing or
+    /// appending it.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct TransferMultiArgs {
+    #[arg(long)]
+    from: String,
+    /// Recipient and amount as `<account>:<value>` or `@<alias>:<value>`, repeatable.
+    #[arg(long = "to", required = true, value_parser = parse_payment)]
+    payments: Vec<(String, u64)>,
+    /// Fee paid once for the whole batch, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+#[command(group = clap::ArgGroup::new("unlock").required(true))]
+pub struct TransferLockedArgs {
+    #[arg(long)]
+    from: String,
+    /// Recipient account, or `@<alias>`.
+    #[arg(long)]
+    to: String,
+    #[arg(long)]
+    value: u64,
+    /// Block number from which the recipient can spend the coins.
+    #[arg(long, group = "unlock")]
+    unlock_height: Option<u64>,
+    /// Unix time, in seconds, from which the recipient can spend the coins.
+    #[arg(long, group = "unlock")]
+    unlock_time: Option<u64>,
+    /// Fee paid by the sender, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct TransferVestingArgs {
+    #[arg(long)]
+    from: String,
+    /// Recipient account, or `@<alias>`.
+    #[arg(long)]
+    to: String,
+    #[arg(long)]
+    value: u64,
+    /// Blocks by which every coin vested, linearly from the including block.
+    #[arg(long)]
+    duration: u64,
+    /// Blocks before anything vests.
+    #[arg(long, default_value_t = 0)]
+    cliff: u64,
+    /// Fee paid by the sender, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct GenerateArgs {
+    #[arg(long)]
+    to: String,
+    #[arg(long)]
+    value: u64,
+    /// Asset to generate, declared in the genesis. Defaults to the native coin.
+    #[arg(long)]
+    denom: Option<String>,
+    /// Authorized minter signing the transaction.
+    #[arg(long)]
+    minter: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SetMintersArgs {
+    #[arg(long)]
+    admin: String,
+    /// Account allowed to generate coins, repeatable. None closes minting.
+    #[arg(long = "minter")]
+    minters: Vec<String>,
+    /// Fee paid by the admin, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct RegisterAliasArgs {
+    #[arg(long)]
+    account: String,
+    #[arg(long)]
+    name: String,
+    /// Fee paid by the account, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct StakeArgs {
+    #[arg(long)]
+    account: String,
+    #[arg(long)]
+    value: u64,
+    /// Fee paid by the account, defaults to the configured `default_fee`, at least
+    /// the chain minimum fee.
+    #[arg(long)]
+    fee: Option<u64>,
+}
+
+pub fn run(db_dir: &Path, fees: &FeePolicy, command: TxCommand) -> Result<()> {
+    match command {
+        TxCommand::List(args) => list(db_dir, args),
+        TxCommand::Add { json } => add(db_dir, &json),
+        TxCommand::Transfer(args) => transfer(db_dir, fees, args),
+        TxCommand::TransferMulti(args) => transfer_multi(db_dir, fees, args),
+        TxCommand::TransferLocked(args) => transfer_locked(db_dir, fees, args),
+        TxCommand::TransferVesting(args) => transfer_vesting(db_dir, fees, args),
+        TxCommand::Generate(args) => generate(db_dir, args),
+        TxCommand::SetMinters(args) => set_minters(db_dir, fees, args),
+        TxCommand::RegisterAlias(args) => register_alias(db_dir, fees, args),
+        TxCommand::Stake(args) => stake(db_dir, fees, args, false),
+        TxCommand::Unstake(args) => stake(db_dir, fees, args, true),
+        TxCommand::Burn(args) => burn(db_dir, fees, args),
+    }
+}
+
+fn list(db_dir: &Path, args: ListArgs) -> Result<()> {
+    let state = State::open(db_dir)?;
+    let filter = TxFilter {
+        account: args.account.map(Account::new).transpose()?,
+        kind: args.kind,
+        min_value: args.min_value,
+        memo: args.memo,
+    };
+    let limit = args.limit.unwrap_or(usize::MAX);
+    let txs: Box<dyn Iterator<Item = (u64, &SignedTx)>> = match (
+        filter == TxFilter::default(),
+        args.from_height,
+        args.to_height,
+    ) {
+        (true, None, None) => Box::new(state.txs_range(args.offset, limit)),
+        (_, from, to) => Box::new(
+            state
+                .find_txs(&filter, from.unwrap_or(0), to.unwrap_or(u64::MAX))
+                .skip(args.offset)
+                .take(limit),
+        ),
+    };
+
+    for (number, tx) in txs {
+        println!("#{} {} {}", number, tx.hash(), tx);
+    }
+
+    Ok(())
+}
+
+fn add(db_dir: &Path, json: &str) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let tx = serde_json::from_str::<SignedTx>(json).context("Invalid transaction JSON.")?;
+
+    println!("{} {}", tx.hash(), tx);
+
+    append(db_dir, &mut state, tx)?;
+
+    Ok(())
+}
+
+fn transfer(db_dir: &Path, fees: &FeePolicy, args: TransferArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let from = Account::new(args.from)?;
+    let to = resolve(&state, &args.to)?;
+    let tx = Tx::Transfer {
+        from: from.clone(),
+        to: to.clone(),
+        value: args.value,
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&from),
+        memo: args.memo,
+        denom: args.denom.clone(),
+    };
+
+    if args.dry_run {
+        let outcome = state.simulate(&tx)?;
+
+        println!("Transfer {} is valid, not appended", tx.hash());
+
+        for (account, change) in outcome.balances.iter() {
+            println!("{}: {} -> {}", account, change.before, change.after);
+        }
+
+        return Ok(());
+    }
+
+    let signed = sign(db_dir, state.chain_id(), &from, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+
+    for account in [&from, &to] {
+        println!(
+            "{}: {}",
+            account,
+            state
+                .get_asset_balance(account, args.denom.as_deref())
+                .unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+fn transfer_locked(db_dir: &Path, fees: &FeePolicy, args: TransferLockedArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let from = Account::new(args.from)?;
+    let to = resolve(&state, &args.to)?;
+    let unlock = match (args.unlock_height, args.unlock_time) {
+        (Some(height), _) => Unlock::Height(height),
+        (_, Some(time)) => Unlock::Time(time),
+        _ => unreachable!("clap requires an unlock condition"),
+    };
+    let tx = Tx::TransferLocked {
+        from: from.clone(),
+        to: to.clone(),
+        value: args.value,
+        unlock,
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&from),
+    };
+    let signed = sign(db_dir, state.chain_id(), &from, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+    println!("{}: {}", from, state.get_balance(&from).unwrap_or_default());
+    println!("{}: {} locked", to, state.locks().locked_of(&to));
+
+    Ok(())
+}
+
+fn transfer_vesting(db_dir: &Path, fees: &FeePolicy, args: TransferVestingArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let from = Account::new(args.from)?;
+    let to = resolve(&state, &args.to)?;
+    let tx = Tx::TransferVesting {
+        from: from.clone(),
+        to: to.clone(),
+        value: args.value,
+        cliff: args.cliff,
+        duration: args.duration,
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&from),
+    };
+    let signed = sign(db_dir, state.chain_id(), &from, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+
+    for account in [&from, &to] {
+        println!(
+            "{}: {} ({} spendable)",
+            account,
+            state.get_balance(account).unwrap_or_default(),
+            state.spendable_balance(account).unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+fn transfer_multi(db_dir: &Path, fees: &FeePolicy, args: TransferMultiArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let from = Account::new(args.from)?;
+    let payments = args
+        .payments
+        .iter()
+        .map(|(to, value)| {
+            Ok(Payment {
+                to: resolve(&state, to)?,
+                value: *value,
+            })
+        })
+        .collect::<Result<Vec<Payment>>>()?;
+    let tx = Tx::TransferMulti {
+        from: from.clone(),
+        payments: payments.clone(),
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&from),
+    };
+    let signed = sign(db_dir, state.chain_id(), &from, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+
+    let recipients = payments.iter().map(|payment| &payment.to);
+
+    for account in std::iter::once(&from).chain(recipients) {
+        println!(
+            "{}: {}",
+            account,
+            state.get_balance(account).unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+fn generate(db_dir: &Path, args: GenerateArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let to = Account::new(args.to)?;
+    let tx = Tx::Generate {
+        to: to.clone(),
+        value: args.value,
+        denom: args.denom.clone(),
+    };
+    let signed = match args.minter {
+        Some(minter) => sign(db_dir, state.chain_id(), &Account::new(minter)?, tx)?,
+        None => SignedTx::unsigned(tx),
+    };
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+    println!(
+        "{}: {}",
+        to,
+        state
+            .get_asset_balance(&to, args.denom.as_deref())
+            .unwrap_or_default()
+    );
+
+    Ok(())
+}
+
+fn set_minters(db_dir: &Path, fees: &FeePolicy, args: SetMintersArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let admin = Account::new(args.admin)?;
+    let tx = Tx::SetMinters {
+        admin: admin.clone(),
+        minters: args
+            .minters
+            .into_iter()
+            .map(Account::new)
+            .collect::<chigui_core::Result<Vec<Account>>>()?,
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&admin),
+    };
+    let signed = sign(db_dir, state.chain_id(), &admin, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+
+    Ok(())
+}
+
+fn parse_payment(s: &str) -> Result<(String, u64)> {
+    let (to, value) = s.rsplit_once(':').context("Expected <account>:<value>.")?;
+
+    Ok((to.to_string(), value.parse().context("Invalid value.")?))
+}
+
+fn register_alias(db_dir: &Path, fees: &FeePolicy, args: RegisterAliasArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let account = Account::new(args.account)?;
+    let tx = Tx::RegisterAlias {
+        account: account.clone(),
+        name: args.name.clone(),
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&account),
+    };
+    let signed = sign(db_dir, state.chain_id(), &account, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+    println!("@{} -> {}", args.name.trim().to_lowercase(), account);
+
+    Ok(())
+}
+
+/// Parse a recipient, looking `@<alias>` up in the chain's alias registry.
+pub fn resolve(state: &State, recipient: &str) -> Result<Account> {
+    match recipient.strip_prefix('@') {
+        Some(name) => {
+            Ok(state
+                .resolve_alias(name)
+                .cloned()
+                .ok_or_else(|| ChiguiError::UnknownAlias {
+                    name: name.to_string(),
+                })?)
+        }
+        None => Ok(Account::new(recipient)?),
+    }
+}
+
+fn stake(db_dir: &Path, fees: &FeePolicy, args: StakeArgs, unstake: bool) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let account = Account::new(args.account)?;
+    let fee = fees.fee(args.fee, state.fee_schedule().min_fee)?;
+    let nonce = state.next_nonce(&account);
+    let tx = if unstake {
+        Tx::Unstake {
+            account: account.clone(),
+            value: args.value,
+            fee,
+            nonce,
+        }
+    } else {
+        Tx::Stake {
+            account: account.clone(),
+            value: args.value,
+            fee,
+            nonce,
+        }
+    };
+    let signed = sign(db_dir, state.chain_id(), &account, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+    println!(
+        "{}: {} (stake {})",
+        account,
+        state.get_balance(&account).unwrap_or_default(),
+        state.stakes().stake_of(&account)
+    );
+
+    Ok(())
+}
+
+fn burn(db_dir: &Path, fees: &FeePolicy, args: StakeArgs) -> Result<()> {
+    let mut state = State::open(db_dir)?;
+    let account = Account::new(args.account)?;
+    let tx = Tx::Burn {
+        account: account.clone(),
+        value: args.value,
+        fee: fees.fee(args.fee, state.fee_schedule().min_fee)?,
+        nonce: state.next_nonce(&account),
+    };
+    let signed = sign(db_dir, state.chain_id(), &account, tx)?;
+    let hash = signed.hash();
+
+    append(db_dir, &mut state, signed)?;
+
+    println!("Transaction {} appended", hash);
+    println!(
+        "{}: {}",
+        account,
+        state.get_balance(&account).unwrap_or_default()
+    );
+    println!("Total supply: {}", state.total_supply());
+
+    Ok(())
+}
+
+/// Sign the transaction for the chain with the sender's keystore wallet, if it has one.
+pub fn sign(db_dir: &Path, chain_id: &str, from: &Account, tx: Tx) -> Result<SignedTx> {
+    let keystore = Keystore::open(db_dir);
+
+    if keystore.accounts()?.contains(from) {
+        let password = read_password()?;
+
+        Ok(keystore.load(from, &password)?.sign(&tx, chain_id))
+    } else {
+        Ok(SignedTx::unsigned(tx))
+    }
+}
+
+/// Append a transaction to the chain, recording it to the audit log if it's rejected.
+pub fn append(db_dir: &Path, state: &mut State, tx: SignedTx) -> Result<()> {
+    let hash = tx.hash();
+
+    if let Err(err) = state.add_tx(tx) {
+        // Failing to audit mustn't hide why the transaction was rejected.
+        AuditLog::open(db_dir)
+            .record(&AuditEntry::new(Source::Cli, hash, &err))
+            .ok();
+
+        return Err(err.into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+
+pub fn run(db_dir: &Path) -> Result<()> {
+    let report = chigui_core::verify::verify(db_dir)?;
+
+    println!(
+        "Replayed {} blocks, {} transactions",
+        report.height, report.txs
+    );
+    println!("State root: {}", report.state_root);
+    println!("Total supply: {}", report.total_supply);
+
+    if let Some(offense) = report.offense {
+        match offense.block {
+            Some(number) => println!("Block {} on line {} is invalid:", number, offense.line),
+            None => println!("Line {} is invalid:", offense.line),
+        }
+
+        println!("  {}", offense.error);
+
+        if let Some(source) = std::error::Error::source(&offense.error) {
+            println!("  {}", source);
+        }
+
+        if let Some((number, line)) = offense.rewritten() {
+            println!(
+                "  Block {} on line {} was rewritten, or the parent hash of this one was.",
+                number, line
+            );
+        }
+
+        bail!("Chain verification failed.");
+    }
+
+    println!("Chain is valid");
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+
+use chigui_core::Account;
+use chigui_wallet::{Keystore, Wallet};
+
+use super::{prompt, read_password};
+
+#[derive(Debug, Subcommand)]
+pub enum WalletCommand {
+    /// Generate a new mnemonic-backed key and store it encrypted in the keystore.
+    Create {
+        /// Number of words of the recovery phrase, 12 or 24.
+        #[arg(long, default_value_t = 12)]
+        words: usize,
+    },
+    /// Restore a wallet from its recovery phrase into the keystore.
+    Recover,
+    /// Print the recovery phrase of a keystore wallet.
+    Export { account: String },
+    /// Derive a new account from the recovery phrase of a keystore wallet.
+    Derive {
+        account: String,
+        /// Index of the derived account.
+        #[arg(long)]
+        index: u32,
+    },
+}
+
+pub fn run(db_dir: &Path, command: WalletCommand) -> Result<()> {
+    match command {
+        WalletCommand::Create { words } => create(db_dir, words),
+        WalletCommand::Recover => recover(db_dir),
+        WalletCommand::Export { account } => export(db_dir, &account),
+        WalletCommand::Derive { account, index } => derive(db_dir, &account, index),
+    }
+}
+
+fn create(db_dir: &Path, word_count: usize) -> Result<()> {
+    let wallet = Wallet::create_with_words(word_count)?;
+    let password = read_password()?;
+    let path = Keystore::open(db_dir).save(&wallet, &password)?;
+
+    println!("Created account {}", wallet.account());
+    println!("Key stored in {}", path.display());
+
+    if let Some(mnemonic) = wallet.mnemonic() {
+        println!("Write down your recovery phrase and keep it offline:");
+        println!("{}", mnemonic);
+    }
+
+    Ok(())
+}
+
+fn recover(db_dir: &Path) -> Result<()> {
+    let phrase = prompt("Recovery phrase: ")?;
+    let wallet = Wallet::from_mnemonic(&phrase)?;
+    let password = read_password()?;
+    let path = Keystore::open(db_dir).save(&wallet, &password)?;
+
+    println!("Recovered account {}", wallet.account());
+    println!("Key stored in {}", path.display());
+
+    Ok(())
+}
+
+fn export(db_dir: &Path, account: &str) -> Result<()> {
+    let account = Account::new(account)?;
+    let password = read_password()?;
+    let wallet = Keystore::open(db_dir).load(&account, &password)?;
+    let mnemonic = wallet
+        .mnemonic()
+        .context("This wallet has no recovery phrase.")?;
+
+    println!("{}", mnemonic);
+
+    Ok(())
+}
+
+fn derive(db_dir: &Path, account: &str, index: u32) -> Result<()> {
+    let account = Account::new(account)?;
+    let password = read_password()?;
+    let keystore = Keystore::open(db_dir);
+    let wallet = keystore.load(&account, &password)?.derive(index)?;
+    let path = keystore.save(&wallet, &password)?;
+
+    println!("Derived account {} at {}", wallet.account(), wallet.path());
+    println!("Key stored in {}", path.display());
+
+    Ok(())
+}
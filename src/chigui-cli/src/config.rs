@@ -0,0 +1,335 @@
+use std::error::Error;
+use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// Configuration file read from the working directory when `--config` isn't given.
+pub const DEFAULT_PATH: &str = "chigui.toml";
+/// Chain whose data directory is used when no db dir is configured.
+pub const DEFAULT_CHAIN: &str = "chigui";
+
+/// Settings read from `chigui.toml`, every one of them optional and overridden by its
+/// environment variable, then by its flag.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directory holding `genesis.json`, `block.db` and the wallet keystore, defaults to
+    /// `$XDG_DATA_HOME/chigui/<chain>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub db_dir: Option<PathBuf>,
+    /// Chain ID naming the default db dir.
+    pub chain: String,
+    /// `tracing` filter directives logged to stderr, e.g. `info` or `chigui_node=debug`.
+    pub log_level: String,
+    pub network: NetworkConfig,
+    pub rpc: RpcConfig,
+    pub fees: FeePolicy,
+    /// File the settings were read from, if any.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            db_dir: None,
+            chain: DEFAULT_CHAIN.to_string(),
+            log_level: "warn".to_string(),
+            network: NetworkConfig::default(),
+            rpc: RpcConfig::default(),
+            fees: FeePolicy::default(),
+            source: None,
+        }
+    }
+}
+
+impl Config {
+    /// Read the configuration file at `path`, or `chigui.toml` if it exists, falling back to the
+    /// defaults.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_PATH).exists() => Path::new(DEFAULT_PATH),
+            None => return Ok(Self::default()),
+        };
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}.", path.display()))?;
+        let mut config = Self::parse(&text)
+            .with_context(|| format!("Invalid configuration in {}.", path.display()))?;
+
+        config.source = Some(path.to_path_buf());
+
+        Ok(config)
+    }
+
+    /// Override the settings with the `CHIGUI_*` environment variables among `vars`, e.g.
+    /// `CHIGUI_RPC_ADDR` for `rpc.addr`. `CHIGUI_PEERS` is a comma-separated list.
+    pub fn apply_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+        for (name, value) in vars {
+            let (name, value) = (name.as_str(), value.as_str());
+
+            match name {
+                "CHIGUI_DB_DIR" => self.db_dir = Some(PathBuf::from(value)),
+                "CHIGUI_CHAIN" => self.chain = value.to_string(),
+                "CHIGUI_LOG" => self.log_level = value.to_string(),
+                "CHIGUI_P2P_ADDR" => self.network.p2p_addr = parse_var(name, value)?,
+                "CHIGUI_PEERS" => {
+                    self.network.peers = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|peer| !peer.is_empty())
+                        .map(|peer| parse_var(name, peer))
+                        .collect::<Result<Vec<SocketAddr>>>()?
+                }
+                "CHIGUI_RPC_ADDR" => self.rpc.addr = parse_var(name, value)?,
+                "CHIGUI_RATE_LIMIT" => self.rpc.rate_limit = parse_var(name, value)?,
+                "CHIGUI_BURST" => self.rpc.burst = parse_var(name, value)?,
+                "CHIGUI_MAX_BODY_SIZE" => self.rpc.max_body_size = parse_var(name, value)?,
+                "CHIGUI_TLS_CERT" => self.rpc.tls_cert = Some(PathBuf::from(value)),
+                "CHIGUI_TLS_KEY" => self.rpc.tls_key = Some(PathBuf::from(value)),
+                "CHIGUI_ADMIN_TOKEN_FILE" => self.rpc.admin_token_file = Some(PathBuf::from(value)),
+                "CHIGUI_DEFAULT_FEE" => self.fees.default_fee = Some(parse_var(name, value)?),
+                "CHIGUI_MAX_FEE" => self.fees.max_fee = Some(parse_var(name, value)?),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Per-user directory of the configured chain, `chigui/<chain>` under `$XDG_DATA_HOME` or
+    /// else `~/.local/share`.
+    pub fn default_db_dir(&self) -> Result<PathBuf> {
+        let data_home = data_home(std::env::var_os("XDG_DATA_HOME"), std::env::var_os("HOME"))
+            .context("Failed to find the data directory, set --db-dir or XDG_DATA_HOME.")?;
+
+        chain_dir(&data_home, &self.chain)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Render the settings as they'd be written in `chigui.toml`.
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+}
+
+/// `$XDG_DATA_HOME`, ignored unless absolute as the XDG spec requires, or `$HOME/.local/share`.
+fn data_home(xdg_data_home: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    let absolute = |dir: Option<OsString>| dir.map(PathBuf::from).filter(|dir| dir.is_absolute());
+
+    absolute(xdg_data_home).or_else(|| Some(absolute(home)?.join(".local").join("share")))
+}
+
+fn chain_dir(data_home: &Path, chain: &str) -> Result<PathBuf> {
+    if chain.is_empty() || chain == "." || chain == ".." || chain.contains(['/', '\\']) {
+        bail!("Invalid chain \"{}\", can't name a directory.", chain);
+    }
+
+    Ok(data_home.join("chigui").join(chain))
+}
+
+fn parse_var<T>(name: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Error + Send + Sync + 'static,
+{
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid {} environment variable.", name))
+}
+
+/// Settings of the peer-to-peer sync protocol.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Address the sync protocol listens on.
+    pub p2p_addr: SocketAddr,
+    /// Bootstrap peers, joined by those passed with `--peer`.
+    pub peers: Vec<SocketAddr>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            p2p_addr: SocketAddr::from(([127, 0, 0, 1], 9090)),
+            peers: Vec::new(),
+        }
+    }
+}
+
+/// Settings of the HTTP API, see `chigui node start --help`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcConfig {
+    pub addr: SocketAddr,
+    pub rate_limit: f64,
+    pub burst: u32,
+    pub max_body_size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_token_file: Option<PathBuf>,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            rate_limit: 50.0,
+            burst: 100,
+            max_body_size: 1024 * 1024,
+            tls_cert: None,
+            tls_key: None,
+            admin_token_file: None,
+        }
+    }
+}
+
+/// Fees of the transactions built by the CLI when `--fee` isn't given.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeePolicy {
+    /// Fee paid by default, raised to the chain minimum fee if under it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_fee: Option<u64>,
+    /// Highest fee the CLI agrees to pay, `--fee` included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee: Option<u64>,
+}
+
+impl FeePolicy {
+    /// Fee of a transaction: the requested one, else the default fee, else the chain minimum fee.
+    pub fn fee(&self, requested: Option<u64>, min_fee: u64) -> Result<u64> {
+        let fee = match (requested, self.default_fee) {
+            (Some(fee), _) => fee,
+            (None, Some(fee)) => fee.max(min_fee),
+            (None, None) => min_fee,
+        };
+
+        match self.max_fee {
+            Some(max_fee) if fee > max_fee => {
+                bail!("A fee of {} exceeds the maximum fee of {}.", fee, max_fee)
+            }
+            _ => Ok(fee),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn parses_partial_files() -> Result<()> {
+        let config = Config::parse(
+            r#"
+                db_dir = "/var/lib/chigui"
+
+                [rpc]
+                addr = "0.0.0.0:8545"
+
+                [fees]
+                default_fee = 5
+                max_fee = 10
+            "#,
+        )?;
+
+        assert_eq!(config.db_dir, Some(PathBuf::from("/var/lib/chigui")));
+        assert_eq!(config.log_level, "warn");
+        assert_eq!(config.rpc.addr, SocketAddr::from(([0, 0, 0, 0], 8545)));
+        assert_eq!(config.rpc.burst, 100);
+        assert_eq!(config.network, NetworkConfig::default());
+        assert_eq!(Config::parse(&config.to_toml()?)?, config);
+        assert!(Config::parse("[rpc]\nport = 8545").is_err());
+
+        assert_eq!(config.fees.fee(None, 1)?, 5);
+        assert_eq!(config.fees.fee(None, 7)?, 7);
+        assert_eq!(config.fees.fee(Some(2), 7)?, 2);
+        assert!(config.fees.fee(Some(11), 1).is_err());
+        assert_eq!(FeePolicy::default().fee(None, 3)?, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn overrides_with_environment_variables() -> Result<()> {
+        let mut config = Config::parse("log_level = \"info\"\n[rpc]\nburst = 5")?;
+        let var = |name: &str, value: &str| (name.to_string(), value.to_string());
+
+        config.apply_env([
+            var("CHIGUI_DB_DIR", "/data"),
+            var("CHIGUI_RPC_ADDR", "0.0.0.0:8080"),
+            var("CHIGUI_PEERS", "10.0.0.1:9090, 10.0.0.2:9090"),
+            var("CHIGUI_MAX_FEE", "20"),
+            var("CHIGUI_WALLET_PASSWORD", "hunter2"),
+        ])?;
+
+        assert_eq!(config.db_dir, Some(PathBuf::from("/data")));
+        assert_eq!(config.log_level, "info");
+        assert_eq!(config.rpc.addr, SocketAddr::from(([0, 0, 0, 0], 8080)));
+        assert_eq!(config.rpc.burst, 5);
+        assert_eq!(config.network.peers.len(), 2);
+        assert_eq!(config.fees.max_fee, Some(20));
+
+        let error = config.apply_env([var("CHIGUI_BURST", "many")]).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid CHIGUI_BURST environment variable."
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_data_directories() -> Result<()> {
+        let home = Some(OsString::from("/home/alice"));
+
+        assert_eq!(
+            data_home(Some("/data".into()), home.clone()),
+            Some(PathBuf::from("/data"))
+        );
+        assert_eq!(
+            data_home(Some("relative".into()), home.clone()),
+            Some(PathBuf::from("/home/alice/.local/share"))
+        );
+        assert_eq!(data_home(None, None), None);
+        assert_eq!(
+            chain_dir(Path::new("/data"), "testnet")?,
+            PathBuf::from("/data/chigui/testnet")
+        );
+        assert!(chain_dir(Path::new("/data"), "../etc").is_err());
+        assert!(chain_dir(Path::new("/data"), "..").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn loads_explicit_files() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("node.toml");
+
+        assert!(Config::load(Some(&path)).is_err());
+
+        std::fs::write(&path, "log_level = \"debug\"\n")?;
+
+        let config = Config::load(Some(&path))?;
+
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.source, Some(path));
+
+        Ok(())
+    }
+}
@@ -1,19 +1,288 @@
-use anyhow::Result;
+mod commands;
+mod config;
 
-use chigui_core::state::State;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser, Subcommand};
+use tracing_subscriber::EnvFilter;
+
+use commands::{
+    audit::AuditCommand, config::ConfigCommand, db::DbCommand, escrow::EscrowCommand,
+    export::ExportCommand, governance::GovernanceCommand, import::ImportCommand,
+    multisig::MultisigCommand, node::NodeCommand, script::ScriptCommand, tx::TxCommand,
+    wallet::WalletCommand,
+};
+
+/// Chigüi Coin: a toy blockchain for Web3 introduction.
+#[derive(Debug, Parser)]
+#[command(name = "chigui", version)]
+struct Cli {
+    /// Configuration file, defaults to `CHIGUI_CONFIG` or else `chigui.toml` in the working
+    /// directory if it exists. `CHIGUI_*` environment variables override its settings, e.g.
+    /// `CHIGUI_DB_DIR`, `CHIGUI_RPC_ADDR` or `CHIGUI_LOG`, and flags override both.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Directory holding `genesis.json`, `block.db` and the wallet keystore [default:
+    /// $XDG_DATA_HOME/chigui/<CHAIN>].
+    #[arg(long, global = true)]
+    db_dir: Option<PathBuf>,
+
+    /// Chain whose per-user db dir is used when no db dir is configured [default: chigui].
+    #[arg(long, global = true)]
+    chain: Option<String>,
+
+    /// Log more details to stderr, repeatable, overriding `log_level` of the configuration file,
+    /// `CHIGUI_LOG` and `RUST_LOG`, which itself overrides the other two when set.
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Inspect the log of rejected submissions.
+    #[command(subcommand)]
+    Audit(AuditCommand),
+    /// Print account balances.
+    Balances(commands::balances::BalancesArgs),
+    /// Print the completion script of a shell, e.g. for bash:
+    /// `chigui completions bash > ~/.local/share/bash-completion/completions/chigui`.
+    Completions(commands::completions::CompletionsArgs),
+    /// Inspect the configuration read from `chigui.toml` and the command line.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Maintain the database directory.
+    #[command(subcommand)]
+    Db(DbCommand),
+    /// Show the balance changes between two heights and the transactions behind them.
+    Diff(commands::diff::DiffArgs),
+    /// Create, release and refund escrows.
+    #[command(subcommand)]
+    Escrow(EscrowCommand),
+    /// Export chain data for spreadsheets and other tools.
+    #[command(subcommand)]
+    Export(ExportCommand),
+    /// Propose and vote on changes of chain parameters.
+    #[command(subcommand)]
+    Governance(GovernanceCommand),
+    /// Import chain data from spreadsheets and other tools.
+    #[command(subcommand)]
+    Import(ImportCommand),
+    /// Scaffold a new chain in the database directory.
+    Init(commands::init::InitArgs),
+    /// Propose, approve and execute transfers out of multisig accounts.
+    #[command(subcommand)]
+    Multisig(MultisigCommand),
+    /// Inspect and run the local node.
+    #[command(subcommand)]
+    Node(NodeCommand),
+    /// Run scripts and inspect their storage.
+    #[command(subcommand)]
+    Script(ScriptCommand),
+    /// List and submit transactions.
+    #[command(subcommand)]
+    Tx(TxCommand),
+    /// Replay the whole chain, checking every block, and report the first invalid one.
+    Verify,
+    /// Manage keys in the local keystore.
+    #[command(subcommand)]
+    Wallet(WalletCommand),
+}
 
 fn main() -> Result<()> {
-    let state = State::open("./database")?;
+    let cli = Cli::parse();
+
+    // Completions don't depend on any configuration or chain.
+    if let Command::Completions(args) = cli.command {
+        return commands::completions::run(args);
+    }
+
+    let path = cli
+        .config
+        .or_else(|| env::var_os("CHIGUI_CONFIG").map(PathBuf::from));
+    let mut config = config::Config::load(path.as_deref())?;
+
+    config.apply_env(
+        env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        }),
+    )?;
+
+    if let Some(db_dir) = cli.db_dir {
+        config.db_dir = Some(db_dir);
+    }
+
+    if let Some(chain) = cli.chain {
+        config.chain = chain;
+    }
+
+    let command = prepare(cli.command, &mut config, &mut io::stdin().lock())?;
 
-    for tx in state.txs.iter() {
-        println!("{}", tx);
+    if let Some(level) = verbosity(cli.verbose) {
+        config.log_level = level.to_string();
     }
 
-    let balances = state.balances.borrow();
+    init_tracing(&log_filter(
+        &config.log_level,
+        cli.verbose > 0,
+        env::var("RUST_LOG").ok(),
+    ))?;
 
-    for (account, balance) in balances.iter() {
-        println!("{}: {}", account, balance);
+    // Only showing the configuration doesn't touch the db dir.
+    let uses_db_dir = !matches!(command, Command::Config(_));
+    let db_dir = match &config.db_dir {
+        Some(db_dir) => db_dir.clone(),
+        None => {
+            let db_dir = config.default_db_dir()?;
+
+            if uses_db_dir {
+                fs::create_dir_all(&db_dir)
+                    .with_context(|| format!("Failed to create {}.", db_dir.display()))?;
+            }
+
+            config.db_dir = Some(db_dir.clone());
+            db_dir
+        }
+    };
+
+    if uses_db_dir {
+        eprintln!("Using db dir {}", db_dir.display());
     }
+    let (db_dir, fees) = (db_dir.as_path(), &config.fees);
+
+    match command {
+        Command::Audit(command) => commands::audit::run(db_dir, command),
+        Command::Balances(args) => commands::balances::run(db_dir, args),
+        Command::Completions(_) => unreachable!("completions are printed first"),
+        Command::Config(command) => commands::config::run(&config, command),
+        Command::Db(command) => commands::db::run(db_dir, command),
+        Command::Diff(args) => commands::diff::run(db_dir, args),
+        Command::Escrow(command) => commands::escrow::run(db_dir, fees, command),
+        Command::Export(command) => commands::export::run(db_dir, command),
+        Command::Governance(command) => commands::governance::run(db_dir, fees, command),
+        Command::Import(command) => commands::import::run(db_dir, fees, command),
+        Command::Init(args) => commands::init::run(db_dir, args),
+        Command::Multisig(command) => commands::multisig::run(db_dir, fees, command),
+        Command::Node(command) => commands::node::run(db_dir, &config, command),
+        Command::Script(command) => commands::script::run(db_dir, fees, command),
+        Command::Tx(command) => commands::tx::run(db_dir, fees, command),
+        Command::Verify => commands::verify::run(db_dir),
+        Command::Wallet(command) => commands::wallet::run(db_dir, command),
+    }
+}
+
+/// Prompt on `input` for what `init` wasn't given, so that the chain id it settles on, whether
+/// given or prompted for, picks the db dir of the new chain when none is configured.
+fn prepare(
+    command: Command,
+    config: &mut config::Config,
+    input: &mut impl BufRead,
+) -> Result<Command> {
+    let Command::Init(args) = command else {
+        return Ok(command);
+    };
+    let args = args.prompt_missing(input)?;
+
+    // A new chain gets a db dir of its own.
+    if let Some(chain_id) = args.chain_id() {
+        config.chain = chain_id.to_string();
+    }
+
+    Ok(Command::Init(args))
+}
+
+/// Log level asked for with `-v`, one level up from warnings per flag.
+fn verbosity(verbose: u8) -> Option<&'static str> {
+    match verbose {
+        0 => None,
+        1 => Some("info"),
+        2 => Some("debug"),
+        _ => Some("trace"),
+    }
+}
+
+/// Log filter of the configured `level`, which a non-empty `RUST_LOG` overrides unless `-v` was
+/// given.
+fn log_filter(level: &str, verbose: bool, rust_log: Option<String>) -> String {
+    match rust_log {
+        Some(filter) if !verbose && !filter.trim().is_empty() => filter,
+        _ => level.to_string(),
+    }
+}
+
+/// Log to stderr with the given filter.
+fn init_tracing(level: &str) -> Result<()> {
+    let filter =
+        EnvFilter::try_new(level).with_context(|| format!("Invalid log level \"{}\".", level))?;
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn verify_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn verbose_flag_overrides_rust_log() {
+        let rust_log = || Some("chigui_core=trace".to_string());
+
+        assert_eq!(log_filter("warn", false, None), "warn");
+        assert_eq!(log_filter("warn", false, rust_log()), "chigui_core=trace");
+        assert_eq!(log_filter("warn", false, Some(" ".into())), "warn");
+        assert_eq!(log_filter("debug", true, rust_log()), "debug");
+    }
+
+    #[test]
+    fn prompted_chain_id_picks_the_db_dir() -> Result<()> {
+        let mut config = config::Config::default();
+        let cli = Cli::try_parse_from(["chigui", "--chain", "mainnet", "init"])?;
+        let mut input = "testnet\nalice=10\n\n".as_bytes();
+
+        config.chain = cli.chain.unwrap();
+
+        let Command::Init(args) = prepare(cli.command, &mut config, &mut input)? else {
+            panic!("init command expected");
+        };
+
+        assert_eq!(args.chain_id(), Some("testnet"));
+        assert_eq!(config.chain, "testnet");
+
+        if let Ok(db_dir) = config.default_db_dir() {
+            assert!(db_dir.ends_with("chigui/testnet"));
+        }
+
+        let cli = Cli::try_parse_from(["chigui", "init"])?;
+        let mut input = "\n\n".as_bytes();
+
+        prepare(cli.command, &mut config, &mut input)?;
+
+        assert_eq!(config.chain, "chigui");
+
+        let cli = Cli::try_parse_from(["chigui", "balances"])?;
+
+        prepare(cli.command, &mut config, &mut "unread".as_bytes())?;
+
+        assert_eq!(config.chain, "chigui");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+fn main() {
+    #[cfg(feature = "proto")]
+    proto();
+}
+
+/// Generate the `chigui.v1` protobuf types, parsing the schema with `protox` so that building
+/// doesn't need `protoc`.
+#[cfg(feature = "proto")]
+fn proto() {
+    const ROOT: &str = "../../proto";
+    const SCHEMA: &str = "chigui/v1/chigui.proto";
+
+    println!("cargo:rerun-if-changed={}/{}", ROOT, SCHEMA);
+
+    let files = protox::compile([SCHEMA], [ROOT]).expect("invalid protobuf schema");
+
+    prost_build::Config::new()
+        .compile_fds(files)
+        .expect("failed to generate protobuf types");
+}
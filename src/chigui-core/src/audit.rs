@@ -0,0 +1,161 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Hash;
+use crate::error::{ChiguiError, Result};
+
+/// Where a rejected submission came from.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Cli,
+    /// The node's HTTP API, REST or JSON-RPC.
+    Rpc,
+    /// A block received from a peer.
+    P2p,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Source::Cli => write!(f, "cli"),
+            Source::Rpc => write!(f, "rpc"),
+            Source::P2p => write!(f, "p2p"),
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = ChiguiError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cli" => Ok(Source::Cli),
+            "rpc" => Ok(Source::Rpc),
+            "p2p" => Ok(Source::P2p),
+            _ => Err(ChiguiError::InvalidAuditSource {
+                name: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// A rejected submission, as recorded in the audit log.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Unix time of the rejection, in seconds.
+    pub time: u64,
+    pub source: Source,
+    /// Hash of the rejected transaction, or of the block for [`Source::P2p`].
+    pub hash: Hash,
+    pub reason: String,
+}
+
+impl AuditEntry {
+    /// An entry for a submission rejected now.
+    pub fn new(source: Source, hash: Hash, reason: &ChiguiError) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            time,
+            source,
+            hash,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Append-only log of rejected submissions, persisted as `audit.log` JSONL in the db dir.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn open<P: AsRef<Path>>(dbdir: P) -> Self {
+        Self {
+            path: dbdir.as_ref().join("audit.log"),
+        }
+    }
+
+    pub fn record(&self, entry: &AuditEntry) -> Result<()> {
+        let line = serde_json::to_string(entry)
+            .map_err(|source| ChiguiError::SerializeError { source })?;
+        let io_error = |source| ChiguiError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .map_err(io_error)?;
+
+        file.write_all(format!("{}\n", line).as_bytes())
+            .map_err(io_error)
+    }
+
+    /// Every recorded entry, oldest first.
+    pub fn entries(&self) -> Result<Vec<AuditEntry>> {
+        let log = match std::fs::read_to_string(&self.path) {
+            Ok(log) => log,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => {
+                return Err(ChiguiError::Io {
+                    path: self.path.clone(),
+                    source,
+                });
+            }
+        };
+
+        log.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|source| ChiguiError::AuditParseError {
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_entries() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let log = AuditLog::open(dbdir.path());
+
+        assert!(log.entries()?.is_empty());
+
+        let entry = AuditEntry::new(Source::Rpc, Hash::default(), &ChiguiError::InvalidSignature);
+
+        log.record(&entry)?;
+        log.record(&AuditEntry {
+            source: Source::Cli,
+            ..entry.clone()
+        })?;
+
+        let entries = AuditLog::open(dbdir.path()).entries()?;
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], entry);
+        assert_eq!(entries[0].reason, "Invalid signature.");
+        assert_eq!(entries[1].source, Source::Cli);
+
+        Ok(())
+    }
+}
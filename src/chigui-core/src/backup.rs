@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::Hash;
+use crate::block::Block;
+use crate::error::{ChiguiError, Result};
+use crate::migrate::SCHEMA_VERSION;
+use crate::state::{Genesis, State};
+use crate::storage::{FileStorage, Storage};
+
+/// A whole chain in a single portable document, as returned by [`State::backup`]: the genesis and
+/// every block, along with what the restored chain must end up at.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Backup {
+    /// Schema version of the db dir the chain was exported from.
+    pub version: u32,
+    pub chain_id: String,
+    pub height: u64,
+    /// Hash of the latest block, that of the genesis being [`Hash::default`].
+    pub tip: Hash,
+    pub state_root: Hash,
+    pub genesis: Genesis,
+    pub blocks: Vec<Block>,
+}
+
+impl Backup {
+    /// Replay the blocks from the genesis, checking every one of them and that they lead to the
+    /// recorded tip and state root, then write the chain to the db dir and open it.
+    ///
+    /// Nothing is written unless the whole chain checks out. The db dir is expected to be empty.
+    pub fn restore<P: AsRef<Path>>(self, dbdir: P) -> Result<State> {
+        if self.version > SCHEMA_VERSION {
+            return Err(ChiguiError::UnsupportedSchema {
+                version: self.version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+
+        let replayed =
+            State::from_snapshot(self.genesis.clone(), self.blocks.clone(), None, false)?;
+        let tip = replayed.latest_block().map(Block::hash).unwrap_or_default();
+
+        if replayed.height() != self.height || tip != self.tip {
+            return Err(ChiguiError::BackupMismatch {
+                what: "tip",
+                expected: self.tip,
+                got: tip,
+            });
+        }
+
+        if replayed.state_root() != self.state_root {
+            return Err(ChiguiError::BackupMismatch {
+                what: "state root",
+                expected: self.state_root,
+                got: replayed.state_root(),
+            });
+        }
+
+        let dbdir = dbdir.as_ref();
+
+        self.genesis.write_to(dbdir)?;
+        FileStorage::new(dbdir).replace_blocks(&self.blocks)?;
+
+        let mut state = State::open(dbdir)?;
+
+        state.write_snapshot()?;
+        info!(chain_id = %self.chain_id, height = self.height, "restored chain");
+
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Account, Tx};
+
+    #[test]
+    fn restores_backups() -> Result<()> {
+        let source = tempfile::TempDir::new().unwrap();
+        let target = tempfile::TempDir::new().unwrap();
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )?;
+
+        genesis.write_to(source.path())?;
+
+        let mut state = State::open(source.path())?;
+
+        for value in 1..=3 {
+            state.add_tx(Tx::Generate {
+                to: Account::new("alice")?,
+                value,
+                denom: None,
+            })?;
+        }
+
+        let json = serde_json::to_string(&state.backup()?).unwrap();
+        let backup = serde_json::from_str::<Backup>(&json).unwrap();
+        let mut tampered = backup.clone();
+
+        tampered.state_root = Hash::default();
+        assert!(matches!(
+            tampered.restore(target.path()),
+            Err(ChiguiError::BackupMismatch {
+                what: "state root",
+                ..
+            })
+        ));
+        assert!(!target.path().join("genesis.json").exists());
+
+        let restored = backup.restore(target.path().join("restored"))?;
+
+        assert_eq!(restored.blocks(), state.blocks());
+        assert_eq!(restored.state_root(), state.state_root());
+        assert_eq!(
+            State::open(target.path().join("restored"))?.get_balance(&Account::new("alice")?),
+            Some(6)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::hash::Hash;
+use crate::merkle::{self, MerkleProof};
+use crate::signed::{SignedTx, TxSignature};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockHeader {
+    /// Height of the block, starting at `1` for the first block after genesis.
+    pub number: u64,
+    /// Hash of the previous block header, or [`Hash::default`] for the first block.
+    pub parent_hash: Hash,
+    /// Merkle root of the hashes of the block transactions, see [`merkle::root`].
+    #[serde(default)]
+    pub tx_root: Hash,
+    /// Unix timestamp, in seconds, at which the block was assembled.
+    pub time: u64,
+    /// Proof-of-work nonce found by the [`Miner`](crate::miner::Miner).
+    #[serde(default)]
+    pub nonce: u64,
+}
+
+/// A batch of [`SignedTx`] instances applied atomically on top of its parent block.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub txs: Vec<SignedTx>,
+    /// Signature of the validator who produced the block over its header hash, on
+    /// proof-of-authority chains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seal: Option<TxSignature>,
+}
+
+impl BlockHeader {
+    pub fn hash(&self) -> Hash {
+        Hash::of(self)
+    }
+
+    /// Whether the header hash has at least `difficulty` leading zero bits.
+    pub fn meets_difficulty(&self, difficulty: u32) -> bool {
+        self.hash().leading_zero_bits() >= difficulty
+    }
+}
+
+impl Block {
+    /// Assemble a block, committing to its transactions in the header `tx_root`.
+    pub fn new(mut header: BlockHeader, txs: Vec<SignedTx>) -> Self {
+        header.tx_root = Self::compute_tx_root(&txs);
+
+        Self {
+            header,
+            txs,
+            seal: None,
+        }
+    }
+
+    /// The Merkle root of the transactions actually held by the block.
+    pub fn tx_root(&self) -> Hash {
+        Self::compute_tx_root(&self.txs)
+    }
+
+    /// Gas used by the transactions of the block altogether.
+    pub fn gas(&self) -> u64 {
+        self.txs
+            .iter()
+            .fold(0u64, |gas, signed| gas.saturating_add(signed.tx.gas()))
+    }
+
+    /// Prove the block includes the transaction with the given hash, against the header
+    /// `tx_root`.
+    pub fn prove_tx(&self, hash: &Hash) -> Option<MerkleProof> {
+        let leaves = self.txs.iter().map(SignedTx::hash).collect::<Vec<Hash>>();
+        let index = leaves.iter().position(|leaf| leaf == hash)?;
+
+        MerkleProof::new(&leaves, index)
+    }
+
+    fn compute_tx_root(txs: &[SignedTx]) -> Hash {
+        merkle::root(&txs.iter().map(SignedTx::hash).collect::<Vec<Hash>>())
+    }
+
+    /// The hash of a block is the hash of its header.
+    pub fn hash(&self) -> Hash {
+        self.header.hash()
+    }
+}
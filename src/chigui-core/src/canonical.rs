@@ -0,0 +1,98 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{ChiguiError, Result};
+
+/// Serialize a value as canonical JSON, the bytes hashed by [`Hash::of`](crate::Hash): object keys
+/// sorted by their UTF-8 bytes, numbers in `serde_json`'s fixed formatting (integers in plain
+/// decimal), strings escaped like `serde_json` does and no whitespace.
+///
+/// Unlike the derived serialization, the output doesn't depend on the declaration order of struct
+/// fields, so clients in other languages derive the same digests and reordering fields doesn't
+/// change them.
+pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
+    let value =
+        serde_json::to_value(value).map_err(|source| ChiguiError::SerializeError { source })?;
+    let mut out = String::new();
+
+    write_value(&mut out, &value);
+
+    Ok(out)
+}
+
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    to_string(value).map(String::into_bytes)
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Array(values) => {
+            out.push('[');
+
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+
+                write_value(out, value);
+            }
+
+            out.push(']');
+        }
+        // Sorted explicitly, `serde_json` maps keep insertion order with its `preserve_order`
+        // feature, which any crate of the build may enable.
+        Value::Object(fields) => {
+            let mut fields = fields.iter().collect::<Vec<_>>();
+
+            fields.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+            out.push('{');
+
+            for (index, (key, value)) in fields.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_value(out, value);
+            }
+
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Account, Tx};
+
+    #[test]
+    fn sorts_keys_without_whitespace() -> Result<()> {
+        let tx = Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 10,
+            fee: 1,
+            nonce: 0,
+            memo: Some("café \"42\"".into()),
+            denom: None,
+        };
+
+        assert_eq!(
+            to_string(&tx)?,
+            r#"{"fee":1,"from":"alice","memo":"café \"42\"","nonce":0,"to":"bob","type":"transfer","value":10}"#
+        );
+
+        let reordered = serde_json::from_str::<Value>(
+            r#"{ "value": 10, "type": "transfer", "to": "bob", "nonce": 0,
+                 "memo": "café \"42\"", "from": "alice", "fee": 1 }"#,
+        )
+        .unwrap();
+
+        assert_eq!(to_vec(&reordered)?, to_vec(&tx)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::Tx;
+use crate::block::BlockHeader;
+use crate::error::{ChiguiError, Result};
+use crate::signed::SignedTx;
+
+/// Encode a value as CBOR (RFC 8949) following its serde data model, like its JSON: structs as
+/// maps keyed by field name, and hashes, keys and signatures as the hex strings they serialize to.
+///
+/// CBOR is only an encoding, hashes and signatures are always computed over
+/// [canonical JSON](crate::canonical), so that nodes agree whatever format they exchange.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+
+    ciborium::into_writer(value, &mut out).map_err(|err| ChiguiError::CborSerializeError {
+        reason: err.to_string(),
+    })?;
+
+    Ok(out)
+}
+
+/// Decode a value encoded by [`to_vec`], which must span all of `bytes`.
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let mut reader = bytes;
+    let value = ciborium::from_reader(&mut reader).map_err(|err| match err {
+        ciborium::de::Error::Io(_) => ChiguiError::InvalidCbor {
+            offset: bytes.len(),
+        },
+        ciborium::de::Error::Syntax(offset) => ChiguiError::InvalidCbor { offset },
+        err => ChiguiError::CborParseError {
+            reason: err.to_string(),
+        },
+    })?;
+
+    if !reader.is_empty() {
+        return Err(ChiguiError::InvalidCbor {
+            offset: bytes.len() - reader.len(),
+        });
+    }
+
+    Ok(value)
+}
+
+impl Tx {
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        to_vec(self)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        from_slice(bytes)
+    }
+}
+
+impl SignedTx {
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        to_vec(self)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        from_slice(bytes)
+    }
+}
+
+impl BlockHeader {
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        to_vec(self)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        from_slice(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockHeader;
+    use crate::{Account, Hash};
+
+    #[test]
+    fn round_trips() -> Result<()> {
+        assert_eq!(
+            to_vec(&serde_json::json!({"a": [-1, 500], "b": null}))?,
+            [
+                0xa2, 0x61, b'a', 0x82, 0x20, 0x19, 0x01, 0xf4, 0x61, b'b', 0xf6
+            ]
+        );
+
+        let header = BlockHeader {
+            number: 1,
+            parent_hash: Hash::digest(b"parent"),
+            tx_root: Hash::default(),
+            time: 1_600_000_000,
+            nonce: 0,
+        };
+        let bytes = header.to_cbor()?;
+
+        assert!(bytes.len() < serde_json::to_vec(&header).unwrap().len());
+        assert_eq!(BlockHeader::from_cbor(&bytes)?, header);
+
+        let tx = SignedTx::unsigned(Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 10,
+            fee: 1,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        });
+        let mut bytes = tx.to_cbor()?;
+
+        assert_eq!(SignedTx::from_cbor(&bytes)?, tx);
+
+        bytes.push(0);
+
+        assert!(matches!(
+            SignedTx::from_cbor(&bytes),
+            Err(ChiguiError::InvalidCbor { offset }) if offset == bytes.len() - 1
+        ));
+        assert!(matches!(
+            Tx::from_cbor(&[0x9f]),
+            Err(ChiguiError::InvalidCbor { offset: 1 })
+        ));
+
+        Ok(())
+    }
+}
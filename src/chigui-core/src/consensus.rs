@@ -0,0 +1,122 @@
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+
+use crate::block::Block;
+use crate::error::{ChiguiError, Result};
+use crate::signed::{PublicKey, TxSignature};
+
+/// Validator set declared in [`Genesis`](crate::state::Genesis) for proof-of-authority chains.
+///
+/// Validators take turns producing blocks: block `n` must be sealed by validator `(n - 1) % len`.
+/// An empty set leaves the chain on proof-of-work.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Validators(Vec<PublicKey>);
+
+impl Validators {
+    pub fn new(validators: Vec<PublicKey>) -> Self {
+        Self(validators)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PublicKey> {
+        self.0.iter()
+    }
+
+    /// The validator whose turn it is to seal the block with the given number.
+    pub fn proposer(&self, number: u64) -> Option<&PublicKey> {
+        let turn = number.checked_sub(1)? % self.0.len().max(1) as u64;
+
+        self.0.get(turn as usize)
+    }
+
+    /// Sign the block header with the given validator key, which must be the block's proposer.
+    pub fn seal(&self, block: &mut Block, key: &SigningKey) -> Result<()> {
+        self.check_proposer(block.header.number, &PublicKey::from(key))?;
+
+        block.seal = Some(TxSignature::sign(&block.hash(), key));
+
+        Ok(())
+    }
+
+    /// Ensure the block is sealed by its proposer.
+    pub fn verify(&self, block: &Block) -> Result<()> {
+        let number = block.header.number;
+        let seal = block
+            .seal
+            .as_ref()
+            .ok_or(ChiguiError::MissingSeal { number })?;
+
+        self.check_proposer(number, &seal.public_key)?;
+        seal.verify(&block.hash())
+    }
+
+    fn check_proposer(&self, number: u64, signer: &PublicKey) -> Result<()> {
+        match self.proposer(number) {
+            Some(expected) if expected == signer => Ok(()),
+            Some(expected) => Err(ChiguiError::WrongProposer {
+                number,
+                expected: Box::new(*expected),
+            }),
+            None => Err(ChiguiError::MissingSeal { number }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockHeader;
+    use crate::hash::Hash;
+
+    fn block(number: u64) -> Block {
+        Block::new(
+            BlockHeader {
+                number,
+                parent_hash: Hash::default(),
+                tx_root: Hash::default(),
+                time: 0,
+                nonce: 0,
+            },
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn validators_take_turns() -> Result<()> {
+        let first = SigningKey::from_bytes(&[1; 32]);
+        let second = SigningKey::from_bytes(&[2; 32]);
+        let validators = Validators::new(vec![PublicKey::from(&first), PublicKey::from(&second)]);
+
+        assert_eq!(validators.proposer(3), Some(&PublicKey::from(&first)));
+        assert_eq!(validators.proposer(0), None);
+
+        let mut sealed = block(2);
+        assert!(matches!(
+            validators.seal(&mut sealed, &first),
+            Err(ChiguiError::WrongProposer { number: 2, .. })
+        ));
+        assert!(matches!(
+            validators.verify(&sealed),
+            Err(ChiguiError::MissingSeal { number: 2 })
+        ));
+
+        validators.seal(&mut sealed, &second)?;
+        validators.verify(&sealed)?;
+
+        sealed.header.time = 1;
+        assert!(matches!(
+            validators.verify(&sealed),
+            Err(ChiguiError::InvalidSignature)
+        ));
+
+        Ok(())
+    }
+}
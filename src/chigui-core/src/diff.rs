@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::block::Block;
+use crate::error::Result;
+use crate::state::{Genesis, State};
+use crate::{Account, Hash};
+
+/// Native balance changes between two heights of a chain, as returned by [`State::diff`].
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct StateDiff {
+    pub from_height: u64,
+    pub to_height: u64,
+    /// Accounts whose balance changed, or that transactions touched, by account.
+    pub accounts: BTreeMap<Account, AccountDiff>,
+}
+
+/// How the native balance of an account moved within a [`StateDiff`].
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub before: u64,
+    pub after: u64,
+    /// Transactions naming the account in the blocks that changed its balance, oldest first.
+    ///
+    /// Changes coming from the chain itself, e.g. unbonded stakes or released escrows credited to
+    /// their recipient, have no transaction of their own naming the account.
+    pub txs: Vec<Hash>,
+}
+
+impl AccountDiff {
+    pub fn delta(&self) -> i128 {
+        i128::from(self.after) - i128::from(self.before)
+    }
+}
+
+impl StateDiff {
+    /// Replay the given blocks, the first ones up to `from_height`, then the ones up to
+    /// `to_height` one at a time, recording what each of them changed.
+    pub(crate) fn replay(
+        genesis: Genesis,
+        blocks: &[Block],
+        from_height: u64,
+        to_height: u64,
+    ) -> Result<Self> {
+        let (before, range) = blocks[..to_height as usize].split_at(from_height as usize);
+        let mut state = State::from_snapshot(genesis, before.to_vec(), None, false)?;
+        let mut diff = StateDiff {
+            from_height,
+            to_height,
+            accounts: BTreeMap::new(),
+        };
+
+        for block in range {
+            let previous = state.balances().clone();
+
+            state.replay(block.clone())?;
+
+            for (account, balance) in state.balances() {
+                let touching = block
+                    .txs
+                    .iter()
+                    .filter(|tx| tx.tx.accounts().contains(&account))
+                    .map(|tx| tx.hash())
+                    .collect::<Vec<Hash>>();
+                let before = previous.get(account).copied().unwrap_or_default();
+
+                if before == *balance && touching.is_empty() {
+                    continue;
+                }
+
+                let entry = diff
+                    .accounts
+                    .entry(account.clone())
+                    .or_insert_with(|| AccountDiff {
+                        before,
+                        ..AccountDiff::default()
+                    });
+
+                entry.after = *balance;
+                entry.txs.extend(touching);
+            }
+        }
+
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tx;
+    use crate::error::ChiguiError;
+
+    #[test]
+    fn lists_deltas_and_their_txs() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":0,"carol":5},"fee_collector":"carol","permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let (alice, bob, carol) = (
+            Account::new("alice")?,
+            Account::new("bob")?,
+            Account::new("carol")?,
+        );
+        let transfer = |value, nonce| Tx::Transfer {
+            from: alice.clone(),
+            to: bob.clone(),
+            value,
+            fee: 1,
+            nonce,
+            memo: None,
+            denom: None,
+        };
+
+        state.add_tx(transfer(10, 0))?;
+        state.add_tx(transfer(20, 1))?;
+        state.add_tx(transfer(30, 2))?;
+
+        let diff = state.diff(1, 3)?;
+        let hashes = state.blocks()[1..]
+            .iter()
+            .map(|block| block.txs[0].hash())
+            .collect::<Vec<Hash>>();
+
+        assert_eq!(diff.accounts.len(), 3);
+        assert_eq!(diff.accounts[&alice].before, 89);
+        assert_eq!(diff.accounts[&alice].delta(), -52);
+        assert_eq!(diff.accounts[&alice].txs, hashes);
+        assert_eq!(diff.accounts[&bob].delta(), 50);
+        assert_eq!(diff.accounts[&carol].delta(), 2);
+        assert!(diff.accounts[&carol].txs.is_empty());
+        assert!(state.diff(2, 2)?.accounts.is_empty());
+        assert!(matches!(
+            state.diff(3, 2),
+            Err(ChiguiError::InvalidHeightRange { from: 3, to: 2 })
+        ));
+        assert!(matches!(
+            state.diff(0, 4),
+            Err(ChiguiError::HeightNotFound { height: 4 })
+        ));
+
+        Ok(())
+    }
+}
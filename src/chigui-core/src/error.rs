@@ -0,0 +1,263 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::signed::PublicKey;
+use crate::{Account, Hash};
+
+pub type Result<T> = std::result::Result<T, ChiguiError>;
+
+/// Errors produced while loading, validating or persisting the chain state.
+#[derive(Debug, Error)]
+pub enum ChiguiError {
+    #[error("Invalid account \"{account}\": {reason}.")]
+    InvalidAccount {
+        account: String,
+        reason: &'static str,
+    },
+    #[error("Account \"{account}\" not found.")]
+    AccountNotFound { account: Account },
+    #[error("Insufficient balance on \"{account}\": have {have}, need {need}.")]
+    InsufficientBalance {
+        account: Account,
+        have: u64,
+        need: u64,
+    },
+    #[error("Only {spendable} coins of \"{account}\" have vested, need {need}.")]
+    Unvested {
+        account: Account,
+        spendable: u64,
+        need: u64,
+    },
+    #[error("Insufficient stake on \"{account}\": have {have}, need {need}.")]
+    InsufficientStake {
+        account: Account,
+        have: u64,
+        need: u64,
+    },
+    #[error("Balance of \"{account}\" would overflow.")]
+    Overflow { account: Account },
+    #[error("Total supply would overflow or underflow.")]
+    SupplyOverflow,
+    #[error("Total supply after block {number} is {got}, expected {expected}.")]
+    SupplyMismatch {
+        number: u64,
+        expected: u64,
+        got: u64,
+    },
+    #[error("Block {number} must be sealed by \"{expected}\".")]
+    NotProposer { number: u64, expected: Account },
+    #[error("Invalid nonce for \"{account}\": expected {expected}, got {got}.")]
+    InvalidNonce {
+        account: Account,
+        expected: u64,
+        got: u64,
+    },
+    #[error("Fee {fee} is below the minimum fee of {min_fee}.")]
+    FeeTooLow { fee: u64, min_fee: u64 },
+    #[error("Total supply of {supply} would exceed the maximum supply of {max_supply}.")]
+    MaxSupplyExceeded { supply: u64, max_supply: u64 },
+    #[error("Generated coins must be signed by an authorized minter.")]
+    NotMinter,
+    #[error("\"{account}\" is not the minter admin.")]
+    NotMinterAdmin { account: Account },
+    #[error("Alias \"{name}\" is already registered.")]
+    AliasTaken { name: String },
+    #[error("Unknown alias \"{name}\".")]
+    UnknownAlias { name: String },
+    #[error("\"{account}\" is not a multisig account.")]
+    UnknownMultisig { account: Account },
+    #[error("\"{account}\" is not a member of multisig account \"{multisig}\".")]
+    NotMultisigMember { account: Account, multisig: Account },
+    #[error("Coins of multisig account \"{account}\" only move through approved proposals.")]
+    MultisigRequired { account: Account },
+    #[error("Unknown multisig proposal {id}.")]
+    UnknownProposal { id: Hash },
+    #[error("Multisig proposal {id} has {have} approvals out of the {need} required.")]
+    NotEnoughApprovals { id: Hash, have: usize, need: usize },
+    #[error("Unknown governance proposal {id}, or its voting period is over.")]
+    UnknownParamProposal { id: Hash },
+    #[error("\"{account}\" has no stake to vote with.")]
+    NotStaker { account: Account },
+    #[error("Script ran out of gas, limit {limit}.")]
+    OutOfGas { limit: u64 },
+    #[error("Transaction uses {gas} gas, more than the block gas limit of {limit}.")]
+    TxGasExceeded { gas: u64, limit: u64 },
+    #[error("Block #{number} uses {gas} gas, more than the limit of {limit}.")]
+    BlockGasExceeded { number: u64, gas: u64, limit: u64 },
+    #[error("Script failed at instruction {pc}: {reason}.")]
+    ScriptFailed { pc: usize, reason: String },
+    #[error("Unknown escrow {id}.")]
+    UnknownEscrow { id: Hash },
+    #[error("\"{account}\" may not close escrow {id}.")]
+    NotEscrowParty { account: Account, id: Hash },
+    #[error("Unknown asset \"{denom}\".")]
+    UnknownAsset { denom: String },
+    #[error("Batch transfer from \"{account}\" has no recipients.")]
+    EmptyBatch { account: Account },
+    #[error("Memo of {len} bytes is longer than the maximum of {max}.")]
+    MemoTooLong { len: usize, max: usize },
+    #[error("Transaction {hash} is already pending.")]
+    DuplicateTx { hash: Hash },
+    #[error("A transaction from \"{account}\" with nonce {nonce} is already pending.")]
+    NonceConflict { account: Account, nonce: u64 },
+    #[error("Invalid block number: expected {expected}, got {got}.")]
+    InvalidBlockNumber { expected: u64, got: u64 },
+    #[error("Invalid parent hash for block {number}: expected {expected}, got {got}.")]
+    InvalidParentHash {
+        number: u64,
+        expected: Hash,
+        got: Hash,
+    },
+    #[error("Transaction root of block {number} doesn't match its transactions.")]
+    InvalidTxRoot { number: u64 },
+    #[error("Block header {number} is unknown to the light client.")]
+    UnknownHeader { number: u64 },
+    #[error("Merkle proof of {leaf} doesn't lead to the expected root.")]
+    InvalidProof { leaf: Hash },
+    #[error("Block {number} does not meet the difficulty target of {difficulty} bits.")]
+    InsufficientWork { number: u64, difficulty: u32 },
+    #[error("Block {number} is not sealed by a validator.")]
+    MissingSeal { number: u64 },
+    #[error("Block {number} must be sealed by validator {expected}.")]
+    WrongProposer {
+        number: u64,
+        expected: Box<PublicKey>,
+    },
+    #[error("Mining was cancelled.")]
+    MiningCancelled,
+    #[error("Invalid signature.")]
+    InvalidSignature,
+    #[error("Invalid public key \"{key}\".")]
+    InvalidPublicKey { key: String },
+    #[error("Transfer from \"{account}\" must be signed.")]
+    MissingSignature { account: Account },
+    #[error("Transfer from \"{account}\" is not signed by the account key.")]
+    SignerMismatch { account: Account },
+    #[error("Block {number} doesn't fork off a known block.")]
+    UnknownForkPoint { number: u64 },
+    #[error("Branch forking at block {number} doesn't carry more work than the current chain.")]
+    LighterFork { number: u64 },
+    #[error("Can't roll back {n} transactions, only {txs} are left.")]
+    RollbackTooFar { n: usize, txs: usize },
+    #[error(
+        "Rolling back would split block {number}, its {txs} transactions must be undone together."
+    )]
+    PartialRollback { number: u64, txs: usize },
+    #[error("Transaction is signed for chain \"{got}\", expected \"{expected}\".")]
+    ChainIdMismatch { expected: String, got: String },
+    #[error("Invalid transaction type \"{kind}\".")]
+    InvalidTxKind { kind: String },
+    #[error("Invalid audit source \"{name}\", expected cli, rpc or p2p.")]
+    InvalidAuditSource { name: String },
+    #[error("Invalid hash \"{hash}\".")]
+    InvalidHash { hash: String },
+    #[error("Failed to parse block on line {line}.")]
+    ParseError {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Checksum mismatch on line {line}, the block database is corrupted.")]
+    ChecksumMismatch { line: usize },
+    #[error("Binary record {record} of \"{}\" is corrupted.", path.display())]
+    CorruptedRecord { path: PathBuf, record: usize },
+    #[error("Failed to parse binary record {record} of \"{}\".", path.display())]
+    BinaryParseError {
+        path: PathBuf,
+        record: usize,
+        #[source]
+        source: Box<ChiguiError>,
+    },
+    #[error("Invalid CBOR at byte {offset}.")]
+    InvalidCbor { offset: usize },
+    #[error("Failed to parse CBOR value: {reason}.")]
+    CborParseError { reason: String },
+    #[error("Failed to encode CBOR value: {reason}.")]
+    CborSerializeError { reason: String },
+    #[cfg(feature = "proto")]
+    #[error("Invalid protobuf message: {reason}.")]
+    InvalidProto { reason: String },
+    #[cfg(feature = "proto")]
+    #[error("Failed to decode protobuf message.")]
+    ProtoDecodeError {
+        #[source]
+        source: prost::DecodeError,
+    },
+    #[error("Failed to parse genesis.")]
+    GenesisParseError {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Invalid genesis: {}.", violations.join("; "))]
+    InvalidGenesis { violations: Vec<String> },
+    #[error("Failed to parse the db dir manifest.")]
+    ManifestParseError {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Db dir schema version {version} is newer than the supported version {supported}.")]
+    UnsupportedSchema { version: u32, supported: u32 },
+    #[error("Failed to parse known peers.")]
+    PeersParseError {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Failed to parse audit log entry on line {line}.")]
+    AuditParseError {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Failed to parse snapshot \"{}\".", path.display())]
+    SnapshotParseError {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Snapshot at height {height} doesn't match the chain.")]
+    InvalidSnapshot { height: u64 },
+    #[error("Backup {what} is {expected} but its blocks lead to {got}.")]
+    BackupMismatch {
+        what: &'static str,
+        expected: Hash,
+        got: Hash,
+    },
+    #[error("Height {height} is past the latest block.")]
+    HeightNotFound { height: u64 },
+    #[error("Invalid height range: {from} is past {to}.")]
+    InvalidHeightRange { from: u64, to: u64 },
+    #[error("Balances at height {height} are pruned, open the chain in archival mode.")]
+    HistoryPruned { height: u64 },
+    #[error("Pruned chain is missing its snapshot at height {height}.")]
+    MissingSnapshot { height: u64 },
+    #[error("Failed to serialize transaction.")]
+    SerializeError {
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Failed to parse record at height {height}.")]
+    RecordParseError {
+        height: u64,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[cfg(feature = "sled")]
+    #[error("sled database error.")]
+    Sled {
+        #[source]
+        source: sled::Error,
+    },
+    #[cfg(feature = "rocksdb")]
+    #[error("RocksDB error.")]
+    RocksDb {
+        #[source]
+        source: rocksdb::Error,
+    },
+    #[error("I/O error on \"{}\".", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ChiguiError, Result};
+use crate::{Account, Hash};
+
+/// Coins taken from `from` and held until they're released to `to` or refunded.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Escrow {
+    pub from: Account,
+    pub to: Account,
+    pub value: u64,
+    /// Account allowed to both release and refund the escrow, besides its parties.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arbiter: Option<Account>,
+}
+
+impl Escrow {
+    /// The payer or the arbiter may release the coins to the payee.
+    fn can_release(&self, account: &Account) -> bool {
+        self.from == *account || self.arbiter.as_ref() == Some(account)
+    }
+
+    /// The payee or the arbiter may refund the coins to the payer.
+    fn can_refund(&self, account: &Account) -> bool {
+        self.to == *account || self.arbiter.as_ref() == Some(account)
+    }
+}
+
+/// Open [`Escrow`]s, by the hash of the transaction creating them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Escrows {
+    open: BTreeMap<Hash, Escrow>,
+}
+
+impl Escrows {
+    pub fn get(&self, id: &Hash) -> Option<&Escrow> {
+        self.open.get(id)
+    }
+
+    /// Every open escrow, sorted by id.
+    pub fn iter(&self) -> impl Iterator<Item = (&Hash, &Escrow)> {
+        self.open.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.open.is_empty()
+    }
+
+    pub(crate) fn create(&mut self, id: Hash, escrow: Escrow) {
+        self.open.insert(id, escrow);
+    }
+
+    /// Close an escrow on behalf of `account`, for the caller to credit the payee when
+    /// `release` is set, or the payer otherwise.
+    pub(crate) fn close(&mut self, id: &Hash, account: &Account, release: bool) -> Result<Escrow> {
+        let escrow = self
+            .open
+            .get(id)
+            .ok_or(ChiguiError::UnknownEscrow { id: *id })?;
+        let allowed = if release {
+            escrow.can_release(account)
+        } else {
+            escrow.can_refund(account)
+        };
+
+        if !allowed {
+            return Err(ChiguiError::NotEscrowParty {
+                account: account.clone(),
+                id: *id,
+            });
+        }
+
+        Ok(self.open.remove(id).expect("escrow is open"))
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// Fee policy declared in [`Genesis`](crate::state::Genesis) which every transfer must satisfy.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FeeSchedule {
+    /// Smallest fee a [`Tx::Transfer`](crate::Tx::Transfer) may carry.
+    #[serde(default)]
+    pub min_fee: u64,
+}
+
+impl FeeSchedule {
+    pub fn new(min_fee: u64) -> Self {
+        Self { min_fee }
+    }
+
+    /// Whether the given fee is accepted by this schedule.
+    pub fn accepts(&self, fee: u64) -> bool {
+        fee >= self.min_fee
+    }
+}
@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+use crate::block::Block;
+use crate::signed::SignedTx;
+
+/// Work it takes to produce a block at the given difficulty: the number of hashes expected to be
+/// tried before one has enough leading zero bits. Sealed chains weigh every block the same.
+pub fn block_work(difficulty: u32) -> u128 {
+    1 << difficulty.min(127)
+}
+
+/// Work of a branch of `len` blocks at the given difficulty.
+pub fn branch_work(difficulty: u32, len: usize) -> u128 {
+    block_work(difficulty).saturating_mul(len as u128)
+}
+
+/// The fork choice rule: switch to a competing branch only when it carries more work than the
+/// blocks it replaces, so that ties keep the branch seen first.
+pub fn prefers(current: u128, candidate: u128) -> bool {
+    candidate > current
+}
+
+/// Outcome of switching to a competing branch with [`State::reorg`](crate::state::State::reorg).
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct Reorg {
+    /// Height of the last block both branches share.
+    pub fork_height: u64,
+    /// Blocks of the abandoned branch, oldest first.
+    pub reverted: Vec<Block>,
+    /// Transactions of the abandoned branch missing from the new one, to be queued again.
+    pub reorged_out: Vec<SignedTx>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::error::{ChiguiError, Result};
+    use crate::state::State;
+    use crate::{Account, Tx};
+
+    fn state() -> Result<State> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0,"bob":0},"permissive":true}"#,
+        )?;
+
+        State::in_memory(genesis)
+    }
+
+    fn generate(to: &str, value: u64) -> Result<Tx> {
+        Ok(Tx::Generate {
+            to: Account::new(to)?,
+            value,
+            denom: None,
+        })
+    }
+
+    #[test]
+    fn heavier_branches_win() {
+        assert_eq!(block_work(0), 1);
+        assert_eq!(branch_work(4, 3), 48);
+        assert!(prefers(branch_work(4, 2), branch_work(4, 3)));
+        assert!(!prefers(branch_work(4, 2), branch_work(4, 2)));
+    }
+
+    #[test]
+    fn reorgs_onto_heavier_branch() -> Result<()> {
+        let mut local = state()?;
+        let mut remote = state()?;
+
+        local.add_tx(generate("alice", 5)?)?;
+        remote.add_block(local.blocks()[0].clone())?;
+        local.add_tx(generate("alice", 1)?)?;
+        remote.add_tx(generate("bob", 2)?)?;
+        remote.add_tx(generate("bob", 3)?)?;
+
+        let reorged = Arc::new(Mutex::new(Vec::new()));
+        let recorded = reorged.clone();
+        local.on_tx_reorged(move |tx, number| recorded.lock().unwrap().push((tx.hash(), number)));
+
+        assert!(matches!(
+            local.reorg(remote.blocks()[1..2].to_vec()),
+            Err(ChiguiError::LighterFork { number: 2 })
+        ));
+        assert_eq!(local.height(), 2);
+
+        let abandoned = local.blocks()[1].clone();
+        let reorg = local.reorg(remote.blocks()[1..].to_vec())?;
+
+        assert_eq!(reorg.fork_height, 1);
+        assert_eq!(reorg.reverted, vec![abandoned.clone()]);
+        assert_eq!(reorg.reorged_out, abandoned.txs);
+        assert_eq!(*reorged.lock().unwrap(), vec![(abandoned.txs[0].hash(), 2)]);
+        assert_eq!(local.blocks(), remote.blocks());
+        assert_eq!(local.state_root(), remote.state_root());
+        assert_eq!(local.get_balance(&Account::new("alice")?), Some(5));
+        assert_eq!(local.get_balance(&Account::new("bob")?), Some(5));
+
+        let mut orphan = remote.next_block(Vec::new());
+        orphan.header.number = 7;
+        assert!(matches!(
+            local.reorg(vec![orphan]),
+            Err(ChiguiError::UnknownForkPoint { number: 7 })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use crate::Tx;
+use crate::multisig::MultisigAction;
+
+/// Gas used by every transaction, covering its signature check and nonce update.
+pub const TX_GAS: u64 = 1_000;
+/// Gas used for each account credited by a transaction, e.g. each payment of a batch transfer.
+pub const RECIPIENT_GAS: u64 = 500;
+/// Gas used to create or update an entry kept in the state, e.g. a stake, escrow or alias.
+pub const RECORD_GAS: u64 = 2_000;
+/// Gas used for each byte of memo.
+pub const MEMO_BYTE_GAS: u64 = 10;
+
+impl Tx {
+    /// Gas counted against the [`block_gas_limit`](crate::state::State::block_gas_limit) of the
+    /// block including this transaction.
+    ///
+    /// Scripts count their whole `gas_limit`, however much of it they end up using.
+    pub fn gas(&self) -> u64 {
+        let work = match self {
+            Tx::Transfer { .. } | Tx::Generate { .. } => RECIPIENT_GAS,
+            Tx::TransferMulti { payments, .. } => {
+                RECIPIENT_GAS.saturating_mul(payments.len() as u64)
+            }
+            Tx::TransferLocked { .. } | Tx::TransferVesting { .. } | Tx::EscrowCreate { .. } => {
+                RECIPIENT_GAS + RECORD_GAS
+            }
+            Tx::Multisig {
+                action: MultisigAction::Execute { .. },
+                ..
+            }
+            | Tx::EscrowRelease { .. }
+            | Tx::EscrowRefund { .. } => RECIPIENT_GAS + RECORD_GAS,
+            Tx::Stake { .. }
+            | Tx::Unstake { .. }
+            | Tx::SetMinters { .. }
+            | Tx::RegisterAlias { .. }
+            | Tx::Multisig { .. }
+            | Tx::Propose { .. }
+            | Tx::Vote { .. } => RECORD_GAS,
+            Tx::Script { gas_limit, .. } => *gas_limit,
+            Tx::Burn { .. } => 0,
+        };
+        let memo = self.memo().map_or(0, str::len) as u64;
+
+        TX_GAS
+            .saturating_add(work)
+            .saturating_add(MEMO_BYTE_GAS.saturating_mul(memo))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ChiguiError, Result};
+    use crate::mempool::Mempool;
+    use crate::state::State;
+    use crate::{Account, Payment};
+
+    #[test]
+    fn meters_recipients_and_memos() -> Result<()> {
+        let (alice, bob) = (Account::new("alice")?, Account::new("bob")?);
+        let transfer = Tx::Transfer {
+            from: alice.clone(),
+            to: bob.clone(),
+            value: 1,
+            fee: 0,
+            nonce: 0,
+            memo: Some("invoice 42".into()),
+            denom: None,
+        };
+        let batch = Tx::TransferMulti {
+            from: alice.clone(),
+            payments: vec![
+                Payment {
+                    to: bob.clone(),
+                    value: 1,
+                };
+                3
+            ],
+            fee: 0,
+            nonce: 0,
+        };
+        let burn = Tx::Burn {
+            account: alice,
+            value: 1,
+            fee: 0,
+            nonce: 0,
+        };
+
+        assert_eq!(transfer.gas(), TX_GAS + RECIPIENT_GAS + 10 * MEMO_BYTE_GAS);
+        assert_eq!(batch.gas(), TX_GAS + 3 * RECIPIENT_GAS);
+        assert_eq!(burn.gas(), TX_GAS);
+
+        Ok(())
+    }
+
+    #[test]
+    fn enforces_block_gas_limit() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":100,"carol":0},"block_gas_limit":2000,"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let transfer = |from: &str, fee, memo: Option<&str>| -> Result<Tx> {
+            Ok(Tx::Transfer {
+                from: Account::new(from)?,
+                to: Account::new("carol")?,
+                value: 1,
+                fee,
+                nonce: 0,
+                memo: memo.map(str::to_string),
+                denom: None,
+            })
+        };
+        let mut mempool = Mempool::new();
+
+        assert!(matches!(
+            mempool.insert(&state, transfer("alice", 1, Some(&"x".repeat(200)))?.into()),
+            Err(ChiguiError::TxGasExceeded { limit: 2000, .. })
+        ));
+
+        mempool.insert(&state, transfer("alice", 2, None)?.into())?;
+        mempool.insert(&state, transfer("bob", 1, None)?.into())?;
+
+        let selected = mempool.select(&state, 10);
+
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].tx.fee(), 2);
+
+        let block = state.next_block(mempool.txs().into_iter().cloned().collect());
+
+        assert_eq!(block.gas(), 3000);
+        assert!(matches!(
+            state.add_block(block),
+            Err(ChiguiError::BlockGasExceeded {
+                number: 1,
+                gas: 3000,
+                ..
+            })
+        ));
+        state.add_block(state.next_block(selected))?;
+        assert_eq!(state.height(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ChiguiError, Result};
+use crate::fee::FeeSchedule;
+use crate::{Account, Hash};
+
+/// How [`Tx::Propose`](crate::Tx::Propose) proposals are voted, declared in the genesis.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GovernanceRules {
+    /// Blocks after the proposing one during which stakers may vote.
+    pub voting_period: u64,
+    /// Percentage of the total stake that must vote for the outcome to count.
+    pub quorum: u64,
+    /// Percentage of the voting stake that must approve for a proposal to pass.
+    pub threshold: u64,
+}
+
+impl Default for GovernanceRules {
+    fn default() -> Self {
+        Self {
+            voting_period: 100,
+            quorum: 33,
+            threshold: 50,
+        }
+    }
+}
+
+impl GovernanceRules {
+    /// Whether a proposal passes given the stake approving it, the stake voting, and the total
+    /// stake on the chain.
+    pub fn passes(&self, approving: u64, voting: u64, total: u64) -> bool {
+        let quorum = u128::from(voting) * 100 >= u128::from(total) * u128::from(self.quorum);
+        let threshold =
+            u128::from(approving) * 100 > u128::from(voting) * u128::from(self.threshold);
+
+        voting > 0 && quorum && threshold
+    }
+}
+
+/// Chain parameters passed proposals may change, starting from the genesis values.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Params {
+    pub fee_schedule: FeeSchedule,
+    pub unbonding_period: u64,
+}
+
+impl Params {
+    pub(crate) fn apply(&mut self, change: &ParamChange) {
+        match change {
+            ParamChange::MinFee(min_fee) => self.fee_schedule.min_fee = *min_fee,
+            ParamChange::UnbondingPeriod(period) => self.unbonding_period = *period,
+        }
+    }
+}
+
+/// A change of chain parameter carried out when its proposal passes.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamChange {
+    MinFee(u64),
+    UnbondingPeriod(u64),
+}
+
+/// A parameter change open to stake-weighted votes until `ends_at`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ParamProposal {
+    pub proposer: Account,
+    pub change: ParamChange,
+    /// Last block in which votes are accepted, the proposal is tallied once it's applied.
+    pub ends_at: u64,
+    /// Whether each voter approves, weighted by its stake when the votes are tallied.
+    pub votes: BTreeMap<Account, bool>,
+}
+
+/// Proposals open to votes, by the hash of the transaction proposing them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Governance {
+    open: BTreeMap<Hash, ParamProposal>,
+}
+
+impl Governance {
+    pub fn get(&self, id: &Hash) -> Option<&ParamProposal> {
+        self.open.get(id)
+    }
+
+    /// Every proposal open to votes, sorted by id.
+    pub fn iter(&self) -> impl Iterator<Item = (&Hash, &ParamProposal)> {
+        self.open.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.open.is_empty()
+    }
+
+    pub(crate) fn propose(
+        &mut self,
+        id: Hash,
+        proposer: &Account,
+        change: &ParamChange,
+        ends_at: u64,
+    ) {
+        self.open.insert(
+            id,
+            ParamProposal {
+                proposer: proposer.clone(),
+                change: change.clone(),
+                ends_at,
+                votes: BTreeMap::new(),
+            },
+        );
+    }
+
+    /// Record the vote of an account, replacing its previous one.
+    pub(crate) fn vote(&mut self, id: &Hash, voter: &Account, approve: bool) -> Result<()> {
+        let proposal = self
+            .open
+            .get_mut(id)
+            .ok_or(ChiguiError::UnknownParamProposal { id: *id })?;
+
+        proposal.votes.insert(voter.clone(), approve);
+
+        Ok(())
+    }
+
+    /// Remove and return the proposals whose voting period ends with the given block.
+    pub(crate) fn take_ended(&mut self, height: u64) -> Vec<ParamProposal> {
+        let ended = self
+            .open
+            .iter()
+            .filter(|(_, proposal)| proposal.ends_at <= height)
+            .map(|(id, _)| *id)
+            .collect::<Vec<Hash>>();
+
+        ended.iter().filter_map(|id| self.open.remove(id)).collect()
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use sha2::{Digest, Sha256};
+
+use crate::error::{ChiguiError, Result};
+
+/// A SHA-256 digest identifying a [`Tx`](crate::Tx) or a [`Block`](crate::block::Block).
+///
+/// Hashes are rendered and (de)serialized as lowercase hex strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hash([u8; 32]);
+
+impl Hash {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Compute the SHA-256 digest of the given bytes.
+    pub fn digest(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+
+    /// Compute the SHA-256 digest of the [canonical JSON](crate::canonical) of the given value,
+    /// which every node of a chain must agree on whatever format it's exchanged or stored in.
+    ///
+    /// Only meant for chigui's own data types, whose serialization can't fail.
+    pub(crate) fn of<T: Serialize>(value: &T) -> Self {
+        let bytes = match crate::migrate::LegacyRules::current().json_hashes {
+            true => serde_json::to_vec(value).ok(),
+            false => crate::canonical::to_vec(value).ok(),
+        }
+        .expect("chigui types always serialize to JSON");
+
+        Self::digest(&bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Number of leading zero bits, used as the proof-of-work measure of a block header.
+    pub fn leading_zero_bits(&self) -> u32 {
+        let mut bits = 0;
+
+        for byte in self.0 {
+            bits += byte.leading_zeros();
+
+            if byte != 0 {
+                break;
+            }
+        }
+
+        bits
+    }
+
+    /// Whether this is the all-zero hash used as the parent of the first block.
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 32]
+    }
+}
+
+impl Display for Hash {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl FromStr for Hash {
+    type Err = ChiguiError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut bytes = [0; 32];
+
+        hex::decode_to_slice(s, &mut bytes).map_err(|_| ChiguiError::InvalidHash {
+            hash: s.to_string(),
+        })?;
+
+        Ok(Self(bytes))
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_roundtrip() -> Result<()> {
+        let hash = Hash::digest(b"chigui");
+        let parsed = hash.to_string().parse::<Hash>()?;
+
+        assert_eq!(hash, parsed);
+        assert_eq!(
+            hash.to_string(),
+            "c45de5c8c306d02e25c9ea580f6281de85769be32c68205675e0538180274fb1"
+        );
+        assert!("zz".parse::<Hash>().is_err());
+        assert_eq!(hash.leading_zero_bits(), 0);
+        assert_eq!(Hash::default().leading_zero_bits(), 256);
+        assert_eq!(
+            Hash::new([
+                0, 0x1f, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0
+            ])
+            .leading_zero_bits(),
+            11
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use serde::Serialize;
+
+use crate::block::Block;
+use crate::signed::SignedTx;
+use crate::{Account, Hash};
+
+type TxHook = Box<dyn FnMut(&SignedTx, u64) + Send + Sync>;
+type BalanceHook = Box<dyn FnMut(&Account, u64, u64) + Send + Sync>;
+
+/// A transaction delivered to [`State::subscribe`](crate::state::State::subscribe) receivers.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct AppliedTx {
+    pub block: u64,
+    pub hash: Hash,
+    pub tx: SignedTx,
+    /// Native balances of the accounts the transaction touches once its block is applied.
+    pub balances: BTreeMap<Account, u64>,
+}
+
+/// Callbacks registered on a [`State`](crate::state::State), run once a block is applied and
+/// persisted.
+#[derive(Default)]
+pub struct Hooks {
+    tx_applied: Vec<TxHook>,
+    tx_reorged: Vec<TxHook>,
+    balance_changed: Vec<BalanceHook>,
+    subscribers: Vec<Sender<AppliedTx>>,
+}
+
+impl Debug for Hooks {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("tx_applied", &self.tx_applied.len())
+            .field("tx_reorged", &self.tx_reorged.len())
+            .field("balance_changed", &self.balance_changed.len())
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+impl Hooks {
+    pub(crate) fn on_tx_applied(&mut self, hook: TxHook) {
+        self.tx_applied.push(hook);
+    }
+
+    pub(crate) fn on_tx_reorged(&mut self, hook: TxHook) {
+        self.tx_reorged.push(hook);
+    }
+
+    pub(crate) fn on_balance_changed(&mut self, hook: BalanceHook) {
+        self.balance_changed.push(hook);
+    }
+
+    pub(crate) fn subscribe(&mut self) -> Receiver<AppliedTx> {
+        let (sender, receiver) = mpsc::channel();
+
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Run the hooks for a transaction dropped from the chain by a reorg, given the number of the
+    /// abandoned block that held it.
+    pub(crate) fn notify_reorged(&mut self, tx: &SignedTx, number: u64) {
+        for hook in self.tx_reorged.iter_mut() {
+            hook(tx, number);
+        }
+    }
+
+    /// Run the hooks for a block, given the native balances before and after it.
+    pub(crate) fn notify(
+        &mut self,
+        block: &Block,
+        before: &HashMap<Account, u64>,
+        after: &HashMap<Account, u64>,
+    ) {
+        for tx in block.txs.iter() {
+            for hook in self.tx_applied.iter_mut() {
+                hook(tx, block.header.number);
+            }
+
+            if !self.subscribers.is_empty() {
+                let applied = AppliedTx {
+                    block: block.header.number,
+                    hash: tx.hash(),
+                    tx: tx.clone(),
+                    balances: tx
+                        .tx
+                        .accounts()
+                        .into_iter()
+                        .filter_map(|account| Some((account.clone(), *after.get(account)?)))
+                        .collect(),
+                };
+
+                // Receivers dropped since are forgotten.
+                self.subscribers
+                    .retain(|subscriber| subscriber.send(applied.clone()).is_ok());
+            }
+        }
+
+        if self.balance_changed.is_empty() {
+            return;
+        }
+
+        let mut changed = after
+            .iter()
+            .map(|(account, balance)| {
+                let previous = before.get(account).copied().unwrap_or_default();
+
+                (account, previous, *balance)
+            })
+            .filter(|(_, previous, balance)| previous != balance)
+            .collect::<Vec<(&Account, u64, u64)>>();
+
+        changed.sort();
+
+        for (account, previous, balance) in changed {
+            for hook in self.balance_changed.iter_mut() {
+                hook(account, previous, balance);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Result;
+    use crate::state::State;
+    use crate::{Account, Payment, Tx};
+
+    #[test]
+    fn delivers_applied_txs_to_live_subscribers() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":0,"carol":0},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let (alice, bob, carol) = (
+            Account::new("alice")?,
+            Account::new("bob")?,
+            Account::new("carol")?,
+        );
+        let first = state.subscribe();
+        let dropped = state.subscribe();
+
+        drop(dropped);
+
+        let batch = Tx::TransferMulti {
+            from: alice.clone(),
+            payments: vec![
+                Payment {
+                    to: bob.clone(),
+                    value: 10,
+                },
+                Payment {
+                    to: carol.clone(),
+                    value: 20,
+                },
+            ],
+            fee: 0,
+            nonce: 0,
+        };
+
+        state.add_tx(batch.clone())?;
+
+        let second = state.subscribe();
+
+        state.add_tx(Tx::Burn {
+            account: bob.clone(),
+            value: 5,
+            fee: 0,
+            nonce: 0,
+        })?;
+
+        let applied = first.try_recv().expect("batch delivered");
+
+        assert_eq!((applied.block, applied.hash), (1, batch.hash()));
+        assert_eq!(
+            applied
+                .balances
+                .into_iter()
+                .collect::<Vec<(Account, u64)>>(),
+            vec![(alice, 70), (bob.clone(), 10), (carol, 20)]
+        );
+        assert_eq!(first.try_recv().expect("burn delivered").block, 2);
+        assert!(first.try_recv().is_err());
+
+        let applied = second.try_recv().expect("only the burn delivered");
+
+        assert_eq!(applied.block, 2);
+        assert_eq!(applied.balances.get(&bob), Some(&5));
+        assert!(second.try_recv().is_err());
+
+        Ok(())
+    }
+}
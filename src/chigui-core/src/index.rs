@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Account;
+use crate::block::Block;
+
+/// Where a transaction sits in the chain: the number of its block and its position in the block.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TxIndex {
+    pub block: u64,
+    pub position: usize,
+}
+
+/// The transactions naming each account, oldest first, kept along with the blocks held in memory
+/// so that [`State::txs_for`](crate::state::State::txs_for) doesn't scan the whole history.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AccountIndex(HashMap<Account, Vec<TxIndex>>);
+
+impl AccountIndex {
+    pub(crate) fn get(&self, account: &Account) -> &[TxIndex] {
+        self.0.get(account).map_or(&[], Vec::as_slice)
+    }
+
+    /// Index the transactions of a block appended to the chain.
+    pub(crate) fn add_block(&mut self, block: &Block) {
+        for (position, tx) in block.txs.iter().enumerate() {
+            let index = TxIndex {
+                block: block.header.number,
+                position,
+            };
+            let mut accounts = tx.tx.accounts();
+
+            accounts.sort();
+            accounts.dedup();
+
+            for account in accounts {
+                self.0.entry(account.clone()).or_default().push(index);
+            }
+        }
+    }
+
+    /// Forget the transactions of blocks up to the given number, once they're pruned.
+    pub(crate) fn prune(&mut self, number: u64) {
+        self.0.retain(|_, indexes| {
+            indexes.drain(..indexes.partition_point(|index| index.block <= number));
+            !indexes.is_empty()
+        });
+    }
+
+    /// Forget the transactions of blocks past the given number, once they're reverted.
+    pub(crate) fn truncate(&mut self, number: u64) {
+        self.0.retain(|_, indexes| {
+            indexes.truncate(indexes.partition_point(|index| index.block <= number));
+            !indexes.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tx;
+    use crate::error::Result;
+    use crate::state::State;
+
+    #[test]
+    fn indexes_txs_by_account() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":0,"carol":0},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let (alice, bob, carol) = (
+            Account::new("alice")?,
+            Account::new("bob")?,
+            Account::new("carol")?,
+        );
+        let transfer = |to: &Account, nonce| Tx::Transfer {
+            from: alice.clone(),
+            to: to.clone(),
+            value: 1,
+            fee: 0,
+            nonce,
+            memo: None,
+            denom: None,
+        };
+
+        state.add_tx(transfer(&bob, 0))?;
+        state.add_tx(transfer(&carol, 1))?;
+        state.add_tx(transfer(&alice, 2))?;
+        state.add_block(
+            state.next_block(vec![transfer(&carol, 3).into(), transfer(&bob, 4).into()]),
+        )?;
+
+        let nonces = |account| {
+            state
+                .txs_for(account)
+                .map(|(_, tx)| tx.tx.nonce())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(nonces(&alice).len(), 5);
+        assert_eq!(nonces(&bob), vec![Some(0), Some(4)]);
+        assert_eq!(nonces(&carol), vec![Some(1), Some(3)]);
+        assert_eq!(state.txs_for(&Account::new("dave")?).count(), 0);
+
+        let mut index = AccountIndex::default();
+
+        for block in state.blocks() {
+            index.add_block(block);
+        }
+
+        index.prune(1);
+        assert_eq!(
+            index.get(&bob),
+            [TxIndex {
+                block: 4,
+                position: 1
+            }]
+        );
+        index.truncate(3);
+        assert!(index.get(&bob).is_empty());
+        assert_eq!(
+            index.get(&carol),
+            [TxIndex {
+                block: 2,
+                position: 0
+            }]
+        );
+
+        Ok(())
+    }
+}
@@ -1,8 +1,58 @@
+pub mod audit;
+pub mod backup;
+pub mod block;
+pub mod canonical;
+pub mod cbor;
+pub mod consensus;
+pub mod diff;
+pub mod error;
+pub mod escrow;
+pub mod fee;
+pub mod fork;
+pub mod gas;
+pub mod governance;
+pub mod hash;
+pub mod hooks;
+pub mod index;
+pub mod light;
+pub mod mempool;
+pub mod merkle;
+pub mod migrate;
+pub mod miner;
+pub mod multisig;
+pub mod peers;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod query;
+pub mod script;
+pub mod signed;
+pub mod simulate;
+pub mod snapshot;
+pub mod staking;
 pub mod state;
+pub mod statement;
+pub mod storage;
+pub mod sync;
+pub mod timelock;
+pub mod verify;
+pub mod vesting;
+pub mod view;
 
 use std::fmt::{self, Display, Formatter};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+pub use error::{ChiguiError, Result};
+use governance::ParamChange;
+pub use hash::Hash;
+use multisig::MultisigAction;
+pub use query::TxKind;
+use script::Op;
+use signed::PublicKey;
+use timelock::Unlock;
+
+/// Longest memo a transfer may carry, in bytes.
+pub const MAX_MEMO_LEN: usize = 256;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
@@ -12,40 +62,745 @@ pub enum Tx {
         from: Account,
         to: Account,
         value: u64,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+        /// Free-form reference attached by the sender, e.g. an invoice ID.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+        /// Asset moved, declared in the genesis. The native coin when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        denom: Option<String>,
     },
     Generate {
         to: Account,
         value: u64,
+        /// Asset created, declared in the genesis. The native coin when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        denom: Option<String>,
+    },
+    /// Transfer coins the recipient can only spend once `unlock` is reached.
+    TransferLocked {
+        from: Account,
+        to: Account,
+        value: u64,
+        unlock: Unlock,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Transfer coins that vest for the recipient over `duration` blocks from the including one.
+    TransferVesting {
+        from: Account,
+        to: Account,
+        value: u64,
+        #[serde(default)]
+        cliff: u64,
+        duration: u64,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Debit one sender and credit several recipients at once, paying a single fee.
+    TransferMulti {
+        from: Account,
+        payments: Vec<Payment>,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Lock coins of an account as validator stake.
+    Stake {
+        account: Account,
+        value: u64,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Release staked coins back to the account once the unbonding period is over.
+    Unstake {
+        account: Account,
+        value: u64,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Replace the accounts allowed to sign `Generate` transactions, signed by the genesis
+    /// minter admin.
+    SetMinters {
+        admin: Account,
+        minters: Vec<Account>,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
     },
+    /// Claim a human-readable name resolving to the account.
+    RegisterAlias {
+        account: Account,
+        name: String,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Propose, approve or execute a transfer out of a multisig account, as one of its members.
+    Multisig {
+        account: Account,
+        #[serde(flatten)]
+        action: MultisigAction,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Hold coins of the sender in an escrow identified by the hash of this transaction.
+    EscrowCreate {
+        from: Account,
+        to: Account,
+        value: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        arbiter: Option<Account>,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Pay an escrow to its recipient, signed by its sender or arbiter.
+    EscrowRelease {
+        account: Account,
+        escrow: Hash,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Return an escrow to its sender, signed by its recipient or arbiter.
+    EscrowRefund {
+        account: Account,
+        escrow: Hash,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Propose a change of chain parameter, voted by stakers and carried out if it passes.
+    Propose {
+        account: Account,
+        change: ParamChange,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Vote on a governance proposal with the stake of the account.
+    Vote {
+        account: Account,
+        proposal: Hash,
+        approve: bool,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Run a script reading and writing the storage of the account, failing past `gas_limit`.
+    Script {
+        account: Account,
+        code: Vec<Op>,
+        gas_limit: u64,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+    /// Destroy coins of an account, removing them from the total supply.
+    Burn {
+        account: Account,
+        value: u64,
+        #[serde(default)]
+        fee: u64,
+        nonce: u64,
+    },
+}
+
+impl Tx {
+    pub fn hash(&self) -> Hash {
+        Hash::of(self)
+    }
+
+    pub fn kind(&self) -> TxKind {
+        match self {
+            Tx::Transfer { .. } => TxKind::Transfer,
+            Tx::Generate { .. } => TxKind::Generate,
+            Tx::TransferLocked { .. } => TxKind::TransferLocked,
+            Tx::TransferVesting { .. } => TxKind::TransferVesting,
+            Tx::TransferMulti { .. } => TxKind::TransferMulti,
+            Tx::Stake { .. } => TxKind::Stake,
+            Tx::Unstake { .. } => TxKind::Unstake,
+            Tx::SetMinters { .. } => TxKind::SetMinters,
+            Tx::RegisterAlias { .. } => TxKind::RegisterAlias,
+            Tx::Multisig { .. } => TxKind::Multisig,
+            Tx::EscrowCreate { .. } => TxKind::EscrowCreate,
+            Tx::EscrowRelease { .. } => TxKind::EscrowRelease,
+            Tx::EscrowRefund { .. } => TxKind::EscrowRefund,
+            Tx::Propose { .. } => TxKind::Propose,
+            Tx::Vote { .. } => TxKind::Vote,
+            Tx::Script { .. } => TxKind::Script,
+            Tx::Burn { .. } => TxKind::Burn,
+        }
+    }
+
+    /// The amount of coins moved or created by this transaction.
+    pub fn value(&self) -> u64 {
+        match self {
+            Tx::Transfer { value, .. }
+            | Tx::Generate { value, .. }
+            | Tx::Stake { value, .. }
+            | Tx::Unstake { value, .. }
+            | Tx::Burn { value, .. }
+            | Tx::TransferLocked { value, .. }
+            | Tx::TransferVesting { value, .. }
+            | Tx::EscrowCreate { value, .. } => *value,
+            Tx::TransferMulti { payments, .. } => payments
+                .iter()
+                .fold(0u64, |total, payment| total.saturating_add(payment.value)),
+            Tx::Multisig {
+                action: MultisigAction::Propose { value, .. },
+                ..
+            } => *value,
+            Tx::SetMinters { .. }
+            | Tx::RegisterAlias { .. }
+            | Tx::Multisig { .. }
+            | Tx::EscrowRelease { .. }
+            | Tx::EscrowRefund { .. }
+            | Tx::Propose { .. }
+            | Tx::Vote { .. }
+            | Tx::Script { .. } => 0,
+        }
+    }
+
+    /// The fee paid by this transaction, `0` for generated coins.
+    pub fn fee(&self) -> u64 {
+        match self {
+            Tx::Transfer { fee, .. }
+            | Tx::Stake { fee, .. }
+            | Tx::Unstake { fee, .. }
+            | Tx::Burn { fee, .. }
+            | Tx::TransferMulti { fee, .. }
+            | Tx::SetMinters { fee, .. }
+            | Tx::RegisterAlias { fee, .. }
+            | Tx::Multisig { fee, .. }
+            | Tx::TransferLocked { fee, .. }
+            | Tx::TransferVesting { fee, .. }
+            | Tx::EscrowCreate { fee, .. }
+            | Tx::EscrowRelease { fee, .. }
+            | Tx::EscrowRefund { fee, .. }
+            | Tx::Propose { fee, .. }
+            | Tx::Vote { fee, .. }
+            | Tx::Script { fee, .. } => *fee,
+            Tx::Generate { .. } => 0,
+        }
+    }
+
+    /// The account signing this transaction and paying its fee, `None` for generated coins.
+    pub fn sender(&self) -> Option<&Account> {
+        match self {
+            Tx::Transfer { from, .. }
+            | Tx::TransferMulti { from, .. }
+            | Tx::TransferLocked { from, .. }
+            | Tx::TransferVesting { from, .. }
+            | Tx::EscrowCreate { from, .. } => Some(from),
+            Tx::SetMinters { admin, .. } => Some(admin),
+            Tx::Stake { account, .. }
+            | Tx::Unstake { account, .. }
+            | Tx::Burn { account, .. }
+            | Tx::RegisterAlias { account, .. }
+            | Tx::Multisig { account, .. }
+            | Tx::EscrowRelease { account, .. }
+            | Tx::EscrowRefund { account, .. }
+            | Tx::Propose { account, .. }
+            | Tx::Vote { account, .. }
+            | Tx::Script { account, .. } => Some(account),
+            Tx::Generate { .. } => None,
+        }
+    }
+
+    /// The sender nonce carried by this transaction, `None` for generated coins.
+    pub fn nonce(&self) -> Option<u64> {
+        match self {
+            Tx::Transfer { nonce, .. }
+            | Tx::Stake { nonce, .. }
+            | Tx::Unstake { nonce, .. }
+            | Tx::Burn { nonce, .. }
+            | Tx::TransferMulti { nonce, .. }
+            | Tx::SetMinters { nonce, .. }
+            | Tx::RegisterAlias { nonce, .. }
+            | Tx::Multisig { nonce, .. }
+            | Tx::TransferLocked { nonce, .. }
+            | Tx::TransferVesting { nonce, .. }
+            | Tx::EscrowCreate { nonce, .. }
+            | Tx::EscrowRelease { nonce, .. }
+            | Tx::EscrowRefund { nonce, .. }
+            | Tx::Propose { nonce, .. }
+            | Tx::Vote { nonce, .. }
+            | Tx::Script { nonce, .. } => Some(*nonce),
+            Tx::Generate { .. } => None,
+        }
+    }
+
+    /// Coins taken from the sender's balance when this transaction is applied, fee included.
+    pub fn cost(&self) -> u64 {
+        match self {
+            Tx::Transfer {
+                fee,
+                denom: Some(_),
+                ..
+            } => *fee,
+            Tx::Transfer { value, fee, .. }
+            | Tx::Stake { value, fee, .. }
+            | Tx::Burn { value, fee, .. }
+            | Tx::TransferLocked { value, fee, .. }
+            | Tx::TransferVesting { value, fee, .. }
+            | Tx::EscrowCreate { value, fee, .. } => value.saturating_add(*fee),
+            Tx::TransferMulti { fee, .. } => self.value().saturating_add(*fee),
+            Tx::SetMinters { fee, .. }
+            | Tx::RegisterAlias { fee, .. }
+            | Tx::Multisig { fee, .. }
+            | Tx::Unstake { fee, .. }
+            | Tx::EscrowRelease { fee, .. }
+            | Tx::EscrowRefund { fee, .. }
+            | Tx::Propose { fee, .. }
+            | Tx::Vote { fee, .. }
+            | Tx::Script { fee, .. } => *fee,
+            Tx::Generate { .. } => 0,
+        }
+    }
+
+    /// The memo attached to a transfer, if any.
+    pub fn memo(&self) -> Option<&str> {
+        match self {
+            Tx::Transfer { memo, .. } => memo.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The asset moved or created by this transaction, `None` for the native coin.
+    pub fn denom(&self) -> Option<&str> {
+        match self {
+            Tx::Transfer { denom, .. } | Tx::Generate { denom, .. } => denom.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Reject memos longer than [`MAX_MEMO_LEN`].
+    pub fn check_memo(&self) -> Result<()> {
+        match self.memo() {
+            Some(memo) if memo.len() > MAX_MEMO_LEN => Err(ChiguiError::MemoTooLong {
+                len: memo.len(),
+                max: MAX_MEMO_LEN,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every account whose balance is touched by this transaction, fee collectors aside.
+    pub fn accounts(&self) -> Vec<&Account> {
+        match self {
+            Tx::Transfer { from, to, .. }
+            | Tx::TransferLocked { from, to, .. }
+            | Tx::TransferVesting { from, to, .. }
+            | Tx::EscrowCreate { from, to, .. } => vec![from, to],
+            Tx::Generate { to, .. } => vec![to],
+            Tx::SetMinters { admin, .. } => vec![admin],
+            Tx::TransferMulti { from, payments, .. } => std::iter::once(from)
+                .chain(payments.iter().map(|payment| &payment.to))
+                .collect(),
+            Tx::Stake { account, .. }
+            | Tx::Unstake { account, .. }
+            | Tx::Burn { account, .. }
+            | Tx::RegisterAlias { account, .. }
+            | Tx::EscrowRelease { account, .. }
+            | Tx::EscrowRefund { account, .. }
+            | Tx::Propose { account, .. }
+            | Tx::Vote { account, .. }
+            | Tx::Script { account, .. } => vec![account],
+            Tx::Multisig {
+                account,
+                action: MultisigAction::Propose { multisig, to, .. },
+                ..
+            } => vec![account, multisig, to],
+            Tx::Multisig { account, .. } => vec![account],
+        }
+    }
 }
 
 impl Display for Tx {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            Tx::Transfer { from, to, value } => {
+            Tx::Transfer {
+                from,
+                to,
+                value,
+                fee,
+                nonce,
+                memo,
+                denom,
+            } => {
+                write!(
+                    f,
+                    "[TXN] \"{}\" transferred \"{}\" {} to \"{}\" account (fee {}, nonce {})",
+                    from,
+                    value,
+                    denom.as_deref().unwrap_or("coins"),
+                    to,
+                    fee,
+                    nonce
+                )?;
+
+                match memo {
+                    Some(memo) => write!(f, " memo {:?}", memo),
+                    None => Ok(()),
+                }
+            }
+            Tx::TransferLocked {
+                from,
+                to,
+                value,
+                unlock,
+                fee,
+                nonce,
+            } => {
                 write!(
                     f,
-                    "[TXN] \"{}\" transferred \"{}\" coins to \"{}\" account",
+                    "[TXL] \"{}\" transferred \"{}\" coins to \"{}\" account, locked until ",
                     from, value, to
+                )?;
+
+                match unlock {
+                    Unlock::Height(height) => write!(f, "block {}", height)?,
+                    Unlock::Time(time) => write!(f, "time {}", time)?,
+                }
+
+                write!(f, " (fee {}, nonce {})", fee, nonce)
+            }
+            Tx::TransferVesting {
+                from,
+                to,
+                value,
+                cliff,
+                duration,
+                fee,
+                nonce,
+            } => write!(
+                f,
+                "[TXV] \"{}\" transferred \"{}\" coins to \"{}\" account, vesting over {} blocks after a {} blocks cliff (fee {}, nonce {})",
+                from, value, to, duration, cliff, fee, nonce
+            ),
+            Tx::Generate { to, value, denom } => {
+                write!(
+                    f,
+                    "[GEN] generated \"{}\" {} on \"{}\" account",
+                    value,
+                    denom.as_deref().unwrap_or("coins"),
+                    to
                 )
             }
-            Tx::Generate { to, value } => {
+            Tx::TransferMulti {
+                from,
+                payments,
+                fee,
+                nonce,
+            } => {
                 write!(
                     f,
-                    "[GEN] generated \"{}\" coins on \"{}\" account",
-                    value, to
+                    "[TXM] \"{}\" transferred \"{}\" coins to",
+                    from,
+                    self.value()
+                )?;
+
+                for (i, payment) in payments.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+
+                    write!(f, "{}\"{}\" ({})", separator, payment.to, payment.value)?;
+                }
+
+                write!(f, " (fee {}, nonce {})", fee, nonce)
+            }
+            Tx::Stake {
+                account,
+                value,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[STK] \"{}\" staked \"{}\" coins (fee {}, nonce {})",
+                    account, value, fee, nonce
+                )
+            }
+            Tx::Unstake {
+                account,
+                value,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[UNS] \"{}\" unstaked \"{}\" coins (fee {}, nonce {})",
+                    account, value, fee, nonce
+                )
+            }
+            Tx::SetMinters {
+                admin,
+                minters,
+                fee,
+                nonce,
+            } => {
+                write!(f, "[MNT] \"{}\" set minters to", admin)?;
+
+                for (i, minter) in minters.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+
+                    write!(f, "{}\"{}\"", separator, minter)?;
+                }
+
+                write!(f, " (fee {}, nonce {})", fee, nonce)
+            }
+            Tx::RegisterAlias {
+                account,
+                name,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[ALS] \"{}\" registered alias \"{}\" (fee {}, nonce {})",
+                    account, name, fee, nonce
+                )
+            }
+            Tx::Multisig {
+                account,
+                action,
+                fee,
+                nonce,
+            } => {
+                match action {
+                    MultisigAction::Propose {
+                        multisig,
+                        to,
+                        value,
+                    } => write!(
+                        f,
+                        "[MSG] \"{}\" proposed sending \"{}\" coins from \"{}\" to \"{}\"",
+                        account, value, multisig, to
+                    )?,
+                    MultisigAction::Approve { proposal } => {
+                        write!(f, "[MSG] \"{}\" approved proposal {}", account, proposal)?
+                    }
+                    MultisigAction::Execute { proposal } => {
+                        write!(f, "[MSG] \"{}\" executed proposal {}", account, proposal)?
+                    }
+                }
+
+                write!(f, " (fee {}, nonce {})", fee, nonce)
+            }
+            Tx::EscrowCreate {
+                from,
+                to,
+                value,
+                arbiter,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[ESC] \"{}\" escrowed \"{}\" coins for \"{}\" account",
+                    from, value, to
+                )?;
+
+                if let Some(arbiter) = arbiter {
+                    write!(f, ", arbitrated by \"{}\"", arbiter)?;
+                }
+
+                write!(f, " (fee {}, nonce {})", fee, nonce)
+            }
+            Tx::EscrowRelease {
+                account,
+                escrow,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[ESR] \"{}\" released escrow {} (fee {}, nonce {})",
+                    account, escrow, fee, nonce
+                )
+            }
+            Tx::EscrowRefund {
+                account,
+                escrow,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[ESF] \"{}\" refunded escrow {} (fee {}, nonce {})",
+                    account, escrow, fee, nonce
+                )
+            }
+            Tx::Propose {
+                account,
+                change,
+                fee,
+                nonce,
+            } => {
+                write!(f, "[GOV] \"{}\" proposed setting ", account)?;
+
+                match change {
+                    ParamChange::MinFee(min_fee) => write!(f, "the minimum fee to {}", min_fee)?,
+                    ParamChange::UnbondingPeriod(period) => {
+                        write!(f, "the unbonding period to {} blocks", period)?
+                    }
+                }
+
+                write!(f, " (fee {}, nonce {})", fee, nonce)
+            }
+            Tx::Vote {
+                account,
+                proposal,
+                approve,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[VOT] \"{}\" voted {} proposal {} (fee {}, nonce {})",
+                    account,
+                    if *approve { "for" } else { "against" },
+                    proposal,
+                    fee,
+                    nonce
+                )
+            }
+            Tx::Script {
+                account,
+                code,
+                gas_limit,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[SCR] \"{}\" ran a script of {} instructions (gas limit {}, fee {}, nonce {})",
+                    account,
+                    code.len(),
+                    gas_limit,
+                    fee,
+                    nonce
+                )
+            }
+            Tx::Burn {
+                account,
+                value,
+                fee,
+                nonce,
+            } => {
+                write!(
+                    f,
+                    "[BRN] \"{}\" burned \"{}\" coins (fee {}, nonce {})",
+                    account, value, fee, nonce
                 )
             }
         }
     }
 }
 
+/// One recipient of a [`Tx::TransferMulti`].
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Payment {
+    pub to: Account,
+    pub value: u64,
+}
+
+/// Prefix of account identifiers derived from a public key.
+const DERIVED_ACCOUNT_PREFIX: &str = "0x";
+
+/// Number of hash bytes kept in a derived account identifier.
+const DERIVED_ACCOUNT_LEN: usize = 20;
+
+/// Longest free-form account name, in bytes.
+pub const MAX_ACCOUNT_NAME_LEN: usize = 64;
+
+/// An account identifier.
+///
+/// Accounts are either derived from the signer's public key (`0x` followed by 40 hex digits, see
+/// [`Account::from_public_key`]) or free-form names, which are kept for dev chains.
+///
+/// Identifiers are normalized, trimmed and lowercased, so that `"Alice "` and `"alice"` are the
+/// same account. Names are made of ASCII letters, digits, `_`, `-` and `.`, up to
+/// [`MAX_ACCOUNT_NAME_LEN`] bytes.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Account(String);
 
 impl Account {
-    pub fn new<S: Into<String>>(s: S) -> Self {
-        Self(s.into())
+    pub fn new<S: Into<String>>(s: S) -> Result<Self> {
+        Self::parse(&s.into())
+    }
+
+    /// Normalize and validate an account identifier.
+    pub fn parse(s: &str) -> Result<Self> {
+        let account = s.trim().to_ascii_lowercase();
+        let invalid = |reason| {
+            Err(ChiguiError::InvalidAccount {
+                account: s.to_string(),
+                reason,
+            })
+        };
+
+        if account.is_empty() {
+            return invalid("account names can't be empty");
+        }
+
+        if let Some(digest) = account.strip_prefix(DERIVED_ACCOUNT_PREFIX) {
+            let well_formed = digest.len() == DERIVED_ACCOUNT_LEN * 2
+                && digest.chars().all(|c| c.is_ascii_hexdigit());
+
+            if !well_formed {
+                return invalid("derived accounts must be 40 hex digits");
+            }
+        } else if account.len() > MAX_ACCOUNT_NAME_LEN {
+            return invalid("account names can't be longer than 64 bytes");
+        } else if !account
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            return invalid("account names may only hold letters, digits, '_', '-' and '.'");
+        }
+
+        Ok(Self(account))
+    }
+
+    /// Derive the account controlled by the given public key from the first 20 bytes of its hash.
+    pub fn from_public_key(public_key: &PublicKey) -> Self {
+        let digest = Hash::digest(public_key.as_bytes());
+
+        Self(format!(
+            "{}{}",
+            DERIVED_ACCOUNT_PREFIX,
+            hex::encode(&digest.as_bytes()[..DERIVED_ACCOUNT_LEN])
+        ))
+    }
+
+    /// Whether this account is derived from a public key rather than a free-form name.
+    pub fn is_derived(&self) -> bool {
+        self.0.starts_with(DERIVED_ACCOUNT_PREFIX)
+    }
+}
+
+impl TryFrom<&str> for Account {
+    type Error = ChiguiError;
+
+    fn try_from(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl std::str::FromStr for Account {
+    type Err = ChiguiError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
     }
 }
 
@@ -54,3 +809,58 @@ impl Display for Account {
         write!(f, "{}", self.0)
     }
 }
+
+impl<'de> Deserialize<'de> for Account {
+    /// Accounts deserialize either from their string form or from `{ "public_key": "<hex>" }`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Name(String),
+            PublicKey { public_key: PublicKey },
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Name(name) => Account::new(name).map_err(de::Error::custom),
+            Repr::PublicKey { public_key } => Ok(Account::from_public_key(&public_key)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derived_accounts() -> Result<()> {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let public_key = PublicKey::from(&key);
+        let account = Account::from_public_key(&public_key);
+
+        assert!(account.is_derived());
+        assert_eq!(Account::new(account.to_string())?, account);
+
+        let json = format!(r#"{{"public_key":"{}"}}"#, public_key);
+        assert_eq!(serde_json::from_str::<Account>(&json).unwrap(), account);
+        assert_eq!(
+            serde_json::from_str::<Account>(r#""alice""#).unwrap(),
+            Account::new("alice")?
+        );
+
+        assert!(Account::new("").is_err());
+        assert!(Account::new("0x1234").is_err());
+        assert_eq!(Account::parse(" Alice ")?, Account::new("alice")?);
+        assert_eq!(Account::try_from("BOB.eth")?, Account::new("bob.eth")?);
+        assert_eq!(
+            Account::new(account.to_string().to_uppercase().replace("0X", "0x"))?,
+            account
+        );
+        assert!(Account::new("al ice").is_err());
+        assert!(Account::new("alice!").is_err());
+        assert!(Account::new("a".repeat(MAX_ACCOUNT_NAME_LEN + 1)).is_err());
+        assert!(Account::new("a".repeat(MAX_ACCOUNT_NAME_LEN)).is_ok());
+        assert!(serde_json::from_str::<Account>(r#""0xZZ""#).is_err());
+
+        Ok(())
+    }
+}
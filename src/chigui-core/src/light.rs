@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Account;
+use crate::block::{Block, BlockHeader};
+use crate::consensus::Validators;
+use crate::error::{ChiguiError, Result};
+use crate::hash::Hash;
+use crate::merkle::MerkleProof;
+use crate::signed::{SignedTx, TxSignature};
+use crate::state::{Genesis, State};
+
+/// Follows a chain through its block headers alone, then checks Merkle proofs against them, so
+/// that thin clients can trust a node's answers without holding the blocks or the state.
+///
+/// Headers are checked like [`State::add_block`] does, bar the transactions: numbers, parent
+/// hashes, proof of work and, on proof-of-authority chains, validator seals. Proof-of-stake
+/// proposers depend on stakes the client doesn't track, only their seal signature is checked.
+#[derive(Clone, Debug)]
+pub struct LightClient {
+    difficulty: u32,
+    validators: Validators,
+    proof_of_stake: bool,
+    headers: Vec<BlockHeader>,
+}
+
+impl LightClient {
+    /// Follow the chain starting from the given genesis.
+    pub fn new(genesis: &Genesis) -> Self {
+        Self {
+            difficulty: genesis.difficulty(),
+            validators: genesis.validators().clone(),
+            proof_of_stake: genesis.proof_of_stake(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Number of headers on top of the genesis.
+    pub fn height(&self) -> u64 {
+        self.headers.len() as u64
+    }
+
+    pub fn header(&self, number: u64) -> Option<&BlockHeader> {
+        let index = number.checked_sub(1)?;
+
+        self.headers.get(usize::try_from(index).ok()?)
+    }
+
+    /// Check the header of the next block, along with its seal on sealed chains, and append it.
+    pub fn add_header(&mut self, header: BlockHeader, seal: Option<TxSignature>) -> Result<()> {
+        let number = header.number;
+        let (expected, parent_hash) = self
+            .headers
+            .last()
+            .map_or((1, Hash::default()), |last| (last.number + 1, last.hash()));
+
+        if number != expected {
+            return Err(ChiguiError::InvalidBlockNumber {
+                expected,
+                got: number,
+            });
+        }
+
+        if header.parent_hash != parent_hash {
+            return Err(ChiguiError::InvalidParentHash {
+                number,
+                expected: parent_hash,
+                got: header.parent_hash,
+            });
+        }
+
+        let block = Block {
+            header,
+            txs: Vec::new(),
+            seal,
+        };
+
+        match &block.seal {
+            _ if !self.validators.is_empty() => self.validators.verify(&block)?,
+            Some(seal) if self.proof_of_stake => seal.verify(&block.hash())?,
+            _ if !block.header.meets_difficulty(self.difficulty) => {
+                return Err(ChiguiError::InsufficientWork {
+                    number,
+                    difficulty: self.difficulty,
+                });
+            }
+            _ => {}
+        }
+
+        self.headers.push(block.header);
+
+        Ok(())
+    }
+
+    /// Check then append the headers of the given blocks, stopping at the first invalid one.
+    pub fn add_blocks<'a>(&mut self, blocks: impl IntoIterator<Item = &'a Block>) -> Result<()> {
+        for block in blocks {
+            self.add_header(block.header.clone(), block.seal)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that the block with the given number includes the transaction, see
+    /// [`Block::prove_tx`].
+    pub fn verify_tx(&self, number: u64, tx: &SignedTx, proof: &MerkleProof) -> Result<()> {
+        let header = self
+            .header(number)
+            .ok_or(ChiguiError::UnknownHeader { number })?;
+
+        verify_proof(proof, tx.hash(), &header.tx_root)
+    }
+
+    /// Check a balance proof against the state committed to by `state_root`.
+    ///
+    /// Headers don't commit to state roots, which must come from a source the client trusts, e.g.
+    /// several nodes agreeing on the root at a given height.
+    pub fn verify_balance(&self, proof: &BalanceProof, state_root: &Hash) -> Result<()> {
+        verify_proof(&proof.proof, proof.leaf(), state_root)
+    }
+}
+
+/// Proof that an account holds a native balance, as returned by
+/// [`State::prove_balance`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BalanceProof {
+    pub account: Account,
+    pub balance: u64,
+    /// Height of the chain whose state root the proof leads to.
+    pub height: u64,
+    pub proof: MerkleProof,
+}
+
+impl BalanceProof {
+    /// Whether the proof commits to the account balance and leads to the given state root.
+    pub fn verify(&self, state_root: &Hash) -> bool {
+        self.proof.leaf == self.leaf() && self.proof.verify(state_root)
+    }
+
+    fn leaf(&self) -> Hash {
+        State::balance_leaf(&self.account, self.balance)
+    }
+}
+
+fn verify_proof(proof: &MerkleProof, leaf: Hash, root: &Hash) -> Result<()> {
+    if proof.leaf != leaf || !proof.verify(root) {
+        return Err(ChiguiError::InvalidProof { leaf });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tx;
+
+    #[test]
+    fn proves_balances() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"carol":3,"alice":1,"bob":2},"assets":{"usd":{"alice":9}},"permissive":true}"#,
+        )?;
+        let state = State::in_memory(genesis)?;
+        let root = state.state_root();
+
+        for (name, balance) in [("alice", 1), ("bob", 2), ("carol", 3)] {
+            let proof = state.prove_balance(&Account::new(name)?)?;
+
+            assert_eq!(proof.balance, balance);
+            assert!(proof.verify(&root));
+        }
+
+        let mut forged = state.prove_balance(&Account::new("bob")?)?;
+        forged.account = Account::new("carol")?;
+        assert!(!forged.verify(&root));
+        assert!(matches!(
+            state.prove_balance(&Account::new("dave")?),
+            Err(ChiguiError::AccountNotFound { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn follows_headers_and_checks_proofs() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0,"bob":7},"permissive":true,"difficulty":4}"#,
+        )?;
+        let mut state = State::in_memory(genesis.clone())?;
+        let alice = Account::new("alice")?;
+
+        for value in 1..=3 {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+        }
+
+        let mut client = LightClient::new(&genesis);
+        let mut unmined = state.blocks()[0].clone();
+
+        while unmined.header.meets_difficulty(4) {
+            unmined.header.nonce += 1;
+        }
+
+        assert!(matches!(
+            client.add_blocks([&unmined]),
+            Err(ChiguiError::InsufficientWork { number: 1, .. })
+        ));
+        assert!(matches!(
+            client.add_blocks(&state.blocks()[1..]),
+            Err(ChiguiError::InvalidBlockNumber { .. })
+        ));
+
+        client.add_blocks(state.blocks())?;
+        assert_eq!(client.height(), 3);
+
+        let block = &state.blocks()[1];
+        let tx = &block.txs[0];
+        let proof = block.prove_tx(&tx.hash()).unwrap();
+
+        client.verify_tx(2, tx, &proof)?;
+        assert!(matches!(
+            client.verify_tx(3, tx, &proof),
+            Err(ChiguiError::InvalidProof { .. })
+        ));
+        assert!(matches!(
+            client.verify_tx(4, tx, &proof),
+            Err(ChiguiError::UnknownHeader { number: 4 })
+        ));
+
+        let mut proof = state.prove_balance(&alice)?;
+
+        client.verify_balance(&proof, &state.state_root())?;
+        proof.balance = 60;
+        assert!(matches!(
+            client.verify_balance(&proof, &state.state_root()),
+            Err(ChiguiError::InvalidProof { .. })
+        ));
+
+        Ok(())
+    }
+}
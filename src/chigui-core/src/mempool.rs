@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use crate::error::{ChiguiError, Result};
+use crate::signed::SignedTx;
+use crate::state::State;
+use crate::{Account, Hash, Tx};
+
+/// Validated transactions waiting to be included in a block.
+///
+/// Transactions are checked against the [`State`] they were submitted on, so the pool only holds
+/// transactions that could be applied on top of it. Block producers pick them by decreasing fee
+/// with [`Mempool::select`], while keeping the transactions of each sender in nonce order.
+#[derive(Debug, Default)]
+pub struct Mempool {
+    txs: HashMap<Hash, SignedTx>,
+    /// Pending transactions by sender and nonce, used to detect conflicts.
+    nonces: HashMap<(Account, u64), Hash>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.txs.contains_key(hash)
+    }
+
+    pub fn get(&self, hash: &Hash) -> Option<&SignedTx> {
+        self.txs.get(hash)
+    }
+
+    /// Validate a transaction against the given state and add it to the pool, returning its hash.
+    ///
+    /// Transactions already pending, and transactions reusing the nonce of a pending transaction
+    /// from the same sender, are rejected.
+    pub fn insert(&mut self, state: &State, tx: SignedTx) -> Result<Hash> {
+        let hash = tx.hash();
+
+        if self.txs.contains_key(&hash) {
+            return Err(ChiguiError::DuplicateTx { hash });
+        }
+
+        state.authorize(&tx)?;
+        tx.tx.check_memo()?;
+
+        // Transactions that can't fit in any block would stay pending forever.
+        if let Some(limit) = state.block_gas_limit() {
+            let gas = tx.tx.gas();
+
+            if gas > limit {
+                return Err(ChiguiError::TxGasExceeded { gas, limit });
+            }
+        }
+
+        // Senders must exist, their balance is checked below.
+        for account in tx.tx.accounts() {
+            if tx.tx.sender() != Some(account) {
+                state.check_recipient(account)?;
+            }
+        }
+
+        if let (Some(from), Some(nonce)) = (tx.tx.sender(), tx.tx.nonce()) {
+            let expected = state.next_nonce(from);
+
+            if nonce < expected {
+                return Err(ChiguiError::InvalidNonce {
+                    account: from.clone(),
+                    expected,
+                    got: nonce,
+                });
+            }
+
+            if self.nonces.contains_key(&(from.clone(), nonce)) {
+                return Err(ChiguiError::NonceConflict {
+                    account: from.clone(),
+                    nonce,
+                });
+            }
+
+            let fee = tx.tx.fee();
+
+            if !state.fee_schedule().accepts(fee) {
+                return Err(ChiguiError::FeeTooLow {
+                    fee,
+                    min_fee: state.fee_schedule().min_fee,
+                });
+            }
+
+            if let Tx::Unstake { value, .. } = &tx.tx {
+                state.stakes().check_unbond(from, *value)?;
+            }
+
+            let have = Self::balance(state, from)?;
+            let need = tx.tx.cost();
+
+            if need > have {
+                return Err(ChiguiError::InsufficientBalance {
+                    account: from.clone(),
+                    have,
+                    need,
+                });
+            }
+
+            if let Some(denom) = tx.tx.denom() {
+                let have = state.get_asset_balance(from, Some(denom)).ok_or_else(|| {
+                    ChiguiError::UnknownAsset {
+                        denom: denom.to_string(),
+                    }
+                })?;
+                let need = tx.tx.value();
+
+                if need > have {
+                    return Err(ChiguiError::InsufficientBalance {
+                        account: from.clone(),
+                        have,
+                        need,
+                    });
+                }
+            }
+
+            self.nonces.insert((from.clone(), nonce), hash);
+        }
+
+        self.txs.insert(hash, tx);
+
+        Ok(hash)
+    }
+
+    pub fn remove(&mut self, hash: &Hash) -> Option<SignedTx> {
+        let tx = self.txs.remove(hash)?;
+
+        if let (Some(from), Some(nonce)) = (tx.tx.sender(), tx.tx.nonce()) {
+            self.nonces.remove(&(from.clone(), nonce));
+        }
+
+        Some(tx)
+    }
+
+    /// Every pending transaction, highest fee first.
+    pub fn txs(&self) -> Vec<&SignedTx> {
+        let mut txs = self.txs.values().collect::<Vec<&SignedTx>>();
+
+        txs.sort_by_key(|tx| (std::cmp::Reverse(tx.tx.fee()), tx.hash()));
+        txs
+    }
+
+    /// Pick up to `limit` transactions that can be applied in order on top of the given state,
+    /// highest fee first, within the block gas limit of the state.
+    ///
+    /// A transaction is only picked once every earlier nonce of its sender has been, and as long as
+    /// the sender can still afford it and the block has gas left for it.
+    pub fn select(&self, state: &State, limit: usize) -> Vec<SignedTx> {
+        let mut candidates = self.txs();
+        let mut selected = Vec::new();
+        let mut gas_left = state.block_gas_limit().unwrap_or(u64::MAX);
+        let mut nonces = HashMap::<&Account, u64>::new();
+        let mut spent = HashMap::<&Account, u64>::new();
+
+        while selected.len() < limit {
+            let ready = candidates.iter().position(|signed| {
+                if signed.tx.gas() > gas_left {
+                    return false;
+                }
+
+                let (Some(from), Some(nonce)) = (signed.tx.sender(), signed.tx.nonce()) else {
+                    return true;
+                };
+                let expected = nonces
+                    .get(from)
+                    .copied()
+                    .unwrap_or_else(|| state.next_nonce(from));
+                let have = state
+                    .spendable_balance(from)
+                    .unwrap_or_default()
+                    .saturating_sub(spent.get(from).copied().unwrap_or_default());
+
+                nonce == expected && signed.tx.cost() <= have
+            });
+
+            let Some(index) = ready else {
+                break;
+            };
+            let signed = candidates.remove(index);
+
+            gas_left -= signed.tx.gas();
+
+            if let (Some(from), Some(nonce)) = (signed.tx.sender(), signed.tx.nonce()) {
+                nonces.insert(from, nonce + 1);
+                let spent = spent.entry(from).or_default();
+
+                *spent = spent.saturating_add(signed.tx.cost());
+            }
+
+            selected.push(signed.clone());
+        }
+
+        selected
+    }
+
+    /// Drop the transactions included in the chain since they were submitted, along with those
+    /// whose nonce has been used in the meantime.
+    pub fn prune(&mut self, state: &State) {
+        let stale = self
+            .txs
+            .iter()
+            .filter(
+                |(hash, signed)| match (signed.tx.sender(), signed.tx.nonce()) {
+                    (Some(from), Some(nonce)) => nonce < state.next_nonce(from),
+                    _ => state.tx_by_hash(hash).is_some(),
+                },
+            )
+            .map(|(hash, _)| *hash)
+            .collect::<Vec<Hash>>();
+
+        for hash in stale {
+            self.remove(&hash);
+        }
+    }
+
+    fn balance(state: &State, account: &Account) -> Result<u64> {
+        state
+            .spendable_balance(account)
+            .ok_or_else(|| ChiguiError::AccountNotFound {
+                account: account.clone(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> Result<State> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":100,"carol":0},"permissive":true}"#,
+        )?;
+
+        State::in_memory(genesis)
+    }
+
+    fn transfer(from: &str, value: u64, fee: u64, nonce: u64) -> Result<SignedTx> {
+        Ok(Tx::Transfer {
+            from: Account::new(from)?,
+            to: Account::new("carol")?,
+            value,
+            fee,
+            nonce,
+            memo: None,
+            denom: None,
+        }
+        .into())
+    }
+
+    #[test]
+    fn rejects_duplicates_and_conflicts() -> Result<()> {
+        let state = state()?;
+        let mut mempool = Mempool::new();
+
+        mempool.insert(&state, transfer("alice", 10, 1, 0)?)?;
+
+        assert!(matches!(
+            mempool.insert(&state, transfer("alice", 10, 1, 0)?),
+            Err(ChiguiError::DuplicateTx { .. })
+        ));
+        assert!(matches!(
+            mempool.insert(&state, transfer("alice", 20, 5, 0)?),
+            Err(ChiguiError::NonceConflict { nonce: 0, .. })
+        ));
+        assert!(matches!(
+            mempool.insert(&state, transfer("bob", 101, 0, 0)?),
+            Err(ChiguiError::InsufficientBalance { .. })
+        ));
+        assert_eq!(mempool.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn selects_by_fee_in_nonce_order() -> Result<()> {
+        let mut state = state()?;
+        let mut mempool = Mempool::new();
+
+        mempool.insert(&state, transfer("alice", 10, 1, 0)?)?;
+        mempool.insert(&state, transfer("alice", 10, 9, 1)?)?;
+        mempool.insert(&state, transfer("bob", 10, 5, 0)?)?;
+        mempool.insert(&state, transfer("bob", 90, 5, 1)?)?;
+
+        let selected = mempool.select(&state, 10);
+        let fees = selected.iter().map(|tx| tx.tx.fee()).collect::<Vec<u64>>();
+
+        assert_eq!(fees, vec![5, 1, 9]);
+        assert_eq!(mempool.txs()[0].tx.fee(), 9);
+
+        let block = state.next_block(selected);
+        state.add_block(block)?;
+        mempool.prune(&state);
+
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.select(&state, 10).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn estimates_fees_from_congestion() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":100,"carol":0},"fee_schedule":{"min_fee":2},"permissive":true}"#,
+        )?;
+        let state = State::in_memory(genesis)?;
+        let mut mempool = Mempool::new();
+        let tx = transfer("carol", 1, 0, 0)?.tx;
+
+        assert_eq!(state.estimate_fee(&tx, &mempool, 2), 2);
+
+        mempool.insert(&state, transfer("alice", 10, 7, 0)?)?;
+        mempool.insert(&state, transfer("bob", 10, 3, 0)?)?;
+
+        assert_eq!(state.estimate_fee(&tx, &mempool, 3), 2);
+        assert_eq!(state.estimate_fee(&tx, &mempool, 2), 4);
+        assert_eq!(state.estimate_fee(&tx, &mempool, 1), 8);
+
+        let generate = Tx::Generate {
+            to: Account::new("carol")?,
+            value: 1,
+            denom: None,
+        };
+
+        assert_eq!(state.estimate_fee(&generate, &mempool, 1), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use crate::hash::Hash;
+
+/// Side of the path node a [`Sibling`] hash is combined on.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// A hash combined with the running hash at one level of a [`MerkleProof`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Sibling {
+    pub hash: Hash,
+    pub side: Side,
+}
+
+/// Proof that a leaf is part of the Merkle tree with a given root, from the leaf up to the root.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub leaf: Hash,
+    pub siblings: Vec<Sibling>,
+}
+
+impl MerkleProof {
+    /// Build the proof of the leaf at `index`, or `None` if there's no such leaf.
+    pub fn new(leaves: &[Hash], index: usize) -> Option<Self> {
+        let leaf = *leaves.get(index)?;
+        let mut siblings = Vec::new();
+        let mut level = leaves.to_vec();
+        let mut index = index;
+
+        while level.len() > 1 {
+            let sibling = index ^ 1;
+
+            // The last node of an odd level is promoted as is and contributes no sibling.
+            if let Some(hash) = level.get(sibling) {
+                let side = if sibling < index {
+                    Side::Left
+                } else {
+                    Side::Right
+                };
+
+                siblings.push(Sibling { hash: *hash, side });
+            }
+
+            level = parent_level(&level);
+            index /= 2;
+        }
+
+        Some(Self { leaf, siblings })
+    }
+
+    /// Whether the proof leads from its leaf to the given root.
+    pub fn verify(&self, root: &Hash) -> bool {
+        let computed = self
+            .siblings
+            .iter()
+            .fold(self.leaf, |hash, sibling| match sibling.side {
+                Side::Left => node(&sibling.hash, &hash),
+                Side::Right => node(&hash, &sibling.hash),
+            });
+
+        computed == *root
+    }
+}
+
+/// Compute the root of the Merkle tree over the given leaves, [`Hash::default`] when empty.
+///
+/// Each node is the hash of its two children concatenated; the last node of an odd level is
+/// promoted to the next level unchanged.
+pub fn root(leaves: &[Hash]) -> Hash {
+    let mut level = leaves.to_vec();
+
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+
+    level.first().copied().unwrap_or_default()
+}
+
+fn parent_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node(left, right),
+            [single] => *single,
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+fn node(left: &Hash, right: &Hash) -> Hash {
+    let mut bytes = [0; 64];
+    bytes[..32].copy_from_slice(left.as_bytes());
+    bytes[32..].copy_from_slice(right.as_bytes());
+
+    Hash::digest(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proves_every_leaf() {
+        let leaves = (0..5u8).map(|i| Hash::digest(&[i])).collect::<Vec<Hash>>();
+        let root = root(&leaves);
+
+        for index in 0..leaves.len() {
+            let proof = MerkleProof::new(&leaves, index).unwrap();
+
+            assert!(proof.verify(&root));
+        }
+
+        let mut forged = MerkleProof::new(&leaves, 2).unwrap();
+        forged.leaf = Hash::digest(b"forged");
+
+        assert!(!forged.verify(&root));
+        assert!(MerkleProof::new(&leaves, 5).is_none());
+        assert_eq!(super::root(&[]), Hash::default());
+        assert_eq!(super::root(&leaves[..1]), leaves[0]);
+    }
+}
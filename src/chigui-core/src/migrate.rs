@@ -0,0 +1,675 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+use crate::block::{Block, BlockHeader};
+use crate::error::{ChiguiError, Result};
+use crate::signed::SignedTx;
+use crate::snapshot::Snapshot;
+use crate::state::State;
+use crate::storage::{FileStorage, Storage};
+use crate::{Account, Hash, Tx};
+
+/// Version of the db dir layout written by this release.
+///
+/// - `0`: transactions as JSONL in a flat `tx.db`, before blocks.
+/// - `1`: blocks as JSONL in `block.db`.
+/// - `2`: the version recorded in `manifest.json` and `genesis.json`.
+/// - `3`: blocks hashed over canonical JSON.
+/// - `4`: transaction signatures bound to the chain ID.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Upgrades the db dir from the version before the one it's listed with.
+type Migration = fn(&Path) -> Result<()>;
+
+/// Upgrades of the db dir, by the version they upgrade it to.
+const MIGRATIONS: [(u32, &str, Migration); 4] = [
+    (1, "group tx.db transactions into blocks", tx_db_to_block_db),
+    (2, "record the schema version", record_version),
+    (3, "rehash blocks over canonical JSON", rehash_blocks),
+    (4, "bind signatures to the chain ID", bind_signatures),
+];
+
+/// Rules of older releases, followed while replaying the blocks they wrote, see [`fold_blocks`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct LegacyRules {
+    /// Hash values over their derived JSON serialization rather than canonical JSON.
+    pub json_hashes: bool,
+    /// Accept signatures without a chain ID, made over the transaction hash alone.
+    pub unbound_signatures: bool,
+}
+
+thread_local! {
+    static LEGACY_RULES: Cell<LegacyRules> = const {
+        Cell::new(LegacyRules {
+            json_hashes: false,
+            unbound_signatures: false,
+        })
+    };
+}
+
+impl LegacyRules {
+    /// Rules followed on the current thread, none outside of migrations.
+    pub(crate) fn current() -> Self {
+        LEGACY_RULES.get()
+    }
+
+    /// Run `f` following these rules on the current thread.
+    fn apply<T>(self, f: impl FnOnce() -> T) -> T {
+        let previous = LEGACY_RULES.replace(self);
+        let result = f();
+
+        LEGACY_RULES.set(previous);
+
+        result
+    }
+}
+
+/// Layout version of a db dir, persisted as `manifest.json`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: u32,
+}
+
+impl Manifest {
+    pub fn read(dbdir: &Path) -> Result<Option<Self>> {
+        let path = dbdir.join("manifest.json");
+
+        match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|source| ChiguiError::ManifestParseError { source }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(ChiguiError::Io { path, source }),
+        }
+    }
+
+    pub fn write(&self, dbdir: &Path) -> Result<()> {
+        let path = dbdir.join("manifest.json");
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|source| ChiguiError::SerializeError { source })?;
+
+        std::fs::write(&path, json + "\n").map_err(|source| ChiguiError::Io { path, source })
+    }
+}
+
+/// Layout version of a db dir, from its manifest, or guessed from its files for db dirs written
+/// before manifests.
+pub fn schema_version(dbdir: &Path) -> Result<u32> {
+    if let Some(manifest) = Manifest::read(dbdir)? {
+        return Ok(manifest.version);
+    }
+
+    let legacy = dbdir.join("tx.db").exists() && !dbdir.join("block.db").exists();
+
+    Ok(if legacy { 0 } else { 1 })
+}
+
+/// Upgrade a db dir to [`SCHEMA_VERSION`], returning the migrations applied, oldest first.
+///
+/// Db dirs written by a newer release are rejected rather than misread.
+pub fn migrate(dbdir: &Path) -> Result<Vec<&'static str>> {
+    let version = schema_version(dbdir)?;
+
+    if version > SCHEMA_VERSION {
+        return Err(ChiguiError::UnsupportedSchema {
+            version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    let mut applied = Vec::new();
+
+    for (to, name, migration) in MIGRATIONS.into_iter().filter(|(to, ..)| *to > version) {
+        migration(dbdir)?;
+        Manifest { version: to }.write(dbdir)?;
+        info!(version = to, migration = name, "migrated db dir");
+        applied.push(name);
+    }
+
+    Ok(applied)
+}
+
+/// Replay the transactions of a flat `tx.db` one block each into `block.db`, keeping the
+/// original as `tx.db.bak`.
+///
+/// Those chains had neither signatures nor nonces, so the genesis is made permissive and
+/// transfers get the next nonce of their sender.
+fn tx_db_to_block_db(dbdir: &Path) -> Result<()> {
+    let tx_db_path = dbdir.join("tx.db");
+    let block_db_path = dbdir.join("block.db");
+    let tx_db = std::fs::read_to_string(&tx_db_path).map_err(|source| ChiguiError::Io {
+        path: tx_db_path.clone(),
+        source,
+    })?;
+
+    update_genesis(dbdir, |genesis| {
+        genesis.insert("permissive".into(), Value::Bool(true));
+    })?;
+    std::fs::write(&block_db_path, "").map_err(|source| ChiguiError::Io {
+        path: block_db_path,
+        source,
+    })?;
+
+    let mut state = State::with_storage(FileStorage::new(dbdir))?;
+
+    for (index, line) in tx_db.lines().enumerate() {
+        let parse_error = |source| ChiguiError::ParseError {
+            line: index + 1,
+            source,
+        };
+        let mut tx = serde_json::from_str::<Value>(line).map_err(parse_error)?;
+
+        if let Some(fields) = tx.as_object_mut().filter(|tx| !tx.contains_key("nonce"))
+            && let Some(from) = fields.get("from").and_then(Value::as_str)
+        {
+            let nonce = state.next_nonce(&Account::new(from)?);
+
+            fields.insert("nonce".into(), nonce.into());
+        }
+
+        state.add_tx(serde_json::from_value::<Tx>(tx).map_err(parse_error)?)?;
+    }
+
+    let backup_path = dbdir.join("tx.db.bak");
+
+    std::fs::rename(&tx_db_path, &backup_path).map_err(|source| ChiguiError::Io {
+        path: backup_path,
+        source,
+    })
+}
+
+fn record_version(dbdir: &Path) -> Result<()> {
+    update_genesis(dbdir, |genesis| {
+        genesis.insert("version".into(), SCHEMA_VERSION.into());
+    })
+}
+
+/// Recompute the transaction roots and parent hashes of `block.db` now that hashes are computed
+/// over canonical JSON, pointing transactions at the new hashes of the escrows and proposals they
+/// refer to, and mining blocks again on proof-of-work chains.
+///
+/// Signatures and seals cover the old hashes and can't be made again without their keys, and the
+/// snapshots of pruned chains cover the old blocks, so such chains are folded into a snapshot
+/// instead, see [`fold_blocks`].
+fn rehash_blocks(dbdir: &Path) -> Result<()> {
+    // Other storage backends keep their blocks elsewhere.
+    if !dbdir.join("block.db").exists() {
+        return record_version(dbdir);
+    }
+
+    let genesis_path = dbdir.join("genesis.json");
+    let genesis_json =
+        std::fs::read_to_string(&genesis_path).map_err(|source| ChiguiError::Io {
+            path: genesis_path,
+            source,
+        })?;
+    let genesis = serde_json::from_str::<Value>(&genesis_json)
+        .map_err(|source| ChiguiError::GenesisParseError { source })?;
+    let difficulty = genesis
+        .get("difficulty")
+        .and_then(Value::as_u64)
+        .unwrap_or_default() as u32;
+    let mut storage = FileStorage::new(dbdir);
+    let blocks = storage.iter_blocks()?.collect::<Result<Vec<Block>>>()?;
+    let pruned = match blocks.first() {
+        Some(block) => block.header.number > 1,
+        None => !Snapshot::heights(dbdir)?.is_empty(),
+    };
+    let signed = blocks.iter().any(|block| {
+        block.seal.is_some() || block.txs.iter().any(|signed| signed.signature.is_some())
+    });
+
+    if pruned || signed {
+        return fold_blocks(
+            dbdir,
+            LegacyRules {
+                json_hashes: true,
+                unbound_signatures: true,
+            },
+        );
+    }
+
+    let mut renamed = HashMap::new();
+    let mut parent_hash = Hash::default();
+    let mut rehashed = Vec::with_capacity(blocks.len());
+
+    for block in blocks {
+        let mut txs = Vec::with_capacity(block.txs.len());
+
+        for signed in block.txs {
+            let legacy = serde_json::to_vec(&signed.tx)
+                .map_err(|source| ChiguiError::SerializeError { source })?;
+            let mut tx = serde_json::to_value(&signed.tx)
+                .map_err(|source| ChiguiError::SerializeError { source })?;
+
+            rename_hashes(&mut tx, &renamed);
+
+            let tx = serde_json::from_value::<Tx>(tx)
+                .map_err(|source| ChiguiError::SerializeError { source })?;
+
+            renamed.insert(Hash::digest(&legacy).to_string(), tx.hash().to_string());
+            txs.push(SignedTx::unsigned(tx));
+        }
+
+        let mut block = Block::new(
+            BlockHeader {
+                parent_hash,
+                nonce: 0,
+                ..block.header
+            },
+            txs,
+        );
+
+        while !block.header.meets_difficulty(difficulty) {
+            block.header.nonce += 1;
+        }
+
+        parent_hash = block.hash();
+        rehashed.push(block);
+    }
+
+    storage.replace_blocks(&rehashed)?;
+
+    // Snapshots commit to the old block hashes, unpruned chains don't need them to load.
+    for height in Snapshot::heights(dbdir)? {
+        let path = Snapshot::path(dbdir, height);
+
+        std::fs::remove_file(&path).map_err(|source| ChiguiError::Io { path, source })?;
+    }
+
+    record_version(dbdir)
+}
+
+/// Fold blocks holding signatures not bound to a chain ID, which this release rejects, see
+/// [`fold_blocks`].
+fn bind_signatures(dbdir: &Path) -> Result<()> {
+    // Other storage backends keep their blocks elsewhere.
+    if !dbdir.join("block.db").exists() {
+        return record_version(dbdir);
+    }
+
+    let blocks = FileStorage::new(dbdir)
+        .iter_blocks()?
+        .collect::<Result<Vec<Block>>>()?;
+    let unbound = blocks
+        .iter()
+        .flat_map(|block| &block.txs)
+        .any(|signed| signed.signature.is_some() && signed.chain_id.is_none());
+
+    if !unbound {
+        return record_version(dbdir);
+    }
+
+    fold_blocks(
+        dbdir,
+        LegacyRules {
+            json_hashes: false,
+            unbound_signatures: true,
+        },
+    )
+}
+
+/// Replay the blocks of a chain written by an older release following its `rules`, then fold
+/// them into a snapshot like [`State::prune`] with an empty window, keeping them as
+/// `block.db.bak`.
+///
+/// The chain carries on from the snapshot, whose block hash is the one its latest block had when
+/// it was written, so every node upgrading the same chain agrees on the parent of the next block.
+/// The folded blocks can no longer be queried or served to peers.
+fn fold_blocks(dbdir: &Path, rules: LegacyRules) -> Result<()> {
+    let block_db_path = dbdir.join("block.db");
+    let backup_path = dbdir.join("block.db.bak");
+
+    std::fs::copy(&block_db_path, &backup_path).map_err(|source| ChiguiError::Io {
+        path: backup_path,
+        source,
+    })?;
+
+    let mut state = rules.apply(|| {
+        let mut state = State::with_storage(FileStorage::new(dbdir))?;
+
+        state.prune(0)?;
+
+        Ok::<State, ChiguiError>(state)
+    })?;
+
+    // The snapshot written while pruning has the state root as the older release computed it.
+    state.refresh_state_root();
+    state.write_snapshot()?;
+
+    record_version(dbdir)
+}
+
+/// Replace the strings of a JSON value found in `renamed` with their new value.
+fn rename_hashes(value: &mut Value, renamed: &HashMap<String, String>) {
+    match value {
+        Value::String(text) => {
+            if let Some(new) = renamed.get(text.as_str()) {
+                *text = new.clone();
+            }
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| rename_hashes(value, renamed)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|value| rename_hashes(value, renamed)),
+        _ => {}
+    }
+}
+
+fn update_genesis(
+    dbdir: &Path,
+    update: impl FnOnce(&mut serde_json::Map<String, Value>),
+) -> Result<()> {
+    let path = dbdir.join("genesis.json");
+    let json = std::fs::read_to_string(&path).map_err(|source| ChiguiError::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let mut genesis = serde_json::from_str::<Value>(&json)
+        .map_err(|source| ChiguiError::GenesisParseError { source })?;
+
+    if let Some(fields) = genesis.as_object_mut() {
+        update(fields);
+    }
+
+    let json = serde_json::to_string_pretty(&genesis)
+        .map_err(|source| ChiguiError::SerializeError { source })?;
+
+    std::fs::write(&path, json + "\n").map_err(|source| ChiguiError::Io { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_flat_tx_db() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let dbdir = dbdir.path();
+
+        std::fs::write(
+            dbdir.join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"chigui":1000,"bob":0}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dbdir.join("tx.db"),
+            concat!(
+                r#"{"type": "transfer", "from":"chigui","to":"bob","value":300}"#,
+                "\n",
+                r#"{"type": "generate", "from":"chigui","to":"chigui","value":700}"#,
+                "\n",
+                r#"{"type": "transfer", "from":"chigui","to":"bob","value":200}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(schema_version(dbdir)?, 0);
+
+        let state = State::open(dbdir)?;
+
+        assert_eq!(state.height(), 3);
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(500));
+        assert_eq!(schema_version(dbdir)?, SCHEMA_VERSION);
+        assert!(dbdir.join("tx.db.bak").exists());
+        assert!(migrate(dbdir)?.is_empty());
+
+        Manifest {
+            version: SCHEMA_VERSION + 1,
+        }
+        .write(dbdir)?;
+
+        assert!(matches!(
+            State::open(dbdir),
+            Err(ChiguiError::UnsupportedSchema { .. })
+        ));
+
+        Ok(())
+    }
+
+    /// Hash as computed before canonical JSON, over the derived serialization.
+    fn legacy_hash<T: Serialize>(value: &T) -> Hash {
+        Hash::digest(&serde_json::to_vec(value).unwrap())
+    }
+
+    #[test]
+    fn rehashes_legacy_blocks() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let dbdir = dbdir.path();
+        let alice = Account::new("alice")?;
+        let create = Tx::EscrowCreate {
+            from: alice.clone(),
+            to: Account::new("bob")?,
+            value: 5,
+            arbiter: None,
+            fee: 0,
+            nonce: 0,
+        };
+        let release = Tx::EscrowRelease {
+            account: alice.clone(),
+            escrow: legacy_hash(&create),
+            fee: 0,
+            nonce: 1,
+        };
+        let mut parent_hash = Hash::default();
+        let mut block_db = String::new();
+
+        for (number, tx) in [(1, create), (2, release)] {
+            let block = Block {
+                header: BlockHeader {
+                    number,
+                    parent_hash,
+                    tx_root: crate::merkle::root(&[legacy_hash(&tx)]),
+                    time: 0,
+                    nonce: 0,
+                },
+                txs: vec![SignedTx::unsigned(tx)],
+                seal: None,
+            };
+
+            parent_hash = legacy_hash(&block.header);
+            block_db.push_str(&serde_json::to_string(&block).unwrap());
+            block_db.push('\n');
+        }
+
+        std::fs::write(
+            dbdir.join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10,"bob":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.join("block.db"), block_db).unwrap();
+        Manifest { version: 2 }.write(dbdir)?;
+
+        let state = State::open(dbdir)?;
+
+        assert_eq!(state.height(), 2);
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(5));
+        assert_eq!(schema_version(dbdir)?, SCHEMA_VERSION);
+
+        Ok(())
+    }
+
+    #[test]
+    fn folds_signed_legacy_blocks() -> Result<()> {
+        use crate::signed::{PublicKey, TxSignature};
+
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let dbdir = dbdir.path();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let alice = Account::from_public_key(&PublicKey::from(&key));
+        let create = Tx::EscrowCreate {
+            from: alice.clone(),
+            to: Account::new("bob")?,
+            value: 5,
+            arbiter: None,
+            fee: 0,
+            nonce: 0,
+        };
+        let release = Tx::EscrowRelease {
+            account: alice.clone(),
+            escrow: legacy_hash(&create),
+            fee: 0,
+            nonce: 1,
+        };
+        let mut parent_hash = Hash::default();
+        let mut block_db = String::new();
+
+        for (number, tx) in [(1, create), (2, release)] {
+            // Signed over the transaction hash alone, before signatures were bound to the chain.
+            let signature = TxSignature::sign(&legacy_hash(&tx), &key);
+            let block = Block {
+                header: BlockHeader {
+                    number,
+                    parent_hash,
+                    tx_root: crate::merkle::root(&[legacy_hash(&tx)]),
+                    time: 0,
+                    nonce: 0,
+                },
+                txs: vec![SignedTx {
+                    tx,
+                    chain_id: None,
+                    signature: Some(signature),
+                }],
+                seal: None,
+            };
+
+            parent_hash = legacy_hash(&block.header);
+            block_db.push_str(&serde_json::to_string(&block).unwrap());
+            block_db.push('\n');
+        }
+
+        std::fs::write(
+            dbdir.join("genesis.json"),
+            format!(
+                r#"{{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{{"{alice}":10,"bob":0}}}}"#
+            ),
+        )
+        .unwrap();
+        std::fs::write(dbdir.join("block.db"), &block_db).unwrap();
+        Manifest { version: 2 }.write(dbdir)?;
+
+        let mut state = State::open(dbdir)?;
+
+        assert_eq!(state.height(), 2);
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(5));
+        assert_eq!(schema_version(dbdir)?, SCHEMA_VERSION);
+        assert_eq!(
+            std::fs::read_to_string(dbdir.join("block.db.bak")).unwrap(),
+            block_db
+        );
+        assert_eq!(std::fs::read_to_string(dbdir.join("block.db")).unwrap(), "");
+
+        state.add_tx(SignedTx::sign(
+            Tx::Burn {
+                account: alice.clone(),
+                value: 1,
+                fee: 0,
+                nonce: 2,
+            },
+            "testnet",
+            &key,
+        ))?;
+
+        let reopened = State::open(dbdir)?;
+
+        assert_eq!(reopened.height(), 3);
+        assert_eq!(
+            reopened.block_by_number(3).unwrap().header.parent_hash,
+            parent_hash
+        );
+        assert_eq!(reopened.get_balance(&alice), Some(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn folds_signatures_unbound_to_the_chain() -> Result<()> {
+        use crate::signed::{PublicKey, TxSignature};
+
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let dbdir = dbdir.path();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let alice = Account::from_public_key(&PublicKey::from(&key));
+        let burn = |nonce| Tx::Burn {
+            account: alice.clone(),
+            value: 1,
+            fee: 0,
+            nonce,
+        };
+        let unbound = |tx: Tx| SignedTx {
+            signature: Some(TxSignature::sign(&tx.hash(), &key)),
+            chain_id: None,
+            tx,
+        };
+        let first = Block::new(
+            BlockHeader {
+                number: 1,
+                parent_hash: Hash::default(),
+                tx_root: Hash::default(),
+                time: 0,
+                nonce: 0,
+            },
+            vec![unbound(burn(0))],
+        );
+        let second = Block::new(
+            BlockHeader {
+                number: 2,
+                parent_hash: first.hash(),
+                tx_root: Hash::default(),
+                time: 0,
+                nonce: 0,
+            },
+            vec![SignedTx::sign(burn(1), "testnet", &key)],
+        );
+
+        std::fs::write(
+            dbdir.join("genesis.json"),
+            format!(
+                r#"{{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{{"{alice}":10}}}}"#
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dbdir.join("block.db"),
+            format!(
+                "{}\n{}\n",
+                serde_json::to_string(&first).unwrap(),
+                serde_json::to_string(&second).unwrap()
+            ),
+        )
+        .unwrap();
+        Manifest { version: 3 }.write(dbdir)?;
+
+        let mut state = State::open(dbdir)?;
+
+        assert_eq!(state.height(), 2);
+        assert_eq!(state.get_balance(&alice), Some(8));
+        assert_eq!(schema_version(dbdir)?, SCHEMA_VERSION);
+        assert!(dbdir.join("block.db.bak").exists());
+        assert!(matches!(
+            state.add_tx(unbound(burn(2))),
+            Err(ChiguiError::ChainIdMismatch { .. })
+        ));
+
+        state.add_tx(SignedTx::sign(burn(2), "testnet", &key))?;
+
+        assert_eq!(
+            State::open(dbdir)?
+                .block_by_number(3)
+                .unwrap()
+                .header
+                .parent_hash,
+            second.hash()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use tracing::debug;
+
+use crate::block::{Block, BlockHeader};
+use crate::error::{ChiguiError, Result};
+use crate::signed::SignedTx;
+use crate::state::State;
+
+/// How many nonces are tried between two checks of the [`CancelHandle`].
+const CANCEL_CHECK_INTERVAL: u64 = 1024;
+
+/// Shared flag used to abort a running [`Miner::mine`] call, e.g. when a competing block arrives.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Proof-of-work block producer building on top of the latest block of a [`State`].
+#[derive(Debug)]
+pub struct Miner {
+    template: BlockHeader,
+    difficulty: u32,
+    cancel: CancelHandle,
+}
+
+impl Miner {
+    pub fn new(state: &State) -> Self {
+        Self {
+            template: state.next_block(Vec::new()).header,
+            difficulty: state.difficulty(),
+            cancel: CancelHandle::default(),
+        }
+    }
+
+    /// Stop mining once the given handle fires, e.g. one shared by every miner of a node.
+    pub fn with_cancel(mut self, cancel: CancelHandle) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Assemble a block holding the pending transactions and search for a header nonce meeting the
+    /// difficulty target.
+    ///
+    /// Returns [`ChiguiError::MiningCancelled`] if the [`CancelHandle`] fires before a nonce is found.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(number = self.template.number, txs = pending.len(), difficulty = self.difficulty)
+    )]
+    pub fn mine(&self, pending: &[SignedTx]) -> Result<Block> {
+        let started = Instant::now();
+        let mut block = Block::new(self.template.clone(), pending.to_vec());
+
+        for nonce in 0.. {
+            if nonce % CANCEL_CHECK_INTERVAL == 0 && self.cancel.is_cancelled() {
+                return Err(ChiguiError::MiningCancelled);
+            }
+
+            block.header.nonce = nonce;
+
+            if block.header.meets_difficulty(self.difficulty) {
+                debug!(
+                    nonce,
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    "mined block"
+                );
+
+                return Ok(block);
+            }
+        }
+
+        Err(ChiguiError::MiningCancelled)
+    }
+
+    /// Async variant of [`Miner::mine`], searching for the nonce on tokio's blocking thread pool
+    /// so that the runtime keeps serving other tasks.
+    #[cfg(feature = "async")]
+    pub async fn mine_async(self, pending: Vec<SignedTx>) -> Result<Block> {
+        crate::state::blocking(move || self.mine(&pending)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Account, Tx};
+
+    fn state(difficulty: u32) -> Result<State> {
+        let genesis = State::parse_genesis(&format!(
+            r#"{{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{{"alice":0}},"difficulty":{}}}"#,
+            difficulty
+        ))?;
+
+        State::in_memory(genesis)
+    }
+
+    #[test]
+    fn mined_block_meets_difficulty() -> Result<()> {
+        let mut state = state(8)?;
+        let pending = [SignedTx::from(Tx::Generate {
+            to: Account::new("alice")?,
+            value: 1,
+            denom: None,
+        })];
+
+        let block = Miner::new(&state).mine(&pending)?;
+
+        assert!(block.hash().leading_zero_bits() >= 8);
+        assert_eq!(block.txs, pending);
+
+        state.add_block(block)?;
+
+        let mut unsealed = state.next_block(Vec::new());
+        while unsealed.header.meets_difficulty(8) {
+            unsealed.header.nonce += 1;
+        }
+
+        assert!(matches!(
+            state.add_block(unsealed),
+            Err(ChiguiError::InsufficientWork { number: 2, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cancelled_miner_stops() -> Result<()> {
+        let state = state(256)?;
+        let miner = Miner::new(&state);
+
+        miner.cancel_handle().cancel();
+
+        assert!(matches!(miner.mine(&[]), Err(ChiguiError::MiningCancelled)));
+
+        let shared = CancelHandle::default();
+        let miner = Miner::new(&state).with_cancel(shared.clone());
+
+        std::thread::spawn(move || shared.cancel());
+
+        assert!(matches!(miner.mine(&[]), Err(ChiguiError::MiningCancelled)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ChiguiError, Result};
+use crate::{Account, Hash};
+
+/// An account whose coins only move once `threshold` of its `members` approved the transfer.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MultisigPolicy {
+    pub threshold: usize,
+    pub members: BTreeSet<Account>,
+}
+
+impl MultisigPolicy {
+    fn check_member(&self, multisig: &Account, account: &Account) -> Result<()> {
+        if !self.members.contains(account) {
+            return Err(ChiguiError::NotMultisigMember {
+                account: account.clone(),
+                multisig: multisig.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// What a member of a multisig account does with a [`Tx::Multisig`](crate::Tx::Multisig).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+#[serde(tag = "action")]
+pub enum MultisigAction {
+    /// Propose a transfer out of the multisig account, approved by the proposer. The proposal
+    /// is identified by the hash of the proposing transaction.
+    Propose {
+        multisig: Account,
+        to: Account,
+        value: u64,
+    },
+    Approve {
+        proposal: Hash,
+    },
+    /// Carry out a proposal approved by enough members.
+    Execute {
+        proposal: Hash,
+    },
+}
+
+/// A transfer out of a multisig account waiting for approvals.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Proposal {
+    pub multisig: Account,
+    pub to: Account,
+    pub value: u64,
+    pub approvals: BTreeSet<Account>,
+}
+
+/// Pending [`Proposal`]s, by the hash of the transaction proposing them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Proposals {
+    pending: BTreeMap<Hash, Proposal>,
+}
+
+impl Proposals {
+    pub fn get(&self, id: &Hash) -> Option<&Proposal> {
+        self.pending.get(id)
+    }
+
+    /// Every pending proposal, sorted by id.
+    pub fn iter(&self) -> impl Iterator<Item = (&Hash, &Proposal)> {
+        self.pending.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn propose(
+        &mut self,
+        id: Hash,
+        policy: &MultisigPolicy,
+        proposer: &Account,
+        multisig: &Account,
+        to: &Account,
+        value: u64,
+    ) -> Result<()> {
+        policy.check_member(multisig, proposer)?;
+
+        self.pending.insert(
+            id,
+            Proposal {
+                multisig: multisig.clone(),
+                to: to.clone(),
+                value,
+                approvals: BTreeSet::from([proposer.clone()]),
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn approve(&mut self, id: &Hash, policy: &MultisigPolicy, member: &Account) -> Result<()> {
+        let proposal = self
+            .pending
+            .get_mut(id)
+            .ok_or(ChiguiError::UnknownProposal { id: *id })?;
+
+        policy.check_member(&proposal.multisig, member)?;
+        proposal.approvals.insert(member.clone());
+
+        Ok(())
+    }
+
+    /// Remove a proposal approved by enough members, for the caller to carry it out.
+    pub fn take_approved(
+        &mut self,
+        id: &Hash,
+        policy: &MultisigPolicy,
+        member: &Account,
+    ) -> Result<Proposal> {
+        let proposal = self
+            .pending
+            .get(id)
+            .ok_or(ChiguiError::UnknownProposal { id: *id })?;
+        let need = policy.threshold.max(1);
+
+        policy.check_member(&proposal.multisig, member)?;
+
+        if proposal.approvals.len() < need {
+            return Err(ChiguiError::NotEnoughApprovals {
+                id: *id,
+                have: proposal.approvals.len(),
+                need,
+            });
+        }
+
+        Ok(self.pending.remove(id).expect("proposal is pending"))
+    }
+}
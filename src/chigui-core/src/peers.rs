@@ -0,0 +1,98 @@
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use crate::error::{ChiguiError, Result};
+
+/// Addresses of the peers a node has heard of, persisted as `known_peers.json` in the db dir.
+#[derive(Debug, Default)]
+pub struct KnownPeers {
+    peers: BTreeSet<SocketAddr>,
+    path: Option<PathBuf>,
+}
+
+impl KnownPeers {
+    /// Load the known peers of the given db dir, starting empty if the file doesn't exist yet.
+    pub fn open<P: AsRef<Path>>(dbdir: P) -> Result<Self> {
+        let path = dbdir.as_ref().join("known_peers.json");
+        let peers = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|source| ChiguiError::PeersParseError { source })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(source) => return Err(ChiguiError::Io { path, source }),
+        };
+
+        Ok(Self {
+            peers,
+            path: Some(path),
+        })
+    }
+
+    /// Record a peer, returning whether it was new.
+    pub fn insert(&mut self, peer: SocketAddr) -> Result<bool> {
+        if !self.peers.insert(peer) {
+            return Ok(false);
+        }
+
+        self.persist()?;
+
+        Ok(true)
+    }
+
+    /// Forget a peer, returning whether it was known.
+    pub fn remove(&mut self, peer: &SocketAddr) -> Result<bool> {
+        if !self.peers.remove(peer) {
+            return Ok(false);
+        }
+
+        self.persist()?;
+
+        Ok(true)
+    }
+
+    pub fn contains(&self, peer: &SocketAddr) -> bool {
+        self.peers.contains(peer)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SocketAddr> {
+        self.peers.iter()
+    }
+
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&self.peers)
+            .map_err(|source| ChiguiError::SerializeError { source })?;
+
+        std::fs::write(path, json).map_err(|source| ChiguiError::Io {
+            path: path.clone(),
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persists_new_peers() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let peer = "127.0.0.1:9090".parse().unwrap();
+
+        let mut peers = KnownPeers::open(dbdir.path())?;
+        assert!(peers.insert(peer)?);
+        assert!(!peers.insert(peer)?);
+
+        let reopened = KnownPeers::open(dbdir.path())?;
+        assert!(reopened.contains(&peer));
+        assert_eq!(reopened.iter().count(), 1);
+
+        assert!(peers.remove(&peer)?);
+        assert!(!peers.remove(&peer)?);
+        assert!(!KnownPeers::open(dbdir.path())?.contains(&peer));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,818 @@
+use std::net::SocketAddr;
+
+use prost::Message as _;
+
+use crate::block::{Block, BlockHeader};
+use crate::error::{ChiguiError, Result};
+use crate::governance::ParamChange;
+use crate::multisig::MultisigAction;
+use crate::script::Op;
+use crate::signed::{PublicKey, Signature, SignedTx, TxSignature};
+use crate::sync::Message;
+use crate::timelock::Unlock;
+use crate::{Account, Hash, Payment, Tx};
+
+/// Types of the `chigui.v1` package, generated by `build.rs` from `proto/chigui/v1/chigui.proto`.
+#[allow(clippy::all)]
+pub mod v1 {
+    include!(concat!(env!("OUT_DIR"), "/chigui.v1.rs"));
+}
+
+/// Encode a sync message as a protobuf `chigui.v1.SyncMessage`.
+pub fn encode(message: &Message) -> Vec<u8> {
+    v1::SyncMessage::from(message).encode_to_vec()
+}
+
+/// Encode a sync message as a protobuf `chigui.v1.SyncMessage` prefixed by its length as a varint,
+/// the framing of protobuf streams.
+pub fn encode_length_delimited(message: &Message) -> Vec<u8> {
+    v1::SyncMessage::from(message).encode_length_delimited_to_vec()
+}
+
+/// Decode a protobuf `chigui.v1.SyncMessage`, checking the hashes, keys, accounts and addresses
+/// it holds.
+pub fn decode(bytes: &[u8]) -> Result<Message> {
+    v1::SyncMessage::decode(bytes)
+        .map_err(|source| ChiguiError::ProtoDecodeError { source })?
+        .try_into()
+}
+
+fn invalid(reason: impl Into<String>) -> ChiguiError {
+    ChiguiError::InvalidProto {
+        reason: reason.into(),
+    }
+}
+
+fn required<T>(value: Option<T>, field: &str) -> Result<T> {
+    value.ok_or_else(|| invalid(format!("missing {}", field)))
+}
+
+fn hash(bytes: Vec<u8>, field: &str) -> Result<Hash> {
+    <[u8; 32]>::try_from(bytes)
+        .map(Hash::new)
+        .map_err(|_| invalid(format!("{} isn't 32 bytes", field)))
+}
+
+fn addr(text: &str) -> Result<SocketAddr> {
+    text.parse()
+        .map_err(|_| invalid(format!("invalid address \"{}\"", text)))
+}
+
+impl From<&Message> for v1::SyncMessage {
+    fn from(message: &Message) -> Self {
+        use v1::sync_message::Message as Proto;
+
+        let message = match message {
+            Message::Hello {
+                height,
+                state_root,
+                listen,
+            } => Proto::Hello(v1::Hello {
+                height: *height,
+                state_root: state_root.map(|root| root.as_bytes().to_vec()),
+                listen: listen.map(|listen| listen.to_string()),
+            }),
+            Message::GetBlocks { from } => Proto::GetBlocks(v1::GetBlocks { from: *from }),
+            Message::Blocks { blocks } => Proto::Blocks(v1::Blocks {
+                blocks: blocks.iter().map(v1::Block::from).collect(),
+            }),
+            Message::GetPeers => Proto::GetPeers(v1::GetPeers {}),
+            Message::Peers { peers } => Proto::Peers(v1::Peers {
+                peers: peers.iter().map(SocketAddr::to_string).collect(),
+            }),
+        };
+
+        Self {
+            message: Some(message),
+        }
+    }
+}
+
+impl TryFrom<v1::SyncMessage> for Message {
+    type Error = ChiguiError;
+
+    fn try_from(message: v1::SyncMessage) -> Result<Self> {
+        use v1::sync_message::Message as Proto;
+
+        Ok(match required(message.message, "sync message")? {
+            Proto::Hello(hello) => Message::Hello {
+                height: hello.height,
+                state_root: hello
+                    .state_root
+                    .map(|root| hash(root, "state_root"))
+                    .transpose()?,
+                listen: hello.listen.as_deref().map(addr).transpose()?,
+            },
+            Proto::GetBlocks(get_blocks) => Message::GetBlocks {
+                from: get_blocks.from,
+            },
+            Proto::Blocks(blocks) => Message::Blocks {
+                blocks: blocks
+                    .blocks
+                    .into_iter()
+                    .map(Block::try_from)
+                    .collect::<Result<Vec<Block>>>()?,
+            },
+            Proto::GetPeers(_) => Message::GetPeers,
+            Proto::Peers(peers) => Message::Peers {
+                peers: peers
+                    .peers
+                    .iter()
+                    .map(|peer| addr(peer))
+                    .collect::<Result<Vec<SocketAddr>>>()?,
+            },
+        })
+    }
+}
+
+impl From<&Block> for v1::Block {
+    fn from(block: &Block) -> Self {
+        let header = &block.header;
+
+        Self {
+            header: Some(v1::BlockHeader {
+                number: header.number,
+                parent_hash: header.parent_hash.as_bytes().to_vec(),
+                tx_root: header.tx_root.as_bytes().to_vec(),
+                time: header.time,
+                nonce: header.nonce,
+            }),
+            txs: block.txs.iter().map(v1::SignedTx::from).collect(),
+            seal: block.seal.as_ref().map(v1::TxSignature::from),
+        }
+    }
+}
+
+impl TryFrom<v1::Block> for Block {
+    type Error = ChiguiError;
+
+    fn try_from(block: v1::Block) -> Result<Self> {
+        let header = required(block.header, "block header")?;
+
+        Ok(Self {
+            header: BlockHeader {
+                number: header.number,
+                parent_hash: hash(header.parent_hash, "parent_hash")?,
+                tx_root: hash(header.tx_root, "tx_root")?,
+                time: header.time,
+                nonce: header.nonce,
+            },
+            txs: block
+                .txs
+                .into_iter()
+                .map(SignedTx::try_from)
+                .collect::<Result<Vec<SignedTx>>>()?,
+            seal: block.seal.map(TxSignature::try_from).transpose()?,
+        })
+    }
+}
+
+impl From<&TxSignature> for v1::TxSignature {
+    fn from(signature: &TxSignature) -> Self {
+        Self {
+            public_key: signature.public_key.as_bytes().to_vec(),
+            signature: signature.signature.to_bytes().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<v1::TxSignature> for TxSignature {
+    type Error = ChiguiError;
+
+    fn try_from(signature: v1::TxSignature) -> Result<Self> {
+        let public_key = <[u8; 32]>::try_from(signature.public_key)
+            .map_err(|_| invalid("public_key isn't 32 bytes"))?;
+        let signature = <[u8; 64]>::try_from(signature.signature)
+            .map_err(|_| invalid("signature isn't 64 bytes"))?;
+
+        Ok(Self {
+            public_key: PublicKey::from_bytes(&public_key)?,
+            signature: Signature::from_bytes(&signature),
+        })
+    }
+}
+
+impl From<&SignedTx> for v1::SignedTx {
+    fn from(signed: &SignedTx) -> Self {
+        Self {
+            tx: Some(v1::Tx::from(&signed.tx)),
+            signature: signed.signature.as_ref().map(v1::TxSignature::from),
+            chain_id: signed.chain_id.clone(),
+        }
+    }
+}
+
+impl TryFrom<v1::SignedTx> for SignedTx {
+    type Error = ChiguiError;
+
+    fn try_from(signed: v1::SignedTx) -> Result<Self> {
+        Ok(Self {
+            tx: required(signed.tx, "transaction")?.try_into()?,
+            chain_id: signed.chain_id,
+            signature: signed.signature.map(TxSignature::try_from).transpose()?,
+        })
+    }
+}
+
+impl From<&Tx> for v1::Tx {
+    fn from(tx: &Tx) -> Self {
+        use v1::tx::Tx as Proto;
+
+        let tx = match tx.clone() {
+            Tx::Transfer {
+                from,
+                to,
+                value,
+                fee,
+                nonce,
+                memo,
+                denom,
+            } => Proto::Transfer(v1::Transfer {
+                from: from.to_string(),
+                to: to.to_string(),
+                value,
+                fee,
+                nonce,
+                memo,
+                denom,
+            }),
+            Tx::Generate { to, value, denom } => Proto::Generate(v1::Generate {
+                to: to.to_string(),
+                value,
+                denom,
+            }),
+            Tx::TransferLocked {
+                from,
+                to,
+                value,
+                unlock,
+                fee,
+                nonce,
+            } => Proto::TransferLocked(v1::TransferLocked {
+                from: from.to_string(),
+                to: to.to_string(),
+                value,
+                unlock: Some(match unlock {
+                    Unlock::Height(height) => v1::transfer_locked::Unlock::UnlockHeight(height),
+                    Unlock::Time(time) => v1::transfer_locked::Unlock::UnlockTime(time),
+                }),
+                fee,
+                nonce,
+            }),
+            Tx::TransferVesting {
+                from,
+                to,
+                value,
+                cliff,
+                duration,
+                fee,
+                nonce,
+            } => Proto::TransferVesting(v1::TransferVesting {
+                from: from.to_string(),
+                to: to.to_string(),
+                value,
+                cliff,
+                duration,
+                fee,
+                nonce,
+            }),
+            Tx::TransferMulti {
+                from,
+                payments,
+                fee,
+                nonce,
+            } => Proto::TransferMulti(v1::TransferMulti {
+                from: from.to_string(),
+                payments: payments
+                    .into_iter()
+                    .map(|payment| v1::Payment {
+                        to: payment.to.to_string(),
+                        value: payment.value,
+                    })
+                    .collect(),
+                fee,
+                nonce,
+            }),
+            Tx::Stake {
+                account,
+                value,
+                fee,
+                nonce,
+            } => Proto::Stake(v1::Stake {
+                account: account.to_string(),
+                value,
+                fee,
+                nonce,
+            }),
+            Tx::Unstake {
+                account,
+                value,
+                fee,
+                nonce,
+            } => Proto::Unstake(v1::Unstake {
+                account: account.to_string(),
+                value,
+                fee,
+                nonce,
+            }),
+            Tx::SetMinters {
+                admin,
+                minters,
+                fee,
+                nonce,
+            } => Proto::SetMinters(v1::SetMinters {
+                admin: admin.to_string(),
+                minters: minters.iter().map(Account::to_string).collect(),
+                fee,
+                nonce,
+            }),
+            Tx::RegisterAlias {
+                account,
+                name,
+                fee,
+                nonce,
+            } => Proto::RegisterAlias(v1::RegisterAlias {
+                account: account.to_string(),
+                name,
+                fee,
+                nonce,
+            }),
+            Tx::Multisig {
+                account,
+                action,
+                fee,
+                nonce,
+            } => Proto::Multisig(v1::Multisig {
+                account: account.to_string(),
+                action: Some(match action {
+                    MultisigAction::Propose {
+                        multisig,
+                        to,
+                        value,
+                    } => v1::multisig::Action::Propose(v1::MultisigPropose {
+                        multisig: multisig.to_string(),
+                        to: to.to_string(),
+                        value,
+                    }),
+                    MultisigAction::Approve { proposal } => {
+                        v1::multisig::Action::Approve(proposal.as_bytes().to_vec())
+                    }
+                    MultisigAction::Execute { proposal } => {
+                        v1::multisig::Action::Execute(proposal.as_bytes().to_vec())
+                    }
+                }),
+                fee,
+                nonce,
+            }),
+            Tx::EscrowCreate {
+                from,
+                to,
+                value,
+                arbiter,
+                fee,
+                nonce,
+            } => Proto::EscrowCreate(v1::EscrowCreate {
+                from: from.to_string(),
+                to: to.to_string(),
+                value,
+                arbiter: arbiter.map(|arbiter| arbiter.to_string()),
+                fee,
+                nonce,
+            }),
+            Tx::EscrowRelease {
+                account,
+                escrow,
+                fee,
+                nonce,
+            } => Proto::EscrowRelease(v1::EscrowRelease {
+                account: account.to_string(),
+                escrow: escrow.as_bytes().to_vec(),
+                fee,
+                nonce,
+            }),
+            Tx::EscrowRefund {
+                account,
+                escrow,
+                fee,
+                nonce,
+            } => Proto::EscrowRefund(v1::EscrowRefund {
+                account: account.to_string(),
+                escrow: escrow.as_bytes().to_vec(),
+                fee,
+                nonce,
+            }),
+            Tx::Propose {
+                account,
+                change,
+                fee,
+                nonce,
+            } => Proto::Propose(v1::Propose {
+                account: account.to_string(),
+                change: Some(match change {
+                    ParamChange::MinFee(min_fee) => v1::propose::Change::MinFee(min_fee),
+                    ParamChange::UnbondingPeriod(period) => {
+                        v1::propose::Change::UnbondingPeriod(period)
+                    }
+                }),
+                fee,
+                nonce,
+            }),
+            Tx::Vote {
+                account,
+                proposal,
+                approve,
+                fee,
+                nonce,
+            } => Proto::Vote(v1::Vote {
+                account: account.to_string(),
+                proposal: proposal.as_bytes().to_vec(),
+                approve,
+                fee,
+                nonce,
+            }),
+            Tx::Script {
+                account,
+                code,
+                gas_limit,
+                fee,
+                nonce,
+            } => Proto::Script(v1::Script {
+                account: account.to_string(),
+                code: code.iter().map(v1::Op::from).collect(),
+                gas_limit,
+                fee,
+                nonce,
+            }),
+            Tx::Burn {
+                account,
+                value,
+                fee,
+                nonce,
+            } => Proto::Burn(v1::Burn {
+                account: account.to_string(),
+                value,
+                fee,
+                nonce,
+            }),
+        };
+
+        Self { tx: Some(tx) }
+    }
+}
+
+impl TryFrom<v1::Tx> for Tx {
+    type Error = ChiguiError;
+
+    fn try_from(tx: v1::Tx) -> Result<Self> {
+        use v1::tx::Tx as Proto;
+
+        Ok(match required(tx.tx, "transaction")? {
+            Proto::Transfer(tx) => Tx::Transfer {
+                from: Account::new(tx.from)?,
+                to: Account::new(tx.to)?,
+                value: tx.value,
+                fee: tx.fee,
+                nonce: tx.nonce,
+                memo: tx.memo,
+                denom: tx.denom,
+            },
+            Proto::Generate(tx) => Tx::Generate {
+                to: Account::new(tx.to)?,
+                value: tx.value,
+                denom: tx.denom,
+            },
+            Proto::TransferLocked(tx) => Tx::TransferLocked {
+                from: Account::new(tx.from)?,
+                to: Account::new(tx.to)?,
+                value: tx.value,
+                unlock: match required(tx.unlock, "unlock")? {
+                    v1::transfer_locked::Unlock::UnlockHeight(height) => Unlock::Height(height),
+                    v1::transfer_locked::Unlock::UnlockTime(time) => Unlock::Time(time),
+                },
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::TransferVesting(tx) => Tx::TransferVesting {
+                from: Account::new(tx.from)?,
+                to: Account::new(tx.to)?,
+                value: tx.value,
+                cliff: tx.cliff,
+                duration: tx.duration,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::TransferMulti(tx) => Tx::TransferMulti {
+                from: Account::new(tx.from)?,
+                payments: tx
+                    .payments
+                    .into_iter()
+                    .map(|payment| {
+                        Ok(Payment {
+                            to: Account::new(payment.to)?,
+                            value: payment.value,
+                        })
+                    })
+                    .collect::<Result<Vec<Payment>>>()?,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::Stake(tx) => Tx::Stake {
+                account: Account::new(tx.account)?,
+                value: tx.value,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::Unstake(tx) => Tx::Unstake {
+                account: Account::new(tx.account)?,
+                value: tx.value,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::SetMinters(tx) => Tx::SetMinters {
+                admin: Account::new(tx.admin)?,
+                minters: tx
+                    .minters
+                    .into_iter()
+                    .map(Account::new)
+                    .collect::<Result<Vec<Account>>>()?,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::RegisterAlias(tx) => Tx::RegisterAlias {
+                account: Account::new(tx.account)?,
+                name: tx.name,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::Multisig(tx) => Tx::Multisig {
+                account: Account::new(tx.account)?,
+                action: match required(tx.action, "multisig action")? {
+                    v1::multisig::Action::Propose(propose) => MultisigAction::Propose {
+                        multisig: Account::new(propose.multisig)?,
+                        to: Account::new(propose.to)?,
+                        value: propose.value,
+                    },
+                    v1::multisig::Action::Approve(proposal) => MultisigAction::Approve {
+                        proposal: hash(proposal, "approve")?,
+                    },
+                    v1::multisig::Action::Execute(proposal) => MultisigAction::Execute {
+                        proposal: hash(proposal, "execute")?,
+                    },
+                },
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::EscrowCreate(tx) => Tx::EscrowCreate {
+                from: Account::new(tx.from)?,
+                to: Account::new(tx.to)?,
+                value: tx.value,
+                arbiter: tx.arbiter.map(Account::new).transpose()?,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::EscrowRelease(tx) => Tx::EscrowRelease {
+                account: Account::new(tx.account)?,
+                escrow: hash(tx.escrow, "escrow")?,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::EscrowRefund(tx) => Tx::EscrowRefund {
+                account: Account::new(tx.account)?,
+                escrow: hash(tx.escrow, "escrow")?,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::Propose(tx) => Tx::Propose {
+                account: Account::new(tx.account)?,
+                change: match required(tx.change, "parameter change")? {
+                    v1::propose::Change::MinFee(min_fee) => ParamChange::MinFee(min_fee),
+                    v1::propose::Change::UnbondingPeriod(period) => {
+                        ParamChange::UnbondingPeriod(period)
+                    }
+                },
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::Vote(tx) => Tx::Vote {
+                account: Account::new(tx.account)?,
+                proposal: hash(tx.proposal, "proposal")?,
+                approve: tx.approve,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::Script(tx) => Tx::Script {
+                account: Account::new(tx.account)?,
+                code: tx
+                    .code
+                    .into_iter()
+                    .map(Op::try_from)
+                    .collect::<Result<Vec<Op>>>()?,
+                gas_limit: tx.gas_limit,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+            Proto::Burn(tx) => Tx::Burn {
+                account: Account::new(tx.account)?,
+                value: tx.value,
+                fee: tx.fee,
+                nonce: tx.nonce,
+            },
+        })
+    }
+}
+
+impl From<&Op> for v1::Op {
+    fn from(op: &Op) -> Self {
+        use v1::op::{Op as Proto, Simple};
+
+        let op = match op {
+            Op::Push(value) => Proto::Push(*value),
+            Op::Jump(target) => Proto::Jump(*target as u64),
+            Op::JumpIf(target) => Proto::JumpIf(*target as u64),
+            Op::Load(key) => Proto::Load(key.clone()),
+            Op::Store(key) => Proto::Store(key.clone()),
+            Op::Pop => Proto::Simple(Simple::Pop.into()),
+            Op::Dup => Proto::Simple(Simple::Dup.into()),
+            Op::Swap => Proto::Simple(Simple::Swap.into()),
+            Op::Add => Proto::Simple(Simple::Add.into()),
+            Op::Sub => Proto::Simple(Simple::Sub.into()),
+            Op::Mul => Proto::Simple(Simple::Mul.into()),
+            Op::Div => Proto::Simple(Simple::Div.into()),
+            Op::Eq => Proto::Simple(Simple::Eq.into()),
+            Op::Lt => Proto::Simple(Simple::Lt.into()),
+            Op::Not => Proto::Simple(Simple::Not.into()),
+            Op::Height => Proto::Simple(Simple::Height.into()),
+            Op::Halt => Proto::Simple(Simple::Halt.into()),
+        };
+
+        Self { op: Some(op) }
+    }
+}
+
+impl TryFrom<v1::Op> for Op {
+    type Error = ChiguiError;
+
+    fn try_from(op: v1::Op) -> Result<Self> {
+        use v1::op::{Op as Proto, Simple};
+
+        let target = |target: u64| {
+            usize::try_from(target).map_err(|_| invalid(format!("jump target {}", target)))
+        };
+
+        Ok(match required(op.op, "script instruction")? {
+            Proto::Push(value) => Op::Push(value),
+            Proto::Jump(to) => Op::Jump(target(to)?),
+            Proto::JumpIf(to) => Op::JumpIf(target(to)?),
+            Proto::Load(key) => Op::Load(key),
+            Proto::Store(key) => Op::Store(key),
+            Proto::Simple(simple) => match Simple::try_from(simple) {
+                Ok(Simple::Pop) => Op::Pop,
+                Ok(Simple::Dup) => Op::Dup,
+                Ok(Simple::Swap) => Op::Swap,
+                Ok(Simple::Add) => Op::Add,
+                Ok(Simple::Sub) => Op::Sub,
+                Ok(Simple::Mul) => Op::Mul,
+                Ok(Simple::Div) => Op::Div,
+                Ok(Simple::Eq) => Op::Eq,
+                Ok(Simple::Lt) => Op::Lt,
+                Ok(Simple::Not) => Op::Not,
+                Ok(Simple::Height) => Op::Height,
+                Ok(Simple::Halt) => Op::Halt,
+                Ok(Simple::Unspecified) | Err(_) => {
+                    return Err(invalid(format!("unknown script instruction {}", simple)));
+                }
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_sync_messages() -> Result<()> {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let (alice, bob) = (Account::new("alice")?, Account::new("bob")?);
+        let txs = vec![
+            Tx::Transfer {
+                from: alice.clone(),
+                to: bob.clone(),
+                value: 5,
+                fee: 1,
+                nonce: 0,
+                memo: Some("invoice 42".into()),
+                denom: None,
+            },
+            Tx::TransferLocked {
+                from: alice.clone(),
+                to: bob.clone(),
+                value: 1,
+                unlock: Unlock::Time(1_700_000_000),
+                fee: 0,
+                nonce: 1,
+            },
+            Tx::Multisig {
+                account: alice.clone(),
+                action: MultisigAction::Approve {
+                    proposal: Hash::new([3; 32]),
+                },
+                fee: 0,
+                nonce: 2,
+            },
+            Tx::Propose {
+                account: alice.clone(),
+                change: ParamChange::UnbondingPeriod(10),
+                fee: 0,
+                nonce: 3,
+            },
+            Tx::Script {
+                account: alice.clone(),
+                code: vec![Op::Push(1), Op::JumpIf(3), Op::Store("x".into()), Op::Halt],
+                gas_limit: 100,
+                fee: 0,
+                nonce: 4,
+            },
+            Tx::EscrowCreate {
+                from: alice.clone(),
+                to: bob.clone(),
+                value: 2,
+                arbiter: Some(Account::new("carol")?),
+                fee: 0,
+                nonce: 5,
+            },
+        ];
+        let mut block = Block::new(
+            BlockHeader {
+                number: 1,
+                parent_hash: Hash::default(),
+                tx_root: Hash::default(),
+                time: 1_600_000_000,
+                nonce: 7,
+            },
+            txs.into_iter()
+                .map(|tx| SignedTx::sign(tx, "testnet", &key))
+                .chain([SignedTx::unsigned(Tx::Generate {
+                    to: bob,
+                    value: 1,
+                    denom: Some("gold".into()),
+                })])
+                .collect(),
+        );
+
+        block.seal = Some(TxSignature::sign(&block.header.hash(), &key));
+
+        let messages = [
+            Message::Hello {
+                height: 1,
+                state_root: Some(Hash::new([9; 32])),
+                listen: Some("127.0.0.1:9090".parse().unwrap()),
+            },
+            Message::GetBlocks { from: 1 },
+            Message::Blocks {
+                blocks: vec![block.clone()],
+            },
+            Message::GetPeers,
+            Message::Peers {
+                peers: vec!["10.0.0.1:9090".parse().unwrap()],
+            },
+        ];
+
+        for message in messages {
+            assert_eq!(decode(&encode(&message))?, message);
+        }
+
+        let decoded = Block::try_from(v1::Block::from(&block))?;
+
+        assert_eq!(decoded.header.hash(), block.header.hash());
+        assert!(decoded.txs.iter().all(|tx| tx.verify().is_ok()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        let short_hash = v1::SyncMessage {
+            message: Some(v1::sync_message::Message::Hello(v1::Hello {
+                height: 1,
+                state_root: Some(vec![0; 31]),
+                listen: None,
+            })),
+        };
+
+        assert!(matches!(
+            decode(&short_hash.encode_to_vec()),
+            Err(ChiguiError::InvalidProto { .. })
+        ));
+        assert!(matches!(
+            decode(&v1::SyncMessage::default().encode_to_vec()),
+            Err(ChiguiError::InvalidProto { .. })
+        ));
+        assert!(matches!(
+            decode(&[0xff]),
+            Err(ChiguiError::ProtoDecodeError { .. })
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ChiguiError, Result};
+use crate::{Account, Tx};
+
+/// The variant of a [`Tx`], without its payload.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum TxKind {
+    Transfer,
+    Generate,
+    TransferMulti,
+    TransferLocked,
+    TransferVesting,
+    EscrowCreate,
+    EscrowRelease,
+    EscrowRefund,
+    Propose,
+    Vote,
+    Script,
+    Stake,
+    Unstake,
+    SetMinters,
+    RegisterAlias,
+    Multisig,
+    Burn,
+}
+
+impl Display for TxKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TxKind::Transfer => write!(f, "transfer"),
+            TxKind::Generate => write!(f, "generate"),
+            TxKind::TransferMulti => write!(f, "transfermulti"),
+            TxKind::TransferLocked => write!(f, "transferlocked"),
+            TxKind::TransferVesting => write!(f, "transfervesting"),
+            TxKind::EscrowCreate => write!(f, "escrowcreate"),
+            TxKind::EscrowRelease => write!(f, "escrowrelease"),
+            TxKind::EscrowRefund => write!(f, "escrowrefund"),
+            TxKind::Propose => write!(f, "propose"),
+            TxKind::Vote => write!(f, "vote"),
+            TxKind::Script => write!(f, "script"),
+            TxKind::Stake => write!(f, "stake"),
+            TxKind::Unstake => write!(f, "unstake"),
+            TxKind::SetMinters => write!(f, "setminters"),
+            TxKind::RegisterAlias => write!(f, "registeralias"),
+            TxKind::Multisig => write!(f, "multisig"),
+            TxKind::Burn => write!(f, "burn"),
+        }
+    }
+}
+
+impl FromStr for TxKind {
+    type Err = ChiguiError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "transfer" => Ok(TxKind::Transfer),
+            "generate" => Ok(TxKind::Generate),
+            "transfermulti" => Ok(TxKind::TransferMulti),
+            "transferlocked" => Ok(TxKind::TransferLocked),
+            "transfervesting" => Ok(TxKind::TransferVesting),
+            "escrowcreate" => Ok(TxKind::EscrowCreate),
+            "escrowrelease" => Ok(TxKind::EscrowRelease),
+            "escrowrefund" => Ok(TxKind::EscrowRefund),
+            "propose" => Ok(TxKind::Propose),
+            "vote" => Ok(TxKind::Vote),
+            "script" => Ok(TxKind::Script),
+            "stake" => Ok(TxKind::Stake),
+            "unstake" => Ok(TxKind::Unstake),
+            "setminters" => Ok(TxKind::SetMinters),
+            "registeralias" => Ok(TxKind::RegisterAlias),
+            "multisig" => Ok(TxKind::Multisig),
+            "burn" => Ok(TxKind::Burn),
+            _ => Err(ChiguiError::InvalidTxKind {
+                kind: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Criteria a [`Tx`] must all satisfy to be selected. Unset criteria match everything.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxFilter {
+    /// Only transactions touching this account.
+    pub account: Option<Account>,
+    pub kind: Option<TxKind>,
+    /// Only transactions moving at least this many coins.
+    pub min_value: Option<u64>,
+    /// Only transfers whose memo contains this text.
+    pub memo: Option<String>,
+}
+
+impl TxFilter {
+    pub fn matches(&self, tx: &Tx) -> bool {
+        let account = self
+            .account
+            .as_ref()
+            .is_none_or(|account| tx.accounts().contains(&account));
+        let kind = self.kind.is_none_or(|kind| tx.kind() == kind);
+        let min_value = self
+            .min_value
+            .is_none_or(|min_value| tx.value() >= min_value);
+        let memo = self
+            .memo
+            .as_ref()
+            .is_none_or(|needle| tx.memo().is_some_and(|memo| memo.contains(needle.as_str())));
+
+        account && kind && min_value && memo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_matches_all_criteria() -> Result<()> {
+        let tx = Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 10,
+            fee: 0,
+            nonce: 0,
+            memo: Some(String::from("invoice #42")),
+            denom: None,
+        };
+        let filter = TxFilter {
+            account: Some(Account::new("bob")?),
+            kind: Some("transfer".parse()?),
+            min_value: Some(10),
+            memo: Some(String::from("#42")),
+        };
+
+        assert!(filter.matches(&tx));
+        assert!(TxFilter::default().matches(&tx));
+        assert!(
+            !TxFilter {
+                min_value: Some(11),
+                ..filter.clone()
+            }
+            .matches(&tx)
+        );
+        assert!(
+            !TxFilter {
+                kind: Some(TxKind::Generate),
+                ..filter.clone()
+            }
+            .matches(&tx)
+        );
+        assert!(
+            !TxFilter {
+                memo: Some(String::from("#43")),
+                ..filter.clone()
+            }
+            .matches(&tx)
+        );
+        assert!(
+            !TxFilter {
+                account: Some(Account::new("carol")?),
+                ..filter
+            }
+            .matches(&tx)
+        );
+        assert_eq!("burn".parse::<TxKind>()?, TxKind::Burn);
+        assert!("swap".parse::<TxKind>().is_err());
+
+        Ok(())
+    }
+}
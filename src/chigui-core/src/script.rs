@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Account;
+use crate::error::{ChiguiError, Result};
+
+/// Most values a script may hold on its stack.
+pub const MAX_STACK: usize = 256;
+
+/// One instruction of a script, e.g. `{"push":1}` or `"add"` in JSON.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    Push(u64),
+    Pop,
+    Dup,
+    Swap,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// Push `1` if the two topmost values are equal, `0` otherwise.
+    Eq,
+    /// Push `1` if the second value is lower than the topmost one, `0` otherwise.
+    Lt,
+    /// Push `1` if the topmost value is `0`, `0` otherwise.
+    Not,
+    /// Continue at the given instruction.
+    Jump(usize),
+    /// Pop a value and continue at the given instruction unless it's `0`.
+    JumpIf(usize),
+    /// Push the value stored under the key, `0` if unset.
+    Load(String),
+    /// Pop a value and store it under the key.
+    Store(String),
+    /// Push the number of the block the script runs in.
+    Height,
+    /// Stop successfully.
+    Halt,
+}
+
+impl Op {
+    /// Gas charged for running this instruction.
+    pub fn gas(&self) -> u64 {
+        match self {
+            Op::Load(_) => 10,
+            Op::Store(_) => 50,
+            _ => 1,
+        }
+    }
+}
+
+/// Key-value storage of each account written by scripts.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ContractStore {
+    accounts: BTreeMap<Account, BTreeMap<String, u64>>,
+}
+
+impl ContractStore {
+    pub fn get(&self, account: &Account, key: &str) -> Option<u64> {
+        self.accounts.get(account)?.get(key).copied()
+    }
+
+    /// Every key stored by scripts of the account, sorted.
+    pub fn storage_of(&self, account: &Account) -> Option<&BTreeMap<String, u64>> {
+        self.accounts.get(account)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Run a script on behalf of the account, returning the gas it used. Its writes are only kept
+    /// if it succeeds.
+    pub(crate) fn run(
+        &mut self,
+        account: &Account,
+        code: &[Op],
+        height: u64,
+        gas_limit: u64,
+    ) -> Result<u64> {
+        let mut storage = self.accounts.get(account).cloned().unwrap_or_default();
+        let gas = execute(code, &mut storage, height, gas_limit)?;
+
+        if !storage.is_empty() {
+            self.accounts.insert(account.clone(), storage);
+        }
+
+        Ok(gas)
+    }
+}
+
+/// Run a script of the experimental stack machine behind [`Tx::Script`](crate::Tx::Script)
+/// against the given storage, returning the gas it used.
+///
+/// Scripts operate on a stack of `u64` values. Every instruction costs gas, and a script running
+/// out of its limit fails with its transaction, so execution always terminates the same way on
+/// every node.
+pub fn execute(
+    code: &[Op],
+    storage: &mut BTreeMap<String, u64>,
+    height: u64,
+    gas_limit: u64,
+) -> Result<u64> {
+    let mut stack = Vec::<u64>::new();
+    let mut gas = 0u64;
+    let mut pc = 0;
+
+    while let Some(op) = code.get(pc) {
+        let at = pc;
+        let fail = move |reason: &str| ChiguiError::ScriptFailed {
+            pc: at,
+            reason: reason.to_string(),
+        };
+
+        gas = gas.saturating_add(op.gas());
+
+        if gas > gas_limit {
+            return Err(ChiguiError::OutOfGas { limit: gas_limit });
+        }
+
+        let mut pop = || stack.pop().ok_or_else(|| fail("stack underflow"));
+
+        pc += 1;
+
+        match op {
+            Op::Push(value) => stack.push(*value),
+            Op::Pop => {
+                pop()?;
+            }
+            Op::Dup => {
+                let value = pop()?;
+
+                stack.extend([value, value]);
+            }
+            Op::Swap => {
+                let (a, b) = (pop()?, pop()?);
+
+                stack.extend([a, b]);
+            }
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Eq | Op::Lt => {
+                let (b, a) = (pop()?, pop()?);
+                let result = match op {
+                    Op::Add => a.checked_add(b).ok_or_else(|| fail("overflow"))?,
+                    Op::Sub => a.checked_sub(b).ok_or_else(|| fail("underflow"))?,
+                    Op::Mul => a.checked_mul(b).ok_or_else(|| fail("overflow"))?,
+                    Op::Div => a.checked_div(b).ok_or_else(|| fail("division by zero"))?,
+                    Op::Eq => u64::from(a == b),
+                    _ => u64::from(a < b),
+                };
+
+                stack.push(result);
+            }
+            Op::Not => {
+                let value = pop()?;
+
+                stack.push(u64::from(value == 0));
+            }
+            Op::Jump(target) => pc = *target,
+            Op::JumpIf(target) => {
+                if pop()? != 0 {
+                    pc = *target;
+                }
+            }
+            Op::Load(key) => stack.push(storage.get(key).copied().unwrap_or_default()),
+            Op::Store(key) => {
+                let value = pop()?;
+
+                storage.insert(key.clone(), value);
+            }
+            Op::Height => stack.push(height),
+            Op::Halt => break,
+        }
+
+        if stack.len() > MAX_STACK {
+            return Err(fail("stack overflow"));
+        }
+    }
+
+    Ok(gas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_loops_within_gas() -> Result<()> {
+        // Add 1 to `counter` three times.
+        let code = serde_json::from_str::<Vec<Op>>(
+            r#"[{"push":3},"dup","not",{"jump_if":11},{"load":"counter"},{"push":1},"add",{"store":"counter"},{"push":1},"sub",{"jump":1},"halt"]"#,
+        )
+        .expect("valid script");
+        let mut storage = BTreeMap::new();
+
+        let gas = execute(&code, &mut storage, 1, 1000)?;
+
+        assert_eq!(storage.get("counter"), Some(&3));
+        assert_eq!(gas, 1 + 3 * 68 + 4);
+        assert!(matches!(
+            execute(&code, &mut storage, 1, 100),
+            Err(ChiguiError::OutOfGas { limit: 100 })
+        ));
+        assert!(matches!(
+            execute(&[Op::Add], &mut storage, 1, 100),
+            Err(ChiguiError::ScriptFailed { pc: 0, .. })
+        ));
+
+        Ok(())
+    }
+}
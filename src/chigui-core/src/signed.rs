@@ -0,0 +1,257 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+use crate::error::{ChiguiError, Result};
+use crate::migrate::LegacyRules;
+use crate::{Hash, Tx};
+
+/// An ed25519 public key, (de)serialized as a lowercase hex string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PublicKey(VerifyingKey);
+
+impl PublicKey {
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self> {
+        VerifyingKey::from_bytes(bytes)
+            .map(Self)
+            .map_err(|_| ChiguiError::InvalidPublicKey {
+                key: hex::encode(bytes),
+            })
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        self.0.as_bytes()
+    }
+}
+
+impl From<&SigningKey> for PublicKey {
+    fn from(key: &SigningKey) -> Self {
+        Self(key.verifying_key())
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.as_bytes()))
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = ChiguiError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut bytes = [0; 32];
+
+        hex::decode_to_slice(s, &mut bytes)
+            .map_err(|_| ChiguiError::InvalidPublicKey { key: s.to_string() })?;
+
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// An ed25519 signature, (de)serialized as a lowercase hex string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature(ed25519_dalek::Signature);
+
+impl Signature {
+    pub fn from_bytes(bytes: &[u8; 64]) -> Self {
+        Self(ed25519_dalek::Signature::from_bytes(bytes))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 64] {
+        self.0.to_bytes()
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0.to_bytes()))
+    }
+}
+
+impl FromStr for Signature {
+    type Err = ChiguiError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut bytes = [0; 64];
+
+        hex::decode_to_slice(s, &mut bytes).map_err(|_| ChiguiError::InvalidSignature)?;
+
+        Ok(Self(ed25519_dalek::Signature::from_bytes(&bytes)))
+    }
+}
+
+impl Serialize for Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// The signer's public key together with its signature over a transaction or a block header.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxSignature {
+    pub public_key: PublicKey,
+    pub signature: Signature,
+}
+
+impl TxSignature {
+    /// Sign the given digest with the given key.
+    pub fn sign(payload: &Hash, key: &SigningKey) -> Self {
+        Self {
+            public_key: PublicKey::from(key),
+            signature: Signature(key.sign(payload.as_bytes())),
+        }
+    }
+
+    /// Check the signature against the given digest.
+    pub fn verify(&self, payload: &Hash) -> Result<()> {
+        self.public_key
+            .0
+            .verify(payload.as_bytes(), &self.signature.0)
+            .map_err(|_| ChiguiError::InvalidSignature)
+    }
+}
+
+/// A [`Tx`] as stored in blocks, optionally authenticated by an ed25519 signature.
+///
+/// Unsigned transfers are only accepted by chains running in permissive (dev) mode.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedTx {
+    pub tx: Tx,
+    /// Chain the signature is bound to, so it can't be replayed on another chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<TxSignature>,
+}
+
+impl SignedTx {
+    /// Sign the given transaction for the given chain with the given key.
+    pub fn sign(tx: Tx, chain_id: &str, key: &SigningKey) -> Self {
+        let signature = TxSignature::sign(&Self::payload(chain_id, &tx), key);
+
+        Self {
+            tx,
+            chain_id: Some(chain_id.to_owned()),
+            signature: Some(signature),
+        }
+    }
+
+    pub fn unsigned(tx: Tx) -> Self {
+        Self {
+            tx,
+            chain_id: None,
+            signature: None,
+        }
+    }
+
+    /// The hash of a signed transaction is the hash of the transaction itself.
+    pub fn hash(&self) -> Hash {
+        self.tx.hash()
+    }
+
+    /// Check the signature, if any, against the transaction payload.
+    ///
+    /// Returns the signer's public key, or `None` for unsigned transactions.
+    pub fn verify(&self) -> Result<Option<&PublicKey>> {
+        let Some(signature) = &self.signature else {
+            return Ok(None);
+        };
+
+        let payload = match self.chain_id.as_deref() {
+            None if LegacyRules::current().unbound_signatures => self.tx.hash(),
+            chain_id => Self::payload(chain_id.unwrap_or_default(), &self.tx),
+        };
+
+        signature.verify(&payload)?;
+
+        Ok(Some(&signature.public_key))
+    }
+
+    /// The bytes covered by the signature: the chain ID and the transaction.
+    fn payload(chain_id: &str, tx: &Tx) -> Hash {
+        Hash::of(&(chain_id, tx.hash()))
+    }
+}
+
+impl From<Tx> for SignedTx {
+    fn from(tx: Tx) -> Self {
+        Self::unsigned(tx)
+    }
+}
+
+impl Display for SignedTx {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.signature {
+            Some(signature) => write!(f, "{} [SIG {}]", self.tx, signature.public_key),
+            None => write!(f, "{}", self.tx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Account;
+
+    #[test]
+    fn sign_and_verify() -> Result<()> {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let tx = Tx::Generate {
+            to: Account::new("alice")?,
+            value: 1,
+            denom: None,
+        };
+        let signed = SignedTx::sign(tx, "testnet", &key);
+
+        assert_eq!(signed.verify()?, Some(&PublicKey::from(&key)));
+
+        let json = serde_json::to_string(&signed).unwrap();
+        let parsed = serde_json::from_str::<SignedTx>(&json).unwrap();
+        assert_eq!(parsed, signed);
+
+        let mut tampered = signed.clone();
+        tampered.tx = Tx::Generate {
+            to: Account::new("alice")?,
+            value: 2,
+            denom: None,
+        };
+        assert!(matches!(
+            tampered.verify(),
+            Err(ChiguiError::InvalidSignature)
+        ));
+
+        let mut replayed = signed.clone();
+        replayed.chain_id = Some("mainnet".into());
+        assert!(matches!(
+            replayed.verify(),
+            Err(ChiguiError::InvalidSignature)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::{Account, Tx};
+
+/// What applying a transaction would do, as reported by [`State::simulate`](crate::state::State::simulate).
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct SimulationOutcome {
+    pub fee: u64,
+    /// Native balances of the accounts the transaction names or changes, fee collector included.
+    pub balances: BTreeMap<Account, BalanceChange>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub before: u64,
+    pub after: u64,
+}
+
+impl BalanceChange {
+    pub fn delta(&self) -> i128 {
+        i128::from(self.after) - i128::from(self.before)
+    }
+}
+
+impl SimulationOutcome {
+    pub(crate) fn new(
+        tx: &Tx,
+        before: &HashMap<Account, u64>,
+        after: &HashMap<Account, u64>,
+    ) -> Self {
+        let changed = after
+            .iter()
+            .filter(|(account, balance)| before.get(*account) != Some(*balance))
+            .map(|(account, _)| account);
+        let balances = tx
+            .accounts()
+            .into_iter()
+            .chain(changed)
+            .map(|account| {
+                let change = BalanceChange {
+                    before: before.get(account).copied().unwrap_or_default(),
+                    after: after.get(account).copied().unwrap_or_default(),
+                };
+
+                (account.clone(), change)
+            })
+            .collect();
+
+        Self {
+            fee: tx.fee(),
+            balances,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ChiguiError, Result};
+    use crate::state::State;
+
+    #[test]
+    fn simulates_without_applying() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10,"bob":0,"carol":0},"fee_collector":"carol"}"#,
+        )?;
+        let state = State::in_memory(genesis)?;
+        let (alice, bob, carol) = (
+            Account::new("alice")?,
+            Account::new("bob")?,
+            Account::new("carol")?,
+        );
+        let transfer = |value| Tx::Transfer {
+            from: alice.clone(),
+            to: bob.clone(),
+            value,
+            fee: 1,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        };
+        let root = state.state_root();
+        let outcome = state.simulate(&transfer(4))?;
+
+        assert_eq!(outcome.fee, 1);
+        assert_eq!(outcome.balances.len(), 3);
+        assert_eq!(outcome.balances[&alice].delta(), -5);
+        assert_eq!(
+            outcome.balances[&bob],
+            BalanceChange {
+                before: 0,
+                after: 4
+            }
+        );
+        assert_eq!(outcome.balances[&carol].after, 1);
+        assert!(matches!(
+            state.simulate(&transfer(10)),
+            Err(ChiguiError::InsufficientBalance { .. })
+        ));
+        assert_eq!(state.state_root(), root);
+        assert_eq!(state.next_nonce(&alice), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ChiguiError, Result};
+use crate::escrow::Escrows;
+use crate::governance::{Governance, Params};
+use crate::multisig::Proposals;
+use crate::script::ContractStore;
+use crate::staking::StakeRegistry;
+use crate::timelock::LockRegistry;
+use crate::vesting::VestingRegistry;
+use crate::{Account, Hash};
+
+/// Number of blocks between two snapshots written by [`State::add_block`](crate::state::State).
+pub const SNAPSHOT_INTERVAL: u64 = 1000;
+
+/// The state after a given block, persisted as `snapshot-<height>.json` in the db dir so that
+/// opening the chain only replays the blocks past it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Snapshot {
+    pub height: u64,
+    /// Hash of the block at `height`, checked against `block.db` when loading the snapshot.
+    pub block_hash: Hash,
+    pub state_root: Hash,
+    pub balances: BTreeMap<Account, u64>,
+    /// Balances of the assets declared in the genesis, by denomination.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<String, BTreeMap<Account, u64>>,
+    #[serde(default)]
+    pub nonces: BTreeMap<Account, u64>,
+    #[serde(default)]
+    pub stakes: StakeRegistry,
+    /// Accounts allowed to sign `Generate` transactions, unrestricted when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minters: Option<BTreeSet<Account>>,
+    /// Registered aliases and the accounts they resolve to.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, Account>,
+    /// Multisig transfers waiting for approvals.
+    #[serde(default, skip_serializing_if = "Proposals::is_empty")]
+    pub proposals: Proposals,
+    /// Coins sent by time-locked transfers, not spendable yet.
+    #[serde(default, skip_serializing_if = "LockRegistry::is_empty")]
+    pub locks: LockRegistry,
+    /// Vesting schedules with coins left to vest.
+    #[serde(default, skip_serializing_if = "VestingRegistry::is_empty")]
+    pub vesting: VestingRegistry,
+    /// Coins held in escrows not released or refunded yet.
+    #[serde(default, skip_serializing_if = "Escrows::is_empty")]
+    pub escrows: Escrows,
+    /// Chain parameters changed by governance, the genesis ones when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Params>,
+    /// Governance proposals open to votes.
+    #[serde(default, skip_serializing_if = "Governance::is_empty")]
+    pub governance: Governance,
+    /// Storage written by scripts, per account.
+    #[serde(default, skip_serializing_if = "ContractStore::is_empty")]
+    pub contracts: ContractStore,
+}
+
+impl Snapshot {
+    pub fn path(dbdir: &Path, height: u64) -> PathBuf {
+        dbdir.join(format!("snapshot-{}.json", height))
+    }
+
+    /// Write the snapshot to the db dir, returning its path.
+    pub fn write(&self, dbdir: &Path) -> Result<PathBuf> {
+        let path = Self::path(dbdir, self.height);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|source| ChiguiError::SerializeError { source })?;
+
+        std::fs::write(&path, json).map_err(|source| ChiguiError::Io {
+            path: path.clone(),
+            source,
+        })?;
+
+        Ok(path)
+    }
+
+    /// Heights of the snapshots found in the db dir, in ascending order.
+    pub fn heights(dbdir: &Path) -> Result<Vec<u64>> {
+        let io_error = |source| ChiguiError::Io {
+            path: dbdir.to_path_buf(),
+            source,
+        };
+        let mut heights = Vec::new();
+
+        for entry in std::fs::read_dir(dbdir).map_err(io_error)? {
+            let name = entry.map_err(io_error)?.file_name();
+            let height = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("snapshot-"))
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|height| height.parse::<u64>().ok());
+
+            heights.extend(height);
+        }
+
+        heights.sort_unstable();
+
+        Ok(heights)
+    }
+
+    /// Load the most recent snapshot no higher than `max_height`, if any.
+    pub fn latest(dbdir: &Path, max_height: u64) -> Result<Option<Self>> {
+        let Some(height) = Self::heights(dbdir)?
+            .into_iter()
+            .rfind(|height| *height <= max_height)
+        else {
+            return Ok(None);
+        };
+        let path = Self::path(dbdir, height);
+        let json = std::fs::read_to_string(&path).map_err(|source| ChiguiError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let snapshot = serde_json::from_str(&json)
+            .map_err(|source| ChiguiError::SnapshotParseError { path, source })?;
+
+        Ok(Some(snapshot))
+    }
+}
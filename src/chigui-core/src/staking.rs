@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ChiguiError, Result};
+use crate::{Account, Hash};
+
+/// Coins leaving a validator's stake, locked until the chain reaches `release_height`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Unbonding {
+    pub account: Account,
+    pub value: u64,
+    pub release_height: u64,
+}
+
+/// Coins locked by [`Tx::Stake`](crate::Tx::Stake), per validator, along with the coins being
+/// unbonded by [`Tx::Unstake`](crate::Tx::Unstake).
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StakeRegistry {
+    stakes: BTreeMap<Account, u64>,
+    unbonding: Vec<Unbonding>,
+}
+
+impl StakeRegistry {
+    pub fn stake_of(&self, account: &Account) -> u64 {
+        self.stakes.get(account).copied().unwrap_or_default()
+    }
+
+    pub fn total_stake(&self) -> u64 {
+        self.stakes.values().sum()
+    }
+
+    /// Every account with a non-zero stake, sorted by account.
+    pub fn validators(&self) -> impl Iterator<Item = (&Account, u64)> {
+        self.stakes.iter().map(|(account, stake)| (account, *stake))
+    }
+
+    pub fn unbonding(&self) -> &[Unbonding] {
+        &self.unbonding
+    }
+
+    /// Pick a validator with a probability proportional to its stake, using `seed` as the source
+    /// of randomness so every node picks the same one.
+    pub fn select(&self, seed: &Hash) -> Option<&Account> {
+        let total = self.total_stake();
+
+        if total == 0 {
+            return None;
+        }
+
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&seed.as_bytes()[..8]);
+
+        let mut ticket = u64::from_be_bytes(bytes) % total;
+
+        self.stakes.iter().find_map(|(account, stake)| {
+            if ticket < *stake {
+                Some(account)
+            } else {
+                ticket -= stake;
+                None
+            }
+        })
+    }
+
+    pub(crate) fn bond(&mut self, account: &Account, value: u64) -> Result<()> {
+        if value > 0 {
+            let stake = self.stakes.entry(account.clone()).or_default();
+
+            *stake = stake
+                .checked_add(value)
+                .ok_or_else(|| ChiguiError::Overflow {
+                    account: account.clone(),
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Ensure the account has at least `value` coins staked.
+    pub(crate) fn check_unbond(&self, account: &Account, value: u64) -> Result<()> {
+        let have = self.stake_of(account);
+
+        if value > have {
+            return Err(ChiguiError::InsufficientStake {
+                account: account.clone(),
+                have,
+                need: value,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Move `value` staked coins to the unbonding queue until `release_height`.
+    pub(crate) fn unbond(
+        &mut self,
+        account: &Account,
+        value: u64,
+        release_height: u64,
+    ) -> Result<()> {
+        self.check_unbond(account, value)?;
+
+        let stake = self.stakes.entry(account.clone()).or_default();
+        *stake -= value;
+
+        if *stake == 0 {
+            self.stakes.remove(account);
+        }
+
+        self.unbonding.push(Unbonding {
+            account: account.clone(),
+            value,
+            release_height,
+        });
+
+        Ok(())
+    }
+
+    /// Remove and return the unbonding entries released at the given height.
+    pub(crate) fn release(&mut self, height: u64) -> Vec<Unbonding> {
+        let (released, locked) = self
+            .unbonding
+            .drain(..)
+            .partition(|entry| entry.release_height <= height);
+
+        self.unbonding = locked;
+
+        released
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_is_stake_weighted() -> Result<()> {
+        let alice = Account::new("alice")?;
+        let bob = Account::new("bob")?;
+        let mut registry = StakeRegistry::default();
+
+        assert_eq!(registry.select(&Hash::default()), None);
+
+        registry.bond(&alice, 1)?;
+        registry.bond(&bob, 3)?;
+
+        let picks = (0..400u32)
+            .filter_map(|i| registry.select(&Hash::digest(&i.to_be_bytes())))
+            .filter(|account| **account == bob)
+            .count();
+
+        assert!((250..350).contains(&picks));
+
+        registry.unbond(&bob, 3, 10)?;
+
+        assert_eq!(registry.select(&Hash::digest(b"seed")), Some(&alice));
+        assert!(registry.unbond(&alice, 2, 10).is_err());
+        assert!(registry.release(9).is_empty());
+        assert_eq!(registry.release(10).len(), 1);
+
+        Ok(())
+    }
+}
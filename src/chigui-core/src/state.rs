@@ -1,169 +1,4486 @@
-use std::cell::RefCell;
-use std::fs::read_to_string;
-use std::{collections::HashMap, path::Path};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Error, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, info, warn};
 
-use crate::{Account, Tx};
+use crate::backup::Backup;
+use crate::block::{Block, BlockHeader};
+use crate::consensus::Validators;
+use crate::diff::StateDiff;
+use crate::error::{ChiguiError, Result};
+use crate::escrow::{Escrow, Escrows};
+use crate::fee::FeeSchedule;
+use crate::fork::{self, Reorg};
+use crate::gas::TX_GAS;
+use crate::governance::{Governance, GovernanceRules, Params};
+use crate::hooks::{AppliedTx, Hooks};
+use crate::index::AccountIndex;
+use crate::light::BalanceProof;
+use crate::mempool::Mempool;
+use crate::merkle::{self, MerkleProof};
+use crate::migrate::{self, LegacyRules};
+use crate::miner::{CancelHandle, Miner};
+use crate::multisig::{MultisigAction, MultisigPolicy, Proposals};
+use crate::query::TxFilter;
+use crate::script::ContractStore;
+use crate::signed::{PublicKey, SignedTx};
+use crate::simulate::SimulationOutcome;
+use crate::snapshot::{SNAPSHOT_INTERVAL, Snapshot};
+use crate::staking::StakeRegistry;
+use crate::statement::{self, StatementEntry};
+use crate::storage::memory::MemoryStorage;
+use crate::storage::{FileStorage, Storage};
+use crate::timelock::{LockRegistry, Unlock};
+use crate::vesting::{VestingRegistry, VestingSchedule};
+use crate::view::StateView;
+use crate::{Account, Hash, Payment, Tx, TxKind};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Genesis {
     genesis_time: String,
     chain_id: String,
     balances: HashMap<Account, u64>,
+    /// Assets tracked besides the native coin, by denomination, with their initial balances.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    assets: HashMap<String, HashMap<Account, u64>>,
+    /// Accounts whose coins only move once enough of their members approved the transfer.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    multisig: HashMap<Account, MultisigPolicy>,
+    /// Part of the genesis balance of accounts only spendable as it vests.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    vesting: HashMap<Account, VestingSchedule>,
+    /// What happens to coins sent to accounts missing from the balances.
+    #[serde(default, skip_serializing_if = "is_default")]
+    new_accounts: NewAccountPolicy,
+    /// Accounts allowed to sign `Generate` transactions. Anyone may generate coins when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    minters: Option<BTreeSet<Account>>,
+    /// Account allowed to replace the minters with a `SetMinters` transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    minter_admin: Option<Account>,
+    /// Native coins that may ever exist, `Generate` transactions minting past it are rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_supply: Option<u64>,
+    /// Account credited with transfer fees. Fees are burned when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fee_collector: Option<Account>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    fee_schedule: FeeSchedule,
+    /// Gas the transactions of a block may use altogether, see [`Tx::gas`]. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block_gas_limit: Option<u64>,
+    /// Leading zero bits every block header hash must have. `0` disables proof-of-work.
+    #[serde(default, skip_serializing_if = "is_default")]
+    difficulty: u32,
+    /// Public keys allowed to sign transfers on behalf of each account.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    account_keys: HashMap<Account, PublicKey>,
+    /// Dev mode accepting unsigned transfers.
+    #[serde(default, skip_serializing_if = "is_default")]
+    permissive: bool,
+    /// Validators sealing blocks in turn. When set, they replace proof-of-work.
+    #[serde(default, skip_serializing_if = "is_default")]
+    validators: Validators,
+    /// Blocks are sealed by a staker picked in proportion to its stake, once anything is staked.
+    #[serde(default, skip_serializing_if = "is_default")]
+    proof_of_stake: bool,
+    /// Blocks during which unstaked coins stay locked before returning to the balance.
+    #[serde(default, skip_serializing_if = "is_default")]
+    unbonding_period: u64,
+    /// How stakers vote on `Propose` transactions changing the parameters above.
+    #[serde(default, skip_serializing_if = "is_default")]
+    governance: GovernanceRules,
+}
+
+impl Genesis {
+    /// Identifier of the chain, telling it apart from others sharing the same software.
+    pub fn chain_id(&self) -> &str {
+        &self.chain_id
+    }
+
+    /// Time the chain started at, as written in the genesis, e.g. `2021-01-01T00:00:00Z`.
+    pub fn genesis_time(&self) -> &str {
+        &self.genesis_time
+    }
+
+    /// Native coin balances the chain started with.
+    pub fn balances(&self) -> &HashMap<Account, u64> {
+        &self.balances
+    }
+
+    /// Leading zero bits required of block hashes on proof-of-work chains.
+    pub fn difficulty(&self) -> u32 {
+        self.difficulty
+    }
+
+    /// Validators taking turns sealing blocks on proof-of-authority chains.
+    pub fn validators(&self) -> &Validators {
+        &self.validators
+    }
+
+    /// Whether blocks are sealed by the stakers taking turns rather than declared validators.
+    pub fn proof_of_stake(&self) -> bool {
+        self.proof_of_stake
+    }
+
+    /// Scaffold a chain starting from this genesis in the db dir: `genesis.json`, along with an
+    /// empty `block.db` and the manifest of the current schema version.
+    pub fn write_to<P: AsRef<Path>>(&self, dbdir: P) -> Result<()> {
+        let dbdir = dbdir.as_ref();
+        let io_error = |path: PathBuf| move |source| ChiguiError::Io { path, source };
+        let mut genesis =
+            serde_json::to_value(self).map_err(|source| ChiguiError::SerializeError { source })?;
+
+        if let Some(fields) = genesis.as_object_mut() {
+            fields.insert("version".into(), migrate::SCHEMA_VERSION.into());
+        }
+
+        let json = serde_json::to_string_pretty(&genesis)
+            .map_err(|source| ChiguiError::SerializeError { source })?;
+        let genesis_path = dbdir.join("genesis.json");
+        let block_db_path = dbdir.join("block.db");
+
+        std::fs::create_dir_all(dbdir).map_err(io_error(dbdir.to_path_buf()))?;
+        std::fs::write(&genesis_path, json + "\n").map_err(io_error(genesis_path))?;
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&block_db_path)
+            .map_err(io_error(block_db_path))?;
+
+        migrate::Manifest {
+            version: migrate::SCHEMA_VERSION,
+        }
+        .write(dbdir)
+    }
+}
+
+/// Assembles a [`Genesis`] in code rather than JSON, e.g.
+/// `GenesisBuilder::new("testnet").balance("alice", 1000).build()`.
+#[derive(Clone, Debug)]
+pub struct GenesisBuilder {
+    chain_id: String,
+    time: DateTime<Utc>,
+    balances: Vec<(String, u64)>,
+    permissive: bool,
+    difficulty: u32,
+}
+
+impl GenesisBuilder {
+    /// Start the genesis of a chain starting now, without any balance.
+    pub fn new(chain_id: impl Into<String>) -> Self {
+        Self {
+            chain_id: chain_id.into(),
+            time: Utc::now(),
+            balances: Vec::new(),
+            permissive: false,
+            difficulty: 0,
+        }
+    }
+
+    pub fn time(mut self, time: DateTime<Utc>) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Credit an account with native coins, replacing any balance set before.
+    pub fn balance(mut self, account: impl Into<String>, value: u64) -> Self {
+        self.balances.push((account.into(), value));
+        self
+    }
+
+    /// Accept unsigned transfers, for dev chains.
+    pub fn permissive(mut self, permissive: bool) -> Self {
+        self.permissive = permissive;
+        self
+    }
+
+    /// Require proof-of-work with the given number of leading zero bits.
+    pub fn difficulty(mut self, difficulty: u32) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    /// Assemble the genesis, failing on invalid account names.
+    pub fn build(self) -> Result<Genesis> {
+        let balances = self
+            .balances
+            .into_iter()
+            .map(|(account, value)| Ok((Account::new(account)?, value)))
+            .collect::<Result<HashMap<Account, u64>>>()?;
+
+        Ok(Genesis {
+            genesis_time: self.time.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            chain_id: self.chain_id,
+            balances,
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: self.difficulty,
+            account_keys: HashMap::new(),
+            permissive: self.permissive,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        })
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// How a chain treats transfers to accounts it doesn't know yet.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NewAccountPolicy {
+    /// Create the account with the received coins.
+    #[default]
+    Create,
+    /// Reject the transfer, accounts only come from the genesis.
+    Reject,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct State {
-    pub balances: RefCell<HashMap<Account, u64>>,
-    pub txs: Vec<Tx>,
+    balances: HashMap<Account, u64>,
+    /// Balances of the assets declared in the genesis, by denomination. Fees are always paid in
+    /// the native coin.
+    #[serde(default)]
+    assets: HashMap<String, HashMap<Account, u64>>,
+    txs: Vec<SignedTx>,
+    blocks: Vec<Block>,
     genesis: Genesis,
+    #[serde(skip)]
+    nonces: HashMap<Account, u64>,
+    #[serde(skip)]
+    stakes: StakeRegistry,
+    #[serde(skip)]
+    minters: Option<BTreeSet<Account>>,
+    /// Registered aliases and the accounts they resolve to.
+    #[serde(skip)]
+    aliases: HashMap<String, Account>,
+    #[serde(skip)]
+    proposals: Proposals,
+    #[serde(skip)]
+    locks: LockRegistry,
+    #[serde(skip)]
+    vesting: VestingRegistry,
+    #[serde(skip)]
+    escrows: Escrows,
+    #[serde(skip)]
+    params: Params,
+    #[serde(skip)]
+    governance: Governance,
+    #[serde(skip)]
+    contracts: ContractStore,
+    #[serde(skip)]
+    state_root: Hash,
+    /// Coins in existence, balances and stakes included, checked against them after every block.
+    #[serde(skip)]
+    supply: u64,
+    /// Height and hash of the last block pruned from `block.db`, whose state comes from a
+    /// [`Snapshot`].
+    #[serde(skip)]
+    base: (u64, Hash),
+    /// Number of recent blocks kept in `block.db` by pruned chains.
+    #[serde(skip)]
+    prune_window: Option<u64>,
+    /// On archival chains, the successive balances of every account along with the height of
+    /// the block that set them.
+    #[serde(skip)]
+    archive: Option<HashMap<Account, Vec<(u64, u64)>>>,
+    #[serde(skip)]
+    hooks: Hooks,
+    /// Transactions of the blocks held in memory by account.
+    #[serde(skip)]
+    by_account: AccountIndex,
+    /// Time taken by [`State::open`] to read the chain and replay its blocks.
+    #[serde(skip)]
+    load_duration: Duration,
+    #[serde(skip)]
+    storage: Option<SharedStorage>,
+}
+
+/// The storage of a [`State`], shared with the blocking tasks persisting blocks on the async API.
+type SharedStorage = Arc<Mutex<Box<dyn Storage>>>;
+
+/// What's needed to revert a block applied to a [`State`] that couldn't be persisted.
+pub(crate) struct Checkpoint {
+    balances: HashMap<Account, u64>,
+    assets: HashMap<String, HashMap<Account, u64>>,
+    nonces: HashMap<Account, u64>,
+    stakes: StakeRegistry,
+    minters: Option<BTreeSet<Account>>,
+    aliases: HashMap<String, Account>,
+    proposals: Proposals,
+    locks: LockRegistry,
+    vesting: VestingRegistry,
+    escrows: Escrows,
+    params: Params,
+    governance: Governance,
+    contracts: ContractStore,
+    state_root: Hash,
+    supply: u64,
+    txs: usize,
 }
 
 impl State {
+    /// Load the chain from the given db dir, starting from the most recent snapshot, if any, and
+    /// replaying the blocks past it. Db dirs of older releases are upgraded first, see
+    /// [`migrate::migrate`].
     pub fn open<P: AsRef<Path>>(dbdir: P) -> Result<Self> {
-        let genesis_path = dbdir.as_ref().join("genesis.json");
-        let tx_db_path = dbdir.as_ref().join("tx.db");
-        let genesis_json = read_to_string(genesis_path)?;
-        let tx_db = read_to_string(tx_db_path)?;
-        let genesis = Self::parse_genesis(&genesis_json)?;
-        let txs = Self::parse_txs(&tx_db)?;
-        let state = State::from_parts(genesis, txs)?;
+        migrate::migrate(dbdir.as_ref())?;
+
+        Self::with_storage(FileStorage::new(dbdir))
+    }
+
+    /// Load the chain from the given db dir, replaying every block from genesis to index the
+    /// balances of every account at every height, see [`State::balance_at`].
+    pub fn open_archival<P: AsRef<Path>>(dbdir: P) -> Result<Self> {
+        migrate::migrate(dbdir.as_ref())?;
+
+        Self::with_storage_archival(FileStorage::new(dbdir))
+    }
+
+    /// Load the chain from the given [`Storage`], starting from its most recent snapshot, if any,
+    /// and replaying the blocks past it. New blocks are persisted to the same storage.
+    pub fn with_storage(storage: impl Storage + 'static) -> Result<Self> {
+        Self::load(Box::new(storage), false)
+    }
+
+    /// Start a new chain from the given genesis, kept in a [`MemoryStorage`].
+    pub fn in_memory(genesis: Genesis) -> Result<Self> {
+        Self::with_storage(MemoryStorage::new(genesis))
+    }
+
+    /// Load the chain from the given [`Storage`] like [`State::open_archival`].
+    pub fn with_storage_archival(storage: impl Storage + 'static) -> Result<Self> {
+        Self::load(Box::new(storage), true)
+    }
+
+    /// Load the chain from the given [`Storage`] like [`State::open_pruned`].
+    pub fn with_storage_pruned(storage: impl Storage + 'static, window: u64) -> Result<Self> {
+        let mut state = Self::with_storage(storage)?;
+
+        state.prune_window = Some(window);
+        state.prune(window)?;
 
         Ok(state)
     }
 
-    pub fn get_balance(&self, acct: &Account) -> Option<u64> {
-        let balances = self.balances.borrow();
-        balances.get(acct).cloned()
+    #[tracing::instrument(level = "info", skip(storage))]
+    fn load(storage: Box<dyn Storage>, archival: bool) -> Result<Self> {
+        let started = Instant::now();
+        let genesis = storage.load_genesis()?;
+        let blocks = storage.iter_blocks()?.collect::<Result<Vec<Block>>>()?;
+        // Pruned chains may have no block left past their snapshot.
+        let max_height = blocks.last().map_or(u64::MAX, |block| block.header.number);
+        let snapshot = match archival {
+            true => None,
+            false => storage.latest_snapshot(max_height)?,
+        };
+        let mut state = State::from_snapshot(genesis, blocks, snapshot, archival)?;
+
+        state.storage = Some(Arc::new(Mutex::new(storage)));
+        state.load_duration = started.elapsed();
+
+        info!(
+            height = state.height(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "loaded chain"
+        );
+
+        Ok(state)
     }
 
-    fn apply(&mut self, tx: &Tx) -> Result<()> {
-        match tx {
-            Tx::Transfer { from, to, value } => {
-                let balances = self.balances.get_mut();
-                let [Some(from_balance), Some(to_balance)] = balances.get_disjoint_mut([from, to])
-                else {
-                    return Err(Error::msg("Account not found."));
-                };
+    /// Open the chain like [`State::open`], then only keep the latest snapshot and the `window`
+    /// most recent blocks in the db dir, pruning older history again every
+    /// [`SNAPSHOT_INTERVAL`] blocks.
+    pub fn open_pruned<P: AsRef<Path>>(dbdir: P, window: u64) -> Result<Self> {
+        migrate::migrate(dbdir.as_ref())?;
 
-                if *value > *from_balance {
-                    return Err(Error::msg("Insufficient balance."));
-                }
+        Self::with_storage_pruned(FileStorage::new(dbdir), window)
+    }
 
-                *to_balance += value;
-                *from_balance -= value;
+    /// Snapshot the current state, then drop every block but the `window` most recent ones from
+    /// the storage, along with older snapshots.
+    ///
+    /// Pruned blocks and their transactions can no longer be queried or served to peers.
+    pub fn prune(&mut self, window: u64) -> Result<()> {
+        let snapshot = self.snapshot();
+        let Some(storage) = self.storage.clone() else {
+            return Ok(());
+        };
+        let mut storage = storage.lock().expect("storage lock poisoned");
 
-                Ok(())
-            }
-            Tx::Generate { to, value } => {
-                let mut balances = self.balances.borrow_mut();
-                let to = balances
-                    .get_mut(to)
-                    .ok_or(Error::msg("[To] Account not found."))?;
+        storage.write_snapshot(&snapshot)?;
 
-                *to += value;
-                Ok(())
-            }
+        let keep_from = self.blocks.len().saturating_sub(window as usize);
+
+        if keep_from > 0 {
+            storage.replace_blocks(&self.blocks[keep_from..])?;
+
+            let pruned = self.blocks.drain(..keep_from).collect::<Vec<Block>>();
+            let pruned_txs = pruned.iter().map(|block| block.txs.len()).sum::<usize>();
+
+            self.txs.drain(..pruned_txs);
+            self.base = pruned
+                .last()
+                .map_or(self.base, |block| (block.header.number, block.hash()));
+            self.by_account.prune(self.base.0);
         }
+
+        storage.retain_snapshot(snapshot.height)
     }
 
-    /// Create a new [`State`] instance from the given [`Genesis`] and a collection of [`Tx`] instances.
-    fn from_parts(genesis: Genesis, txs: Vec<Tx>) -> Result<State> {
-        let balances = genesis.balances.clone();
-        let mut state = State {
-            balances: RefCell::new(balances),
-            txs,
-            genesis,
+    /// Validate and apply a new [`SignedTx`] to the current state, persisting it to the storage as
+    /// a single-transaction [`Block`] mined at the chain difficulty.
+    pub fn add_tx(&mut self, tx: impl Into<SignedTx>) -> Result<()> {
+        self.add_tx_cancellable(tx, CancelHandle::default())
+    }
+
+    /// Like [`State::add_tx`], giving up with [`ChiguiError::MiningCancelled`] once `cancel`
+    /// fires, e.g. when a peer block arrives first.
+    pub fn add_tx_cancellable(
+        &mut self,
+        tx: impl Into<SignedTx>,
+        cancel: CancelHandle,
+    ) -> Result<()> {
+        let block = Miner::new(self).with_cancel(cancel).mine(&[tx.into()])?;
+
+        self.add_block(block)
+    }
+
+    /// Validate and apply a new [`Block`] on top of the latest one, persisting it to the storage.
+    ///
+    /// The block is applied atomically: if any of its transactions fails, or it can't be written to
+    /// disk, the state is left untouched.
+    pub fn add_block(&mut self, block: Block) -> Result<()> {
+        let checkpoint = self.commit_block(block)?;
+        let persisted = match &self.storage {
+            Some(storage) => Self::persist(storage, self.blocks.last().expect("block committed")),
+            None => Ok(()),
         };
-        let txs = state.txs.clone();
 
-        for tx in txs.iter() {
-            state.apply(tx)?;
+        self.settle_block(checkpoint, persisted)
+    }
+
+    /// Validate a [`Block`] on top of the latest one like [`State::add_block`] does, without
+    /// keeping or persisting it: the state is left untouched either way.
+    pub fn check_block(&mut self, block: Block) -> Result<()> {
+        let checkpoint = self.commit_block(block)?;
+
+        self.uncommit(checkpoint);
+
+        Ok(())
+    }
+
+    /// Check a transaction against the latest state and report the balances it would leave,
+    /// without applying or persisting anything.
+    ///
+    /// Signatures aren't checked: the transaction is taken as signed by its sender.
+    pub fn simulate(&self, tx: &Tx) -> Result<SimulationOutcome> {
+        let mut scratch = State::from_snapshot(
+            self.genesis.clone(),
+            Vec::new(),
+            Some(self.snapshot()),
+            false,
+        )?;
+
+        scratch.apply_tx(tx)?;
+
+        Ok(SimulationOutcome::new(
+            tx,
+            &self.balances,
+            &scratch.balances,
+        ))
+    }
+
+    /// Suggest a fee for a transaction so that it makes the next block, given the transactions
+    /// queued in the mempool and blocks of up to `block_txs` transactions.
+    ///
+    /// That's the smallest fee of the schedule while the next block has room, and otherwise just
+    /// above the lowest fee that still makes it. Transactions without a sender carry no fee.
+    pub fn estimate_fee(&self, tx: &Tx, mempool: &Mempool, block_txs: usize) -> u64 {
+        if tx.sender().is_none() {
+            return 0;
         }
 
-        Ok(state)
+        let min_fee = self.fee_schedule().min_fee;
+
+        match block_txs
+            .checked_sub(1)
+            .and_then(|last| mempool.txs().get(last).copied())
+        {
+            Some(lowest) => lowest.tx.fee().saturating_add(1).max(min_fee),
+            None => min_fee,
+        }
     }
 
-    /// Parse the `genesis.json` file into a [`Genesis`] instance.
-    fn parse_genesis(genesis_json: &str) -> Result<Genesis> {
-        let genesis =
-            serde_json::from_str::<Genesis>(genesis_json).context("Failed to parse genesis.")?;
-        Ok(genesis)
+    /// Register a callback run with every transaction applied, along with the number of its
+    /// block, once the block is persisted.
+    pub fn on_tx_applied(&mut self, hook: impl FnMut(&SignedTx, u64) + Send + Sync + 'static) {
+        self.hooks.on_tx_applied(Box::new(hook));
+    }
+
+    /// Register a callback run with every transaction dropped from the chain by a reorg, along
+    /// with the number of the abandoned block that held it, see [`State::reorg`].
+    pub fn on_tx_reorged(&mut self, hook: impl FnMut(&SignedTx, u64) + Send + Sync + 'static) {
+        self.hooks.on_tx_reorged(Box::new(hook));
+    }
+
+    /// Register a callback run with every account whose native balance changed, along with its
+    /// previous and new balance, once the block changing it is persisted.
+    pub fn on_balance_changed(
+        &mut self,
+        hook: impl FnMut(&Account, u64, u64) + Send + Sync + 'static,
+    ) {
+        self.hooks.on_balance_changed(Box::new(hook));
+    }
+
+    /// Receive every transaction applied from now on, once its block is persisted, along with
+    /// the resulting balances.
+    pub fn subscribe(&mut self) -> Receiver<AppliedTx> {
+        self.hooks.subscribe()
     }
 
-    /// Parse the `tx.db` file which is basically a JSONL file into a collection of [`Tx`] instances.
-    fn parse_txs(tx_db_str: &str) -> Result<Vec<Tx>> {
-        let lines = tx_db_str.lines().collect::<Vec<&str>>();
-        let txs = lines
+    /// Switch to a competing branch of blocks, forking off the chain right before its first block,
+    /// if it carries more work than the blocks it replaces, see [`fork::prefers`].
+    ///
+    /// The state is rebuilt from the fork point and the branch applied atomically: if any of its
+    /// blocks is invalid, or the new chain can't be persisted, the state is left untouched. Hooks
+    /// run for the transactions only found in the abandoned blocks, then for the new blocks.
+    pub fn reorg(&mut self, branch: Vec<Block>) -> Result<Reorg> {
+        let Some(first) = branch.first() else {
+            return Ok(Reorg {
+                fork_height: self.height(),
+                ..Reorg::default()
+            });
+        };
+        let number = first.header.number;
+        let fork_height = number.saturating_sub(1);
+
+        // Blocks before the base were pruned, their state is gone.
+        if number == 0 || fork_height < self.base.0 || fork_height > self.height() {
+            return Err(ChiguiError::UnknownForkPoint { number });
+        }
+
+        let kept = (fork_height - self.base.0) as usize;
+        let difficulty = self.genesis.difficulty;
+
+        if !fork::prefers(
+            fork::branch_work(difficulty, self.blocks.len() - kept),
+            fork::branch_work(difficulty, branch.len()),
+        ) {
+            return Err(ChiguiError::LighterFork { number });
+        }
+
+        let mut state = self.rewind(kept)?;
+        let mut before = Vec::with_capacity(branch.len());
+
+        for block in branch {
+            before.push(state.balances.clone());
+            state.replay(block)?;
+        }
+
+        let reverted = self.switch_to(state, kept)?;
+        let applied = self.blocks[kept..]
             .iter()
-            .map(|line| serde_json::from_str::<Tx>(line).context("Failed to parse transaction."))
-            .collect::<Result<Vec<Tx>>>()?;
+            .flat_map(|block| block.txs.iter().map(SignedTx::hash))
+            .collect::<BTreeSet<Hash>>();
+        let mut reorged_out = Vec::new();
+
+        for block in reverted.iter() {
+            for tx in block.txs.iter().filter(|tx| !applied.contains(&tx.hash())) {
+                self.hooks.notify_reorged(tx, block.header.number);
+                reorged_out.push(tx.clone());
+            }
+        }
+
+        for (index, block) in self.blocks[kept..].iter().enumerate() {
+            let after = before.get(index + 1).unwrap_or(&self.balances);
+
+            self.hooks.notify(block, &before[index], after);
+        }
+
+        info!(fork_height, reverted = reverted.len(), "reorganized chain");
 
-        Ok(txs)
+        Ok(Reorg {
+            fork_height,
+            reverted,
+            reorged_out,
+        })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use anyhow::Result;
+    /// Undo the last `n` transactions along with their blocks, truncating the storage, and return
+    /// them, oldest first. Meant for dev chains, where a bad transaction needs to be undone.
+    ///
+    /// Blocks are undone whole, so `n` must cover every transaction of the oldest block undone.
+    /// The state is rebuilt from the latest snapshot below the remaining tip, or from genesis.
+    pub fn rollback(&mut self, n: usize) -> Result<Vec<SignedTx>> {
+        let mut kept = self.blocks.len();
+        let mut undone = 0;
 
-    use super::*;
+        while undone < n {
+            let Some(block) = kept.checked_sub(1).map(|index| &self.blocks[index]) else {
+                return Err(ChiguiError::RollbackTooFar {
+                    n,
+                    txs: self.txs.len(),
+                });
+            };
 
-    #[test]
-    fn apply_transfer_tx() -> Result<()> {
-        let genesis = Genesis {
-            genesis_time: String::from("2021-01-01T00:00:00Z"),
-            chain_id: String::from("testnet"),
-            balances: {
-                let mut map = HashMap::new();
-                map.insert(Account(String::from("alice")), 1000);
-                map.insert(Account(String::from("bob")), 1000);
-                map
-            },
-        };
-        let mut state = State::from_parts(genesis, Vec::default())?;
+            undone += block.txs.len();
+            kept -= 1;
 
-        state.apply(&Tx::Transfer {
-            from: Account(String::from("alice")),
-            to: Account(String::from("bob")),
-            value: 10,
-        })?;
+            if undone > n {
+                return Err(ChiguiError::PartialRollback {
+                    number: block.header.number,
+                    txs: block.txs.len(),
+                });
+            }
+        }
 
-        assert_eq!(
-            state.get_balance(&Account(String::from("bob"))).unwrap(),
-            1010
-        );
-        assert_eq!(
-            state.get_balance(&Account(String::from("alice"))).unwrap(),
-            990
+        if kept == self.blocks.len() {
+            return Ok(Vec::new());
+        }
+
+        let state = self.rewind(kept)?;
+        let reverted = self.switch_to(state, kept)?;
+
+        info!(
+            height = self.height(),
+            txs = n,
+            blocks = reverted.len(),
+            "rolled back chain"
         );
 
+        Ok(reverted.into_iter().flat_map(|block| block.txs).collect())
+    }
+
+    /// Rebuild the state as of the first `kept` blocks past the base, from the latest snapshot at
+    /// or below them, if any.
+    fn rewind(&self, kept: usize) -> Result<State> {
+        let height = self.base.0 + kept as u64;
+        let snapshot = match (&self.storage, &self.archive) {
+            (Some(storage), None) => storage
+                .lock()
+                .expect("storage lock poisoned")
+                .latest_snapshot(height)?,
+            _ => None,
+        };
+
+        State::from_snapshot(
+            self.genesis.clone(),
+            self.blocks[..kept].to_vec(),
+            snapshot,
+            self.archive.is_some(),
+        )
+    }
+
+    /// Persist a state rebuilt by [`State::rewind`] in place of this one, returning the blocks past
+    /// the first `kept` ones it replaces.
+    fn switch_to(&mut self, mut state: State, kept: usize) -> Result<Vec<Block>> {
+        if let Some(storage) = &self.storage {
+            let mut storage = storage.lock().expect("storage lock poisoned");
+
+            storage.replace_blocks(&state.blocks)?;
+            // Snapshots past the remaining blocks describe the replaced ones.
+            storage.write_snapshot(&state.snapshot())?;
+            storage.retain_snapshot(state.height())?;
+        }
+
+        let replaced = self.blocks.split_off(kept);
+
+        state.storage = self.storage.take();
+        state.hooks = std::mem::take(&mut self.hooks);
+        state.prune_window = self.prune_window;
+        state.load_duration = self.load_duration;
+        *self = state;
+
+        Ok(replaced)
+    }
+
+    /// Persist a snapshot of the current state to the storage, returning whether there was a
+    /// storage to write it to.
+    pub fn write_snapshot(&mut self) -> Result<bool> {
+        let snapshot = self.snapshot();
+
+        match &self.storage {
+            Some(storage) => storage
+                .lock()
+                .expect("storage lock poisoned")
+                .write_snapshot(&snapshot)
+                .map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Recompute the state root, after replaying blocks under the [`LegacyRules`] of an older
+    /// release.
+    pub(crate) fn refresh_state_root(&mut self) {
+        self.state_root = self.compute_state_root();
+    }
+
+    /// Apply a block and record it as the latest one, returning the checkpoint to revert it if it
+    /// can't be persisted. Nothing changes if it can't be applied.
+    pub(crate) fn commit_block(&mut self, block: Block) -> Result<Checkpoint> {
+        let checkpoint = Checkpoint {
+            balances: self.balances.clone(),
+            assets: self.assets.clone(),
+            nonces: self.nonces.clone(),
+            stakes: self.stakes.clone(),
+            minters: self.minters.clone(),
+            aliases: self.aliases.clone(),
+            proposals: self.proposals.clone(),
+            locks: self.locks.clone(),
+            vesting: self.vesting.clone(),
+            escrows: self.escrows.clone(),
+            params: self.params.clone(),
+            governance: self.governance.clone(),
+            contracts: self.contracts.clone(),
+            state_root: self.state_root,
+            supply: self.supply,
+            txs: self.txs.len(),
+        };
+
+        if let Err(err) = self.apply_block(&block) {
+            self.restore(checkpoint);
+            return Err(err);
+        }
+
+        self.record_history(block.header.number, &checkpoint.balances);
+        self.push_block(block);
+
+        Ok(checkpoint)
+    }
+
+    fn persist(storage: &SharedStorage, block: &Block) -> Result<()> {
+        storage
+            .lock()
+            .expect("storage lock poisoned")
+            .append_block(block)
+    }
+
+    /// Revert the latest block committed, given the checkpoint taken before it.
+    fn uncommit(&mut self, checkpoint: Checkpoint) {
+        let number = self.blocks.pop().map(|block| block.header.number);
+
+        if let (Some(archive), Some(number)) = (&mut self.archive, number) {
+            for history in archive.values_mut() {
+                if history.last().is_some_and(|(height, _)| *height == number) {
+                    history.pop();
+                }
+            }
+        }
+
+        self.txs.truncate(checkpoint.txs);
+        self.by_account.truncate(self.height());
+        self.restore(checkpoint);
+    }
+
+    /// Revert the latest block if it couldn't be persisted, or snapshot and prune the chain once
+    /// in a while if it was.
+    fn settle_block(&mut self, checkpoint: Checkpoint, persisted: Result<()>) -> Result<()> {
+        if let Err(err) = persisted {
+            self.uncommit(checkpoint);
+            return Err(err);
+        }
+
+        let block = self.blocks.last().expect("block committed");
+
+        self.hooks
+            .notify(block, &checkpoint.balances, &self.balances);
+
+        if self.height() % SNAPSHOT_INTERVAL == 0 {
+            // Snapshots and pruning only speed up the next open or save disk space, the block
+            // itself is safely persisted.
+            let persisted = match self.prune_window {
+                Some(window) => self.prune(window),
+                None => self.write_snapshot().map(|_| ()),
+            };
+
+            if let Err(err) = persisted {
+                warn!(height = self.height(), error = %err, "failed to persist snapshot");
+            }
+        }
+
         Ok(())
     }
 
-    #[test]
-    fn generate_coins() -> Result<()> {
-        let genesis = Genesis {
-            genesis_time: String::from("2021-01-01T00:00:00Z"),
-            chain_id: String::from("testnet"),
-            balances: {
-                let mut map = HashMap::new();
-                map.insert(Account::new("alice"), 1000);
-                map.insert(Account::new("bob"), 1000);
-                map
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.balances = checkpoint.balances;
+        self.assets = checkpoint.assets;
+        self.nonces = checkpoint.nonces;
+        self.stakes = checkpoint.stakes;
+        self.minters = checkpoint.minters;
+        self.aliases = checkpoint.aliases;
+        self.proposals = checkpoint.proposals;
+        self.locks = checkpoint.locks;
+        self.vesting = checkpoint.vesting;
+        self.escrows = checkpoint.escrows;
+        self.params = checkpoint.params;
+        self.governance = checkpoint.governance;
+        self.contracts = checkpoint.contracts;
+        self.state_root = checkpoint.state_root;
+        self.supply = checkpoint.supply;
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            height: self.height(),
+            block_hash: self.tip().1,
+            state_root: self.state_root,
+            balances: self.balances.clone().into_iter().collect(),
+            assets: self
+                .assets
+                .iter()
+                .map(|(denom, balances)| (denom.clone(), balances.clone().into_iter().collect()))
+                .collect(),
+            nonces: self.nonces.clone().into_iter().collect(),
+            stakes: self.stakes.clone(),
+            minters: self.minters.clone(),
+            aliases: self.aliases.clone().into_iter().collect(),
+            proposals: self.proposals.clone(),
+            locks: self.locks.clone(),
+            vesting: self.vesting.clone(),
+            escrows: self.escrows.clone(),
+            params: Some(self.params.clone()),
+            governance: self.governance.clone(),
+            contracts: self.contracts.clone(),
+        }
+    }
+
+    /// Assemble an unsealed [`Block`] holding the given transactions on top of the latest block.
+    pub fn next_block(&self, txs: Vec<SignedTx>) -> Block {
+        let (number, parent_hash) = self.tip();
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+
+        Block::new(
+            BlockHeader {
+                number,
+                parent_hash,
+                tx_root: Hash::default(),
+                time,
+                nonce: 0,
             },
+            txs,
+        )
+    }
+
+    /// Number of blocks on top of the genesis.
+    pub fn height(&self) -> u64 {
+        self.base.0 + self.blocks.len() as u64
+    }
+
+    /// Time taken to load the chain from its storage, zero for states built in memory.
+    pub fn load_duration(&self) -> Duration {
+        self.load_duration
+    }
+
+    /// Check that new blocks can be persisted to the storage, if any.
+    pub fn check_storage(&self) -> Result<()> {
+        match &self.storage {
+            Some(storage) => storage
+                .lock()
+                .expect("storage lock poisoned")
+                .check_writable(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn latest_block(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    pub fn block_by_number(&self, number: u64) -> Option<&Block> {
+        let index = number.checked_sub(self.base.0 + 1)?;
+        self.blocks.get(usize::try_from(index).ok()?)
+    }
+
+    /// Hash of the block with the given number, the genesis being block 0, while it's not pruned.
+    pub(crate) fn hash_at(&self, number: u64) -> Option<Hash> {
+        match number == self.base.0 {
+            true => Some(self.base.1),
+            false => self.block_by_number(number).map(Block::hash),
+        }
+    }
+
+    pub fn block_by_hash(&self, hash: &Hash) -> Option<&Block> {
+        self.blocks.iter().find(|block| block.hash() == *hash)
+    }
+
+    /// Every block held in memory, which excludes the blocks pruned on pruned chains.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Every transaction held in memory, oldest first, which excludes the transactions of pruned
+    /// blocks.
+    pub fn txs(&self) -> impl Iterator<Item = &SignedTx> {
+        self.txs.iter()
+    }
+
+    pub fn tx_count(&self) -> usize {
+        self.txs.len()
+    }
+
+    /// The transaction at the given index of [`State::txs`].
+    pub fn tx_at(&self, index: usize) -> Option<&SignedTx> {
+        self.txs.get(index)
+    }
+
+    /// At most `limit` transactions of [`State::txs`] from the given index on, along with the
+    /// number of their block, borrowed from the blocks rather than collected.
+    pub fn txs_range(&self, offset: usize, limit: usize) -> impl Iterator<Item = (u64, &SignedTx)> {
+        let mut skipped = offset;
+        let start = self
+            .blocks
+            .iter()
+            .position(|block| match skipped < block.txs.len() {
+                true => true,
+                false => {
+                    skipped -= block.txs.len();
+                    false
+                }
+            })
+            .unwrap_or(self.blocks.len());
+
+        Self::block_txs(&self.blocks[start..])
+            .skip(skipped)
+            .take(limit)
+    }
+
+    /// Transactions of the blocks numbered `from` to `to` inclusive, along with their block
+    /// number. Pruned blocks and blocks past the tip are left out.
+    pub fn txs_between(&self, from: u64, to: u64) -> impl Iterator<Item = (u64, &SignedTx)> {
+        let len = self.blocks.len();
+        let index = |number: u64| {
+            usize::try_from(number.saturating_sub(self.base.0 + 1)).map_or(len, |i| i.min(len))
+        };
+        let (start, end) = match from <= to {
+            true => (index(from), index(to.saturating_add(1))),
+            false => (0, 0),
         };
-        let mut state = State::from_parts(genesis, Vec::default())?;
 
-        state.apply(&Tx::Generate {
-            to: Account::new("bob"),
-            value: 10,
-        })?;
+        Self::block_txs(&self.blocks[start..end])
+    }
 
-        assert_eq!(state.get_balance(&Account::new("bob")).unwrap(), 1010);
-        assert_eq!(state.get_balance(&Account::new("alice")).unwrap(), 1000);
+    fn block_txs(blocks: &[Block]) -> impl Iterator<Item = (u64, &SignedTx)> {
+        blocks
+            .iter()
+            .flat_map(|block| block.txs.iter().map(|tx| (block.header.number, tx)))
+    }
+
+    /// Transactions naming the given account, oldest first, along with their block number. Like
+    /// [`State::txs`], the transactions of pruned blocks are left out.
+    pub fn txs_for(&self, account: &Account) -> impl Iterator<Item = (u64, &SignedTx)> {
+        self.by_account.get(account).iter().filter_map(|index| {
+            let block = self.block_by_number(index.block)?;
+
+            Some((index.block, block.txs.get(index.position)?))
+        })
+    }
+
+    /// Transactions of the blocks numbered `from` to `to` inclusive matching the filter, looked
+    /// up by account when the filter names one.
+    pub fn find_txs<'a>(
+        &'a self,
+        filter: &'a TxFilter,
+        from: u64,
+        to: u64,
+    ) -> Box<dyn Iterator<Item = (u64, &'a SignedTx)> + 'a> {
+        let txs: Box<dyn Iterator<Item = (u64, &SignedTx)>> = match &filter.account {
+            Some(account) => Box::new(
+                self.txs_for(account)
+                    .filter(move |(number, _)| (from..=to).contains(number)),
+            ),
+            None => Box::new(self.txs_between(from, to)),
+        };
+
+        Box::new(txs.filter(|(_, tx)| filter.matches(&tx.tx)))
+    }
+
+    pub fn tx_by_hash(&self, hash: &Hash) -> Option<&SignedTx> {
+        self.txs.iter().find(|tx| tx.hash() == *hash)
+    }
+
+    /// Every account balance as of the latest block.
+    pub fn balances(&self) -> &HashMap<Account, u64> {
+        &self.balances
+    }
+
+    pub fn get_balance(&self, acct: &Account) -> Option<u64> {
+        self.balances.get(acct).cloned()
+    }
+
+    /// The balances of an asset declared in the genesis, `None` if it isn't.
+    pub fn asset_balances(&self, denom: &str) -> Option<&HashMap<Account, u64>> {
+        self.assets.get(denom)
+    }
+
+    /// Return the balance of an account in the given asset, the native coin when `denom` is
+    /// `None`. Existing accounts hold none of an asset until they receive some.
+    pub fn get_asset_balance(&self, acct: &Account, denom: Option<&str>) -> Option<u64> {
+        let Some(denom) = denom else {
+            return self.get_balance(acct);
+        };
+        let balances = self.assets.get(denom)?;
+
+        self.balances
+            .contains_key(acct)
+            .then(|| balances.get(acct).copied().unwrap_or_default())
+    }
+
+    /// Return the commitment to every balance as of the latest block, see [`State::balance_leaf`].
+    ///
+    /// Two nodes at the same height with different state roots have diverged.
+    pub fn state_root(&self) -> Hash {
+        self.state_root
+    }
+
+    /// Return the Merkle leaf committing to the balance of an account, the state root being the
+    /// Merkle root of the leaves of every account, sorted by account.
+    pub fn balance_leaf(account: &Account, balance: u64) -> Hash {
+        Hash::of(&(account, balance))
+    }
+
+    /// Prove the native balance of an account against the current [`State::state_root`], for
+    /// parties that only trust the root.
+    pub fn prove_balance(&self, account: &Account) -> Result<BalanceProof> {
+        let balance = self
+            .get_balance(account)
+            .ok_or_else(|| ChiguiError::AccountNotFound {
+                account: account.clone(),
+            })?;
+        // Native balances are the first leaves, sorted by account.
+        let index = self
+            .balances
+            .keys()
+            .filter(|other| *other < account)
+            .count();
+        let proof = MerkleProof::new(&self.state_leaves(), index).expect("account has a leaf");
+
+        Ok(BalanceProof {
+            account: account.clone(),
+            balance,
+            height: self.height(),
+            proof,
+        })
+    }
+
+    /// Return the balance of an account right after the block at `height` was applied, height `0`
+    /// being the genesis.
+    ///
+    /// Archival chains look it up in their index, other chains replay their blocks up to `height`,
+    /// which fails once older blocks are pruned.
+    pub fn balance_at(&self, acct: &Account, height: u64) -> Result<Option<u64>> {
+        if let Some(balance) = self.recorded_balance(acct, height)? {
+            return Ok(balance);
+        }
+
+        Self::replay_balance(
+            self.genesis.clone(),
+            self.blocks[..height as usize].to_vec(),
+            acct,
+        )
+    }
+
+    /// Look up the balance of an account at `height` without replaying the chain, returning
+    /// `None` when it must be replayed.
+    fn recorded_balance(&self, acct: &Account, height: u64) -> Result<Option<Option<u64>>> {
+        if height > self.height() {
+            return Err(ChiguiError::HeightNotFound { height });
+        }
+
+        if let Some(archive) = &self.archive {
+            let recorded = archive.get(acct).and_then(|history| {
+                let index = history.partition_point(|(number, _)| *number <= height);
+
+                index.checked_sub(1).map(|index| history[index].1)
+            });
+
+            return Ok(Some(
+                recorded.or_else(|| self.genesis.balances.get(acct).copied()),
+            ));
+        }
+
+        if self.base.0 > 0 {
+            return Err(ChiguiError::HistoryPruned { height });
+        }
+
+        Ok(None)
+    }
+
+    /// View the state as of the block at `height`, `0` being the genesis, without touching this
+    /// one.
+    ///
+    /// The view starts from the latest snapshot at or below `height`, if any, and only replays the
+    /// blocks past it. Blocks apply atomically, so there's no state between two of them.
+    pub fn at(&self, height: u64) -> Result<StateView> {
+        if height > self.height() {
+            return Err(ChiguiError::HeightNotFound { height });
+        }
+
+        if height < self.base.0 {
+            return Err(ChiguiError::HistoryPruned { height });
+        }
+
+        let snapshot = match &self.storage {
+            Some(storage) => storage
+                .lock()
+                .expect("storage lock poisoned")
+                .latest_snapshot(height)?,
+            None => None,
+        };
+        let blocks = self.blocks[..(height - self.base.0) as usize].to_vec();
+        let state = State::from_snapshot(self.genesis.clone(), blocks, snapshot, false)?;
+
+        Ok(StateView::new(state))
+    }
+
+    /// List the native balance changes between the blocks at `from_height` and `to_height`, along
+    /// with the transactions responsible for them, by replaying the chain up to `to_height`.
+    pub fn diff(&self, from_height: u64, to_height: u64) -> Result<StateDiff> {
+        if from_height > to_height {
+            return Err(ChiguiError::InvalidHeightRange {
+                from: from_height,
+                to: to_height,
+            });
+        }
+
+        if to_height > self.height() {
+            return Err(ChiguiError::HeightNotFound { height: to_height });
+        }
+
+        if self.base.0 > 0 {
+            return Err(ChiguiError::HistoryPruned {
+                height: from_height,
+            });
+        }
+
+        StateDiff::replay(self.genesis.clone(), &self.blocks, from_height, to_height)
+    }
+
+    /// Every transaction naming the account, oldest first, with the native balance it left the
+    /// account with. Fees collected by the account aren't listed but show in the balances.
+    ///
+    /// The chain is replayed from genesis, which pruned chains can't do.
+    pub fn statement(&self, account: &Account) -> Result<Vec<StatementEntry>> {
+        if !self.balances.contains_key(account) {
+            return Err(ChiguiError::AccountNotFound {
+                account: account.clone(),
+            });
+        }
+
+        if self.base.0 > 0 {
+            return Err(ChiguiError::HistoryPruned {
+                height: self.base.0,
+            });
+        }
+
+        statement::replay(
+            self.genesis.clone(),
+            &self.blocks,
+            account,
+            self.by_account.get(account),
+        )
+    }
+
+    /// The genesis and every block of the chain in a single document, to be written to a fresh db
+    /// dir with [`Backup::restore`]. Pruned chains no longer hold the blocks to back up.
+    pub fn backup(&self) -> Result<Backup> {
+        if self.base.0 > 0 {
+            return Err(ChiguiError::HistoryPruned {
+                height: self.base.0,
+            });
+        }
+
+        Ok(Backup {
+            version: migrate::SCHEMA_VERSION,
+            chain_id: self.chain_id().to_string(),
+            height: self.height(),
+            tip: self.hash_at(self.height()).unwrap_or_default(),
+            state_root: self.state_root,
+            genesis: self.genesis.clone(),
+            blocks: self.blocks.clone(),
+        })
+    }
+
+    /// Replay the given blocks from genesis and return the resulting balance of an account.
+    fn replay_balance(genesis: Genesis, blocks: Vec<Block>, acct: &Account) -> Result<Option<u64>> {
+        let replayed = State::from_snapshot(genesis, blocks, None, false)?;
+
+        Ok(replayed.get_balance(acct))
+    }
+
+    /// Return the number of coins in existence, staked and unbonding coins included.
+    pub fn total_supply(&self) -> u64 {
+        self.supply
+    }
+
+    /// Return the accounts allowed to sign `Generate` transactions, `None` when anyone may.
+    pub fn minters(&self) -> Option<&BTreeSet<Account>> {
+        self.minters.as_ref()
+    }
+
+    /// Return the account an alias resolves to, if registered.
+    pub fn resolve_alias(&self, name: &str) -> Option<&Account> {
+        self.aliases.get(&name.trim().to_ascii_lowercase())
+    }
+
+    /// Return the coins sent by time-locked transfers that aren't spendable yet.
+    pub fn locks(&self) -> &LockRegistry {
+        &self.locks
+    }
+
+    /// Return the vesting schedules with coins left to vest.
+    pub fn vesting(&self) -> &VestingRegistry {
+        &self.vesting
+    }
+
+    /// Return the balance of an account minus its coins still vesting at the next block.
+    pub fn spendable_balance(&self, account: &Account) -> Option<u64> {
+        let unvested = self.vesting.unvested_of(account, self.tip().0);
+
+        self.get_balance(account)
+            .map(|balance| balance.saturating_sub(unvested))
+    }
+
+    /// Return the escrows not released or refunded yet.
+    pub fn escrows(&self) -> &Escrows {
+        &self.escrows
+    }
+
+    /// Return the multisig transfers waiting for approvals.
+    pub fn proposals(&self) -> &Proposals {
+        &self.proposals
+    }
+
+    /// Return the genesis the chain started from.
+    pub fn genesis(&self) -> &Genesis {
+        &self.genesis
+    }
+
+    /// Return the identifier of the chain, see [`Genesis::chain_id`].
+    pub fn chain_id(&self) -> &str {
+        self.genesis.chain_id()
+    }
+
+    /// Return the time the chain started at, see [`Genesis::genesis_time`].
+    pub fn genesis_time(&self) -> &str {
+        self.genesis.genesis_time()
+    }
+
+    /// Return the most native coins that may ever exist, if capped.
+    pub fn max_supply(&self) -> Option<u64> {
+        self.genesis.max_supply
+    }
+
+    /// Return the gas the transactions of a block may use altogether, if limited.
+    pub fn block_gas_limit(&self) -> Option<u64> {
+        self.genesis.block_gas_limit
+    }
+
+    /// Return the [`FeeSchedule`] transfers on this chain must satisfy.
+    pub fn fee_schedule(&self) -> &FeeSchedule {
+        &self.params.fee_schedule
+    }
+
+    /// Return the blocks during which unstaked coins stay locked.
+    pub fn unbonding_period(&self) -> u64 {
+        self.params.unbonding_period
+    }
+
+    /// Return the storage written by scripts.
+    pub fn contracts(&self) -> &ContractStore {
+        &self.contracts
+    }
+
+    /// Return the governance proposals open to votes.
+    pub fn governance(&self) -> &Governance {
+        &self.governance
+    }
+
+    /// Return the number of leading zero bits block header hashes must have.
+    pub fn difficulty(&self) -> u32 {
+        self.genesis.difficulty
+    }
+
+    /// Return the validators sealing blocks on proof-of-authority chains, empty on proof-of-work
+    /// chains.
+    pub fn validators(&self) -> &Validators {
+        &self.genesis.validators
+    }
+
+    /// Return the validator stakes and the coins being unbonded.
+    pub fn stakes(&self) -> &StakeRegistry {
+        &self.stakes
+    }
+
+    /// Return the staker expected to seal the next block on proof-of-stake chains, `None` when
+    /// blocks aren't selected by stake.
+    pub fn next_proposer(&self) -> Option<&Account> {
+        if !self.genesis.proof_of_stake {
+            return None;
+        }
+
+        self.stakes.select(&self.tip().1)
+    }
+
+    /// Whether the given key is expected to seal the next block, on proof-of-authority or
+    /// proof-of-stake chains.
+    pub fn is_proposer(&self, key: &PublicKey) -> bool {
+        if !self.genesis.validators.is_empty() {
+            return self.genesis.validators.proposer(self.height() + 1) == Some(key);
+        }
+
+        self.next_proposer()
+            .is_some_and(|account| self.controls(key, account))
+    }
+
+    /// Return the nonce the next [`Tx::Transfer`] sent by `acct` must carry.
+    pub fn next_nonce(&self, acct: &Account) -> u64 {
+        self.nonces.get(acct).copied().unwrap_or_default()
+    }
+
+    /// Return the number and parent hash the next block must carry.
+    fn tip(&self) -> (u64, Hash) {
+        self.latest_block()
+            .map_or((self.base.0 + 1, self.base.1), |block| {
+                (block.header.number + 1, block.hash())
+            })
+    }
+
+    /// Apply every transaction of the given [`Block`], which must directly follow the latest one.
+    fn apply_block(&mut self, block: &Block) -> Result<()> {
+        self.apply_block_with(block, |_, _| {})
+    }
+
+    /// Apply a block, calling `on_tx` with the state right after each of its transactions.
+    fn apply_block_with(
+        &mut self,
+        block: &Block,
+        mut on_tx: impl FnMut(&State, &SignedTx),
+    ) -> Result<()> {
+        let _span = debug_span!("apply_block", number = block.header.number).entered();
+        let (expected, parent_hash) = self.tip();
+
+        if block.header.number != expected {
+            return Err(ChiguiError::InvalidBlockNumber {
+                expected,
+                got: block.header.number,
+            });
+        }
+
+        if block.header.parent_hash != parent_hash {
+            return Err(ChiguiError::InvalidParentHash {
+                number: block.header.number,
+                expected: parent_hash,
+                got: block.header.parent_hash,
+            });
+        }
+
+        if block.header.tx_root != block.tx_root() {
+            return Err(ChiguiError::InvalidTxRoot {
+                number: block.header.number,
+            });
+        }
+
+        if let Some(limit) = self.genesis.block_gas_limit {
+            let gas = block.gas();
+
+            if gas > limit {
+                return Err(ChiguiError::BlockGasExceeded {
+                    number: block.header.number,
+                    gas,
+                    limit,
+                });
+            }
+        }
+
+        if !self.genesis.validators.is_empty() {
+            self.genesis.validators.verify(block)?;
+        } else if let Some(proposer) = self.next_proposer() {
+            let number = block.header.number;
+            let seal = block
+                .seal
+                .as_ref()
+                .ok_or(ChiguiError::MissingSeal { number })?;
+
+            if !self.controls(&seal.public_key, proposer) {
+                return Err(ChiguiError::NotProposer {
+                    number,
+                    expected: proposer.clone(),
+                });
+            }
+
+            seal.verify(&block.hash())?;
+        } else if !block.header.meets_difficulty(self.genesis.difficulty) {
+            return Err(ChiguiError::InsufficientWork {
+                number: block.header.number,
+                difficulty: self.genesis.difficulty,
+            });
+        }
+
+        for tx in block.txs.iter() {
+            self.apply(tx)?;
+            on_tx(self, tx);
+        }
+
+        for released in self.stakes.release(block.header.number) {
+            self.credit(&released.account, released.value)?;
+        }
+
+        for unlocked in self.locks.release(block.header.number, block.header.time) {
+            self.credit(&unlocked.account, unlocked.value)?;
+        }
+
+        self.vesting.prune(block.header.number);
+
+        for proposal in self.governance.take_ended(block.header.number) {
+            let total = self.stakes.total_stake();
+            let (approving, voting) = proposal.votes.iter().fold(
+                (0u64, 0u64),
+                |(approving, voting), (voter, approve)| {
+                    let stake = self.stakes.stake_of(voter);
+
+                    (
+                        approving.saturating_add(if *approve { stake } else { 0 }),
+                        voting.saturating_add(stake),
+                    )
+                },
+            );
+
+            if self.genesis.governance.passes(approving, voting, total) {
+                self.params.apply(&proposal.change);
+            }
+        }
+
+        let supply = self.compute_supply()?;
+
+        if supply != self.supply {
+            return Err(ChiguiError::SupplyMismatch {
+                number: block.header.number,
+                expected: self.supply,
+                got: supply,
+            });
+        }
+
+        self.state_root = self.compute_state_root();
+
+        Ok(())
+    }
+
+    /// Record in the archive the balances changed by the block with the given number.
+    fn record_history(&mut self, number: u64, before: &HashMap<Account, u64>) {
+        let Some(archive) = &mut self.archive else {
+            return;
+        };
+
+        for (account, balance) in self.balances.iter() {
+            if before.get(account) != Some(balance) {
+                archive
+                    .entry(account.clone())
+                    .or_default()
+                    .push((number, *balance));
+            }
+        }
+    }
+
+    fn compute_state_root(&self) -> Hash {
+        merkle::root(&self.state_leaves())
+    }
+
+    /// The leaves of the state Merkle tree: native balances sorted by account, then asset balances
+    /// sorted by denomination and account.
+    fn state_leaves(&self) -> Vec<Hash> {
+        let mut accounts = self.balances.iter().collect::<Vec<(&Account, &u64)>>();
+        let mut assets = self
+            .assets
+            .iter()
+            .flat_map(|(denom, balances)| {
+                balances
+                    .iter()
+                    .map(move |(account, balance)| (denom, account, balance))
+            })
+            .collect::<Vec<(&String, &Account, &u64)>>();
+
+        accounts.sort();
+        assets.sort();
+
+        // Asset balances come after the native ones, so that chains without assets keep their
+        // state root.
+        accounts
+            .into_iter()
+            .map(|(account, balance)| Self::balance_leaf(account, *balance))
+            .chain(assets.into_iter().map(|leaf| Hash::of(&leaf)))
+            .collect()
+    }
+
+    /// Check the transaction signature, then apply it.
+    fn apply(&mut self, signed: &SignedTx) -> Result<()> {
+        let _span = debug_span!(
+            "apply",
+            hash = %signed.hash(),
+            kind = %signed.tx.kind(),
+            account = signed.tx.sender().map(tracing::field::display),
+        )
+        .entered();
+        let applied = self
+            .authorize(signed)
+            .and_then(|()| self.apply_tx(&signed.tx));
+
+        if let Err(err) = &applied {
+            info!(error = %err, "rejected transaction");
+        }
+
+        applied
+    }
+
+    /// Ensure transactions are signed by the key controlling the sending account.
+    ///
+    /// Unsigned transactions are only accepted on permissive chains, signed ones only when bound to
+    /// this chain's ID.
+    pub(crate) fn authorize(&self, signed: &SignedTx) -> Result<()> {
+        let unbound = signed.chain_id.is_none() && LegacyRules::current().unbound_signatures;
+
+        if !unbound
+            && (signed.signature.is_some() || signed.chain_id.is_some())
+            && signed.chain_id.as_deref() != Some(self.chain_id())
+        {
+            return Err(ChiguiError::ChainIdMismatch {
+                expected: self.chain_id().to_owned(),
+                got: signed.chain_id.clone().unwrap_or_default(),
+            });
+        }
+
+        let signer = signed.verify()?;
+        let Some(from) = signed.tx.sender() else {
+            return self.authorize_minter(signed.tx.kind(), signer);
+        };
+
+        if self.genesis.multisig.contains_key(from) {
+            return Err(ChiguiError::MultisigRequired {
+                account: from.clone(),
+            });
+        }
+
+        match signer {
+            Some(signer) if self.controls(signer, from) => Ok(()),
+            Some(_) => Err(ChiguiError::SignerMismatch {
+                account: from.clone(),
+            }),
+            None if self.genesis.permissive => Ok(()),
+            None => Err(ChiguiError::MissingSignature {
+                account: from.clone(),
+            }),
+        }
+    }
+
+    /// Once minters are set, only accept generated coins signed by one of them.
+    fn authorize_minter(&self, kind: TxKind, signer: Option<&PublicKey>) -> Result<()> {
+        let Some(minters) = self.minters.as_ref().filter(|_| kind == TxKind::Generate) else {
+            return Ok(());
+        };
+
+        match signer {
+            Some(key) if minters.iter().any(|minter| self.controls(key, minter)) => Ok(()),
+            _ => Err(ChiguiError::NotMinter),
+        }
+    }
+
+    /// Whether the key controls the account: the account is derived from it, or it is registered
+    /// for the account in genesis.
+    fn controls(&self, key: &PublicKey, account: &Account) -> bool {
+        (account.is_derived() && Account::from_public_key(key) == *account)
+            || self.genesis.account_keys.get(account) == Some(key)
+    }
+
+    fn apply_tx(&mut self, tx: &Tx) -> Result<()> {
+        match tx {
+            Tx::Transfer {
+                from,
+                to,
+                value,
+                fee,
+                nonce,
+                denom,
+                ..
+            } => {
+                tx.check_memo()?;
+
+                match denom {
+                    Some(denom) => self.apply_asset_transfer(denom, from, to, *value, *fee, *nonce),
+                    None => self.apply_transfer(from, to, *value, *fee, *nonce),
+                }
+            }
+            Tx::Generate { to, value, denom } => {
+                self.check_recipient(to)?;
+
+                if let Some(denom) = denom {
+                    return self.credit_asset(denom, to, *value);
+                }
+
+                let supply = self
+                    .supply
+                    .checked_add(*value)
+                    .ok_or(ChiguiError::SupplyOverflow)?;
+
+                if let Some(max_supply) = self.genesis.max_supply.filter(|max| supply > *max) {
+                    return Err(ChiguiError::MaxSupplyExceeded { supply, max_supply });
+                }
+
+                self.supply = supply;
+                self.credit(to, *value)
+            }
+            Tx::TransferMulti {
+                from,
+                payments,
+                fee,
+                nonce,
+            } => self.apply_transfer_multi(from, payments, *fee, *nonce),
+            Tx::TransferLocked {
+                from,
+                to,
+                value,
+                unlock,
+                fee,
+                nonce,
+            } => self.apply_transfer_locked(from, to, *value, *unlock, *fee, *nonce),
+            Tx::TransferVesting {
+                from,
+                to,
+                value,
+                cliff,
+                duration,
+                fee,
+                nonce,
+            } => {
+                let schedule = VestingSchedule {
+                    value: *value,
+                    start: self.tip().0,
+                    cliff: *cliff,
+                    duration: *duration,
+                };
+
+                self.apply_transfer(from, to, *value, *fee, *nonce)?;
+                self.vesting.add(to, schedule);
+
+                Ok(())
+            }
+            Tx::Stake {
+                account,
+                value,
+                fee,
+                nonce,
+            } => {
+                self.charge(account, *value, *fee, *nonce)?;
+                self.stakes.bond(account, *value)
+            }
+            Tx::Unstake {
+                account,
+                value,
+                fee,
+                nonce,
+            } => {
+                let release_height =
+                    (self.height() + 1).saturating_add(self.params.unbonding_period);
+
+                self.stakes.check_unbond(account, *value)?;
+                self.charge(account, 0, *fee, *nonce)?;
+                self.stakes.unbond(account, *value, release_height)
+            }
+            Tx::SetMinters {
+                admin,
+                minters,
+                fee,
+                nonce,
+            } => {
+                if self.genesis.minter_admin.as_ref() != Some(admin) {
+                    return Err(ChiguiError::NotMinterAdmin {
+                        account: admin.clone(),
+                    });
+                }
+
+                self.charge(admin, 0, *fee, *nonce)?;
+                self.minters = Some(minters.iter().cloned().collect());
+
+                Ok(())
+            }
+            Tx::RegisterAlias {
+                account,
+                name,
+                fee,
+                nonce,
+            } => {
+                let name = Self::parse_alias(name)?;
+
+                if self.aliases.contains_key(&name) {
+                    return Err(ChiguiError::AliasTaken { name });
+                }
+
+                self.charge(account, 0, *fee, *nonce)?;
+                self.aliases.insert(name, account.clone());
+
+                Ok(())
+            }
+            Tx::Multisig {
+                account,
+                action,
+                fee,
+                nonce,
+            } => {
+                self.charge(account, 0, *fee, *nonce)?;
+                self.apply_multisig(tx.hash(), account, action)
+            }
+            Tx::EscrowCreate {
+                from,
+                to,
+                value,
+                arbiter,
+                fee,
+                nonce,
+            } => {
+                self.check_recipient(to)?;
+                self.charge(from, *value, *fee, *nonce)?;
+                self.escrows.create(
+                    tx.hash(),
+                    Escrow {
+                        from: from.clone(),
+                        to: to.clone(),
+                        value: *value,
+                        arbiter: arbiter.clone(),
+                    },
+                );
+
+                Ok(())
+            }
+            Tx::EscrowRelease {
+                account,
+                escrow,
+                fee,
+                nonce,
+            } => {
+                self.charge(account, 0, *fee, *nonce)?;
+                let closed = self.escrows.close(escrow, account, true)?;
+
+                self.credit(&closed.to, closed.value)
+            }
+            Tx::EscrowRefund {
+                account,
+                escrow,
+                fee,
+                nonce,
+            } => {
+                self.charge(account, 0, *fee, *nonce)?;
+                let closed = self.escrows.close(escrow, account, false)?;
+
+                self.credit(&closed.from, closed.value)
+            }
+            Tx::Propose {
+                account,
+                change,
+                fee,
+                nonce,
+            } => {
+                let ends_at = self
+                    .tip()
+                    .0
+                    .saturating_add(self.genesis.governance.voting_period);
+
+                self.charge(account, 0, *fee, *nonce)?;
+                self.governance.propose(tx.hash(), account, change, ends_at);
+
+                Ok(())
+            }
+            Tx::Vote {
+                account,
+                proposal,
+                approve,
+                fee,
+                nonce,
+            } => {
+                if self.stakes.stake_of(account) == 0 {
+                    return Err(ChiguiError::NotStaker {
+                        account: account.clone(),
+                    });
+                }
+
+                self.charge(account, 0, *fee, *nonce)?;
+                self.governance.vote(proposal, account, *approve)
+            }
+            Tx::Script {
+                account,
+                code,
+                gas_limit,
+                fee,
+                nonce,
+            } => {
+                let height = self.tip().0;
+
+                self.charge(account, 0, *fee, *nonce)?;
+                self.contracts.run(account, code, height, *gas_limit)?;
+
+                Ok(())
+            }
+            Tx::Burn {
+                account,
+                value,
+                fee,
+                nonce,
+            } => {
+                self.charge(account, *value, *fee, *nonce)?;
+                self.supply = self
+                    .supply
+                    .checked_sub(*value)
+                    .ok_or(ChiguiError::SupplyOverflow)?;
+
+                Ok(())
+            }
+        }
+    }
+
+    fn apply_multisig(
+        &mut self,
+        id: Hash,
+        member: &Account,
+        action: &MultisigAction,
+    ) -> Result<()> {
+        match action {
+            MultisigAction::Propose {
+                multisig,
+                to,
+                value,
+            } => {
+                let policy = self.multisig_policy(multisig)?.clone();
+
+                self.proposals
+                    .propose(id, &policy, member, multisig, to, *value)
+            }
+            MultisigAction::Approve { proposal } => {
+                let policy = self.proposal_policy(proposal)?;
+
+                self.proposals.approve(proposal, &policy, member)
+            }
+            MultisigAction::Execute { proposal } => {
+                let policy = self.proposal_policy(proposal)?;
+                let approved = self.proposals.take_approved(proposal, &policy, member)?;
+                let have = self.get_balance(&approved.multisig).unwrap_or_default();
+
+                if approved.value > have {
+                    return Err(ChiguiError::InsufficientBalance {
+                        account: approved.multisig,
+                        have,
+                        need: approved.value,
+                    });
+                }
+
+                self.check_recipient(&approved.to)?;
+                self.balances
+                    .insert(approved.multisig.clone(), have - approved.value);
+                self.credit(&approved.to, approved.value)
+            }
+        }
+    }
+
+    fn multisig_policy(&self, multisig: &Account) -> Result<&MultisigPolicy> {
+        self.genesis
+            .multisig
+            .get(multisig)
+            .ok_or_else(|| ChiguiError::UnknownMultisig {
+                account: multisig.clone(),
+            })
+    }
+
+    /// The policy of the multisig account a pending proposal moves coins from.
+    fn proposal_policy(&self, id: &Hash) -> Result<MultisigPolicy> {
+        let proposal = self
+            .proposals
+            .get(id)
+            .ok_or(ChiguiError::UnknownProposal { id: *id })?;
+
+        self.multisig_policy(&proposal.multisig).cloned()
+    }
+
+    /// Normalize an alias, which follows the rules of account names but can't look like a
+    /// derived account.
+    fn parse_alias(name: &str) -> Result<String> {
+        let alias = Account::parse(name)?;
+
+        if alias.is_derived() {
+            return Err(ChiguiError::InvalidAccount {
+                account: name.to_string(),
+                reason: "aliases can't look like derived accounts",
+            });
+        }
+
+        Ok(alias.to_string())
+    }
+
+    /// Ensure coins may be sent to the account. Unknown accounts are created on receipt, unless
+    /// the genesis rejects new accounts.
+    pub(crate) fn check_recipient(&self, to: &Account) -> Result<()> {
+        if self.balances.contains_key(to) || self.genesis.new_accounts == NewAccountPolicy::Create {
+            return Ok(());
+        }
+
+        Err(ChiguiError::AccountNotFound {
+            account: to.clone(),
+        })
+    }
+
+    fn apply_transfer(
+        &mut self,
+        from: &Account,
+        to: &Account,
+        value: u64,
+        fee: u64,
+        nonce: u64,
+    ) -> Result<()> {
+        self.check_recipient(to)?;
+
+        self.charge(from, value, fee, nonce)?;
+        self.credit(to, value)
+    }
+
+    /// Take coins from the sender and lock them for the recipient until `unlock`.
+    fn apply_transfer_locked(
+        &mut self,
+        from: &Account,
+        to: &Account,
+        value: u64,
+        unlock: Unlock,
+        fee: u64,
+        nonce: u64,
+    ) -> Result<()> {
+        self.check_recipient(to)?;
+        self.charge(from, value, fee, nonce)?;
+        self.balances.entry(to.clone()).or_default();
+        self.locks.lock(to, value, unlock);
+
+        Ok(())
+    }
+
+    /// Move units of an asset between accounts, the fee being paid in the native coin.
+    fn apply_asset_transfer(
+        &mut self,
+        denom: &str,
+        from: &Account,
+        to: &Account,
+        value: u64,
+        fee: u64,
+        nonce: u64,
+    ) -> Result<()> {
+        self.check_recipient(to)?;
+
+        let have = self
+            .assets
+            .get(denom)
+            .ok_or_else(|| ChiguiError::UnknownAsset {
+                denom: denom.to_string(),
+            })?
+            .get(from)
+            .copied()
+            .unwrap_or_default();
+
+        if value > have {
+            return Err(ChiguiError::InsufficientBalance {
+                account: from.clone(),
+                have,
+                need: value,
+            });
+        }
+
+        self.charge(from, 0, fee, nonce)?;
+
+        if let Some(balances) = self.assets.get_mut(denom) {
+            balances.insert(from.clone(), have - value);
+        }
+
+        self.credit_asset(denom, to, value)
+    }
+
+    /// Add `value` units of an asset to the balance of an account, creating it if needed.
+    fn credit_asset(&mut self, denom: &str, account: &Account, value: u64) -> Result<()> {
+        self.balances.entry(account.clone()).or_default();
+
+        let balance = self
+            .assets
+            .get_mut(denom)
+            .ok_or_else(|| ChiguiError::UnknownAsset {
+                denom: denom.to_string(),
+            })?
+            .entry(account.clone())
+            .or_default();
+
+        *balance = balance
+            .checked_add(value)
+            .ok_or_else(|| ChiguiError::Overflow {
+                account: account.clone(),
+            })?;
+
+        Ok(())
+    }
+
+    /// Pay every recipient of a batch out of the sender's balance. A recipient that can't be
+    /// credited fails the batch, and the block is reverted with it.
+    fn apply_transfer_multi(
+        &mut self,
+        from: &Account,
+        payments: &[Payment],
+        fee: u64,
+        nonce: u64,
+    ) -> Result<()> {
+        if payments.is_empty() {
+            return Err(ChiguiError::EmptyBatch {
+                account: from.clone(),
+            });
+        }
+
+        for payment in payments {
+            self.check_recipient(&payment.to)?;
+        }
+
+        let total = payments
+            .iter()
+            .try_fold(0u64, |total, payment| total.checked_add(payment.value))
+            .ok_or_else(|| ChiguiError::Overflow {
+                account: from.clone(),
+            })?;
+
+        self.charge(from, total, fee, nonce)?;
+
+        for payment in payments {
+            self.credit(&payment.to, payment.value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Add `value` to the balance of an account, creating it if needed.
+    fn credit(&mut self, account: &Account, value: u64) -> Result<()> {
+        let balance = self.balances.entry(account.clone()).or_default();
+
+        *balance = balance
+            .checked_add(value)
+            .ok_or_else(|| ChiguiError::Overflow {
+                account: account.clone(),
+            })?;
+
+        Ok(())
+    }
+
+    /// Sum every balance, stake and unbonding entry.
+    fn compute_supply(&self) -> Result<u64> {
+        let balances = self.balances.values().copied();
+        let stakes = self.stakes.validators().map(|(_, stake)| stake);
+        let unbonding = self.stakes.unbonding().iter().map(|entry| entry.value);
+        let locked = self.locks.locked().iter().map(|entry| entry.value);
+        let escrowed = self.escrows.iter().map(|(_, escrow)| escrow.value);
+
+        balances
+            .chain(stakes)
+            .chain(unbonding)
+            .chain(locked)
+            .chain(escrowed)
+            .try_fold(0u64, |supply, value| supply.checked_add(value))
+            .ok_or(ChiguiError::SupplyOverflow)
+    }
+
+    /// Check the nonce and fee of a transaction sent by `from`, then take `value` plus the fee from
+    /// its balance, paying the fee to the collector.
+    fn charge(&mut self, from: &Account, value: u64, fee: u64, nonce: u64) -> Result<()> {
+        let expected = self.next_nonce(from);
+
+        if nonce != expected {
+            return Err(ChiguiError::InvalidNonce {
+                account: from.clone(),
+                expected,
+                got: nonce,
+            });
+        }
+
+        let min_fee = self.params.fee_schedule.min_fee;
+
+        if !self.params.fee_schedule.accepts(fee) {
+            return Err(ChiguiError::FeeTooLow { fee, min_fee });
+        }
+
+        let have = *self
+            .balances
+            .get(from)
+            .ok_or_else(|| ChiguiError::AccountNotFound {
+                account: from.clone(),
+            })?;
+        let need = value.saturating_add(fee);
+
+        if need > have {
+            return Err(ChiguiError::InsufficientBalance {
+                account: from.clone(),
+                have,
+                need,
+            });
+        }
+
+        let spendable = have.saturating_sub(self.vesting.unvested_of(from, self.tip().0));
+
+        if need > spendable {
+            return Err(ChiguiError::Unvested {
+                account: from.clone(),
+                spendable,
+                need,
+            });
+        }
+
+        self.balances.insert(from.clone(), have - need);
+
+        match self.genesis.fee_collector.clone() {
+            Some(collector) => self.credit(&collector, fee)?,
+            None => {
+                self.supply = self
+                    .supply
+                    .checked_sub(fee)
+                    .ok_or(ChiguiError::SupplyOverflow)?
+            }
+        }
+
+        self.nonces.insert(from.clone(), nonce + 1);
+
+        Ok(())
+    }
+
+    /// Create a new [`State`] instance from the given [`Snapshot`], only applying the blocks past
+    /// it, and indexing the balances they set on archival chains.
+    pub(crate) fn from_snapshot(
+        genesis: Genesis,
+        blocks: Vec<Block>,
+        snapshot: Option<Snapshot>,
+        archival: bool,
+    ) -> Result<State> {
+        let balances = genesis.balances.clone();
+        let assets = genesis.assets.clone();
+        let minters = genesis.minters.clone();
+        let mut state = State {
+            balances,
+            assets,
+            minters,
+            aliases: HashMap::new(),
+            proposals: Proposals::default(),
+            locks: LockRegistry::default(),
+            vesting: VestingRegistry::from_genesis(&genesis.vesting),
+            escrows: Escrows::default(),
+            params: Params {
+                fee_schedule: genesis.fee_schedule.clone(),
+                unbonding_period: genesis.unbonding_period,
+            },
+            governance: Governance::default(),
+            contracts: ContractStore::default(),
+            txs: Vec::new(),
+            blocks: Vec::new(),
+            genesis,
+            nonces: HashMap::new(),
+            stakes: StakeRegistry::default(),
+            state_root: Hash::default(),
+            supply: 0,
+            base: (0, Hash::default()),
+            prune_window: None,
+            archive: archival.then(HashMap::new),
+            storage: None,
+            hooks: Hooks::default(),
+            by_account: AccountIndex::default(),
+            load_duration: Duration::ZERO,
+        };
+
+        state.state_root = state.compute_state_root();
+        state.supply = state.compute_supply()?;
+
+        let first = blocks.first().map_or(1, |block| block.header.number);
+        let mut blocks = blocks.into_iter();
+
+        match snapshot {
+            Some(snapshot) if snapshot.height + 1 >= first => {
+                let height = snapshot.height;
+                let covered = blocks
+                    .by_ref()
+                    .take((height + 1 - first) as usize)
+                    .collect::<Vec<Block>>();
+                let base_hash = covered.first().map(|block| block.header.parent_hash);
+
+                if covered.last().map_or(snapshot.block_hash, Block::hash) != snapshot.block_hash {
+                    return Err(ChiguiError::InvalidSnapshot { height });
+                }
+
+                state.balances = snapshot.balances.into_iter().collect();
+
+                for (denom, balances) in snapshot.assets {
+                    state.assets.insert(denom, balances.into_iter().collect());
+                }
+
+                state.nonces = snapshot.nonces.into_iter().collect();
+                state.stakes = snapshot.stakes;
+                state.minters = snapshot.minters;
+                state.aliases = snapshot.aliases.into_iter().collect();
+                state.proposals = snapshot.proposals;
+                state.locks = snapshot.locks;
+                state.vesting = snapshot.vesting;
+                state.escrows = snapshot.escrows;
+                state.governance = snapshot.governance;
+                state.contracts = snapshot.contracts;
+
+                if let Some(params) = snapshot.params {
+                    state.params = params;
+                }
+                state.state_root = state.compute_state_root();
+                state.supply = state.compute_supply()?;
+                state.base = (
+                    height - covered.len() as u64,
+                    base_hash.unwrap_or(snapshot.block_hash),
+                );
+
+                if state.state_root != snapshot.state_root {
+                    return Err(ChiguiError::InvalidSnapshot { height });
+                }
+
+                for block in covered {
+                    state.push_block(block);
+                }
+            }
+            _ if first > 1 => {
+                return Err(ChiguiError::MissingSnapshot { height: first - 1 });
+            }
+            _ => {}
+        }
+
+        for block in blocks {
+            state.replay(block)?;
+        }
+
+        Ok(state)
+    }
+
+    /// Apply a block loaded from storage and append it to the chain, without persisting it.
+    pub(crate) fn replay(&mut self, block: Block) -> Result<()> {
+        self.replay_with(block, |_, _| {})
+    }
+
+    /// Like [`State::replay`], calling `on_tx` with the state right after each transaction of the
+    /// block.
+    pub(crate) fn replay_with(
+        &mut self,
+        block: Block,
+        on_tx: impl FnMut(&State, &SignedTx),
+    ) -> Result<()> {
+        let before = match self.archive {
+            Some(_) => self.balances.clone(),
+            None => HashMap::new(),
+        };
+
+        self.apply_block_with(&block, on_tx)?;
+        self.record_history(block.header.number, &before);
+        self.push_block(block);
+
+        Ok(())
+    }
+
+    /// Append an applied block to the chain, indexing its transactions.
+    fn push_block(&mut self, block: Block) {
+        self.by_account.add_block(&block);
+        self.txs.extend(block.txs.iter().cloned());
+        self.blocks.push(block);
+    }
+
+    /// Parse the `genesis.json` file into a [`Genesis`] instance.
+    pub(crate) fn parse_genesis(genesis_json: &str) -> Result<Genesis> {
+        let genesis = serde_json::from_str::<serde_json::Value>(genesis_json)
+            .map_err(|source| ChiguiError::GenesisParseError { source })?;
+        let violations = genesis_violations(&genesis);
+
+        if !violations.is_empty() {
+            return Err(ChiguiError::InvalidGenesis { violations });
+        }
+
+        let genesis = serde_json::from_value::<Genesis>(genesis)
+            .map_err(|source| ChiguiError::GenesisParseError { source })?;
+
+        debug!(
+            chain_id = %genesis.chain_id,
+            accounts = genesis.balances.len(),
+            "parsed genesis"
+        );
+
+        Ok(genesis)
+    }
+}
+
+/// Check the fields of a genesis that its deserialization alone doesn't, or would only report one
+/// at a time: the genesis time, chain ID, account names and initial supply.
+fn genesis_violations(genesis: &serde_json::Value) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    match genesis.get("genesis_time").and_then(|time| time.as_str()) {
+        Some(time) if DateTime::parse_from_rfc3339(time).is_err() => {
+            violations.push(format!("genesis_time \"{}\" isn't an RFC 3339 time", time));
+        }
+        Some(_) => {}
+        None => violations.push("genesis_time is missing".into()),
+    }
+
+    match genesis
+        .get("chain_id")
+        .and_then(|chain_id| chain_id.as_str())
+    {
+        Some(chain_id) if chain_id.trim().is_empty() => {
+            violations.push("chain_id can't be empty".into());
+        }
+        Some(_) => {}
+        None => violations.push("chain_id is missing".into()),
+    }
+
+    let asset_balances = genesis
+        .get("assets")
+        .and_then(|assets| assets.as_object())
+        .into_iter()
+        .flat_map(|assets| assets.iter())
+        .map(|(denom, balances)| (format!("assets.{}", denom), Some(balances)));
+    let account_maps = ["balances", "vesting", "multisig", "account_keys"]
+        .into_iter()
+        .map(|field| (field.to_string(), genesis.get(field)))
+        .chain(asset_balances);
+
+    for (field, accounts) in account_maps {
+        let Some(accounts) = accounts.and_then(|accounts| accounts.as_object()) else {
+            continue;
+        };
+
+        for account in accounts.keys() {
+            if let Err(err) = Account::parse(account) {
+                violations.push(format!(
+                    "{}: {}",
+                    field,
+                    err.to_string().trim_end_matches('.')
+                ));
+            }
+        }
+    }
+
+    let Some(balances) = genesis
+        .get("balances")
+        .and_then(|balances| balances.as_object())
+    else {
+        violations.push("balances are missing".into());
+
+        return violations;
+    };
+    let mut supply = Some(0u64);
+
+    for (account, balance) in balances {
+        match balance.as_u64() {
+            Some(balance) => supply = supply.and_then(|supply| supply.checked_add(balance)),
+            None => violations.push(format!(
+                "balance of \"{}\" isn't a non-negative integer",
+                account
+            )),
+        }
+    }
+
+    if genesis
+        .get("block_gas_limit")
+        .is_some_and(|limit| limit.as_u64().is_none_or(|limit| limit < TX_GAS))
+    {
+        violations.push(format!(
+            "block_gas_limit must be an integer of at least {}",
+            TX_GAS
+        ));
+    }
+
+    let max_supply = genesis
+        .get("max_supply")
+        .and_then(|max_supply| max_supply.as_u64());
+
+    match (supply, max_supply) {
+        (None, _) => violations.push("initial supply overflows".into()),
+        (Some(supply), Some(max_supply)) if supply > max_supply => violations.push(format!(
+            "initial supply {} exceeds max_supply {}",
+            supply, max_supply
+        )),
+        _ => {}
+    }
+
+    violations
+}
+
+/// Async variants of the [`State`] API, for callers running on a tokio runtime.
+///
+/// [`Storage`] implementations stay synchronous: their disk I/O, along with replaying blocks, is
+/// moved to tokio's blocking thread pool so that it never stalls the executor threads.
+#[cfg(feature = "async")]
+impl State {
+    /// Async variant of [`State::open`].
+    pub async fn open_async<P: AsRef<Path>>(dbdir: P) -> Result<Self> {
+        let dbdir = dbdir.as_ref().to_path_buf();
+
+        blocking(move || Self::open(dbdir)).await
+    }
+
+    /// Async variant of [`State::with_storage`].
+    pub async fn with_storage_async(storage: impl Storage + 'static) -> Result<Self> {
+        blocking(move || Self::with_storage(storage)).await
+    }
+
+    /// Async variant of [`State::add_tx`].
+    pub async fn add_tx_async(&mut self, tx: impl Into<SignedTx>) -> Result<()> {
+        self.add_tx_async_cancellable(tx, CancelHandle::default())
+            .await
+    }
+
+    /// Async variant of [`State::add_tx_cancellable`].
+    pub async fn add_tx_async_cancellable(
+        &mut self,
+        tx: impl Into<SignedTx>,
+        cancel: CancelHandle,
+    ) -> Result<()> {
+        let block = Miner::new(self)
+            .with_cancel(cancel)
+            .mine_async(vec![tx.into()])
+            .await?;
+
+        self.add_block_async(block).await
+    }
+
+    /// Async variant of [`State::add_block`].
+    ///
+    /// The block is part of the state as soon as it's applied. If the future is dropped while
+    /// the block is being written, the write still completes in the background.
+    pub async fn add_block_async(&mut self, block: Block) -> Result<()> {
+        let checkpoint = self.commit_block(block)?;
+        let persisted = match self.storage.clone() {
+            Some(storage) => {
+                let block = self.blocks.last().expect("block committed").clone();
+
+                blocking(move || Self::persist(&storage, &block)).await
+            }
+            None => Ok(()),
+        };
+
+        self.settle_block(checkpoint, persisted)
+    }
+
+    /// Async variant of [`State::balance_at`].
+    pub async fn balance_at_async(&self, acct: &Account, height: u64) -> Result<Option<u64>> {
+        if let Some(balance) = self.recorded_balance(acct, height)? {
+            return Ok(balance);
+        }
+
+        let genesis = self.genesis.clone();
+        let blocks = self.blocks[..height as usize].to_vec();
+        let acct = acct.clone();
+
+        blocking(move || Self::replay_balance(genesis, blocks, &acct)).await
+    }
+}
+
+/// Run blocking work on tokio's blocking thread pool, forwarding its panics.
+#[cfg(feature = "async")]
+pub(crate) async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(work)
+        .await
+        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_MEMO_LEN;
+    use crate::governance::ParamChange;
+    use crate::multisig::MultisigAction;
+    use crate::signed::TxSignature;
+
+    #[test]
+    fn apply_transfer_tx() -> Result<()> {
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account(String::from("alice")), 1000);
+                map.insert(Account(String::from("bob")), 1000);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: true,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+
+        state.apply_tx(&Tx::Transfer {
+            from: Account(String::from("alice")),
+            to: Account(String::from("bob")),
+            value: 10,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        })?;
+
+        assert_eq!(
+            state.get_balance(&Account(String::from("bob"))).unwrap(),
+            1010
+        );
+        assert_eq!(
+            state.get_balance(&Account(String::from("alice"))).unwrap(),
+            990
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_coins() -> Result<()> {
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, 1000);
+                map.insert(Account::new("bob")?, 1000);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: true,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+
+        state.apply_tx(&Tx::Generate {
+            to: Account::new("bob")?,
+            value: 10,
+            denom: None,
+        })?;
+
+        assert_eq!(state.get_balance(&Account::new("bob")?).unwrap(), 1010);
+        assert_eq!(state.get_balance(&Account::new("alice")?).unwrap(), 1000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_tx_persists_to_block_db() -> Result<()> {
+        let dbdir = std::env::temp_dir().join(format!("chigui-add-tx-{}", std::process::id()));
+        std::fs::create_dir_all(&dbdir).unwrap();
+        std::fs::write(
+            dbdir.join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":1000,"bob":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.join("block.db"), "").unwrap();
+
+        let mut state = State::open(&dbdir)?;
+
+        state.add_tx(Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 400,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        })?;
+        assert!(
+            state
+                .add_tx(Tx::Transfer {
+                    from: Account::new("bob")?,
+                    to: Account::new("alice")?,
+                    value: 401,
+                    fee: 0,
+                    nonce: 0,
+                    memo: None,
+                    denom: None,
+                })
+                .is_err()
+        );
+        assert_eq!(state.tx_count(), 1);
+
+        let reopened = State::open(&dbdir)?;
+
+        assert_eq!(reopened.tx_count(), 1);
+        assert_eq!(
+            reopened.txs().collect::<Vec<_>>(),
+            vec![state.tx_at(0).unwrap()]
+        );
+        assert_eq!(reopened.get_balance(&Account::new("alice")?).unwrap(), 600);
+        assert_eq!(reopened.get_balance(&Account::new("bob")?).unwrap(), 400);
+
+        std::fs::remove_dir_all(&dbdir).unwrap();
+
+        Ok(())
+    }
+
+    #[test]
+    fn builds_genesis() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+
+        GenesisBuilder::new("testnet")
+            .balance("alice", 1000)
+            .balance("bob", 5)
+            .permissive(true)
+            .build()?
+            .write_to(dbdir.path())?;
+
+        let mut state = State::open(dbdir.path())?;
+
+        state.add_tx(Tx::Generate {
+            to: Account::new("bob")?,
+            value: 10,
+            denom: None,
+        })?;
+
+        assert_eq!(state.chain_id(), "testnet");
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(15));
+        assert_eq!(
+            migrate::schema_version(dbdir.path())?,
+            migrate::SCHEMA_VERSION
+        );
+        assert!(
+            GenesisBuilder::new("testnet")
+                .balance("", 1)
+                .build()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_every_genesis_violation() {
+        let invalid = State::parse_genesis(
+            r#"{"genesis_time":"yesterday","chain_id":" ","balances":{"":1,"alice":5,"bob":-1},"max_supply":4}"#,
+        );
+
+        let Err(ChiguiError::InvalidGenesis { violations }) = invalid else {
+            panic!("expected an invalid genesis, got {:?}", invalid);
+        };
+
+        assert_eq!(violations.len(), 5, "{:?}", violations);
+        assert!(violations[0].starts_with("genesis_time"));
+        assert_eq!(violations[1], "chain_id can't be empty");
+        assert!(violations[2].starts_with("balances: Invalid account"));
+        assert_eq!(violations[4], "initial supply 6 exceeds max_supply 4");
+    }
+
+    #[test]
+    fn exposes_genesis() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":1000}}"#,
+        )?;
+        let state = State::in_memory(genesis)?;
+
+        assert_eq!(state.chain_id(), "testnet");
+        assert_eq!(state.genesis_time(), "2021-01-01T00:00:00Z");
+        assert_eq!(
+            state.genesis().balances().get(&Account::new("alice")?),
+            Some(&1000)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn transfer_memos_are_persisted() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let mut state = State::open(dbdir.path())?;
+        let transfer = |memo: &str, nonce| Tx::Transfer {
+            from: Account::new("alice").unwrap(),
+            to: Account::new("bob").unwrap(),
+            value: 10,
+            fee: 0,
+            nonce,
+            memo: Some(String::from(memo)),
+            denom: None,
+        };
+
+        state.add_tx(transfer("invoice 7", 0))?;
+        assert!(matches!(
+            state.add_tx(transfer(&"x".repeat(MAX_MEMO_LEN + 1), 1)),
+            Err(ChiguiError::MemoTooLong { .. })
+        ));
+
+        let reopened = State::open(dbdir.path())?;
+
+        assert_eq!(reopened.tx_count(), 1);
+        assert_eq!(reopened.txs[0].tx.memo(), Some("invoice 7"));
+        assert!(reopened.txs[0].to_string().ends_with(r#"memo "invoice 7""#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn batch_transfers_are_all_or_nothing() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":0,"carol":0},"fee_collector":"bob","new_accounts":"reject","permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let batch = |recipients: &[(&str, u64)], nonce| Tx::TransferMulti {
+            from: Account(String::from("alice")),
+            payments: recipients
+                .iter()
+                .map(|(to, value)| Payment {
+                    to: Account(String::from(*to)),
+                    value: *value,
+                })
+                .collect(),
+            fee: 1,
+            nonce,
+        };
+
+        state.add_tx(batch(&[("bob", 10), ("carol", 20)], 0))?;
+
+        assert_eq!(state.get_balance(&Account::new("alice")?), Some(69));
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(11));
+        assert_eq!(state.get_balance(&Account::new("carol")?), Some(20));
+
+        assert!(matches!(
+            state.add_tx(batch(&[("bob", 10), ("dave", 1)], 1)),
+            Err(ChiguiError::AccountNotFound { .. })
+        ));
+        assert!(matches!(
+            state.add_tx(batch(&[("bob", 60), ("carol", 60)], 1)),
+            Err(ChiguiError::InsufficientBalance { need: 121, .. })
+        ));
+        assert!(matches!(
+            state.add_tx(batch(&[], 1)),
+            Err(ChiguiError::EmptyBatch { .. })
+        ));
+        assert_eq!(state.get_balance(&Account::new("alice")?), Some(69));
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(11));
+        assert_eq!(state.next_nonce(&Account::new("alice")?), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn assets_are_tracked_apart_from_coins() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10,"bob":0},"assets":{"usd":{"alice":500}},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let mut state = State::open(dbdir.path())?;
+        let root = state.state_root();
+        let transfer = |denom: &str, value, nonce| Tx::Transfer {
+            from: Account::new("alice").unwrap(),
+            to: Account::new("bob").unwrap(),
+            value,
+            fee: 1,
+            nonce,
+            memo: None,
+            denom: Some(String::from(denom)),
+        };
+
+        state.add_tx(transfer("usd", 200, 0))?;
+        state.add_tx(Tx::Generate {
+            to: Account::new("bob")?,
+            value: 50,
+            denom: Some(String::from("usd")),
+        })?;
+
+        assert!(matches!(
+            state.add_tx(transfer("usd", 301, 1)),
+            Err(ChiguiError::InsufficientBalance { have: 300, .. })
+        ));
+        assert!(matches!(
+            state.add_tx(transfer("eur", 1, 1)),
+            Err(ChiguiError::UnknownAsset { .. })
+        ));
+
+        let alice = Account::new("alice")?;
+        let bob = Account::new("bob")?;
+
+        assert_eq!(state.get_asset_balance(&alice, Some("usd")), Some(300));
+        assert_eq!(state.get_asset_balance(&bob, Some("usd")), Some(250));
+        assert_eq!(state.get_asset_balance(&alice, None), Some(9));
+        assert_eq!(state.get_asset_balance(&bob, Some("eur")), None);
+        assert_eq!(state.total_supply(), 9);
+        assert_ne!(state.state_root(), root);
+
+        assert!(state.write_snapshot()?);
+
+        let reopened = State::open(dbdir.path())?;
+
+        assert_eq!(reopened.get_asset_balance(&bob, Some("usd")), Some(250));
+        assert_eq!(reopened.state_root(), state.state_root());
+
+        Ok(())
+    }
+
+    #[test]
+    fn transfers_create_new_accounts() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let dave = Account::new("dave")?;
+
+        state.add_tx(Tx::Transfer {
+            from: Account::new("alice")?,
+            to: dave.clone(),
+            value: 10,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        })?;
+
+        assert_eq!(state.get_balance(&dave), Some(10));
+        assert!(matches!(
+            state.add_tx(Tx::Transfer {
+                from: Account::new("erin")?,
+                to: dave,
+                value: 1,
+                fee: 0,
+                nonce: 0,
+                memo: None,
+                denom: None,
+            }),
+            Err(ChiguiError::AccountNotFound { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn transfer_reports_structured_errors() -> Result<()> {
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, 5);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::Reject,
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: true,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+
+        let err = state
+            .apply_tx(&Tx::Transfer {
+                from: Account::new("alice")?,
+                to: Account::new("carol")?,
+                value: 1,
+                fee: 0,
+                nonce: 0,
+                memo: None,
+                denom: None,
+            })
+            .unwrap_err();
+        assert!(
+            matches!(err, ChiguiError::AccountNotFound { account } if account == Account::new("carol")?)
+        );
+
+        let err = state
+            .apply_tx(&Tx::Transfer {
+                from: Account::new("alice")?,
+                to: Account::new("alice")?,
+                value: 6,
+                fee: 0,
+                nonce: 0,
+                memo: None,
+                denom: None,
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ChiguiError::InsufficientBalance {
+                have: 5,
+                need: 6,
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn transfer_rejects_replayed_nonce() -> Result<()> {
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, 1000);
+                map.insert(Account::new("bob")?, 0);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: true,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+        let tx = Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 10,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        };
+
+        assert_eq!(state.next_nonce(&Account::new("alice")?), 0);
+        state.apply_tx(&tx)?;
+        assert_eq!(state.next_nonce(&Account::new("alice")?), 1);
+
+        let err = state.apply_tx(&tx).unwrap_err();
+        assert!(matches!(
+            err,
+            ChiguiError::InvalidNonce {
+                expected: 1,
+                got: 0,
+                ..
+            }
+        ));
+        assert_eq!(state.get_balance(&Account::new("bob")?).unwrap(), 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn transfer_charges_fee_to_collector() -> Result<()> {
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, 100);
+                map.insert(Account::new("bob")?, 0);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: Some(Account::new("treasury")?),
+            fee_schedule: FeeSchedule::new(2),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: true,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+
+        let err = state
+            .apply_tx(&Tx::Transfer {
+                from: Account::new("alice")?,
+                to: Account::new("bob")?,
+                value: 10,
+                fee: 1,
+                nonce: 0,
+                memo: None,
+                denom: None,
+            })
+            .unwrap_err();
+        assert!(matches!(err, ChiguiError::FeeTooLow { fee: 1, min_fee: 2 }));
+
+        let err = state
+            .apply_tx(&Tx::Transfer {
+                from: Account::new("alice")?,
+                to: Account::new("bob")?,
+                value: 99,
+                fee: 2,
+                nonce: 0,
+                memo: None,
+                denom: None,
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ChiguiError::InsufficientBalance {
+                have: 100,
+                need: 101,
+                ..
+            }
+        ));
+
+        state.apply_tx(&Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 10,
+            fee: 3,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        })?;
+
+        assert_eq!(state.get_balance(&Account::new("alice")?).unwrap(), 87);
+        assert_eq!(state.get_balance(&Account::new("bob")?).unwrap(), 10);
+        assert_eq!(state.get_balance(&Account::new("treasury")?).unwrap(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_block_is_atomic() -> Result<()> {
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, 100);
+                map.insert(Account::new("bob")?, 0);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: true,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+        let block = state.next_block(vec![
+            Tx::Transfer {
+                from: Account::new("alice")?,
+                to: Account::new("bob")?,
+                value: 60,
+                fee: 0,
+                nonce: 0,
+                memo: None,
+                denom: None,
+            }
+            .into(),
+            Tx::Transfer {
+                from: Account::new("alice")?,
+                to: Account::new("bob")?,
+                value: 60,
+                fee: 0,
+                nonce: 1,
+                memo: None,
+                denom: None,
+            }
+            .into(),
+        ]);
+
+        let root = state.state_root();
+        assert!(state.add_block(block).is_err());
+        assert!(state.latest_block().is_none());
+        assert_eq!(state.state_root(), root);
+        assert_eq!(state.get_balance(&Account::new("alice")?).unwrap(), 100);
+        assert_eq!(state.next_nonce(&Account::new("alice")?), 0);
+
+        state.add_tx(Tx::Generate {
+            to: Account::new("bob")?,
+            value: 5,
+            denom: None,
+        })?;
+        state.add_tx(Tx::Generate {
+            to: Account::new("bob")?,
+            value: 5,
+            denom: None,
+        })?;
+
+        assert_eq!(state.latest_block().unwrap().header.number, 2);
+        assert_eq!(state.block_by_number(1).unwrap().txs.len(), 1);
+        assert!(state.block_by_number(0).is_none());
+
+        let mut stale = state.next_block(Vec::new());
+        stale.header.number = 2;
+        let err = state.add_block(stale).unwrap_err();
+        assert!(matches!(
+            err,
+            ChiguiError::InvalidBlockNumber {
+                expected: 3,
+                got: 2
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn blocks_are_chained_by_hash() -> Result<()> {
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, 0);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: true,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+        let tx = Tx::Generate {
+            to: Account::new("alice")?,
+            value: 1,
+            denom: None,
+        };
+
+        state.add_tx(tx.clone())?;
+
+        let first = state.latest_block().unwrap().clone();
+        assert!(first.header.parent_hash.is_zero());
+        assert_eq!(state.block_by_hash(&first.hash()), Some(&first));
+        assert_eq!(
+            state.tx_by_hash(&tx.hash()),
+            Some(&SignedTx::from(tx.clone()))
+        );
+
+        let mut tampered = state.next_block(Vec::new());
+        tampered.txs.push(tx.into());
+        let err = state.add_block(tampered).unwrap_err();
+        assert!(matches!(err, ChiguiError::InvalidTxRoot { number: 2 }));
+
+        let mut forged = state.next_block(Vec::new());
+        forged.header.parent_hash = Hash::digest(b"forged");
+        let err = state.add_block(forged).unwrap_err();
+        assert!(
+            matches!(err, ChiguiError::InvalidParentHash { number: 2, expected, .. } if expected == first.hash())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn transfers_require_account_signature() -> Result<()> {
+        let alice_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let mallory_key = ed25519_dalek::SigningKey::from_bytes(&[2; 32]);
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, 100);
+                map.insert(Account::new("bob")?, 0);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, PublicKey::from(&alice_key));
+                map
+            },
+            permissive: false,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+        let tx = Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 10,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        };
+
+        assert!(matches!(
+            state.add_tx(tx.clone()),
+            Err(ChiguiError::MissingSignature { .. })
+        ));
+        assert!(matches!(
+            state.add_tx(SignedTx::sign(tx.clone(), "testnet", &mallory_key)),
+            Err(ChiguiError::SignerMismatch { .. })
+        ));
+        assert!(matches!(
+            state.add_tx(SignedTx::sign(tx.clone(), "mainnet", &alice_key)),
+            Err(ChiguiError::ChainIdMismatch { .. })
+        ));
+
+        state.add_tx(SignedTx::sign(tx, "testnet", &alice_key))?;
+
+        assert_eq!(state.get_balance(&Account::new("bob")?).unwrap(), 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_signatures_made_for_other_chains() -> Result<()> {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let alice = Account::from_public_key(&PublicKey::from(&key));
+        let chain = |chain_id| {
+            GenesisBuilder::new(chain_id)
+                .balance(alice.to_string(), 100)
+                .build()
+                .and_then(State::in_memory)
+        };
+        let (mut testnet, mut mainnet) = (chain("testnet")?, chain("mainnet")?);
+        let signed = SignedTx::sign(
+            Tx::Burn {
+                account: alice.clone(),
+                value: 1,
+                fee: 0,
+                nonce: 0,
+            },
+            "testnet",
+            &key,
+        );
+
+        testnet.add_tx(signed.clone())?;
+
+        assert!(matches!(
+            mainnet.add_tx(signed.clone()),
+            Err(ChiguiError::ChainIdMismatch { expected, got })
+                if expected == "mainnet" && got == "testnet"
+        ));
+        assert!(matches!(
+            mainnet.add_tx(SignedTx {
+                chain_id: Some("mainnet".into()),
+                ..signed.clone()
+            }),
+            Err(ChiguiError::InvalidSignature)
+        ));
+        assert!(matches!(
+            mainnet.add_block(mainnet.next_block(vec![signed])),
+            Err(ChiguiError::ChainIdMismatch { .. })
+        ));
+        assert_eq!(mainnet.height(), 0);
+        assert_eq!(mainnet.get_balance(&alice), Some(100));
+
+        Ok(())
+    }
+
+    #[test]
+    fn derived_accounts_are_controlled_by_their_key() -> Result<()> {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[4; 32]);
+        let owner = Account::from_public_key(&PublicKey::from(&key));
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(owner.clone(), 100);
+                map.insert(Account::new("bob")?, 0);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: false,
+            validators: Validators::default(),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+        let tx = Tx::Transfer {
+            from: owner.clone(),
+            to: Account::new("bob")?,
+            value: 10,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        };
+
+        assert!(matches!(
+            state.add_tx(SignedTx::sign(
+                tx.clone(),
+                "testnet",
+                &ed25519_dalek::SigningKey::from_bytes(&[5; 32])
+            )),
+            Err(ChiguiError::SignerMismatch { .. })
+        ));
+
+        state.add_tx(SignedTx::sign(tx, "testnet", &key))?;
+
+        assert_eq!(state.get_balance(&owner).unwrap(), 90);
+
+        Ok(())
+    }
+
+    #[test]
+    fn authority_chains_require_sealed_blocks() -> Result<()> {
+        let validator = ed25519_dalek::SigningKey::from_bytes(&[6; 32]);
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(Account::new("alice")?, 0);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: true,
+            validators: Validators::new(vec![PublicKey::from(&validator)]),
+            proof_of_stake: false,
+            unbonding_period: 0,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+        let mut block = state.next_block(Vec::new());
+
+        assert!(matches!(
+            state.add_block(block.clone()),
+            Err(ChiguiError::MissingSeal { number: 1 })
+        ));
+
+        state.validators().clone().seal(&mut block, &validator)?;
+        state.add_block(block)?;
+
+        assert_eq!(state.height(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn staked_coins_unbond_after_period() -> Result<()> {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let staker = Account::from_public_key(&PublicKey::from(&key));
+        let genesis = Genesis {
+            genesis_time: String::from("2021-01-01T00:00:00Z"),
+            chain_id: String::from("testnet"),
+            balances: {
+                let mut map = HashMap::new();
+                map.insert(staker.clone(), 100);
+                map
+            },
+            assets: HashMap::new(),
+            multisig: HashMap::new(),
+            vesting: HashMap::new(),
+            new_accounts: NewAccountPolicy::default(),
+            minters: None,
+            minter_admin: None,
+            max_supply: None,
+            fee_collector: None,
+            fee_schedule: FeeSchedule::default(),
+            block_gas_limit: None,
+            difficulty: 0,
+            account_keys: HashMap::new(),
+            permissive: false,
+            validators: Validators::default(),
+            proof_of_stake: true,
+            unbonding_period: 2,
+            governance: GovernanceRules::default(),
+        };
+        let mut state = State::in_memory(genesis)?;
+
+        state.add_tx(SignedTx::sign(
+            Tx::Stake {
+                account: staker.clone(),
+                value: 60,
+                fee: 0,
+                nonce: 0,
+            },
+            "testnet",
+            &key,
+        ))?;
+
+        assert_eq!(state.get_balance(&staker), Some(40));
+        assert_eq!(state.next_proposer(), Some(&staker));
+        assert!(state.is_proposer(&PublicKey::from(&key)));
+        assert!(matches!(
+            state.add_block(state.next_block(Vec::new())),
+            Err(ChiguiError::MissingSeal { number: 2 })
+        ));
+
+        let mut block = state.next_block(vec![SignedTx::sign(
+            Tx::Unstake {
+                account: staker.clone(),
+                value: 60,
+                fee: 0,
+                nonce: 1,
+            },
+            "testnet",
+            &key,
+        )]);
+        block.seal = Some(TxSignature::sign(&block.hash(), &key));
+        state.add_block(block)?;
+
+        assert_eq!(state.stakes().stake_of(&staker), 0);
+        assert_eq!(state.stakes().unbonding().len(), 1);
+        assert_eq!(state.next_proposer(), None);
+
+        state.add_tx(Tx::Generate {
+            to: staker.clone(),
+            value: 0,
+            denom: None,
+        })?;
+        assert_eq!(state.get_balance(&staker), Some(40));
+
+        state.add_tx(Tx::Generate {
+            to: staker.clone(),
+            value: 0,
+            denom: None,
+        })?;
+        assert_eq!(state.get_balance(&staker), Some(100));
+        assert!(state.stakes().unbonding().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn checks_blocks_without_applying_them() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10,"bob":0},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let (alice, bob) = (Account::new("alice")?, Account::new("bob")?);
+        let transfer = |value, nonce| -> SignedTx {
+            Tx::Transfer {
+                from: alice.clone(),
+                to: bob.clone(),
+                value,
+                fee: 0,
+                nonce,
+                memo: None,
+                denom: None,
+            }
+            .into()
+        };
+        let root = state.state_root();
+
+        state.check_block(state.next_block(vec![transfer(4, 0), transfer(6, 1)]))?;
+        assert!(matches!(
+            state.check_block(state.next_block(vec![transfer(4, 0), transfer(7, 1)])),
+            Err(ChiguiError::InsufficientBalance { .. })
+        ));
+        assert_eq!(state.height(), 0);
+        assert_eq!(state.state_root(), root);
+        assert_eq!(state.next_nonce(&alice), 0);
+        assert_eq!(state.txs_for(&bob).count(), 0);
+
+        state.add_block(state.next_block(vec![transfer(4, 0)]))?;
+        assert_eq!(state.get_balance(&bob), Some(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn state_root_commits_to_balances() -> Result<()> {
+        let genesis = || {
+            State::parse_genesis(
+                r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10,"bob":0},"permissive":true}"#,
+            )
+        };
+        let mut state = State::in_memory(genesis()?)?;
+        let mut peer = State::in_memory(genesis()?)?;
+        let root = state.state_root();
+
+        assert_eq!(peer.state_root(), root);
+
+        state.add_tx(Tx::Generate {
+            to: Account::new("bob")?,
+            value: 0,
+            denom: None,
+        })?;
+        assert_eq!(state.state_root(), root);
+
+        state.add_tx(Tx::Generate {
+            to: Account::new("bob")?,
+            value: 1,
+            denom: None,
+        })?;
+        peer.add_tx(Tx::Generate {
+            to: Account::new("alice")?,
+            value: 1,
+            denom: None,
+        })?;
+        assert_ne!(state.state_root(), peer.state_root());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rollback_undoes_last_txs() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let generate = |value| Tx::Generate {
+            to: alice.clone(),
+            value,
+            denom: None,
+        };
+        let mut state = State::open(dbdir.path())?;
+
+        state.add_tx(generate(1))?;
+        state.add_block(state.next_block(vec![generate(2).into(), generate(3).into()]))?;
+        state.add_tx(generate(4))?;
+        state.write_snapshot()?;
+
+        assert!(matches!(
+            state.rollback(2),
+            Err(ChiguiError::PartialRollback { number: 2, txs: 2 })
+        ));
+        assert!(matches!(
+            state.rollback(5),
+            Err(ChiguiError::RollbackTooFar { n: 5, txs: 4 })
+        ));
+        assert_eq!(state.height(), 3);
+
+        let undone = state.rollback(3)?;
+
+        assert_eq!(undone.len(), 3);
+        assert_eq!(undone[2].tx, generate(4));
+        assert_eq!(state.height(), 1);
+        assert_eq!(state.get_balance(&alice), Some(1));
+        assert_eq!(state.total_supply(), 1);
+
+        let reopened = State::open(dbdir.path())?;
+
+        assert_eq!(reopened.height(), 1);
+        assert_eq!(reopened.state_root(), state.state_root());
+        assert!(state.rollback(0)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn open_resumes_from_snapshot() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":1000,"bob":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let mut state = State::open(dbdir.path())?;
+
+        for nonce in 0..3 {
+            state.add_tx(Tx::Transfer {
+                from: Account::new("alice")?,
+                to: Account::new("bob")?,
+                value: 100,
+                fee: 0,
+                nonce,
+                memo: None,
+                denom: None,
+            })?;
+        }
+
+        assert!(state.write_snapshot()?);
+        assert!(Snapshot::path(dbdir.path(), 3).exists());
+
+        state.add_tx(Tx::Transfer {
+            from: Account::new("alice")?,
+            to: Account::new("bob")?,
+            value: 100,
+            fee: 0,
+            nonce: 3,
+            memo: None,
+            denom: None,
+        })?;
+
+        let reopened = State::open(dbdir.path())?;
+
+        assert_eq!(reopened.height(), 4);
+        assert_eq!(reopened.tx_count(), 4);
+        assert_eq!(reopened.get_balance(&Account::new("bob")?), Some(400));
+        assert_eq!(reopened.next_nonce(&Account::new("alice")?), 4);
+        assert_eq!(reopened.state_root(), state.state_root());
+
+        let mut snapshot = Snapshot::latest(dbdir.path(), 4)?.unwrap();
+        snapshot.balances.insert(Account::new("bob")?, 1_000_000);
+        snapshot.write(dbdir.path())?;
+
+        assert!(matches!(
+            State::open(dbdir.path()),
+            Err(ChiguiError::InvalidSnapshot { height: 3 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn pruned_chains_keep_recent_blocks() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let generate = Tx::Generate {
+            to: Account::new("alice")?,
+            value: 1,
+            denom: None,
+        };
+        let mut state = State::open(dbdir.path())?;
+
+        for _ in 0..5 {
+            state.add_tx(generate.clone())?;
+        }
+        state.write_snapshot()?;
+
+        let mut pruned = State::open_pruned(dbdir.path(), 2)?;
+        let block_db = std::fs::read_to_string(dbdir.path().join("block.db")).unwrap();
+
+        assert_eq!(block_db.lines().count(), 2);
+        assert_eq!(pruned.height(), 5);
+        assert_eq!(pruned.tx_count(), 2);
+        assert!(pruned.block_by_number(3).is_none());
+        assert_eq!(pruned.block_by_number(4), state.block_by_number(4));
+
+        pruned.add_tx(generate)?;
+
+        let reopened = State::open(dbdir.path())?;
+
+        assert_eq!(reopened.height(), 6);
+        assert_eq!(reopened.get_balance(&Account::new("alice")?), Some(6));
+        assert_eq!(Snapshot::heights(dbdir.path())?, vec![5]);
+
+        std::fs::remove_file(Snapshot::path(dbdir.path(), 5)).unwrap();
+
+        assert!(matches!(
+            State::open(dbdir.path()),
+            Err(ChiguiError::MissingSnapshot { height: 3 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn compaction_folds_every_block() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let generate = Tx::Generate {
+            to: alice.clone(),
+            value: 1,
+            denom: None,
+        };
+        let mut state = State::open(dbdir.path())?;
+
+        for _ in 0..3 {
+            state.add_tx(generate.clone())?;
+        }
+        state.prune(0)?;
+
+        let mut reopened = State::open(dbdir.path())?;
+
+        assert_eq!(
+            std::fs::read_to_string(dbdir.path().join("block.db")).unwrap(),
+            ""
+        );
+        assert_eq!(reopened.height(), 3);
+        assert_eq!(reopened.get_balance(&alice), Some(3));
+
+        reopened.add_tx(generate)?;
+
+        assert_eq!(State::open(dbdir.path())?.height(), 4);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_api_persists_blocks() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::open_async(dbdir.path()).await?;
+
+        for value in [1, 2] {
+            state
+                .add_tx_async(Tx::Generate {
+                    to: alice.clone(),
+                    value,
+                    denom: None,
+                })
+                .await?;
+        }
+
+        let reopened = State::open_async(dbdir.path()).await?;
+
+        assert_eq!(reopened.height(), 2);
+        assert_eq!(reopened.balance_at_async(&alice, 1).await?, Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn balances_never_overflow() -> Result<()> {
+        let genesis = State::parse_genesis(&format!(
+            r#"{{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{{"alice":{},"bob":1}},"permissive":true}}"#,
+            u64::MAX - 1
+        ))?;
+        let mut state = State::in_memory(genesis)?;
+
+        assert_eq!(state.total_supply(), u64::MAX);
+        assert!(matches!(
+            state.add_tx(Tx::Generate {
+                to: Account::new("bob")?,
+                value: 1,
+                denom: None,
+            }),
+            Err(ChiguiError::SupplyOverflow)
+        ));
+        assert_eq!(state.total_supply(), u64::MAX);
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(1));
+
+        state.add_tx(Tx::Transfer {
+            from: Account::new("bob")?,
+            to: Account::new("alice")?,
+            value: 1,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        })?;
+
+        assert_eq!(state.get_balance(&Account::new("alice")?), Some(u64::MAX));
+
+        let overflowing = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":18446744073709551615,"bob":1}}"#,
+        );
+
+        assert!(matches!(
+            overflowing,
+            Err(ChiguiError::InvalidGenesis { violations }) if violations == ["initial supply overflows"]
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn burned_coins_leave_supply() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":50},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let burn = |value, nonce| Tx::Burn {
+            account: Account(String::from("alice")),
+            value,
+            fee: 0,
+            nonce,
+        };
+
+        state.add_tx(burn(30, 0))?;
+
+        assert_eq!(state.get_balance(&Account::new("alice")?), Some(70));
+        assert_eq!(state.total_supply(), 120);
+        assert!(matches!(
+            state.add_tx(burn(71, 1)),
+            Err(ChiguiError::InsufficientBalance { .. })
+        ));
+        assert_eq!(state.total_supply(), 120);
+
+        Ok(())
+    }
+
+    #[test]
+    fn locked_coins_spendable_after_unlock() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let bob = Account::new("bob")?;
+        let spend = Tx::Burn {
+            account: bob.clone(),
+            value: 30,
+            fee: 0,
+            nonce: 0,
+        };
+
+        state.add_tx(Tx::TransferLocked {
+            from: Account::new("alice")?,
+            to: bob.clone(),
+            value: 30,
+            unlock: Unlock::Height(2),
+            fee: 0,
+            nonce: 0,
+        })?;
+
+        assert_eq!(state.get_balance(&bob), Some(0));
+        assert_eq!(state.locks().locked_of(&bob), 30);
+        assert_eq!(state.total_supply(), 100);
+        assert!(matches!(
+            state.add_tx(spend.clone()),
+            Err(ChiguiError::InsufficientBalance { .. })
+        ));
+
+        state.add_tx(Tx::Generate {
+            to: bob.clone(),
+            value: 0,
+            denom: None,
+        })?;
+
+        assert_eq!(state.get_balance(&bob), Some(30));
+        assert!(state.locks().is_empty());
+        state.add_tx(spend)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn unvested_coins_cannot_be_spent() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100},"vesting":{"alice":{"value":80,"cliff":2,"duration":4}},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let alice = Account::new("alice")?;
+        let burn = |value, nonce| Tx::Burn {
+            account: alice.clone(),
+            value,
+            fee: 0,
+            nonce,
+        };
+
+        assert_eq!(state.spendable_balance(&alice), Some(20));
+        assert!(matches!(
+            state.add_tx(burn(21, 0)),
+            Err(ChiguiError::Unvested {
+                spendable: 20,
+                need: 21,
+                ..
+            })
+        ));
+
+        state.add_tx(burn(20, 0))?;
+
+        assert_eq!(state.spendable_balance(&alice), Some(40));
+
+        state.add_tx(burn(40, 1))?;
+        state.add_tx(burn(20, 2))?;
+
+        assert_eq!(state.get_balance(&alice), Some(20));
+        assert_eq!(state.spendable_balance(&alice), Some(20));
+
+        Ok(())
+    }
+
+    #[test]
+    fn escrows_are_released_or_refunded_by_their_parties() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":0,"judge":0},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let create = |nonce| Tx::EscrowCreate {
+            from: Account(String::from("alice")),
+            to: Account(String::from("bob")),
+            value: 40,
+            arbiter: Some(Account(String::from("judge"))),
+            fee: 0,
+            nonce,
+        };
+        let first = create(0).hash();
+        let second = create(1).hash();
+
+        state.add_tx(create(0))?;
+        state.add_tx(create(1))?;
+
+        assert_eq!(state.get_balance(&Account::new("alice")?), Some(20));
+        assert_eq!(state.total_supply(), 100);
+        assert!(matches!(
+            state.add_tx(Tx::EscrowRelease {
+                account: Account::new("bob")?,
+                escrow: first,
+                fee: 0,
+                nonce: 0,
+            }),
+            Err(ChiguiError::NotEscrowParty { .. })
+        ));
+
+        state.add_tx(Tx::EscrowRelease {
+            account: Account::new("alice")?,
+            escrow: first,
+            fee: 0,
+            nonce: 2,
+        })?;
+        state.add_tx(Tx::EscrowRefund {
+            account: Account::new("judge")?,
+            escrow: second,
+            fee: 0,
+            nonce: 0,
+        })?;
+
+        assert_eq!(state.get_balance(&Account::new("alice")?), Some(60));
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(40));
+        assert!(state.escrows().is_empty());
+        assert!(matches!(
+            state.add_tx(Tx::EscrowRefund {
+                account: Account::new("bob")?,
+                escrow: first,
+                fee: 0,
+                nonce: 0,
+            }),
+            Err(ChiguiError::UnknownEscrow { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn passed_proposals_change_parameters() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":100,"carol":10},"governance":{"voting_period":1,"quorum":50,"threshold":50},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let stake = |account: &str, value| Tx::Stake {
+            account: Account(String::from(account)),
+            value,
+            fee: 0,
+            nonce: 0,
+        };
+        let propose = Tx::Propose {
+            account: Account::new("carol")?,
+            change: ParamChange::MinFee(5),
+            fee: 0,
+            nonce: 0,
+        };
+        let proposal = propose.hash();
+        let vote = |account: &str, nonce| Tx::Vote {
+            account: Account(String::from(account)),
+            proposal,
+            approve: true,
+            fee: 0,
+            nonce,
+        };
+
+        state.add_tx(stake("alice", 60))?;
+        state.add_tx(stake("bob", 40))?;
+        state.add_tx(propose)?;
+
+        assert!(matches!(
+            state.add_tx(vote("carol", 1)),
+            Err(ChiguiError::NotStaker { .. })
+        ));
+
+        state.add_tx(vote("alice", 1))?;
+
+        assert_eq!(state.fee_schedule().min_fee, 5);
+        assert!(state.governance().is_empty());
+        assert!(matches!(
+            state.add_tx(vote("bob", 1)),
+            Err(ChiguiError::FeeTooLow { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn hooks_observe_persisted_blocks() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":0},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let changed = Arc::new(Mutex::new(Vec::new()));
+        let transfer = |value| Tx::Transfer {
+            from: Account(String::from("alice")),
+            to: Account(String::from("bob")),
+            value,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        };
+
+        state.on_tx_applied({
+            let applied = applied.clone();
+            move |tx, number| applied.lock().unwrap().push((tx.hash(), number))
+        });
+        state.on_balance_changed({
+            let changed = changed.clone();
+            move |account, before, after| {
+                changed
+                    .lock()
+                    .unwrap()
+                    .push((account.to_string(), before, after))
+            }
+        });
+
+        let subscription = state.subscribe();
+
+        assert!(state.add_tx(transfer(101)).is_err());
+        state.add_tx(transfer(30))?;
+
+        assert_eq!(*applied.lock().unwrap(), vec![(transfer(30).hash(), 1)]);
+        assert_eq!(
+            *changed.lock().unwrap(),
+            vec![
+                (String::from("alice"), 100, 70),
+                (String::from("bob"), 0, 30)
+            ]
+        );
+
+        let applied = subscription.try_recv().expect("transfer delivered");
+
+        assert_eq!((applied.block, applied.hash), (1, transfer(30).hash()));
+        assert_eq!(applied.balances.get(&Account::new("bob")?), Some(&30));
+        assert!(subscription.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_respects_max_supply() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":90},"max_supply":100,"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let generate = |value| Tx::Generate {
+            to: Account(String::from("alice")),
+            value,
+            denom: None,
+        };
+
+        state.add_tx(generate(10))?;
+
+        assert_eq!(state.total_supply(), 100);
+        assert!(matches!(
+            state.add_tx(generate(1)),
+            Err(ChiguiError::MaxSupplyExceeded {
+                supply: 101,
+                max_supply: 100
+            })
+        ));
+        assert_eq!(state.total_supply(), 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_requires_authorized_minter() -> Result<()> {
+        let minter_key = ed25519_dalek::SigningKey::from_bytes(&[6; 32]);
+        let admin_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let minter = Account::from_public_key(&PublicKey::from(&minter_key));
+        let admin = Account::from_public_key(&PublicKey::from(&admin_key));
+        let genesis = State::parse_genesis(&format!(
+            r#"{{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{{"{admin}":10,"bob":0}},"minters":["{minter}"],"minter_admin":"{admin}","permissive":true}}"#
+        ))?;
+        let mut state = State::in_memory(genesis)?;
+        let generate = Tx::Generate {
+            to: Account::new("bob")?,
+            value: 5,
+            denom: None,
+        };
+
+        assert!(matches!(
+            state.add_tx(generate.clone()),
+            Err(ChiguiError::NotMinter)
+        ));
+        assert!(matches!(
+            state.add_tx(SignedTx::sign(generate.clone(), "testnet", &admin_key)),
+            Err(ChiguiError::NotMinter)
+        ));
+
+        state.add_tx(SignedTx::sign(generate.clone(), "testnet", &minter_key))?;
+        assert_eq!(state.get_balance(&Account::new("bob")?), Some(5));
+
+        let set_minters = |admin: &Account| Tx::SetMinters {
+            admin: admin.clone(),
+            minters: vec![admin.clone()],
+            fee: 0,
+            nonce: 0,
+        };
+
+        assert!(matches!(
+            state.add_tx(set_minters(&Account::new("bob")?)),
+            Err(ChiguiError::NotMinterAdmin { .. })
+        ));
+
+        state.add_tx(SignedTx::sign(set_minters(&admin), "testnet", &admin_key))?;
+
+        assert_eq!(state.minters(), Some(&BTreeSet::from([admin])));
+        assert!(matches!(
+            state.add_tx(SignedTx::sign(generate.clone(), "testnet", &minter_key)),
+            Err(ChiguiError::NotMinter)
+        ));
+        state.add_tx(SignedTx::sign(generate, "testnet", &admin_key))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn aliases_resolve_to_accounts() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10,"bob":10},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let register = |account: &str, name: &str| Tx::RegisterAlias {
+            account: Account(String::from(account)),
+            name: String::from(name),
+            fee: 0,
+            nonce: 0,
+        };
+
+        state.add_tx(register("alice", "Alice-Shop"))?;
+
+        assert_eq!(
+            state.resolve_alias("alice-shop"),
+            Some(&Account::new("alice")?)
+        );
+        assert!(matches!(
+            state.add_tx(register("bob", "alice-shop ")),
+            Err(ChiguiError::AliasTaken { .. })
+        ));
+        assert!(matches!(
+            state.add_tx(register("bob", "no spaces")),
+            Err(ChiguiError::InvalidAccount { .. })
+        ));
+        assert_eq!(state.resolve_alias("bob"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn multisig_transfers_need_approvals() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"treasury":100,"alice":5,"bob":5,"carol":0},"multisig":{"treasury":{"threshold":2,"members":["alice","bob"]}},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let multisig = |account: &str, action, nonce| Tx::Multisig {
+            account: Account(String::from(account)),
+            action,
+            fee: 0,
+            nonce,
+        };
+        let propose = multisig(
+            "alice",
+            MultisigAction::Propose {
+                multisig: Account::new("treasury")?,
+                to: Account::new("carol")?,
+                value: 40,
+            },
+            0,
+        );
+        let proposal = propose.hash();
+
+        assert!(matches!(
+            state.add_tx(Tx::Transfer {
+                from: Account::new("treasury")?,
+                to: Account::new("carol")?,
+                value: 40,
+                fee: 0,
+                nonce: 0,
+                memo: None,
+                denom: None,
+            }),
+            Err(ChiguiError::MultisigRequired { .. })
+        ));
+
+        state.add_tx(propose)?;
+
+        assert!(matches!(
+            state.add_tx(multisig("alice", MultisigAction::Execute { proposal }, 1)),
+            Err(ChiguiError::NotEnoughApprovals {
+                have: 1,
+                need: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            state.add_tx(multisig("carol", MultisigAction::Approve { proposal }, 0)),
+            Err(ChiguiError::NotMultisigMember { .. })
+        ));
+
+        state.add_tx(multisig("bob", MultisigAction::Approve { proposal }, 0))?;
+        state.add_tx(multisig("alice", MultisigAction::Execute { proposal }, 1))?;
+
+        assert_eq!(state.get_balance(&Account::new("treasury")?), Some(60));
+        assert_eq!(state.get_balance(&Account::new("carol")?), Some(40));
+        assert!(state.proposals().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn state_is_shareable_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<State>();
+    }
+
+    #[test]
+    fn balances_at_past_heights() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10,"bob":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::open(dbdir.path())?;
+
+        for value in [1, 2, 3] {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+        }
+        state.add_tx(Tx::Generate {
+            to: Account::new("bob")?,
+            value: 1,
+            denom: None,
+        })?;
+
+        let mut archival = State::open_archival(dbdir.path())?;
+
+        archival.add_tx(Tx::Generate {
+            to: alice.clone(),
+            value: 4,
+            denom: None,
+        })?;
+
+        for queried in [&state, &archival] {
+            assert_eq!(queried.balance_at(&alice, 0)?, Some(10));
+            assert_eq!(queried.balance_at(&alice, 2)?, Some(13));
+            assert_eq!(queried.balance_at(&alice, 4)?, Some(16));
+            assert_eq!(queried.balance_at(&Account::new("carol")?, 1)?, None);
+        }
+
+        assert_eq!(archival.balance_at(&alice, 5)?, Some(20));
+        assert!(matches!(
+            state.balance_at(&alice, 5),
+            Err(ChiguiError::HeightNotFound { height: 5 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn exposes_txs_in_chain_order() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let generate = |value| -> SignedTx {
+            Tx::Generate {
+                to: Account::new("alice").unwrap(),
+                value,
+                denom: None,
+            }
+            .into()
+        };
+
+        assert_eq!(state.tx_count(), 0);
+        assert!(state.txs().next().is_none());
+        assert!(state.tx_at(0).is_none());
+
+        state.add_tx(generate(1).tx)?;
+        state.add_block(state.next_block(vec![generate(2), generate(3)]))?;
+
+        assert_eq!(state.tx_count(), 3);
+        assert_eq!(
+            state.txs().map(|tx| tx.tx.value()).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(state.tx_at(0), Some(&generate(1)));
+        assert_eq!(state.tx_at(2), Some(&generate(3)));
+        assert!(state.tx_at(3).is_none());
+
+        state.rollback(2)?;
+
+        assert_eq!(state.tx_count(), 1);
+        assert!(state.tx_at(1).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pages_through_txs() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let generate = |value| -> SignedTx {
+            Tx::Generate {
+                to: Account::new("alice").unwrap(),
+                value,
+                denom: None,
+            }
+            .into()
+        };
+
+        state.add_tx(generate(1).tx)?;
+        state.add_block(state.next_block(vec![generate(2), generate(3), generate(4)]))?;
+        state.add_tx(generate(5).tx)?;
+
+        let values = |txs: Vec<(u64, &SignedTx)>| {
+            txs.into_iter()
+                .map(|(number, tx)| (number, tx.tx.value()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            values(state.txs_range(2, 3).collect()),
+            vec![(2, 3), (2, 4), (3, 5)]
+        );
+        assert_eq!(values(state.txs_range(0, 1).collect()), vec![(1, 1)]);
+        assert_eq!(state.txs_range(5, 10).count(), 0);
+        assert_eq!(
+            values(state.txs_between(2, 2).collect()),
+            vec![(2, 2), (2, 3), (2, 4)]
+        );
+        assert_eq!(state.txs_between(0, u64::MAX).count(), 5);
+        assert_eq!(state.txs_between(3, 2).count(), 0);
+        assert_eq!(state.txs_between(4, 9).count(), 0);
 
         Ok(())
     }
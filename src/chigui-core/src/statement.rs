@@ -0,0 +1,149 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::Block;
+use crate::error::Result;
+use crate::index::TxIndex;
+use crate::signed::SignedTx;
+use crate::state::{Genesis, State};
+use crate::{Account, Hash};
+
+/// Whether a transaction took coins from an account or gave it some.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Debit,
+    Credit,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Direction::Debit => write!(f, "debit"),
+            Direction::Credit => write!(f, "credit"),
+        }
+    }
+}
+
+/// A transaction naming an account, as listed by [`State::statement`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatementEntry {
+    pub block: u64,
+    /// Unix timestamp, in seconds, of the block.
+    pub time: u64,
+    pub hash: Hash,
+    pub tx: SignedTx,
+    pub direction: Direction,
+    /// Native coins the transaction moved in or out of the account, fees included.
+    pub amount: u64,
+    /// Native balance of the account right after the transaction.
+    pub balance: u64,
+}
+
+/// Replay the given blocks from genesis, recording the balance of the account after each of the
+/// transactions at the given indexes, oldest first.
+pub(crate) fn replay(
+    genesis: Genesis,
+    blocks: &[Block],
+    account: &Account,
+    indexes: &[TxIndex],
+) -> Result<Vec<StatementEntry>> {
+    let mut state = State::from_snapshot(genesis, Vec::new(), None, false)?;
+    let mut entries = Vec::with_capacity(indexes.len());
+    let mut pending = indexes.iter().peekable();
+
+    for block in blocks {
+        let number = block.header.number;
+        let mut previous = state.get_balance(account).unwrap_or_default();
+        let mut position = 0;
+
+        state.replay_with(block.clone(), |state, tx| {
+            let balance = state.get_balance(account).unwrap_or_default();
+            let index = TxIndex {
+                block: number,
+                position,
+            };
+
+            if pending.next_if_eq(&&index).is_some() {
+                entries.push(StatementEntry {
+                    block: number,
+                    time: block.header.time,
+                    hash: tx.hash(),
+                    tx: tx.clone(),
+                    direction: match balance < previous {
+                        true => Direction::Debit,
+                        false => Direction::Credit,
+                    },
+                    amount: balance.abs_diff(previous),
+                    balance,
+                });
+            }
+
+            previous = balance;
+            position += 1;
+        })?;
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tx;
+    use crate::error::ChiguiError;
+
+    #[test]
+    fn tracks_running_balances() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100,"bob":0,"carol":0},"fee_collector":"carol","permissive":true}"#,
+        )?;
+        let mut state = State::in_memory(genesis)?;
+        let (alice, bob) = (Account::new("alice")?, Account::new("bob")?);
+        let transfer = |from: &Account, to: &Account, value, nonce| Tx::Transfer {
+            from: from.clone(),
+            to: to.clone(),
+            value,
+            fee: 1,
+            nonce,
+            memo: None,
+            denom: None,
+        };
+
+        state.add_tx(transfer(&alice, &bob, 10, 0))?;
+        state.add_tx(Tx::Generate {
+            to: Account::new("carol")?,
+            value: 5,
+            denom: None,
+        })?;
+        state.add_block(state.next_block(vec![
+            transfer(&alice, &bob, 20, 1).into(),
+            transfer(&bob, &alice, 5, 0).into(),
+        ]))?;
+
+        let statement = state.statement(&alice)?;
+        let moves = statement
+            .iter()
+            .map(|entry| (entry.block, entry.direction, entry.amount, entry.balance))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            moves,
+            vec![
+                (1, Direction::Debit, 11, 89),
+                (3, Direction::Debit, 21, 68),
+                (3, Direction::Credit, 5, 73),
+            ]
+        );
+        assert_eq!(statement[1].tx, state.blocks()[2].txs[0]);
+        assert_eq!(statement[2].time, state.blocks()[2].header.time);
+        assert_eq!(state.statement(&bob)?.last().unwrap().balance, 24);
+        assert!(matches!(
+            state.statement(&Account::new("dave")?),
+            Err(ChiguiError::AccountNotFound { .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions, read_to_string};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::block::Block;
+use crate::error::{ChiguiError, Result};
+use crate::snapshot::Snapshot;
+use crate::state::{Genesis, State};
+
+pub mod binary;
+pub mod memory;
+#[cfg(feature = "mmap")]
+mod mmap;
+pub(crate) mod record;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb;
+#[cfg(feature = "sled")]
+pub mod sled;
+mod wal;
+
+use wal::Wal;
+
+/// Where a [`State`] loads its genesis from and persists its blocks and snapshots.
+///
+/// Blocks are only ever appended, except when pruning, which replaces them with the most recent
+/// ones.
+pub trait Storage: Debug + Send + Sync {
+    fn load_genesis(&self) -> Result<Genesis>;
+
+    /// Every persisted block, oldest first.
+    fn iter_blocks(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>>;
+
+    fn append_block(&mut self, block: &Block) -> Result<()>;
+
+    /// Replace every persisted block with the given ones.
+    fn replace_blocks(&mut self, blocks: &[Block]) -> Result<()>;
+
+    fn write_snapshot(&mut self, snapshot: &Snapshot) -> Result<()>;
+
+    /// The most recent snapshot no higher than `max_height`, if any.
+    fn latest_snapshot(&self, max_height: u64) -> Result<Option<Snapshot>>;
+
+    /// Drop every snapshot but the one at `height`.
+    fn retain_snapshot(&mut self, height: u64) -> Result<()>;
+
+    /// Check that blocks can still be persisted, assumed by default.
+    fn check_writable(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: Storage + ?Sized> Storage for Box<S> {
+    fn load_genesis(&self) -> Result<Genesis> {
+        (**self).load_genesis()
+    }
+
+    fn iter_blocks(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        (**self).iter_blocks()
+    }
+
+    fn append_block(&mut self, block: &Block) -> Result<()> {
+        (**self).append_block(block)
+    }
+
+    fn replace_blocks(&mut self, blocks: &[Block]) -> Result<()> {
+        (**self).replace_blocks(blocks)
+    }
+
+    fn write_snapshot(&mut self, snapshot: &Snapshot) -> Result<()> {
+        (**self).write_snapshot(snapshot)
+    }
+
+    fn latest_snapshot(&self, max_height: u64) -> Result<Option<Snapshot>> {
+        (**self).latest_snapshot(max_height)
+    }
+
+    fn retain_snapshot(&mut self, height: u64) -> Result<()> {
+        (**self).retain_snapshot(height)
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        (**self).check_writable()
+    }
+}
+
+/// Copy the blocks of one storage into another, along with the snapshot they build on, to
+/// convert a chain between formats. Returns the number of blocks copied.
+pub fn copy(from: &dyn Storage, to: &mut dyn Storage) -> Result<usize> {
+    let blocks = from.iter_blocks()?.collect::<Result<Vec<Block>>>()?;
+    let max_height = blocks.last().map_or(u64::MAX, |block| block.header.number);
+
+    if let Some(snapshot) = from.latest_snapshot(max_height)? {
+        to.write_snapshot(&snapshot)?;
+    }
+
+    to.replace_blocks(&blocks)?;
+
+    Ok(blocks.len())
+}
+
+/// Bytes read at a time while looking for the start of the last line of `block.db`.
+const TAIL_CHUNK: usize = 4096;
+
+/// The default [`Storage`]: a db dir holding `genesis.json`, the blocks as JSONL in `block.db`
+/// and `snapshot-<height>.json` files.
+///
+/// Appends to `block.db` go through a write-ahead log, `block.wal`, so that a block half written
+/// when the process died is rolled back on the next open.
+#[derive(Clone, Debug)]
+pub struct FileStorage {
+    dbdir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new<P: AsRef<Path>>(dbdir: P) -> Self {
+        Self {
+            dbdir: dbdir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn dbdir(&self) -> &Path {
+        &self.dbdir
+    }
+
+    fn block_db_path(&self) -> PathBuf {
+        self.dbdir.join("block.db")
+    }
+
+    fn wal(&self) -> Wal {
+        Wal::new(self.dbdir.join("block.wal"))
+    }
+
+    /// Atomically replace the content of a file, going through a temporary file synced to disk
+    /// before it's renamed.
+    fn replace_file(path: &Path, content: &str) -> Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+
+            move |source| ChiguiError::Io { path, source }
+        };
+        let mut file = File::create(&tmp_path).map_err(io_error(&tmp_path))?;
+
+        file.write_all(content.as_bytes())
+            .and_then(|()| file.sync_all())
+            .map_err(io_error(&tmp_path))?;
+        std::fs::rename(&tmp_path, path).map_err(io_error(path))?;
+        Self::sync_parent(path);
+
+        Ok(())
+    }
+
+    /// Append a single serialized record as a new line at the end of a JSONL database file, and
+    /// sync it to disk before returning.
+    ///
+    /// The record and its line break go out in a single write, so a crash can at worst leave a
+    /// torn last line behind, which [`FileStorage::recover_torn_line`] drops on the next open.
+    fn append_line(db_path: &Path, line: &str) -> Result<()> {
+        let io_error = |source| ChiguiError::Io {
+            path: db_path.to_path_buf(),
+            source,
+        };
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(db_path)
+            .map_err(io_error)?;
+
+        file.write_all(format!("{}\n", line).as_bytes())
+            .and_then(|()| file.sync_data())
+            .map_err(io_error)
+    }
+
+    /// Sync the directory holding `path`, so that a rename into it survives a crash.
+    fn sync_parent(path: &Path) {
+        let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        else {
+            return;
+        };
+
+        // Directories can't be opened for syncing on every platform, syncing is best effort.
+        if let Ok(dir) = File::open(parent) {
+            dir.sync_all().ok();
+        }
+    }
+
+    /// Deal with a last line missing its line break, left behind by a crash in the middle of an
+    /// append: a complete record gets its line break back, while a partially written one is
+    /// truncated away.
+    ///
+    /// Only the end of the file is read, scanning backwards for the start of the last line.
+    fn recover_torn_line(db_path: &Path) -> Result<()> {
+        let io_error = |source| ChiguiError::Io {
+            path: db_path.to_path_buf(),
+            source,
+        };
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(db_path)
+            .map_err(io_error)?;
+        let len = file.metadata().map_err(io_error)?.len();
+        let mut chunk = vec![0; TAIL_CHUNK];
+        let mut start = 0;
+        let mut pos = len;
+
+        while pos > 0 {
+            let size = TAIL_CHUNK.min(pos as usize);
+            pos -= size as u64;
+
+            file.seek(SeekFrom::Start(pos))
+                .and_then(|_| file.read_exact(&mut chunk[..size]))
+                .map_err(io_error)?;
+
+            if pos + size as u64 == len && chunk[size - 1] == b'\n' {
+                return Ok(());
+            }
+
+            if let Some(index) = chunk[..size].iter().rposition(|byte| *byte == b'\n') {
+                start = pos + index as u64 + 1;
+                break;
+            }
+        }
+
+        if start == len {
+            return Ok(());
+        }
+
+        let mut last_line = Vec::new();
+
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_to_end(&mut last_line))
+            .map_err(io_error)?;
+
+        if record::decode(&last_line, 0).is_ok() {
+            file.write_all(b"\n")
+                .and_then(|()| file.sync_data())
+                .map_err(io_error)
+        } else {
+            file.set_len(start)
+                .and_then(|()| file.sync_data())
+                .map_err(io_error)
+        }
+    }
+
+    #[cfg(any(not(feature = "mmap"), test))]
+    /// Lazily parse the `block.db` file, which is basically a JSONL file, into [`Block`] instances,
+    /// one line at a time, checking their checksums.
+    fn parse_blocks(
+        db_path: PathBuf,
+        reader: impl std::io::BufRead,
+    ) -> impl Iterator<Item = Result<Block>> {
+        reader.lines().enumerate().map(move |(index, line)| {
+            let line = line.map_err(|source| ChiguiError::Io {
+                path: db_path.clone(),
+                source,
+            })?;
+
+            record::decode(line.as_bytes(), index + 1)
+        })
+    }
+}
+
+impl Storage for FileStorage {
+    fn load_genesis(&self) -> Result<Genesis> {
+        let path = self.dbdir.join("genesis.json");
+        let genesis_json =
+            read_to_string(&path).map_err(|source| ChiguiError::Io { path, source })?;
+
+        State::parse_genesis(&genesis_json)
+    }
+
+    fn iter_blocks(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        let path = self.block_db_path();
+
+        self.wal().recover(&path)?;
+        Self::recover_torn_line(&path)?;
+
+        #[cfg(feature = "mmap")]
+        {
+            let blocks = mmap::parse_blocks(&path)?;
+
+            Ok(Box::new(blocks.into_iter().map(Ok)))
+        }
+
+        #[cfg(not(feature = "mmap"))]
+        {
+            let file = File::open(&path).map_err(|source| ChiguiError::Io {
+                path: path.clone(),
+                source,
+            })?;
+
+            Ok(Box::new(Self::parse_blocks(
+                path,
+                std::io::BufReader::new(file),
+            )))
+        }
+    }
+
+    fn append_block(&mut self, block: &Block) -> Result<()> {
+        let line = record::encode(block)?;
+        let path = self.block_db_path();
+        let wal = self.wal();
+
+        wal.begin(block.header.number, &path)?;
+        Self::append_line(&path, &line)?;
+        wal.commit(block.header.number)
+    }
+
+    fn replace_blocks(&mut self, blocks: &[Block]) -> Result<()> {
+        let mut block_db = String::new();
+
+        for block in blocks {
+            block_db.push_str(&record::encode(block)?);
+            block_db.push('\n');
+        }
+
+        Self::replace_file(&self.block_db_path(), &block_db)?;
+        self.wal().clear()
+    }
+
+    fn write_snapshot(&mut self, snapshot: &Snapshot) -> Result<()> {
+        snapshot.write(&self.dbdir).map(|_| ())
+    }
+
+    fn latest_snapshot(&self, max_height: u64) -> Result<Option<Snapshot>> {
+        Snapshot::latest(&self.dbdir, max_height)
+    }
+
+    fn retain_snapshot(&mut self, height: u64) -> Result<()> {
+        for other in Snapshot::heights(&self.dbdir)? {
+            if other != height {
+                let path = Snapshot::path(&self.dbdir, other);
+
+                std::fs::remove_file(&path).map_err(|source| ChiguiError::Io { path, source })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        let path = self.block_db_path();
+
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map(|_| ())
+            .map_err(|source| ChiguiError::Io { path, source })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Account, Tx};
+
+    #[test]
+    fn parse_blocks_reports_line_number() {
+        let block_db = "{\"header\":{\"number\":1,\"parent_hash\":\"0000000000000000000000000000000000000000000000000000000000000000\",\"time\":0,\"nonce\":0},\"txs\":[]}\nnot json\n";
+        let err = FileStorage::parse_blocks(PathBuf::from("block.db"), block_db.as_bytes())
+            .collect::<Result<Vec<Block>>>()
+            .unwrap_err();
+
+        assert!(matches!(err, ChiguiError::ParseError { line: 2, .. }));
+    }
+
+    #[test]
+    fn drops_torn_last_line() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let block_db_path = dbdir.path().join("block.db");
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(&block_db_path, "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::open(dbdir.path())?;
+
+        state.add_tx(Tx::Generate {
+            to: alice.clone(),
+            value: 1,
+            denom: None,
+        })?;
+
+        let complete = std::fs::read_to_string(&block_db_path).unwrap();
+        let torn = format!("{}{}", complete, &complete[..complete.len() / 2]);
+        std::fs::write(&block_db_path, torn).unwrap();
+
+        let mut state = State::open(dbdir.path())?;
+
+        assert_eq!(state.height(), 1);
+        assert_eq!(std::fs::read_to_string(&block_db_path).unwrap(), complete);
+
+        state.add_tx(Tx::Generate {
+            to: alice.clone(),
+            value: 2,
+            denom: None,
+        })?;
+
+        let unterminated = std::fs::read_to_string(&block_db_path).unwrap();
+        std::fs::write(&block_db_path, unterminated.trim_end()).unwrap();
+
+        let reopened = State::open(dbdir.path())?;
+
+        assert_eq!(reopened.get_balance(&alice), Some(13));
+        assert_eq!(
+            std::fs::read_to_string(&block_db_path).unwrap(),
+            unterminated
+        );
+
+        Ok(())
+    }
+}
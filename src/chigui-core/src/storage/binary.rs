@@ -0,0 +1,344 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tracing::warn;
+
+use crate::block::Block;
+use crate::cbor;
+use crate::error::{ChiguiError, Result};
+use crate::snapshot::Snapshot;
+use crate::state::{Genesis, State};
+use crate::storage::{FileStorage, Storage};
+
+/// Length and CRC32 of a frame, as little-endian `u32`s.
+const FRAME_HEADER: usize = 8;
+
+/// A [`Storage`] keeping blocks as length-prefixed binary records in `<dbdir>/block.bin` and
+/// snapshots as `snapshot-<height>.bin` files, while the genesis is still read from
+/// `genesis.json`.
+///
+/// Records are blocks and snapshots encoded as [CBOR](crate::cbor), which unlike bincode is
+/// self-describing and so can decode transactions, internally tagged enums.
+///
+/// Every record carries a CRC32, and a torn record left by a crash is truncated away on open.
+#[derive(Clone, Debug)]
+pub struct BinaryStorage {
+    dbdir: PathBuf,
+}
+
+impl BinaryStorage {
+    pub fn open<P: AsRef<Path>>(dbdir: P) -> Result<Self> {
+        let storage = Self {
+            dbdir: dbdir.as_ref().to_path_buf(),
+        };
+        let path = storage.block_bin_path();
+        let io_error = |source| ChiguiError::Io {
+            path: path.clone(),
+            source,
+        };
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map_err(io_error)?;
+        let bytes = std::fs::read(&path).map_err(io_error)?;
+        let (_, complete) = frames(&bytes);
+
+        if complete < bytes.len() {
+            warn!(
+                path = %path.display(),
+                bytes = bytes.len() - complete,
+                "truncating torn record"
+            );
+            file.set_len(complete as u64)
+                .and_then(|()| file.sync_data())
+                .map_err(io_error)?;
+        }
+
+        Ok(storage)
+    }
+
+    pub fn dbdir(&self) -> &Path {
+        &self.dbdir
+    }
+
+    fn block_bin_path(&self) -> PathBuf {
+        self.dbdir.join("block.bin")
+    }
+
+    pub fn snapshot_path(dbdir: &Path, height: u64) -> PathBuf {
+        dbdir.join(format!("snapshot-{}.bin", height))
+    }
+
+    /// Heights of the binary snapshots found in the db dir, in ascending order.
+    fn snapshot_heights(&self) -> Result<Vec<u64>> {
+        let io_error = |source| ChiguiError::Io {
+            path: self.dbdir.clone(),
+            source,
+        };
+        let mut heights = Vec::new();
+
+        for entry in std::fs::read_dir(&self.dbdir).map_err(io_error)? {
+            let name = entry.map_err(io_error)?.file_name();
+            let height = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("snapshot-"))
+                .and_then(|name| name.strip_suffix(".bin"))
+                .and_then(|height| height.parse::<u64>().ok());
+
+            heights.extend(height);
+        }
+
+        heights.sort_unstable();
+
+        Ok(heights)
+    }
+
+    /// Atomically replace the content of a file, like [`FileStorage`] does.
+    fn replace_file(path: &Path, content: &[u8]) -> Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+
+            move |source| ChiguiError::Io { path, source }
+        };
+        let mut file = File::create(&tmp_path).map_err(io_error(&tmp_path))?;
+
+        file.write_all(content)
+            .and_then(|()| file.sync_all())
+            .map_err(io_error(&tmp_path))?;
+        std::fs::rename(&tmp_path, path).map_err(io_error(path))?;
+        FileStorage::sync_parent(path);
+
+        Ok(())
+    }
+}
+
+impl Storage for BinaryStorage {
+    fn load_genesis(&self) -> Result<Genesis> {
+        let path = self.dbdir.join("genesis.json");
+        let genesis_json =
+            std::fs::read_to_string(&path).map_err(|source| ChiguiError::Io { path, source })?;
+
+        State::parse_genesis(&genesis_json)
+    }
+
+    fn iter_blocks(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        let path = self.block_bin_path();
+        let bytes = std::fs::read(&path).map_err(|source| ChiguiError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let blocks = frames(&bytes)
+            .0
+            .into_iter()
+            .enumerate()
+            .map(|(index, frame)| decode_frame(&frame, &path, index + 1))
+            .collect::<Vec<_>>();
+
+        Ok(Box::new(blocks.into_iter()))
+    }
+
+    fn append_block(&mut self, block: &Block) -> Result<()> {
+        let path = self.block_bin_path();
+        let io_error = |source| ChiguiError::Io {
+            path: path.clone(),
+            source,
+        };
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map_err(io_error)?;
+
+        // A single write, so that a crash can at worst leave a torn last record behind.
+        file.write_all(&frame(block)?)
+            .and_then(|()| file.sync_data())
+            .map_err(io_error)
+    }
+
+    fn replace_blocks(&mut self, blocks: &[Block]) -> Result<()> {
+        let mut block_bin = Vec::new();
+
+        for block in blocks {
+            block_bin.extend(frame(block)?);
+        }
+
+        Self::replace_file(&self.block_bin_path(), &block_bin)
+    }
+
+    fn write_snapshot(&mut self, snapshot: &Snapshot) -> Result<()> {
+        Self::replace_file(
+            &Self::snapshot_path(&self.dbdir, snapshot.height),
+            &frame(snapshot)?,
+        )
+    }
+
+    fn latest_snapshot(&self, max_height: u64) -> Result<Option<Snapshot>> {
+        let Some(height) = self
+            .snapshot_heights()?
+            .into_iter()
+            .rfind(|height| *height <= max_height)
+        else {
+            return Ok(None);
+        };
+        let path = Self::snapshot_path(&self.dbdir, height);
+        let bytes = std::fs::read(&path).map_err(|source| ChiguiError::Io {
+            path: path.clone(),
+            source,
+        })?;
+
+        match frames(&bytes).0.first() {
+            Some(frame) => decode_frame(frame, &path, 1).map(Some),
+            None => Err(ChiguiError::CorruptedRecord { path, record: 1 }),
+        }
+    }
+
+    fn retain_snapshot(&mut self, height: u64) -> Result<()> {
+        for other in self.snapshot_heights()? {
+            if other != height {
+                let path = Self::snapshot_path(&self.dbdir, other);
+
+                std::fs::remove_file(&path).map_err(|source| ChiguiError::Io { path, source })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        let path = self.block_bin_path();
+
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map(|_| ())
+            .map_err(|source| ChiguiError::Io { path, source })
+    }
+}
+
+/// Encode a value as a frame: the length and CRC32 of its record, then the record itself.
+fn frame<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let record = cbor::to_vec(value)?;
+    let mut frame = Vec::with_capacity(FRAME_HEADER + record.len());
+
+    frame.extend((record.len() as u32).to_le_bytes());
+    frame.extend(crc32fast::hash(&record).to_le_bytes());
+    frame.extend(record);
+
+    Ok(frame)
+}
+
+/// The complete frames at the start of `bytes`, as their checksum and record, along with their
+/// total length. Anything past it is a torn frame.
+fn frames(bytes: &[u8]) -> (Vec<(u32, &[u8])>, usize) {
+    let mut frames = Vec::new();
+    let mut offset = 0;
+
+    while let Some(header) = bytes.get(offset..offset + FRAME_HEADER) {
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+        let start = offset + FRAME_HEADER;
+        let Some(record) = bytes.get(start..start + len) else {
+            break;
+        };
+
+        frames.push((checksum, record));
+        offset = start + len;
+    }
+
+    (frames, offset)
+}
+
+fn decode_frame<T: DeserializeOwned>(
+    (checksum, record): &(u32, &[u8]),
+    path: &Path,
+    index: usize,
+) -> Result<T> {
+    let corrupted = || ChiguiError::CorruptedRecord {
+        path: path.to_path_buf(),
+        record: index,
+    };
+
+    if crc32fast::hash(record) != *checksum {
+        return Err(corrupted());
+    }
+
+    cbor::from_slice(record).map_err(|err| ChiguiError::BinaryParseError {
+        path: path.to_path_buf(),
+        record: index,
+        source: Box::new(err),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::copy;
+    use crate::{Account, Tx};
+
+    #[test]
+    fn converts_jsonl_chains() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let dbdir = dbdir.path();
+
+        std::fs::write(
+            dbdir.join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.join("block.db"), "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::open(dbdir)?;
+
+        for value in [1, 2, 3] {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+        }
+
+        state.prune(1)?;
+
+        let mut binary = BinaryStorage::open(dbdir)?;
+
+        assert_eq!(copy(&FileStorage::new(dbdir), &mut binary)?, 1);
+        assert!(
+            std::fs::metadata(dbdir.join("block.bin")).unwrap().len()
+                < std::fs::metadata(dbdir.join("block.db")).unwrap().len()
+        );
+
+        let reopened = State::with_storage(binary.clone())?;
+
+        assert_eq!(reopened.height(), 3);
+        assert_eq!(reopened.state_root(), state.state_root());
+
+        // A torn record is truncated away on open.
+        let block_bin = std::fs::read(dbdir.join("block.bin")).unwrap();
+
+        std::fs::write(dbdir.join("block.bin"), &block_bin[..block_bin.len() - 1]).unwrap();
+        BinaryStorage::open(dbdir)?;
+
+        assert!(std::fs::read(dbdir.join("block.bin")).unwrap().is_empty());
+
+        // A flipped bit is reported rather than misread.
+        let mut corrupted = block_bin.clone();
+        let last = corrupted.len() - 1;
+
+        corrupted[last] ^= 1;
+        std::fs::write(dbdir.join("block.bin"), corrupted).unwrap();
+
+        assert!(matches!(
+            binary.iter_blocks()?.next(),
+            Some(Err(ChiguiError::CorruptedRecord { record: 1, .. }))
+        ));
+
+        Ok(())
+    }
+}
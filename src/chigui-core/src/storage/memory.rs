@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use crate::block::Block;
+use crate::error::Result;
+use crate::snapshot::Snapshot;
+use crate::state::Genesis;
+use crate::storage::Storage;
+
+/// A [`Storage`] keeping everything in memory, for tests and simulations that shouldn't touch the
+/// filesystem. Everything it holds is lost when it's dropped.
+#[derive(Clone, Debug)]
+pub struct MemoryStorage {
+    genesis: Genesis,
+    blocks: Vec<Block>,
+    snapshots: BTreeMap<u64, Snapshot>,
+}
+
+impl MemoryStorage {
+    pub fn new(genesis: Genesis) -> Self {
+        Self {
+            genesis,
+            blocks: Vec::new(),
+            snapshots: BTreeMap::new(),
+        }
+    }
+
+    /// Start from the given blocks, as if they had been persisted already.
+    pub fn with_blocks(mut self, blocks: Vec<Block>) -> Self {
+        self.blocks = blocks;
+        self
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load_genesis(&self) -> Result<Genesis> {
+        Ok(self.genesis.clone())
+    }
+
+    fn iter_blocks(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        Ok(Box::new(self.blocks.iter().cloned().map(Ok)))
+    }
+
+    fn append_block(&mut self, block: &Block) -> Result<()> {
+        self.blocks.push(block.clone());
+
+        Ok(())
+    }
+
+    fn replace_blocks(&mut self, blocks: &[Block]) -> Result<()> {
+        self.blocks = blocks.to_vec();
+
+        Ok(())
+    }
+
+    fn write_snapshot(&mut self, snapshot: &Snapshot) -> Result<()> {
+        self.snapshots.insert(snapshot.height, snapshot.clone());
+
+        Ok(())
+    }
+
+    fn latest_snapshot(&self, max_height: u64) -> Result<Option<Snapshot>> {
+        Ok(self
+            .snapshots
+            .range(..=max_height)
+            .next_back()
+            .map(|(_, snapshot)| snapshot.clone()))
+    }
+
+    fn retain_snapshot(&mut self, height: u64) -> Result<()> {
+        self.snapshots.retain(|other, _| *other == height);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+    use crate::{Account, Tx};
+
+    #[test]
+    fn reopens_from_memory() -> Result<()> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10},"permissive":true}"#,
+        )?;
+        let alice = Account::new("alice")?;
+        let mut state = State::in_memory(genesis.clone())?;
+
+        for value in [1, 2] {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+        }
+
+        let storage = MemoryStorage::new(genesis).with_blocks(state.blocks().to_vec());
+        let reopened = State::with_storage(storage)?;
+
+        assert_eq!(reopened.height(), 2);
+        assert_eq!(reopened.get_balance(&alice), Some(13));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+use std::thread;
+
+use memmap2::Mmap;
+
+use crate::block::Block;
+use crate::error::{ChiguiError, Result};
+
+use super::record;
+
+/// Files smaller than this are parsed on a single thread, splitting them isn't worth it.
+const MIN_PARALLEL_LEN: usize = 1 << 20;
+
+/// Parse a JSONL `block.db` file by mapping it in memory rather than buffering it, splitting it
+/// into byte ranges on line boundaries that are parsed in parallel.
+pub(crate) fn parse_blocks(db_path: &Path) -> Result<Vec<Block>> {
+    let io_error = |source| ChiguiError::Io {
+        path: db_path.to_path_buf(),
+        source,
+    };
+    let file = File::open(db_path).map_err(io_error)?;
+
+    if file.metadata().map_err(io_error)?.len() == 0 {
+        return Ok(Vec::new());
+    }
+
+    // SAFETY: `block.db` is only ever appended to or replaced through a rename, so the mapped
+    // bytes aren't modified while they're being parsed.
+    let mmap = unsafe { Mmap::map(&file) }.map_err(io_error)?;
+    let parts = match mmap.len() < MIN_PARALLEL_LEN {
+        true => 1,
+        false => thread::available_parallelism().map_or(1, |parts| parts.get()),
+    };
+    let ranges = split_lines(&mmap, parts);
+    let parsed = thread::scope(|scope| {
+        let handles = ranges
+            .into_iter()
+            .map(|range| scope.spawn(|| parse_range(&mmap[range])))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("block parser panicked"))
+            .collect::<Vec<Result<Vec<Block>>>>()
+    });
+    let mut blocks = Vec::new();
+
+    for result in parsed {
+        match result {
+            Ok(range_blocks) => blocks.extend(range_blocks),
+            Err(err) => return Err(record::offset_line(err, blocks.len())),
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Parse the blocks of a range, one per line, reporting errors with line numbers relative to the
+/// range.
+fn parse_range(bytes: &[u8]) -> Result<Vec<Block>> {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+
+    bytes
+        .split(|byte| *byte == b'\n')
+        .enumerate()
+        .map(|(index, line)| record::decode(line, index + 1))
+        .collect()
+}
+
+/// Split `bytes` into at most `parts` contiguous ranges of similar size, each ending right after
+/// a line break or at the end of `bytes`.
+pub(crate) fn split_lines(bytes: &[u8], parts: usize) -> Vec<Range<usize>> {
+    let target = bytes.len().div_ceil(parts.max(1)).max(1);
+    let mut ranges = Vec::new();
+    let mut start = 0;
+
+    while start < bytes.len() {
+        let end = (start + target).min(bytes.len());
+        let end = match bytes[end - 1..].iter().position(|byte| *byte == b'\n') {
+            Some(index) => end + index,
+            None => bytes.len(),
+        };
+
+        ranges.push(start..end);
+        start = end;
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_line_breaks() {
+        let bytes = b"aaaa\nbb\ncccccc\nd\n";
+        let ranges = split_lines(bytes, 3);
+
+        assert_eq!(ranges, vec![0..8, 8..15, 15..17]);
+        assert_eq!(split_lines(b"abc", 4), vec![0..3]);
+        assert!(split_lines(b"", 4).is_empty());
+    }
+}
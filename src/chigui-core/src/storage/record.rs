@@ -0,0 +1,97 @@
+use crate::block::Block;
+use crate::error::{ChiguiError, Result};
+
+/// Start of a `block.db` line carrying a checksum, followed by 8 hex digits.
+const CHECKSUM_PREFIX: &[u8] = b"{\"checksum\":\"";
+
+/// Serialize a block as a `block.db` line, without its line break.
+///
+/// The line is the block JSON with a leading `checksum` field, the CRC32 of the block JSON
+/// itself, e.g. `{"checksum":"1c291ca3","header":...}`.
+pub(crate) fn encode(block: &Block) -> Result<String> {
+    let json =
+        serde_json::to_string(block).map_err(|source| ChiguiError::SerializeError { source })?;
+    let checksum = crc32fast::hash(json.as_bytes());
+
+    Ok(format!(
+        "{{\"checksum\":\"{:08x}\",{}",
+        checksum,
+        &json[1..]
+    ))
+}
+
+/// Parse a `block.db` line, checking its checksum first so that disk corruption isn't mistaken
+/// for an invalid block. Lines written before checksums were introduced are parsed as is.
+pub(crate) fn decode(bytes: &[u8], line: usize) -> Result<Block> {
+    let parse_error = |source| ChiguiError::ParseError { line, source };
+    let Some(rest) = bytes.strip_prefix(CHECKSUM_PREFIX) else {
+        return serde_json::from_slice(bytes).map_err(parse_error);
+    };
+    let expected = rest
+        .get(..8)
+        .and_then(|hex| std::str::from_utf8(hex).ok())
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+    let json = match rest.get(8..10) {
+        Some(b"\",") => [b"{", &rest[10..]].concat(),
+        _ => Vec::new(),
+    };
+
+    if expected.is_none() || expected != Some(crc32fast::hash(&json)) {
+        return Err(ChiguiError::ChecksumMismatch { line });
+    }
+
+    serde_json::from_slice(&json).map_err(parse_error)
+}
+
+/// Shift the line number of an error returned by [`decode`] by `offset` lines.
+#[cfg(feature = "mmap")]
+pub(crate) fn offset_line(err: ChiguiError, offset: usize) -> ChiguiError {
+    match err {
+        ChiguiError::ParseError { line, source } => ChiguiError::ParseError {
+            line: line + offset,
+            source,
+        },
+        ChiguiError::ChecksumMismatch { line } => ChiguiError::ChecksumMismatch {
+            line: line + offset,
+        },
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hash;
+    use crate::block::BlockHeader;
+
+    #[test]
+    fn detects_corrupted_lines() -> Result<()> {
+        let block = Block::new(
+            BlockHeader {
+                number: 1,
+                parent_hash: Hash::default(),
+                tx_root: Hash::default(),
+                time: 0,
+                nonce: 0,
+            },
+            Vec::new(),
+        );
+        let line = encode(&block)?;
+
+        assert!(line.starts_with("{\"checksum\":\""));
+        assert_eq!(decode(line.as_bytes(), 1)?, block);
+
+        let legacy = serde_json::to_string(&block).unwrap();
+
+        assert_eq!(decode(legacy.as_bytes(), 1)?, block);
+
+        let corrupted = line.replace("\"time\":0", "\"time\":9");
+
+        assert!(matches!(
+            decode(corrupted.as_bytes(), 3),
+            Err(ChiguiError::ChecksumMismatch { line: 3 })
+        ));
+
+        Ok(())
+    }
+}
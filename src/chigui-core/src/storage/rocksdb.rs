@@ -0,0 +1,267 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use ::rocksdb::{
+    ColumnFamily, ColumnFamilyDescriptor, DB, Direction, IteratorMode, Options, WriteBatch,
+    WriteOptions,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::Hash;
+use crate::block::Block;
+use crate::error::{ChiguiError, Result};
+use crate::snapshot::Snapshot;
+use crate::state::{Genesis, State};
+use crate::storage::Storage;
+
+/// Blocks by number.
+const BLOCKS: &str = "blocks";
+/// Number of the block holding each transaction, by transaction hash.
+const TXS: &str = "txs";
+/// Block numbers by block hash.
+const INDEXES: &str = "indexes";
+/// Snapshots by height.
+const SNAPSHOTS: &str = "snapshots";
+
+/// A [`Storage`] keeping blocks and snapshots in a RocksDB database under `<dbdir>/rocksdb`,
+/// for chains whose history outgrows a flat file, while the genesis is still read from
+/// `genesis.json`.
+///
+/// Blocks, transaction and block hash indexes and snapshots each live in their own column
+/// family, and every write goes through a synced [`WriteBatch`].
+pub struct RocksStorage {
+    dbdir: PathBuf,
+    db: DB,
+}
+
+impl fmt::Debug for RocksStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RocksStorage")
+            .field("dbdir", &self.dbdir)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RocksStorage {
+    pub fn open<P: AsRef<Path>>(dbdir: P) -> Result<Self> {
+        let dbdir = dbdir.as_ref().to_path_buf();
+        let mut options = Options::default();
+
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let families = [BLOCKS, TXS, INDEXES, SNAPSHOTS]
+            .into_iter()
+            .map(|name| ColumnFamilyDescriptor::new(name, Options::default()));
+        let db = DB::open_cf_descriptors(&options, dbdir.join("rocksdb"), families)
+            .map_err(|source| ChiguiError::RocksDb { source })?;
+
+        Ok(Self { dbdir, db })
+    }
+
+    /// Number of the block holding the transaction with the given hash.
+    pub fn tx_block_number(&self, hash: &Hash) -> Result<Option<u64>> {
+        self.get_number(TXS, hash)
+    }
+
+    /// Number of the block with the given hash.
+    pub fn block_number(&self, hash: &Hash) -> Result<Option<u64>> {
+        self.get_number(INDEXES, hash)
+    }
+
+    fn get_number(&self, family: &str, hash: &Hash) -> Result<Option<u64>> {
+        let value = self
+            .db
+            .get_cf(self.family(family), hash.as_bytes())
+            .map_err(|source| ChiguiError::RocksDb { source })?;
+
+        Ok(value.map(|value| Self::height(&value)))
+    }
+
+    fn family(&self, name: &str) -> &ColumnFamily {
+        self.db
+            .cf_handle(name)
+            .expect("column families are created on open")
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<()> {
+        let mut options = WriteOptions::default();
+
+        options.set_sync(true);
+
+        self.db
+            .write_opt(batch, &options)
+            .map_err(|source| ChiguiError::RocksDb { source })
+    }
+
+    /// Add a block along with its index entries to the batch.
+    fn put_block(&self, batch: &mut WriteBatch, block: &Block) -> Result<()> {
+        let number = block.header.number.to_be_bytes();
+
+        batch.put_cf(self.family(BLOCKS), number, Self::encode(block)?);
+        batch.put_cf(self.family(INDEXES), block.hash().as_bytes(), number);
+
+        for tx in &block.txs {
+            batch.put_cf(self.family(TXS), tx.hash().as_bytes(), number);
+        }
+
+        Ok(())
+    }
+
+    fn height(key: &[u8]) -> u64 {
+        let mut height = [0; 8];
+        height.copy_from_slice(key);
+
+        u64::from_be_bytes(height)
+    }
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        serde_json::to_vec(value).map_err(|source| ChiguiError::SerializeError { source })
+    }
+
+    fn decode<T: DeserializeOwned>(key: &[u8], value: &[u8]) -> Result<T> {
+        serde_json::from_slice(value).map_err(|source| ChiguiError::RecordParseError {
+            height: Self::height(key),
+            source,
+        })
+    }
+}
+
+impl Storage for RocksStorage {
+    fn load_genesis(&self) -> Result<Genesis> {
+        let path = self.dbdir.join("genesis.json");
+        let genesis_json =
+            std::fs::read_to_string(&path).map_err(|source| ChiguiError::Io { path, source })?;
+
+        State::parse_genesis(&genesis_json)
+    }
+
+    fn iter_blocks(&self) -> Result<Box<dyn Iterator<Item = Result<Block>> + '_>> {
+        let entries = self
+            .db
+            .iterator_cf(self.family(BLOCKS), IteratorMode::Start);
+
+        Ok(Box::new(entries.map(|entry| {
+            let (key, value) = entry.map_err(|source| ChiguiError::RocksDb { source })?;
+
+            Self::decode(&key, &value)
+        })))
+    }
+
+    fn append_block(&mut self, block: &Block) -> Result<()> {
+        let mut batch = WriteBatch::default();
+
+        self.put_block(&mut batch, block)?;
+        self.write(batch)
+    }
+
+    fn replace_blocks(&mut self, blocks: &[Block]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+
+        for block in self.iter_blocks()? {
+            let block = block?;
+
+            batch.delete_cf(self.family(BLOCKS), block.header.number.to_be_bytes());
+            batch.delete_cf(self.family(INDEXES), block.hash().as_bytes());
+
+            for tx in &block.txs {
+                batch.delete_cf(self.family(TXS), tx.hash().as_bytes());
+            }
+        }
+
+        for block in blocks {
+            self.put_block(&mut batch, block)?;
+        }
+
+        self.write(batch)
+    }
+
+    fn write_snapshot(&mut self, snapshot: &Snapshot) -> Result<()> {
+        let mut batch = WriteBatch::default();
+
+        batch.put_cf(
+            self.family(SNAPSHOTS),
+            snapshot.height.to_be_bytes(),
+            Self::encode(snapshot)?,
+        );
+
+        self.write(batch)
+    }
+
+    fn latest_snapshot(&self, max_height: u64) -> Result<Option<Snapshot>> {
+        let key = max_height.to_be_bytes();
+        let mode = IteratorMode::From(&key, Direction::Reverse);
+
+        self.db
+            .iterator_cf(self.family(SNAPSHOTS), mode)
+            .next()
+            .map(|entry| {
+                let (key, value) = entry.map_err(|source| ChiguiError::RocksDb { source })?;
+
+                Self::decode(&key, &value)
+            })
+            .transpose()
+    }
+
+    fn retain_snapshot(&mut self, height: u64) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        let family = self.family(SNAPSHOTS);
+
+        for entry in self.db.iterator_cf(family, IteratorMode::Start) {
+            let (key, _) = entry.map_err(|source| ChiguiError::RocksDb { source })?;
+
+            if Self::height(&key) != height {
+                batch.delete_cf(family, key);
+            }
+        }
+
+        self.write(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Account, Tx};
+
+    #[test]
+    fn persists_blocks_and_indexes() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10},"permissive":true}"#,
+        )
+        .unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::with_storage(RocksStorage::open(dbdir.path())?)?;
+
+        for value in [1, 2, 3] {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+        }
+
+        let first_tx = state.tx_at(0).unwrap().hash();
+        let latest = state.latest_block().unwrap().hash();
+
+        state.prune(1)?;
+        drop(state);
+
+        let storage = RocksStorage::open(dbdir.path())?;
+
+        assert_eq!(storage.block_number(&latest)?, Some(3));
+        assert_eq!(storage.tx_block_number(&first_tx)?, None);
+
+        let reopened = State::with_storage(storage)?;
+
+        assert_eq!(reopened.height(), 3);
+        assert_eq!(reopened.blocks().len(), 1);
+        assert_eq!(reopened.get_balance(&alice), Some(16));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ChiguiError, Result};
+
+/// A record of the [`Wal`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum WalRecord {
+    /// Block `number` is about to be appended to the database file, `offset` bytes long so far.
+    Begin { number: u64, offset: u64 },
+    /// Block `number` is fully written to the database file.
+    Commit { number: u64 },
+}
+
+/// Write-ahead log guarding the appends to a JSONL database file.
+///
+/// Every append is recorded in the log before the database file is touched, and marked as
+/// committed once it's synced to disk. If the process dies in between, [`Wal::recover`] rolls the
+/// database file back to where it was before the append. Appends are sequential, so the log only
+/// ever holds the latest one.
+#[derive(Clone, Debug)]
+pub(crate) struct Wal {
+    path: PathBuf,
+}
+
+impl Wal {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Record that block `number` is about to be appended to `db_path`.
+    pub(crate) fn begin(&self, number: u64, db_path: &Path) -> Result<()> {
+        let offset = match std::fs::metadata(db_path) {
+            Ok(metadata) => metadata.len(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(source) => {
+                return Err(ChiguiError::Io {
+                    path: db_path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+        let file = File::create(&self.path).map_err(|source| self.io_error(source))?;
+
+        self.write(file, &WalRecord::Begin { number, offset })
+    }
+
+    /// Mark the append of block `number` as complete.
+    pub(crate) fn commit(&self, number: u64) -> Result<()> {
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(|source| self.io_error(source))?;
+
+        self.write(file, &WalRecord::Commit { number })
+    }
+
+    /// Roll `db_path` back to its length before the last append if it wasn't committed, returning
+    /// whether it was, then clear the log.
+    pub(crate) fn recover(&self, db_path: &Path) -> Result<bool> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(source) => return Err(self.io_error(source)),
+        };
+        // A torn record means the crash happened while writing it, before the matching write to
+        // the database file, so it can be ignored.
+        let records = content
+            .lines()
+            .map_while(|line| serde_json::from_str::<WalRecord>(line).ok())
+            .collect::<Vec<WalRecord>>();
+        let rollback = match records.as_slice() {
+            [WalRecord::Begin { offset, .. }] => Some(*offset),
+            _ => None,
+        };
+
+        if let Some(offset) = rollback {
+            let io_error = |source| ChiguiError::Io {
+                path: db_path.to_path_buf(),
+                source,
+            };
+            let file = OpenOptions::new()
+                .write(true)
+                .open(db_path)
+                .map_err(io_error)?;
+
+            if file.metadata().map_err(io_error)?.len() > offset {
+                file.set_len(offset)
+                    .and_then(|()| file.sync_all())
+                    .map_err(io_error)?;
+            }
+        }
+
+        self.clear()?;
+
+        Ok(rollback.is_some())
+    }
+
+    /// Forget the last append, once the database file has been rewritten as a whole.
+    pub(crate) fn clear(&self) -> Result<()> {
+        File::create(&self.path)
+            .and_then(|file| file.sync_all())
+            .map_err(|source| self.io_error(source))
+    }
+
+    fn write(&self, mut file: File, record: &WalRecord) -> Result<()> {
+        let line = serde_json::to_string(record)
+            .map_err(|source| ChiguiError::SerializeError { source })?;
+
+        file.write_all(format!("{}\n", line).as_bytes())
+            .and_then(|()| file.sync_data())
+            .map_err(|source| self.io_error(source))
+    }
+
+    fn io_error(&self, source: std::io::Error) -> ChiguiError {
+        ChiguiError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_back_uncommitted_appends() -> Result<()> {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db_path = dir.path().join("block.db");
+        let wal = Wal::new(dir.path().join("block.wal"));
+
+        std::fs::write(&db_path, "1\n").unwrap();
+        wal.begin(2, &db_path)?;
+        std::fs::write(&db_path, "1\n2\n").unwrap();
+        wal.commit(2)?;
+
+        assert!(!wal.recover(&db_path)?);
+        assert_eq!(std::fs::read_to_string(&db_path).unwrap(), "1\n2\n");
+
+        wal.begin(3, &db_path)?;
+        std::fs::write(&db_path, "1\n2\n3\n").unwrap();
+
+        assert!(wal.recover(&db_path)?);
+        assert_eq!(std::fs::read_to_string(&db_path).unwrap(), "1\n2\n");
+        assert!(!wal.recover(&db_path)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::Block;
+use crate::error::Result;
+use crate::fork::Reorg;
+use crate::hash::Hash;
+use crate::state::State;
+
+/// Most blocks sent in a single [`Message::Blocks`] reply.
+pub const MAX_BLOCKS_PER_MESSAGE: usize = 128;
+
+/// A message exchanged between two peers while synchronizing their chains.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Message {
+    /// Announce the local chain height, sent on connect and whenever the chain grows.
+    Hello {
+        height: u64,
+        /// State root of the sender at `height`, see [`State::state_root`].
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        state_root: Option<Hash>,
+        /// Address the sender accepts peer connections on, if it does.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        listen: Option<SocketAddr>,
+    },
+    /// Ask for the blocks starting at the given number.
+    GetBlocks { from: u64 },
+    /// Reply to [`Message::GetBlocks`], empty when the peer has nothing past `from`.
+    Blocks { blocks: Vec<Block> },
+    /// Ask for the peer's known peers.
+    GetPeers,
+    /// Reply to [`Message::GetPeers`].
+    Peers { peers: Vec<SocketAddr> },
+}
+
+/// Where a [`Sync`] session stands with respect to its peer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Status {
+    /// Waiting for the peer's [`Message::Hello`].
+    #[default]
+    Handshake,
+    /// Fetching blocks until the local chain reaches `target`.
+    Downloading { target: u64 },
+    /// The local chain is at least as long as the peer's.
+    Synced,
+    /// Both chains have the same height but different state roots.
+    Diverged,
+}
+
+/// Transport-agnostic state machine synchronizing a [`State`] with a single peer.
+///
+/// Feed every message received from the peer to [`Sync::handle`] and send back whatever it returns.
+/// Blocks are validated by [`State::add_block`] before being applied, so a misbehaving peer can't
+/// corrupt the local chain. When the peer's chain forks off the local one, the blocks of its branch
+/// are collected up to its tip, then switched to with [`State::reorg`] if it carries more work.
+#[derive(Debug, Default)]
+pub struct Sync {
+    status: Status,
+    /// Blocks of the peer's branch downloaded so far, when it forks off the local chain.
+    branch: Vec<Block>,
+    /// The last reorg applied, until taken.
+    reorg: Option<Reorg>,
+}
+
+impl Sync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Take the reorg applied while handling the last messages, if any, e.g. to queue its
+    /// reorged-out transactions again.
+    pub fn take_reorg(&mut self) -> Option<Reorg> {
+        self.reorg.take()
+    }
+
+    /// The message opening a session, also used to announce new blocks.
+    pub fn hello(state: &State) -> Message {
+        Message::Hello {
+            height: state.height(),
+            state_root: Some(state.state_root()),
+            listen: None,
+        }
+    }
+
+    /// Process a message from the peer and return the replies to send it.
+    pub fn handle(&mut self, state: &mut State, message: Message) -> Result<Vec<Message>> {
+        match message {
+            Message::Hello {
+                height, state_root, ..
+            } => {
+                // A batch is already in flight; its reply will trigger the next request.
+                if let Status::Downloading { target } = &mut self.status {
+                    *target = (*target).max(height);
+
+                    return Ok(Vec::new());
+                }
+
+                if height == state.height()
+                    && state_root.is_some_and(|root| root != state.state_root())
+                {
+                    self.status = Status::Diverged;
+
+                    return Ok(Vec::new());
+                }
+
+                self.catch_up(state, height)
+                    .map(|next| next.into_iter().collect())
+            }
+            Message::GetBlocks { from } => {
+                let blocks = state
+                    .blocks()
+                    .iter()
+                    .skip_while(|block| block.header.number < from)
+                    .take(MAX_BLOCKS_PER_MESSAGE)
+                    .cloned()
+                    .collect();
+
+                Ok(vec![Message::Blocks { blocks }])
+            }
+            Message::Blocks { blocks } => {
+                let Status::Downloading { target } = self.status else {
+                    return Ok(Vec::new());
+                };
+
+                if blocks.is_empty() {
+                    self.switch_branch(state)?;
+                    self.status = Status::Synced;
+
+                    return Ok(Vec::new());
+                }
+
+                let first = blocks[0].header.number;
+
+                for block in blocks {
+                    let number = block.header.number;
+
+                    if !self.branch.is_empty() {
+                        self.branch.push(block);
+                        continue;
+                    }
+
+                    // Skip blocks that arrived through another peer in the meantime.
+                    if state.hash_at(number) == Some(block.hash()) {
+                        continue;
+                    }
+
+                    let parent = number
+                        .checked_sub(1)
+                        .and_then(|parent| state.hash_at(parent));
+
+                    if parent == Some(block.header.parent_hash) {
+                        match number > state.height() {
+                            true => state.add_block(block)?,
+                            false => self.branch.push(block),
+                        }
+                    } else if number <= state.height() + 1 && number == first && number > 1 {
+                        // The peer's chain forks off further back, look for the fork point.
+                        let from = number.saturating_sub(MAX_BLOCKS_PER_MESSAGE as u64).max(1);
+
+                        return Ok(vec![Message::GetBlocks { from }]);
+                    } else {
+                        // Forged, or forking off blocks pruned here: let `add_block` say why.
+                        state.add_block(block)?;
+                    }
+                }
+
+                self.catch_up(state, target)
+                    .map(|next| next.into_iter().collect())
+            }
+            // Peer discovery is up to the transport, which knows the addresses involved.
+            Message::GetPeers | Message::Peers { .. } => Ok(Vec::new()),
+        }
+    }
+
+    /// Request the next batch of blocks if the peer's chain is longer than ours, or than the branch
+    /// downloaded so far.
+    fn catch_up(&mut self, state: &mut State, target: u64) -> Result<Option<Message>> {
+        let height = self
+            .branch
+            .last()
+            .map_or(state.height(), |block| block.header.number);
+
+        if height < target {
+            self.status = Status::Downloading { target };
+
+            Ok(Some(Message::GetBlocks { from: height + 1 }))
+        } else {
+            self.switch_branch(state)?;
+            self.status = Status::Synced;
+
+            Ok(None)
+        }
+    }
+
+    /// Reorg onto the peer's branch once it's fully downloaded.
+    fn switch_branch(&mut self, state: &mut State) -> Result<()> {
+        if !self.branch.is_empty() {
+            let branch = std::mem::take(&mut self.branch);
+
+            self.reorg = Some(state.reorg(branch)?);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ChiguiError;
+    use crate::{Account, Tx};
+
+    fn state() -> Result<State> {
+        let genesis = State::parse_genesis(
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )?;
+
+        State::in_memory(genesis)
+    }
+
+    /// Deliver messages back and forth until neither side has anything left to say.
+    fn exchange(a: (&mut Sync, &mut State), b: (&mut Sync, &mut State)) -> Result<()> {
+        let mut to_b = vec![Sync::hello(a.1)];
+        let mut to_a = vec![Sync::hello(b.1)];
+
+        while !to_a.is_empty() || !to_b.is_empty() {
+            let mut replies_to_b = Vec::new();
+            for message in to_a.drain(..) {
+                replies_to_b.extend(a.0.handle(a.1, message)?);
+            }
+
+            let mut replies_to_a = Vec::new();
+            for message in to_b.drain(..) {
+                replies_to_a.extend(b.0.handle(b.1, message)?);
+            }
+
+            to_a = replies_to_a;
+            to_b = replies_to_b;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn downloads_missing_blocks() -> Result<()> {
+        let mut ahead = state()?;
+        let mut behind = state()?;
+
+        for _ in 0..MAX_BLOCKS_PER_MESSAGE + 2 {
+            ahead.add_tx(Tx::Generate {
+                to: Account::new("alice")?,
+                value: 1,
+                denom: None,
+            })?;
+        }
+
+        let (mut a, mut b) = (Sync::new(), Sync::new());
+        exchange((&mut a, &mut ahead), (&mut b, &mut behind))?;
+
+        assert_eq!(a.status(), Status::Synced);
+        assert_eq!(b.status(), Status::Synced);
+        assert_eq!(behind.blocks(), ahead.blocks());
+        assert_eq!(
+            behind.get_balance(&Account::new("alice")?),
+            Some(MAX_BLOCKS_PER_MESSAGE as u64 + 2)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn detects_diverged_state() -> Result<()> {
+        let mut local = state()?;
+        let mut remote = state()?;
+
+        remote.add_tx(Tx::Generate {
+            to: Account::new("alice")?,
+            value: 1,
+            denom: None,
+        })?;
+        local.add_tx(Tx::Generate {
+            to: Account::new("alice")?,
+            value: 2,
+            denom: None,
+        })?;
+
+        let mut sync = Sync::new();
+        assert!(sync.handle(&mut local, Sync::hello(&remote))?.is_empty());
+        assert_eq!(sync.status(), Status::Diverged);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reorgs_onto_longer_fork() -> Result<()> {
+        let mut local = state()?;
+        let mut remote = state()?;
+        let generate = |value| Tx::Generate {
+            to: Account::new("alice").unwrap(),
+            value,
+            denom: None,
+        };
+
+        local.add_tx(generate(1))?;
+        remote.add_block(local.blocks()[0].clone())?;
+        local.add_tx(generate(2))?;
+
+        for _ in 0..MAX_BLOCKS_PER_MESSAGE + 1 {
+            remote.add_tx(generate(3))?;
+        }
+
+        let (mut a, mut b) = (Sync::new(), Sync::new());
+        exchange((&mut a, &mut local), (&mut b, &mut remote))?;
+
+        assert_eq!(a.status(), Status::Synced);
+        assert_eq!(local.blocks(), remote.blocks());
+
+        let reorg = a.take_reorg().expect("reorged");
+        assert_eq!(reorg.fork_height, 1);
+        assert_eq!(reorg.reorged_out.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_blocks() -> Result<()> {
+        let mut local = state()?;
+        let mut forged = state()?.next_block(Vec::new());
+        forged.header.number = 2;
+
+        let mut sync = Sync::new();
+        assert_eq!(
+            sync.handle(
+                &mut local,
+                Message::Hello {
+                    height: 2,
+                    state_root: None,
+                    listen: None
+                }
+            )?,
+            vec![Message::GetBlocks { from: 1 }]
+        );
+
+        assert!(matches!(
+            sync.handle(
+                &mut local,
+                Message::Blocks {
+                    blocks: vec![forged]
+                }
+            ),
+            Err(ChiguiError::InvalidBlockNumber {
+                expected: 1,
+                got: 2
+            })
+        ));
+        assert_eq!(local.height(), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Account;
+
+/// When coins sent by [`Tx::TransferLocked`](crate::Tx::TransferLocked) become spendable.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Unlock {
+    /// Once the block with this number is applied.
+    Height(u64),
+    /// Once a block with at least this Unix time, in seconds, is applied.
+    Time(u64),
+}
+
+impl Unlock {
+    fn is_due(&self, height: u64, time: u64) -> bool {
+        match self {
+            Unlock::Height(unlock) => height >= *unlock,
+            Unlock::Time(unlock) => time >= *unlock,
+        }
+    }
+}
+
+/// Coins received by `account` that it can't spend yet.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Locked {
+    pub account: Account,
+    pub value: u64,
+    pub unlock: Unlock,
+}
+
+/// Coins locked by [`Tx::TransferLocked`](crate::Tx::TransferLocked), credited to their
+/// recipient once the lock expires.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LockRegistry {
+    locked: Vec<Locked>,
+}
+
+impl LockRegistry {
+    pub fn locked(&self) -> &[Locked] {
+        &self.locked
+    }
+
+    /// Coins received by the account that it can't spend yet.
+    pub fn locked_of(&self, account: &Account) -> u64 {
+        self.locked
+            .iter()
+            .filter(|entry| entry.account == *account)
+            .fold(0, |total, entry| total.saturating_add(entry.value))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locked.is_empty()
+    }
+
+    pub(crate) fn lock(&mut self, account: &Account, value: u64, unlock: Unlock) {
+        self.locked.push(Locked {
+            account: account.clone(),
+            value,
+            unlock,
+        });
+    }
+
+    /// Remove and return the entries unlocked by the block with the given number and time.
+    pub(crate) fn release(&mut self, height: u64, time: u64) -> Vec<Locked> {
+        let (released, locked) = self
+            .locked
+            .drain(..)
+            .partition(|entry| entry.unlock.is_due(height, time));
+
+        self.locked = locked;
+
+        released
+    }
+}
@@ -0,0 +1,401 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Hash;
+use crate::error::{ChiguiError, Result};
+use crate::snapshot::Snapshot;
+use crate::state::State;
+use crate::storage::{FileStorage, Storage, record};
+
+/// A record of `block.db` breaking an invariant of the chain.
+#[derive(Debug)]
+pub struct Offense {
+    /// Line of the record in `block.db`, starting at 1.
+    pub line: usize,
+    /// Number of the offending block, unless its line is corrupted or couldn't be parsed.
+    ///
+    /// Each block commits to its predecessor through its parent hash, so a block rewritten along
+    /// with its transaction root surfaces as an [`ChiguiError::InvalidParentHash`] on the next one.
+    pub block: Option<u64>,
+    pub error: ChiguiError,
+}
+
+impl Offense {
+    /// Number and line of the block presumably rewritten, when the parent hash of the offending
+    /// block doesn't match the block on the line before it.
+    ///
+    /// A block on the first line links to the genesis or a snapshot instead, so no other block
+    /// is to blame.
+    pub fn rewritten(&self) -> Option<(u64, usize)> {
+        match self.error {
+            ChiguiError::InvalidParentHash { number, .. } if self.line > 1 => {
+                Some((number - 1, self.line - 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of [`verify`]: how far the chain replayed, and what stopped it, if anything.
+#[derive(Debug)]
+pub struct VerifyReport {
+    /// Height of the last block that replayed successfully.
+    pub height: u64,
+    pub txs: usize,
+    pub state_root: Hash,
+    pub total_supply: u64,
+    pub offense: Option<Offense>,
+}
+
+impl VerifyReport {
+    pub fn is_valid(&self) -> bool {
+        self.offense.is_none()
+    }
+}
+
+/// Outcome of [`repair`].
+#[derive(Debug)]
+pub struct RepairReport {
+    /// Height of the repaired chain.
+    pub height: u64,
+    /// Number of records kept in the rewritten `block.db`.
+    pub kept: usize,
+    /// Number of records dropped from it.
+    pub dropped: usize,
+    /// The dropped records that were invalid: the first one, or every one when forced.
+    pub offenses: Vec<Offense>,
+    /// Where the damaged `block.db` was copied to, unless it needed no repair.
+    pub backup: Option<PathBuf>,
+}
+
+/// Replay every block of a db dir from its genesis, or from the snapshot preceding its first
+/// block on pruned chains, stopping at the first record that can't be parsed or applied.
+///
+/// Replaying checks the whole chain: block numbers and parent hashes, transaction roots, seals and
+/// proof of work, transaction signatures and nonces, balances never going below zero and the
+/// total supply only changing through minting and burning. Unlike [`State::open`], it leaves the
+/// db dir untouched, reporting torn writes instead of recovering them.
+pub fn verify<P: AsRef<Path>>(dbdir: P) -> Result<VerifyReport> {
+    let (state, offenses, _) = replay(dbdir.as_ref(), false)?;
+
+    Ok(VerifyReport {
+        height: state.height(),
+        txs: state.tx_count(),
+        state_root: state.state_root(),
+        total_supply: state.total_supply(),
+        offense: offenses.into_iter().next(),
+    })
+}
+
+/// Rewrite `block.db` with the records that replay, like [`verify`] does, after copying the
+/// damaged file next to it.
+///
+/// Records past the first invalid one are dropped, unless `force` is set, in which case only the
+/// invalid records are skipped and replaying carries on with the next ones.
+pub fn repair<P: AsRef<Path>>(dbdir: P, force: bool) -> Result<RepairReport> {
+    let dbdir = dbdir.as_ref();
+    let (state, offenses, records) = replay(dbdir, force)?;
+    let kept = state.blocks().len();
+    let mut backup = None;
+
+    if kept < records {
+        let block_db_path = dbdir.join("block.db");
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let backup_path = dbdir.join(format!("block.db.{}.bak", time));
+
+        std::fs::copy(&block_db_path, &backup_path).map_err(|source| ChiguiError::Io {
+            path: backup_path.clone(),
+            source,
+        })?;
+        FileStorage::new(dbdir).replace_blocks(state.blocks())?;
+        backup = Some(backup_path);
+    }
+
+    Ok(RepairReport {
+        height: state.height(),
+        kept,
+        dropped: records - kept,
+        offenses,
+        backup,
+    })
+}
+
+/// Replay the records of `block.db`, returning the resulting state, the invalid records and the
+/// number of records. Replaying stops at the first invalid record unless `skip` is set.
+fn replay(dbdir: &Path, skip: bool) -> Result<(State, Vec<Offense>, usize)> {
+    let genesis_path = dbdir.join("genesis.json");
+    let genesis_json =
+        std::fs::read_to_string(&genesis_path).map_err(|source| ChiguiError::Io {
+            path: genesis_path,
+            source,
+        })?;
+    let genesis = State::parse_genesis(&genesis_json)?;
+    let block_db_path = dbdir.join("block.db");
+    let block_db = std::fs::read_to_string(&block_db_path).map_err(|source| ChiguiError::Io {
+        path: block_db_path,
+        source,
+    })?;
+    let records = block_db.lines().count();
+    let first = block_db
+        .lines()
+        .find_map(|line| record::decode(line.as_bytes(), 1).ok())
+        .map_or(1, |block| block.header.number);
+    let snapshot = match first {
+        1 => None,
+        _ => Snapshot::latest(dbdir, first - 1)?,
+    };
+    let mut state = State::from_snapshot(genesis, Vec::new(), snapshot, false)?;
+    let mut offenses = Vec::new();
+
+    for (index, text) in block_db.lines().enumerate() {
+        let line = index + 1;
+        let outcome = record::decode(text.as_bytes(), line)
+            .map_err(|err| (None, err))
+            .and_then(|block| {
+                let number = block.header.number;
+
+                // Skipping records needs the state left as it was by the invalid ones.
+                let applied = match skip {
+                    true => state.commit_block(block).map(|_| ()),
+                    false => state.replay(block),
+                };
+
+                applied.map_err(|err| (Some(number), err))
+            });
+
+        if let Err((block, error)) = outcome {
+            offenses.push(Offense { line, block, error });
+
+            if !skip {
+                break;
+            }
+        }
+    }
+
+    Ok((state, offenses, records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::{Account, Tx};
+
+    #[test]
+    fn reports_first_offending_block() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let block_db_path = dbdir.path().join("block.db");
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(&block_db_path, "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::open(dbdir.path())?;
+
+        for value in 1..=3 {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+        }
+
+        let report = verify(dbdir.path())?;
+
+        assert!(report.is_valid());
+        assert_eq!(report.height, 3);
+        assert_eq!(report.total_supply, 16);
+        assert_eq!(report.state_root, state.state_root());
+
+        // Inflate the value generated by the second block.
+        let block_db = std::fs::read_to_string(&block_db_path).unwrap();
+        let lines = block_db.lines().map(str::to_string).collect::<Vec<_>>();
+        let mut tampered = lines.clone();
+
+        tampered[1] = tampered[1].replace(r#""value":2"#, r#""value":200"#);
+        std::fs::write(&block_db_path, tampered.join("\n")).unwrap();
+
+        let report = verify(dbdir.path())?;
+        let offense = report.offense.expect("tampered block");
+
+        assert_eq!(report.height, 1);
+        assert_eq!((offense.line, offense.block), (2, None));
+        assert!(matches!(
+            offense.error,
+            ChiguiError::ChecksumMismatch { line: 2 }
+        ));
+
+        // Without its checksum, the block is checked against its transaction root instead.
+        let block = serde_json::from_str::<Block>(&tampered[1]).unwrap();
+
+        tampered[1] = serde_json::to_string(&block).unwrap();
+        std::fs::write(&block_db_path, tampered.join("\n")).unwrap();
+
+        let offense = verify(dbdir.path())?.offense.expect("tampered block");
+
+        assert_eq!((offense.line, offense.block), (2, Some(2)));
+        assert!(matches!(
+            offense.error,
+            ChiguiError::InvalidTxRoot { number: 2 }
+        ));
+
+        // Rewrite the first block consistently, transaction root included.
+        let mut rewritten = serde_json::from_str::<Block>(&lines[0]).unwrap();
+
+        rewritten.txs[0].tx = Tx::Generate {
+            to: alice.clone(),
+            value: 100,
+            denom: None,
+        };
+        rewritten.header.tx_root = rewritten.tx_root();
+        std::fs::write(
+            &block_db_path,
+            [serde_json::to_string(&rewritten).unwrap(), lines[1].clone()].join("\n"),
+        )
+        .unwrap();
+
+        let offense = verify(dbdir.path())?.offense.expect("rewritten block");
+
+        assert_eq!((offense.line, offense.block), (2, Some(2)));
+        assert!(matches!(
+            offense.error,
+            ChiguiError::InvalidParentHash { number: 2, .. }
+        ));
+        assert_eq!(offense.rewritten(), Some((1, 1)));
+
+        std::fs::write(&block_db_path, format!("{}\nnot json", lines[0])).unwrap();
+
+        let offense = verify(dbdir.path())?.offense.expect("unparseable line");
+
+        assert_eq!((offense.line, offense.block), (2, None));
+        assert!(matches!(
+            offense.error,
+            ChiguiError::ParseError { line: 2, .. }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn points_at_blocks_rewritten_mid_chain() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let block_db_path = dbdir.path().join("block.db");
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(&block_db_path, "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::open(dbdir.path())?;
+
+        for value in 1..=5 {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+        }
+
+        let block_db = std::fs::read_to_string(&block_db_path).unwrap();
+        let mut lines = block_db.lines().map(str::to_string).collect::<Vec<_>>();
+        let mut rewritten = record::decode(lines[2].as_bytes(), 3)?;
+
+        // Rewrite the third block consistently, leaving its own parent hash intact.
+        rewritten.txs[0].tx = Tx::Generate {
+            to: alice.clone(),
+            value: 300,
+            denom: None,
+        };
+        rewritten.header.tx_root = rewritten.tx_root();
+        lines[2] = serde_json::to_string(&rewritten).unwrap();
+        std::fs::write(&block_db_path, lines.join("\n")).unwrap();
+
+        let report = verify(dbdir.path())?;
+        let offense = report.offense.expect("rewritten block");
+
+        assert_eq!(report.height, 3);
+        assert_eq!((offense.line, offense.block), (4, Some(4)));
+        assert!(matches!(
+            offense.error,
+            ChiguiError::InvalidParentHash { number: 4, .. }
+        ));
+        assert_eq!(offense.rewritten(), Some((3, 3)));
+
+        // A first block not linking to the genesis has no predecessor to blame.
+        let mut orphan = record::decode(lines[0].as_bytes(), 1)?;
+
+        orphan.header.parent_hash = Hash::new([7; 32]);
+        std::fs::write(&block_db_path, serde_json::to_string(&orphan).unwrap()).unwrap();
+
+        let offense = verify(dbdir.path())?.offense.expect("orphan block");
+
+        assert_eq!((offense.line, offense.block), (1, Some(1)));
+        assert!(matches!(
+            offense.error,
+            ChiguiError::InvalidParentHash { number: 1, .. }
+        ));
+        assert_eq!(offense.rewritten(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn repairs_around_bad_records() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+        let block_db_path = dbdir.path().join("block.db");
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":10},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(&block_db_path, "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::open(dbdir.path())?;
+
+        for value in 1..=3 {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+        }
+
+        let block_db = std::fs::read_to_string(&block_db_path).unwrap();
+        let mut lines = block_db.lines().collect::<Vec<_>>();
+
+        lines.insert(1, "garbage");
+        std::fs::write(&block_db_path, lines.join("\n")).unwrap();
+
+        let report = repair(dbdir.path(), true)?;
+
+        assert_eq!((report.height, report.kept, report.dropped), (3, 3, 1));
+        assert_eq!(report.offenses[0].line, 2);
+        assert_eq!(
+            std::fs::read_to_string(report.backup.unwrap()).unwrap(),
+            lines.join("\n")
+        );
+        assert_eq!(State::open(dbdir.path())?.height(), 3);
+        assert!(repair(dbdir.path(), false)?.backup.is_none());
+
+        std::fs::write(&block_db_path, lines.join("\n")).unwrap();
+
+        let report = repair(dbdir.path(), false)?;
+
+        assert_eq!((report.height, report.kept, report.dropped), (1, 1, 3));
+        assert_eq!(State::open(dbdir.path())?.height(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Account;
+
+/// Coins of an account that only become spendable over a number of blocks.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct VestingSchedule {
+    pub value: u64,
+    /// Block the schedule starts at.
+    #[serde(default)]
+    pub start: u64,
+    /// Blocks after `start` before anything vests. Equal to `duration` for a pure cliff.
+    #[serde(default)]
+    pub cliff: u64,
+    /// Blocks after `start` by which every coin vested, linearly since `start`.
+    pub duration: u64,
+}
+
+impl VestingSchedule {
+    /// Coins vested once the block with the given number is reached.
+    pub fn vested_at(&self, height: u64) -> u64 {
+        let elapsed = height.saturating_sub(self.start);
+
+        if elapsed >= self.duration {
+            self.value
+        } else if elapsed < self.cliff {
+            0
+        } else {
+            (u128::from(self.value) * u128::from(elapsed) / u128::from(self.duration)) as u64
+        }
+    }
+
+    pub fn unvested_at(&self, height: u64) -> u64 {
+        self.value - self.vested_at(height)
+    }
+}
+
+/// Vesting schedules of every account, from the genesis and `TransferVesting` transactions.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VestingRegistry {
+    schedules: BTreeMap<Account, Vec<VestingSchedule>>,
+}
+
+impl VestingRegistry {
+    pub(crate) fn from_genesis(schedules: &HashMap<Account, VestingSchedule>) -> Self {
+        Self {
+            schedules: schedules
+                .iter()
+                .map(|(account, schedule)| (account.clone(), vec![*schedule]))
+                .collect(),
+        }
+    }
+
+    pub fn schedules_of(&self, account: &Account) -> &[VestingSchedule] {
+        self.schedules.get(account).map_or(&[], Vec::as_slice)
+    }
+
+    /// Coins of the account still vesting at the given block.
+    pub fn unvested_of(&self, account: &Account, height: u64) -> u64 {
+        self.schedules_of(account)
+            .iter()
+            .fold(0, |total, schedule| {
+                total.saturating_add(schedule.unvested_at(height))
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schedules.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, account: &Account, schedule: VestingSchedule) {
+        self.schedules
+            .entry(account.clone())
+            .or_default()
+            .push(schedule);
+    }
+
+    /// Drop the schedules fully vested at the given block.
+    pub(crate) fn prune(&mut self, height: u64) {
+        self.schedules.retain(|_, schedules| {
+            schedules.retain(|schedule| schedule.unvested_at(height) > 0);
+
+            !schedules.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vests_linearly_after_cliff() {
+        let schedule = VestingSchedule {
+            value: 100,
+            start: 10,
+            cliff: 5,
+            duration: 20,
+        };
+
+        assert_eq!(schedule.vested_at(0), 0);
+        assert_eq!(schedule.vested_at(14), 0);
+        assert_eq!(schedule.vested_at(15), 25);
+        assert_eq!(schedule.vested_at(20), 50);
+        assert_eq!(schedule.vested_at(30), 100);
+        assert_eq!(schedule.unvested_at(25), 25);
+    }
+}
@@ -0,0 +1,75 @@
+use std::ops::Deref;
+
+use crate::state::State;
+
+/// Read-only view of a [`State`] as of a past height, as returned by [`State::at`].
+///
+/// It derefs to a [`State`] detached from the storage and hooks of the live one, so queries work as
+/// usual while nothing can be applied to it.
+#[derive(Debug)]
+pub struct StateView {
+    state: State,
+}
+
+impl StateView {
+    pub(crate) fn new(state: State) -> Self {
+        Self { state }
+    }
+}
+
+impl Deref for StateView {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        &self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{ChiguiError, Result};
+    use crate::state::State;
+    use crate::{Account, Tx};
+
+    #[test]
+    fn views_past_heights() -> Result<()> {
+        let dbdir = tempfile::TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let alice = Account::new("alice")?;
+        let mut state = State::open(dbdir.path())?;
+
+        for value in 1..=4 {
+            state.add_tx(Tx::Generate {
+                to: alice.clone(),
+                value,
+                denom: None,
+            })?;
+
+            if value == 2 {
+                state.write_snapshot()?;
+            }
+        }
+
+        let view = state.at(3)?;
+
+        assert_eq!(view.height(), 3);
+        assert_eq!(view.get_balance(&alice), Some(6));
+        assert_eq!(view.blocks(), &state.blocks()[..3]);
+        assert_eq!(state.at(0)?.get_balance(&alice), Some(0));
+        assert_eq!(state.at(4)?.state_root(), state.state_root());
+        assert!(matches!(
+            state.at(5),
+            Err(ChiguiError::HeightNotFound { height: 5 })
+        ));
+        assert_eq!(state.height(), 4);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt::{self, Debug, Formatter};
+use std::net::SocketAddr;
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State as AxumState;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use chigui_core::Account;
+use chigui_core::state::State;
+
+use crate::SharedState;
+use crate::p2p;
+use crate::rpc::{self, Namespace, RpcError, parse_params};
+
+/// JSON-RPC endpoint of the `admin_*` methods, only served to clients presenting the node's
+/// [`AdminToken`] as a bearer token.
+pub fn router() -> Router<SharedState> {
+    Router::new().route("/admin/rpc", post(admin_rpc))
+}
+
+/// Secret admin clients present in an `Authorization: Bearer` header.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdminToken(String);
+
+impl AdminToken {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    /// Compare in constant time, so that response times don't leak the token.
+    fn matches(&self, candidate: &str) -> bool {
+        let (token, candidate) = (self.0.as_bytes(), candidate.as_bytes());
+
+        token.len() == candidate.len()
+            && token
+                .iter()
+                .zip(candidate)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+impl Debug for AdminToken {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("AdminToken(..)")
+    }
+}
+
+/// Parameters of `admin_mint`: the recipient and value, then optionally the asset denomination.
+#[derive(Debug, Deserialize)]
+struct MintParams(Account, u64, #[serde(default)] Option<String>);
+
+async fn admin_rpc(
+    AxumState(node): AxumState<SharedState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(token) = node.admin_token() else {
+        return (StatusCode::NOT_FOUND, "Admin API disabled.").into_response();
+    };
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if !presented.is_some_and(|presented| token.matches(presented)) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Invalid admin token.",
+        )
+            .into_response();
+    }
+
+    rpc::serve(node, body, Namespace::Admin).await
+}
+
+pub(crate) async fn call_method(
+    node: &SharedState,
+    state: &mut State,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    match method {
+        "admin_mint" => {
+            let MintParams(to, value, denom) = parse_params(params)?;
+
+            Ok(json!(node.mint(state, to, value, denom).await?))
+        }
+        "admin_flushMempool" => Ok(json!(node.flush_mempool())),
+        "admin_getPeers" => Ok(json!(node.known_peers())),
+        "admin_addPeer" => {
+            let (peer,) = parse_params::<(SocketAddr,)>(params)?;
+            let added = node.learn_peer(peer)?;
+
+            if added {
+                p2p::dial(node.clone(), peer);
+            }
+
+            Ok(json!(added))
+        }
+        "admin_removePeer" => {
+            let (peer,) = parse_params::<(SocketAddr,)>(params)?;
+
+            Ok(json!(node.forget_peer(&peer)?))
+        }
+        _ => Err(RpcError::method_not_found(method)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::Request as HttpRequest;
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::Node;
+    use crate::rpc::{METHOD_NOT_FOUND, RpcResponse};
+
+    async fn call(
+        app: &Router,
+        path: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> (StatusCode, Value) {
+        let mut request = HttpRequest::post(path).header(header::CONTENT_TYPE, "application/json");
+
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn guards_admin_methods() {
+        let dbdir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let state = State::open(dbdir.path()).unwrap();
+        let mint = r#"{"jsonrpc":"2.0","method":"admin_mint","params":["alice",50],"id":1}"#;
+        let disabled = crate::router(Arc::new(Node::new(state)));
+
+        assert_eq!(
+            call(&disabled, "/admin/rpc", Some("secret"), mint).await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        let state = State::open(dbdir.path()).unwrap();
+        let node = Arc::new(Node::new(state).with_admin_token(AdminToken::new("secret")));
+        let app = crate::router(node.clone());
+
+        assert_eq!(
+            call(&app, "/admin/rpc", None, mint).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            call(&app, "/admin/rpc", Some("secreT"), mint).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let (_, public) = call(&app, "/rpc", None, mint).await;
+
+        assert_eq!(public["error"]["code"], json!(METHOD_NOT_FOUND));
+
+        let (status, responses) = call(
+            &app,
+            "/admin/rpc",
+            Some("secret"),
+            &format!(
+                r#"[
+                    {mint},
+                    {{"jsonrpc":"2.0","method":"admin_addPeer","params":["127.0.0.1:1"],"id":2}},
+                    {{"jsonrpc":"2.0","method":"admin_getPeers","id":3}},
+                    {{"jsonrpc":"2.0","method":"admin_removePeer","params":["127.0.0.1:1"],"id":4}},
+                    {{"jsonrpc":"2.0","method":"admin_flushMempool","id":5}},
+                    {{"jsonrpc":"2.0","method":"chigui_getBalance","params":["alice"],"id":6}}
+                ]"#
+            ),
+        )
+        .await;
+        let responses = serde_json::from_value::<Vec<RpcResponse>>(responses).unwrap();
+        let results = responses
+            .iter()
+            .map(|response| response.result.clone().unwrap_or_default())
+            .collect::<Vec<Value>>();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(results[0]["block"], json!(1));
+        assert_eq!(
+            results[1..5],
+            [json!(true), json!(["127.0.0.1:1"]), json!(true), json!(0)]
+        );
+        assert_eq!(
+            responses[5].error.as_ref().map(|error| error.code),
+            Some(METHOD_NOT_FOUND)
+        );
+        assert_eq!(
+            node.read()
+                .await
+                .get_balance(&Account::new("alice").unwrap()),
+            Some(50)
+        );
+        assert!(node.known_peers().is_empty());
+        assert_eq!(format!("{:?}", AdminToken::new("secret")), "AdminToken(..)");
+    }
+}
@@ -0,0 +1,33 @@
+use axum::Json;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+
+use chigui_core::ChiguiError;
+
+/// A [`ChiguiError`] rendered as a JSON `{ "error": "..." }` body with a matching status code.
+#[derive(Debug)]
+pub struct ApiError(pub ChiguiError);
+
+impl From<ChiguiError> for ApiError {
+    fn from(err: ChiguiError) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            ChiguiError::AccountNotFound { .. } | ChiguiError::UnknownAsset { .. } => {
+                StatusCode::NOT_FOUND
+            }
+            ChiguiError::Io { .. } | ChiguiError::SerializeError { .. } => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            ChiguiError::MiningCancelled => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+
+        (status, Json(json!({ "error": self.0.to_string() }))).into_response()
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use chigui_core::signed::SignedTx;
+use chigui_core::{Account, Hash};
+
+/// A change pushed to subscribers as the node applies transactions.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum Event {
+    NewTx {
+        block: u64,
+        hash: Hash,
+        tx: Box<SignedTx>,
+    },
+    /// A transaction dropped from the chain when switching to a heavier branch, queued again.
+    TxReorged {
+        hash: Hash,
+        tx: Box<SignedTx>,
+    },
+    NewBlock {
+        number: u64,
+        hash: Hash,
+        txs: usize,
+    },
+    BalanceChanged {
+        account: Account,
+        balance: u64,
+    },
+}
@@ -0,0 +1,108 @@
+use axum::extract::State as AxumState;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::SharedState;
+
+/// Most blocks a node may trail the highest peer it heard from while still ready.
+pub const MAX_SYNC_LAG: u64 = 2;
+
+pub fn router() -> Router<SharedState> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    pub height: u64,
+    /// Highest height announced by a peer, `0` until one is heard from.
+    pub peer_height: u64,
+    /// Why the storage can't persist blocks, if it can't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_error: Option<String>,
+}
+
+/// The process is up and serving requests.
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// The state is loaded, its storage writable and the chain within [`MAX_SYNC_LAG`] blocks of its
+/// peers, answered with `503 Service Unavailable` otherwise.
+async fn readyz(AxumState(node): AxumState<SharedState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let state = node.read().await;
+    let height = state.height();
+    let peer_height = node.peer_height();
+    let storage_error = state.check_storage().err().map(|err| err.to_string());
+    let ready = storage_error.is_none() && height + MAX_SYNC_LAG >= peer_height;
+    let status = match ready {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (
+        status,
+        Json(ReadinessResponse {
+            ready,
+            height,
+            peer_height,
+            storage_error,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use chigui_core::state::State;
+
+    use super::*;
+    use crate::Node;
+
+    #[tokio::test]
+    async fn ready_once_caught_up() {
+        let dbdir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":1000}}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let node = Arc::new(Node::new(State::open(dbdir.path()).unwrap()));
+        let app = crate::router(node.clone());
+        let get = |uri| Request::get(uri).body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(get("/healthz")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(get("/readyz")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        node.observe_peer_height(MAX_SYNC_LAG + 1);
+
+        let response = app.oneshot(get("/readyz")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let readiness = serde_json::from_slice::<ReadinessResponse>(&bytes).unwrap();
+
+        assert!(!readiness.ready);
+        assert_eq!(readiness.peer_height, MAX_SYNC_LAG + 1);
+    }
+}
@@ -0,0 +1,711 @@
+pub mod admin;
+pub mod error;
+pub mod events;
+pub mod health;
+pub mod limit;
+pub mod metrics;
+pub mod p2p;
+pub mod rest;
+pub mod rpc;
+pub mod tls;
+pub mod ws;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::DefaultBodyLimit;
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::net::TcpListener;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, broadcast};
+
+use chigui_core::audit::{AuditEntry, AuditLog, Source};
+use chigui_core::block::Block;
+use chigui_core::fork::Reorg;
+use chigui_core::hooks::AppliedTx;
+use chigui_core::mempool::Mempool;
+use chigui_core::miner::{CancelHandle, Miner};
+use chigui_core::peers::KnownPeers;
+use chigui_core::signed::SignedTx;
+use chigui_core::state::State;
+use chigui_core::sync::{Message, Sync};
+use chigui_core::{Account, ChiguiError, Hash, Tx, TxKind};
+use chigui_wallet::Wallet;
+
+use admin::AdminToken;
+pub use error::ApiError;
+pub use events::Event;
+use limit::{Limits, RateLimiter};
+use metrics::Metrics;
+use rest::SubmitResponse;
+use tls::TlsFiles;
+
+/// Number of events buffered for slow subscribers before they start lagging.
+const EVENT_CAPACITY: usize = 1024;
+
+/// Most pending transactions packed into a single block.
+pub const MAX_BLOCK_TXS: usize = 256;
+
+/// How often the block producer checks the mempool for pending transactions.
+const BLOCK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The chain state shared by every request handler, along with the event feed it publishes.
+pub struct Node {
+    state: RwLock<State>,
+    events: broadcast::Sender<Event>,
+    /// Transactions applied to the state, published as [`Event::NewTx`].
+    applied: StdMutex<Receiver<AppliedTx>>,
+    mempool: StdMutex<Mempool>,
+    peers: StdMutex<KnownPeers>,
+    /// Highest chain height announced by a peer.
+    peer_height: AtomicU64,
+    listen: Option<SocketAddr>,
+    validator: Option<Wallet>,
+    audit: Option<AuditLog>,
+    admin_token: Option<AdminToken>,
+    limiter: RateLimiter,
+    metrics: Metrics,
+    /// Fires to abandon the blocks being mined, replaced by a fresh one once it has.
+    mining: StdMutex<CancelHandle>,
+    stopped: AtomicBool,
+}
+
+impl Node {
+    pub fn new(mut state: State) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        let applied = state.subscribe();
+
+        Self {
+            state: RwLock::new(state),
+            events,
+            applied: StdMutex::new(applied),
+            mempool: StdMutex::default(),
+            peers: StdMutex::default(),
+            peer_height: AtomicU64::new(0),
+            listen: None,
+            validator: None,
+            audit: None,
+            admin_token: None,
+            limiter: RateLimiter::new(Limits::default()),
+            metrics: Metrics::default(),
+            mining: StdMutex::default(),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Seal blocks with the given wallet when it's its turn on a proof-of-authority or
+    /// proof-of-stake chain.
+    pub fn with_validator(mut self, wallet: Wallet) -> Self {
+        self.validator = Some(wallet);
+        self
+    }
+
+    /// Take part in peer discovery, advertising `listen` as this node's peer-to-peer address.
+    pub fn with_peers(mut self, peers: KnownPeers, listen: SocketAddr) -> Self {
+        self.peers = StdMutex::new(peers);
+        self.listen = Some(listen);
+        self
+    }
+
+    /// Record rejected submissions and peer blocks to the given audit log.
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Serve the `admin_*` methods to clients presenting the given token.
+    pub fn with_admin_token(mut self, token: AdminToken) -> Self {
+        self.admin_token = Some(token);
+        self
+    }
+
+    /// Apply the given quotas to the requests of every client of the HTTP API.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limiter = RateLimiter::new(limits);
+        self
+    }
+
+    pub fn listen_addr(&self) -> Option<SocketAddr> {
+        self.listen
+    }
+
+    pub fn known_peers(&self) -> Vec<SocketAddr> {
+        let peers = self.peers.lock().expect("known peers lock poisoned");
+
+        peers.iter().copied().collect()
+    }
+
+    /// Record a peer address, returning whether it wasn't known yet.
+    pub fn learn_peer(&self, peer: SocketAddr) -> chigui_core::Result<bool> {
+        if self.listen == Some(peer) {
+            return Ok(false);
+        }
+
+        self.peers
+            .lock()
+            .expect("known peers lock poisoned")
+            .insert(peer)
+    }
+
+    /// Forget a peer address, so that it's no longer dialed nor advertised, returning whether it
+    /// was known. Open connections to it stay up.
+    pub fn forget_peer(&self, peer: &SocketAddr) -> chigui_core::Result<bool> {
+        self.peers
+            .lock()
+            .expect("known peers lock poisoned")
+            .remove(peer)
+    }
+
+    /// Record the chain height announced by a peer.
+    pub fn observe_peer_height(&self, height: u64) {
+        self.peer_height.fetch_max(height, Ordering::Relaxed);
+    }
+
+    /// Highest chain height announced by a peer, `0` until one is heard from.
+    pub fn peer_height(&self) -> u64 {
+        self.peer_height.load(Ordering::Relaxed)
+    }
+
+    /// Share the state with other readers, for queries.
+    pub async fn read(&self) -> RwLockReadGuard<'_, State> {
+        self.state.read().await
+    }
+
+    /// Lock the state for exclusive access, to grow the chain.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, State> {
+        self.state.write().await
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    pub fn admin_token(&self) -> Option<&AdminToken> {
+        self.admin_token.as_ref()
+    }
+
+    pub fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Handle aborting the blocks mined from now on, cancelled from the start once the node is
+    /// shutting down.
+    pub fn mining_handle(&self) -> CancelHandle {
+        let handle = self
+            .mining
+            .lock()
+            .expect("mining handle lock poisoned")
+            .clone();
+
+        if self.is_stopped() {
+            handle.cancel();
+        }
+
+        handle
+    }
+
+    /// Abandon the blocks being mined, e.g. because a peer block moved the tip they build on.
+    pub fn cancel_mining(&self) {
+        let mut mining = self.mining.lock().expect("mining handle lock poisoned");
+
+        std::mem::take(&mut *mining).cancel();
+    }
+
+    /// Stop producing blocks and abandon those being mined.
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.cancel_mining();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// Append a transaction to the chain and publish the resulting events.
+    pub async fn submit(
+        &self,
+        state: &mut State,
+        tx: SignedTx,
+    ) -> chigui_core::Result<SubmitResponse> {
+        let before = state.balances().clone();
+        let hash = tx.hash();
+        let kind = tx.tx.kind();
+
+        state
+            .add_tx_async_cancellable(tx, self.mining_handle())
+            .await
+            .inspect_err(|err| self.reject(kind, hash, err))?;
+        self.publish(state, &before, state.height() - 1);
+
+        Ok(SubmitResponse {
+            hash,
+            block: state.height(),
+        })
+    }
+
+    /// Append a `Generate` transaction, signed with the validator wallet of the node if it has one.
+    pub async fn mint(
+        &self,
+        state: &mut State,
+        to: Account,
+        value: u64,
+        denom: Option<String>,
+    ) -> chigui_core::Result<SubmitResponse> {
+        let tx = Tx::Generate { to, value, denom };
+        let signed = match &self.validator {
+            Some(wallet) => wallet.sign(&tx, state.chain_id()),
+            None => SignedTx::unsigned(tx),
+        };
+
+        self.submit(state, signed).await
+    }
+
+    /// Validate a transaction and queue it for the next block, returning its hash.
+    pub fn queue(&self, state: &State, tx: SignedTx) -> chigui_core::Result<Hash> {
+        let kind = tx.tx.kind();
+        let hash = tx.hash();
+
+        self.mempool
+            .lock()
+            .expect("mempool lock poisoned")
+            .insert(state, tx)
+            .inspect_err(|err| self.reject(kind, hash, err))
+    }
+
+    /// Count and audit a transaction submitted through the API that was rejected.
+    fn reject(&self, kind: TxKind, hash: Hash, err: &chigui_core::ChiguiError) {
+        self.metrics.apply_error(kind);
+        self.audit(AuditEntry::new(Source::Rpc, hash, err));
+    }
+
+    fn audit(&self, entry: AuditEntry) {
+        if let Some(Err(err)) = self.audit.as_ref().map(|audit| audit.record(&entry)) {
+            tracing::warn!(error = %err, "failed to write audit log");
+        }
+    }
+
+    /// Drop every queued transaction, returning how many there were.
+    pub fn flush_mempool(&self) -> usize {
+        let mut mempool = self.mempool.lock().expect("mempool lock poisoned");
+
+        std::mem::take(&mut *mempool).len()
+    }
+
+    /// Number of transactions queued for the next block.
+    pub fn pending_count(&self) -> usize {
+        self.mempool.lock().expect("mempool lock poisoned").len()
+    }
+
+    /// The queued transactions, highest fee first.
+    pub fn pending(&self) -> Vec<SignedTx> {
+        let mempool = self.mempool.lock().expect("mempool lock poisoned");
+
+        mempool.txs().into_iter().cloned().collect()
+    }
+
+    /// Suggest a fee for a transaction to make the next block, given the queued transactions.
+    pub fn estimate_fee(&self, state: &State, tx: &Tx) -> u64 {
+        let mempool = self.mempool.lock().expect("mempool lock poisoned");
+
+        state.estimate_fee(tx, &mempool, MAX_BLOCK_TXS)
+    }
+
+    /// Pack the best queued transactions into a new block, returning its hash, or `None` when
+    /// there's no block to produce.
+    ///
+    /// Proof-of-work chains only grow when transactions are pending. On chains whose blocks are
+    /// sealed by a proposer, proof-of-authority or proof-of-stake, the proposer seals a block even
+    /// when it's empty, so that turns keep rotating.
+    ///
+    /// The block is mined off the runtime without holding the state lock, and dropped if the chain
+    /// moved on in the meantime, its transactions staying queued.
+    pub async fn produce_block(&self) -> chigui_core::Result<Option<Hash>> {
+        let (miner, txs, tip, sealed) = {
+            let state = self.read().await;
+            let sealed = !state.validators().is_empty() || state.next_proposer().is_some();
+
+            if sealed {
+                let turn = self
+                    .validator
+                    .as_ref()
+                    .is_some_and(|wallet| state.is_proposer(&wallet.public_key()));
+
+                if !turn {
+                    return Ok(None);
+                }
+            }
+
+            let txs = self
+                .mempool
+                .lock()
+                .expect("mempool lock poisoned")
+                .select(&state, MAX_BLOCK_TXS);
+
+            if txs.is_empty() && !sealed {
+                return Ok(None);
+            }
+
+            let miner = Miner::new(&state).with_cancel(self.mining_handle());
+
+            (miner, txs, state.latest_block().map(Block::hash), sealed)
+        };
+
+        let mut block = miner.mine_async(txs).await?;
+
+        if let Some(wallet) = self.validator.as_ref().filter(|_| sealed) {
+            wallet.seal(&mut block);
+        }
+
+        let mut state = self.write().await;
+
+        if state.latest_block().map(Block::hash) != tip {
+            tracing::debug!("dropped block mined on a stale tip");
+
+            return Ok(None);
+        }
+
+        let before = state.balances().clone();
+        let height = state.height();
+        let hash = block.hash();
+
+        state.add_block_async(block).await?;
+        self.publish(&state, &before, height);
+
+        Ok(Some(hash))
+    }
+
+    /// Feed a peer message to a sync session and publish events for any block it imported.
+    pub fn sync(
+        &self,
+        state: &mut State,
+        sync: &mut Sync,
+        message: Message,
+    ) -> chigui_core::Result<Vec<Message>> {
+        let before = state.balances().clone();
+        let height = state.height();
+        let tip = state.latest_block().map(Block::hash);
+        let blocks = match &message {
+            Message::Blocks { blocks } => blocks.clone(),
+            _ => Vec::new(),
+        };
+        let replies = sync.handle(state, message);
+        let reorg = sync.take_reorg();
+        let height = reorg
+            .as_ref()
+            .map_or(height, |reorg| reorg.fork_height.min(height));
+
+        if reorg.is_some() || state.latest_block().map(Block::hash) != tip {
+            self.cancel_mining();
+        }
+
+        self.publish(state, &before, height);
+
+        if let Some(reorg) = reorg {
+            self.requeue(state, reorg);
+        }
+
+        // Blocks are applied in order, so the rejected one is right past the new tip.
+        if let Err(err) = &replies
+            && let Some(block) = blocks
+                .iter()
+                .find(|block| block.header.number == state.height() + 1)
+        {
+            self.audit(AuditEntry::new(Source::P2p, block.hash(), err));
+        }
+
+        replies
+    }
+
+    /// Publish the transactions dropped from the chain by a reorg and queue them again, those
+    /// no longer valid on the new branch are dropped.
+    fn requeue(&self, state: &State, reorg: Reorg) {
+        let mut mempool = self.mempool.lock().expect("mempool lock poisoned");
+
+        for tx in reorg.reorged_out {
+            let hash = tx.hash();
+
+            self.events
+                .send(Event::TxReorged {
+                    hash,
+                    tx: Box::new(tx.clone()),
+                })
+                .ok();
+
+            if let Err(err) = mempool.insert(state, tx) {
+                tracing::debug!(%hash, error = %err, "dropped reorged-out transaction");
+            }
+        }
+    }
+
+    /// Publish the blocks past `height` and the balances that differ from `before`, and drop the
+    /// pending transactions they include.
+    fn publish(&self, state: &State, before: &HashMap<Account, u64>, height: u64) {
+        self.mempool
+            .lock()
+            .expect("mempool lock poisoned")
+            .prune(state);
+
+        let applied = self
+            .applied
+            .lock()
+            .expect("applied txs lock poisoned")
+            .try_iter()
+            .collect::<Vec<AppliedTx>>();
+
+        for _ in applied.iter() {
+            self.metrics.tx_applied();
+        }
+
+        // Sending only fails when nobody is subscribed, which is fine.
+        for block in state
+            .blocks()
+            .iter()
+            .filter(|block| block.header.number > height)
+        {
+            let number = block.header.number;
+
+            self.events
+                .send(Event::NewBlock {
+                    number,
+                    hash: block.hash(),
+                    txs: block.txs.len(),
+                })
+                .ok();
+
+            for applied in applied.iter().filter(|applied| applied.block == number) {
+                self.events
+                    .send(Event::NewTx {
+                        block: number,
+                        hash: applied.hash,
+                        tx: Box::new(applied.tx.clone()),
+                    })
+                    .ok();
+            }
+        }
+
+        for (account, balance) in state.balances().iter() {
+            if before.get(account) != Some(balance) {
+                self.events
+                    .send(Event::BalanceChanged {
+                        account: account.clone(),
+                        balance: *balance,
+                    })
+                    .ok();
+            }
+        }
+    }
+}
+
+pub type SharedState = Arc<Node>;
+
+/// Build the node's HTTP router, REST, public and admin JSON-RPC, WebSocket, metrics and health
+/// checks, on top of the given state, within the request quotas of the node.
+pub fn router(state: SharedState) -> Router {
+    let max_body_bytes = state.limiter().limits().max_body_bytes;
+
+    // Probes and scrapes aren't rate limited, so a flooded node still reports being up.
+    rest::router()
+        .merge(rpc::router())
+        .merge(ws::router())
+        .merge(admin::router())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            limit::rate_limit,
+        ))
+        .merge(metrics::router())
+        .merge(health::router())
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .with_state(state)
+}
+
+/// Serve the router with the address of each client, which rate limiting goes by, over HTTPS
+/// when given a TLS config.
+async fn serve_router(
+    listener: TcpListener,
+    node: SharedState,
+    tls: Option<RustlsConfig>,
+) -> std::io::Result<()> {
+    let app = router(node).into_make_service_with_connect_info::<SocketAddr>();
+
+    match tls {
+        Some(config) => {
+            axum_server::from_tcp_rustls(listener.into_std()?, config)?
+                .serve(app)
+                .await
+        }
+        None => axum::serve(listener, app).await,
+    }
+}
+
+/// How the HTTP API of a node is exposed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiConfig {
+    pub addr: SocketAddr,
+    pub limits: Limits,
+    /// Serve HTTPS with these certificate and key files rather than plain HTTP.
+    pub tls: Option<TlsFiles>,
+    /// Token of the clients allowed to call `admin_*` methods, disabling them when unset.
+    pub admin_token: Option<AdminToken>,
+}
+
+/// Serve the node's HTTP API on the given address until the process is stopped.
+pub async fn serve(state: State, addr: SocketAddr) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    let node = Arc::new(Node::new(state));
+
+    tokio::spawn(produce_blocks(node.clone()));
+
+    serve_router(listener, node, None).await
+}
+
+/// Periodically mine the queued transactions into new blocks, until the node shuts down.
+pub async fn produce_blocks(node: SharedState) {
+    let mut interval = tokio::time::interval(BLOCK_INTERVAL);
+
+    while !node.is_stopped() {
+        interval.tick().await;
+
+        match node.produce_block().await {
+            Err(ChiguiError::MiningCancelled) => tracing::debug!("abandoned block being mined"),
+            Err(err) => tracing::warn!(error = %err, "failed to produce block"),
+            Ok(_) => {}
+        }
+    }
+}
+
+/// Serve the HTTP API and the peer-to-peer sync protocol, following every known peer.
+///
+/// On proof-of-authority and proof-of-stake chains, blocks are sealed with the `validator` wallet
+/// when it's its turn. Rejected submissions and peer blocks are recorded to `audit`.
+pub async fn start(
+    state: State,
+    api: ApiConfig,
+    p2p_addr: SocketAddr,
+    peers: KnownPeers,
+    validator: Option<Wallet>,
+    audit: AuditLog,
+) -> std::io::Result<()> {
+    let tls = match &api.tls {
+        Some(files) => Some(files.load().await?),
+        None => None,
+    };
+    let listener = TcpListener::bind(api.addr).await?;
+    let p2p = TcpListener::bind(p2p_addr).await?;
+    let mut node = Node::new(state)
+        .with_peers(peers, p2p_addr)
+        .with_audit_log(audit)
+        .with_limits(api.limits);
+
+    if let Some(wallet) = validator {
+        node = node.with_validator(wallet);
+    }
+
+    if let Some(token) = api.admin_token {
+        node = node.with_admin_token(token);
+    }
+
+    let node = Arc::new(node);
+
+    for peer in node.known_peers() {
+        p2p::dial(node.clone(), peer);
+    }
+
+    tokio::spawn(produce_blocks(node.clone()));
+
+    let served = tokio::select! {
+        served = async {
+            tokio::try_join!(
+                serve_router(listener, node.clone(), tls),
+                p2p::listen(node.clone(), p2p),
+            )
+        } => served.map(|_| ()),
+        signal = tokio::signal::ctrl_c() => signal,
+    };
+
+    node.shutdown();
+
+    served
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// A node whose blocks can't be mined in the lifetime of a test, only cancelled.
+    fn node() -> (TempDir, SharedState) {
+        let dbdir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"difficulty":256,"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let state = State::open(dbdir.path()).unwrap();
+
+        (dbdir, Arc::new(Node::new(state)))
+    }
+
+    fn generate() -> SignedTx {
+        SignedTx::unsigned(Tx::Generate {
+            to: Account::new("alice").unwrap(),
+            value: 1,
+            denom: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn shutdown_cancels_mining() {
+        let (_dbdir, node) = node();
+        let stopping = node.clone();
+
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            stopping.shutdown();
+        });
+
+        let submitted = node.submit(&mut *node.write().await, generate()).await;
+
+        assert!(matches!(submitted, Err(ChiguiError::MiningCancelled)));
+        assert!(node.is_stopped());
+        assert!(node.mining_handle().is_cancelled());
+        assert_eq!(node.read().await.height(), 0);
+    }
+
+    #[tokio::test]
+    async fn produces_blocks_without_locking_the_state() {
+        let (_dbdir, node) = node();
+
+        node.queue(&*node.read().await, generate()).unwrap();
+
+        let producing = tokio::spawn({
+            let node = node.clone();
+
+            async move { node.produce_block().await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The producer is still mining, yet the state can be locked for writing.
+        assert_eq!(node.write().await.height(), 0);
+
+        node.shutdown();
+
+        assert!(matches!(
+            producing.await.unwrap(),
+            Err(ChiguiError::MiningCancelled)
+        ));
+        assert_eq!(node.pending_count(), 1);
+    }
+}
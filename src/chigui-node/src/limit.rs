@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State as AxumState};
+use axum::http::{StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::SharedState;
+
+/// Clients tracked at most, the buckets of the least recently seen ones being dropped past it.
+const MAX_TRACKED_CLIENTS: usize = 4096;
+
+/// Quotas applied to HTTP API requests, by client IP address, sparing health probes and metric
+/// scrapes from the rate limit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Requests a client may make per second on average, `0` disabling rate limiting.
+    pub requests_per_second: f64,
+    /// Requests a client may make at once before being limited to `requests_per_second`.
+    pub burst: u32,
+    /// Largest request body accepted, in bytes, answered with `413 Payload Too Large` past it.
+    pub max_body_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 50.0,
+            burst: 100,
+            max_body_bytes: 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets of the clients of the HTTP API, refilled at [`Limits::requests_per_second`] up
+/// to [`Limits::burst`] requests.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: Limits,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::default(),
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Take a token from the bucket of the client, returning how long it should wait before
+    /// retrying if the bucket is empty.
+    pub fn check(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let Limits {
+            requests_per_second: rate,
+            burst,
+            ..
+        } = self.limits;
+
+        if rate <= 0.0 {
+            return Ok(());
+        }
+
+        let burst = f64::from(burst.max(1));
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+
+            (bucket.tokens + elapsed * rate).min(burst)
+        };
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            // Full buckets go first, they'd be refilled anyway, then the least recently seen
+            // client, so a flood of spoofed addresses can't grow the map past the cap.
+            buckets.retain(|_, bucket| refill(bucket) < burst);
+
+            if buckets.len() >= MAX_TRACKED_CLIENTS
+                && let Some(oldest) = buckets
+                    .iter()
+                    .min_by_key(|(_, bucket)| bucket.updated)
+                    .map(|(client, _)| *client)
+            {
+                buckets.remove(&oldest);
+            }
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate));
+        }
+
+        bucket.tokens -= 1.0;
+
+        Ok(())
+    }
+}
+
+/// Answer requests of clients over their quota with `429 Too Many Requests`.
+///
+/// Requests whose client address is unknown, i.e. not served with
+/// [`ConnectInfo`](axum::extract::ConnectInfo), aren't limited.
+pub async fn rate_limit(
+    AxumState(node): AxumState<SharedState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if let Some(client) = client {
+        if let Err(wait) = node.limiter().check(client, Instant::now()) {
+            node.metrics().rate_limited();
+            tracing::debug!(%client, "rate limited");
+
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, wait.as_secs().max(1).to_string())],
+                "Too many requests.",
+            )
+                .into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::Request as HttpRequest;
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use chigui_core::state::State;
+
+    use super::*;
+    use crate::Node;
+
+    #[test]
+    fn refills_buckets_over_time() {
+        let limiter = RateLimiter::new(Limits {
+            requests_per_second: 2.0,
+            burst: 3,
+            ..Limits::default()
+        });
+        let (alice, bob) = (IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2]));
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check(alice, now).is_ok());
+        }
+
+        assert_eq!(limiter.check(alice, now), Err(Duration::from_millis(500)));
+        assert!(limiter.check(bob, now).is_ok());
+        assert!(
+            limiter
+                .check(alice, now + Duration::from_millis(500))
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .check(alice, now + Duration::from_millis(500))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn caps_tracked_clients() {
+        let limiter = RateLimiter::new(Limits {
+            requests_per_second: 1.0,
+            burst: 1,
+            ..Limits::default()
+        });
+        let now = Instant::now();
+        let client = |index: usize| IpAddr::from((index as u32).to_be_bytes());
+
+        // Every client drains its bucket, so none is dropped for being full.
+        for index in 0..MAX_TRACKED_CLIENTS + 10 {
+            let at = now + Duration::from_micros(index as u64);
+
+            assert!(limiter.check(client(index), at).is_ok());
+        }
+
+        let later = now + Duration::from_millis(100);
+
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_TRACKED_CLIENTS);
+        assert!(limiter.check(client(0), later).is_ok());
+        assert!(
+            limiter
+                .check(client(MAX_TRACKED_CLIENTS + 9), later)
+                .is_err()
+        );
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_TRACKED_CLIENTS);
+    }
+
+    #[tokio::test]
+    async fn answers_floods_with_too_many_requests() {
+        let dbdir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100}}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let node = Node::new(State::open(dbdir.path()).unwrap()).with_limits(Limits {
+            requests_per_second: 1.0,
+            burst: 2,
+            max_body_bytes: 64,
+        });
+        let app = crate::router(Arc::new(node));
+        let client = ConnectInfo(SocketAddr::from((Ipv4Addr::LOCALHOST, 4000)));
+        let get = |uri| {
+            let mut request = HttpRequest::get(uri).body(Body::empty()).unwrap();
+
+            request.extensions_mut().insert(client);
+            request
+        };
+        let mut statuses = Vec::new();
+
+        for _ in 0..3 {
+            statuses.push(
+                app.clone()
+                    .oneshot(get("/balances"))
+                    .await
+                    .unwrap()
+                    .status(),
+            );
+        }
+
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS
+            ]
+        );
+
+        // Probes and scrapes of the flooding client still get through.
+        for uri in ["/healthz", "/metrics"] {
+            let response = app.clone().oneshot(get(uri)).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = app
+            .clone()
+            .oneshot(
+                HttpRequest::post("/rpc")
+                    .body(Body::from(vec![b' '; 65]))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = app
+            .oneshot(HttpRequest::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        assert!(String::from_utf8_lossy(&body).contains("chigui_rate_limited_total 1\n"));
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::State as AxumState;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+
+use chigui_core::TxKind;
+
+use crate::SharedState;
+
+/// Upper bounds, in seconds, of the RPC latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+pub fn router() -> Router<SharedState> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Counters and histograms of a running node, rendered in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    txs_applied: AtomicU64,
+    /// HTTP requests answered with `429 Too Many Requests`.
+    rate_limited: AtomicU64,
+    apply_errors: Mutex<BTreeMap<String, u64>>,
+    rpc_latency: Mutex<BTreeMap<String, Histogram>>,
+}
+
+impl Metrics {
+    pub fn tx_applied(&self) {
+        self.txs_applied.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn apply_error(&self, kind: TxKind) {
+        *self
+            .apply_errors
+            .lock()
+            .expect("metrics lock poisoned")
+            .entry(kind.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn rpc_call(&self, method: &str, elapsed: Duration) {
+        self.rpc_latency
+            .lock()
+            .expect("metrics lock poisoned")
+            .entry(method.to_string())
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Render the metrics along with the given gauges.
+    fn render(&self, height: u64, mempool: usize, load: Duration) -> String {
+        let mut out = String::new();
+
+        gauge(
+            &mut out,
+            "chigui_block_height",
+            "Height of the chain.",
+            height,
+        );
+        gauge(
+            &mut out,
+            "chigui_mempool_size",
+            "Transactions waiting for a block.",
+            mempool,
+        );
+        gauge(
+            &mut out,
+            "chigui_load_duration_seconds",
+            "Time taken to load the chain and replay its blocks.",
+            load.as_secs_f64(),
+        );
+
+        describe(
+            &mut out,
+            "chigui_txs_applied_total",
+            "Transactions applied to the chain.",
+            "counter",
+        );
+        writeln!(
+            out,
+            "chigui_txs_applied_total {}",
+            self.txs_applied.load(Ordering::Relaxed)
+        )
+        .ok();
+
+        describe(
+            &mut out,
+            "chigui_rate_limited_total",
+            "HTTP requests rejected for exceeding the per-client rate limit.",
+            "counter",
+        );
+        writeln!(
+            out,
+            "chigui_rate_limited_total {}",
+            self.rate_limited.load(Ordering::Relaxed)
+        )
+        .ok();
+
+        describe(
+            &mut out,
+            "chigui_apply_errors_total",
+            "Submitted transactions rejected, by type.",
+            "counter",
+        );
+
+        for (kind, count) in self
+            .apply_errors
+            .lock()
+            .expect("metrics lock poisoned")
+            .iter()
+        {
+            writeln!(
+                out,
+                "chigui_apply_errors_total{{kind=\"{}\"}} {}",
+                kind, count
+            )
+            .ok();
+        }
+
+        describe(
+            &mut out,
+            "chigui_rpc_duration_seconds",
+            "Latency of JSON-RPC calls, by method.",
+            "histogram",
+        );
+
+        for (method, histogram) in self
+            .rpc_latency
+            .lock()
+            .expect("metrics lock poisoned")
+            .iter()
+        {
+            let name = "chigui_rpc_duration_seconds";
+
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                writeln!(
+                    out,
+                    "{}_bucket{{method=\"{}\",le=\"{}\"}} {}",
+                    name, method, bound, count
+                )
+                .ok();
+            }
+
+            writeln!(
+                out,
+                "{}_bucket{{method=\"{}\",le=\"+Inf\"}} {}",
+                name, method, histogram.count
+            )
+            .ok();
+            writeln!(
+                out,
+                "{}_sum{{method=\"{}\"}} {}",
+                name, method, histogram.sum
+            )
+            .ok();
+            writeln!(
+                out,
+                "{}_count{{method=\"{}\"}} {}",
+                name, method, histogram.count
+            )
+            .ok();
+        }
+
+        out
+    }
+}
+
+fn describe(out: &mut String, name: &str, help: &str, kind: &str) {
+    writeln!(out, "# HELP {} {}", name, help).ok();
+    writeln!(out, "# TYPE {} {}", name, kind).ok();
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    describe(out, name, help, "gauge");
+    writeln!(out, "{} {}", name, value).ok();
+}
+
+async fn metrics(AxumState(node): AxumState<SharedState>) -> impl IntoResponse {
+    let state = node.read().await;
+    let body = node
+        .metrics()
+        .render(state.height(), node.pending_count(), state.load_duration());
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_histograms() {
+        let metrics = Metrics::default();
+
+        metrics.tx_applied();
+        metrics.apply_error(TxKind::Transfer);
+        metrics.rpc_call("chigui_getBalance", Duration::from_millis(20));
+
+        let out = metrics.render(3, 1, Duration::from_secs(2));
+
+        assert!(out.contains("chigui_block_height 3\n"));
+        assert!(out.contains("chigui_txs_applied_total 1\n"));
+        assert!(out.contains("chigui_apply_errors_total{kind=\"transfer\"} 1\n"));
+        assert!(out.contains(
+            "chigui_rpc_duration_seconds_bucket{method=\"chigui_getBalance\",le=\"0.01\"} 0\n"
+        ));
+        assert!(out.contains(
+            "chigui_rpc_duration_seconds_bucket{method=\"chigui_getBalance\",le=\"0.05\"} 1\n"
+        ));
+        assert!(
+            out.contains("chigui_rpc_duration_seconds_count{method=\"chigui_getBalance\"} 1\n")
+        );
+    }
+}
@@ -0,0 +1,536 @@
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+use chigui_core::proto;
+use chigui_core::sync::{Message, Status, Sync};
+
+use crate::SharedState;
+use crate::events::Event;
+
+/// Sent first by a dialer to speak protobuf, and echoed back by a listener that does too. It
+/// can't be mistaken for the `{` opening a JSON message.
+const PROTO_PREAMBLE: &[u8] = b"\0chigui-proto/1\n";
+
+/// Largest protobuf message accepted from a peer.
+const MAX_FRAME_LEN: u64 = 16 << 20;
+
+/// Messages read ahead from a peer before they're handled.
+const INCOMING_CAPACITY: usize = 16;
+
+/// How sync messages are framed on a peer connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Newline-delimited JSON, which every node speaks.
+    Json,
+    /// Length-delimited protobuf `chigui.v1.SyncMessage`s, negotiated with a preamble.
+    Proto,
+}
+
+/// Accept peer connections and synchronize with each of them until the listener fails.
+pub async fn listen(node: SharedState, listener: TcpListener) -> io::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let node = node.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = session(node, stream, None).await {
+                tracing::info!(%peer, error = %err, "peer disconnected");
+            }
+        });
+    }
+}
+
+/// Connect to a peer and synchronize with it until either side hangs up, over protobuf unless the
+/// peer only speaks JSON.
+pub async fn connect(node: SharedState, peer: SocketAddr) -> io::Result<()> {
+    let stream = TcpStream::connect(peer).await?;
+
+    match session(node.clone(), stream, Some(Encoding::Proto)).await {
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+            tracing::debug!(%peer, "peer doesn't speak protobuf, falling back to JSON");
+
+            let stream = TcpStream::connect(peer).await?;
+
+            session(node, stream, Some(Encoding::Json)).await
+        }
+        ended => ended,
+    }
+}
+
+/// Connect to a peer in the background, logging why the session ended.
+pub fn dial(node: SharedState, peer: SocketAddr) {
+    let session: Pin<Box<dyn Future<Output = io::Result<()>> + Send>> =
+        Box::pin(connect(node, peer));
+
+    tokio::spawn(async move {
+        if let Err(err) = session.await {
+            tracing::info!(%peer, error = %err, "peer disconnected");
+        }
+    });
+}
+
+/// Agree on the encoding of a session: a dialer offers protobuf and gets
+/// [`io::ErrorKind::Unsupported`] if the peer doesn't echo the preamble, while a listener follows
+/// what the dialer opens with. `None` when the dialer hung up before sending anything.
+async fn negotiate(
+    read: &mut BufReader<OwnedReadHalf>,
+    write: &mut OwnedWriteHalf,
+    dial: Option<Encoding>,
+) -> io::Result<Option<Encoding>> {
+    let mut preamble = vec![0; PROTO_PREAMBLE.len()];
+
+    match dial {
+        Some(Encoding::Json) => Ok(Some(Encoding::Json)),
+        Some(Encoding::Proto) => {
+            write.write_all(PROTO_PREAMBLE).await?;
+
+            // JSON-only peers answer with their hello, or hang up on the preamble.
+            match read.read_exact(&mut preamble).await {
+                Ok(_) if preamble == PROTO_PREAMBLE => Ok(Some(Encoding::Proto)),
+                Ok(_) => Err(io::Error::from(io::ErrorKind::Unsupported)),
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    Err(io::Error::from(io::ErrorKind::Unsupported))
+                }
+                Err(err) => Err(err),
+            }
+        }
+        None => match read.fill_buf().await?.first() {
+            None => Ok(None),
+            Some(byte) if *byte == PROTO_PREAMBLE[0] => {
+                read.read_exact(&mut preamble).await?;
+
+                if preamble != PROTO_PREAMBLE {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unknown protocol preamble",
+                    ));
+                }
+
+                write.write_all(PROTO_PREAMBLE).await?;
+
+                Ok(Some(Encoding::Proto))
+            }
+            Some(_) => Ok(Some(Encoding::Json)),
+        },
+    }
+}
+
+/// Run the sync protocol, over the encoding negotiated with the peer when `dial` is `None` or
+/// `Some(Encoding::Proto)`.
+///
+/// Besides answering the peer, the session announces every block the local chain grows by, so
+/// connected peers keep following it.
+async fn session(node: SharedState, stream: TcpStream, dial: Option<Encoding>) -> io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut read = BufReader::new(read);
+    let Some(encoding) = negotiate(&mut read, &mut write, dial).await? else {
+        return Ok(());
+    };
+
+    tracing::debug!(?encoding, "peer session started");
+
+    // Messages are read in a task of their own, as reading one isn't cancel safe.
+    let (incoming_tx, incoming) = mpsc::channel(INCOMING_CAPACITY);
+    let reader = tokio::spawn(async move {
+        loop {
+            let message = receive(&mut read, encoding).await;
+            let ended = !matches!(message, Ok(Some(_)));
+
+            if incoming_tx.send(message).await.is_err() || ended {
+                break;
+            }
+        }
+    });
+    let synced = sync(node, incoming, &mut write, encoding).await;
+
+    reader.abort();
+
+    synced
+}
+
+async fn sync(
+    node: SharedState,
+    mut incoming: mpsc::Receiver<io::Result<Option<Message>>>,
+    write: &mut OwnedWriteHalf,
+    encoding: Encoding,
+) -> io::Result<()> {
+    let mut events = node.subscribe();
+    let mut sync = Sync::new();
+
+    let hello = match Sync::hello(&*node.read().await) {
+        Message::Hello {
+            height, state_root, ..
+        } => Message::Hello {
+            height,
+            state_root,
+            listen: node.listen_addr(),
+        },
+        message => message,
+    };
+    send(write, encoding, &hello).await?;
+    send(write, encoding, &Message::GetPeers).await?;
+
+    loop {
+        let outgoing = tokio::select! {
+            message = incoming.recv() => {
+                let Some(message) = message.transpose()?.flatten() else {
+                    return Ok(());
+                };
+
+                match message {
+                    Message::GetPeers => vec![Message::Peers {
+                        peers: node.known_peers(),
+                    }],
+                    Message::Peers { peers } => {
+                        for peer in peers {
+                            if node.learn_peer(peer).map_err(io::Error::other)? {
+                                dial(node.clone(), peer);
+                            }
+                        }
+
+                        Vec::new()
+                    }
+                    message => {
+                        if let Message::Hello { height, listen, .. } = &message {
+                            node.observe_peer_height(*height);
+
+                            if let Some(peer) = listen {
+                                node.learn_peer(*peer).map_err(io::Error::other)?;
+                            }
+                        }
+
+                        let mut state = node.write().await;
+                        let replies = node
+                            .sync(&mut state, &mut sync, message)
+                            .map_err(io::Error::other)?;
+
+                        if sync.status() == Status::Diverged {
+                            return Err(io::Error::other(format!(
+                                "state root diverged at height {}",
+                                state.height()
+                            )));
+                        }
+
+                        replies
+                    }
+                }
+            }
+            event = events.recv() => match event {
+                Ok(Event::NewBlock { number, .. }) => vec![Message::Hello {
+                    height: number,
+                    state_root: None,
+                    listen: None,
+                }],
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        };
+
+        for message in outgoing {
+            send(write, encoding, &message).await?;
+        }
+    }
+}
+
+/// Read the next message from a peer, `None` once it hung up.
+async fn receive(
+    read: &mut BufReader<OwnedReadHalf>,
+    encoding: Encoding,
+) -> io::Result<Option<Message>> {
+    match encoding {
+        Encoding::Json => {
+            let mut line = String::new();
+
+            if read.read_line(&mut line).await? == 0 {
+                return Ok(None);
+            }
+
+            Ok(Some(serde_json::from_str(&line)?))
+        }
+        Encoding::Proto => {
+            let Some(len) = frame_len(read).await? else {
+                return Ok(None);
+            };
+            let mut frame = vec![0; len];
+
+            read.read_exact(&mut frame).await?;
+
+            proto::decode(&frame)
+                .map(Some)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        }
+    }
+}
+
+/// Read the varint length prefixing a protobuf frame, `None` at the end of the stream.
+async fn frame_len(read: &mut BufReader<OwnedReadHalf>) -> io::Result<Option<usize>> {
+    let mut len = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let byte = match read.read_u8().await {
+            Ok(byte) => byte,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && shift == 0 => {
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+
+        len |= u64::from(byte & 0x7f) << shift;
+
+        if len > MAX_FRAME_LEN {
+            break;
+        }
+
+        if byte & 0x80 == 0 {
+            return Ok(Some(len as usize));
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "protobuf frame too long",
+    ))
+}
+
+async fn send(write: &mut OwnedWriteHalf, encoding: Encoding, message: &Message) -> io::Result<()> {
+    let bytes = match encoding {
+        Encoding::Json => {
+            let mut line = serde_json::to_vec(message)?;
+            line.push(b'\n');
+            line
+        }
+        Encoding::Proto => proto::encode_length_delimited(message),
+    };
+
+    write.write_all(&bytes).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    use chigui_core::peers::KnownPeers;
+    use chigui_core::state::State;
+    use chigui_core::{Account, Tx};
+
+    use super::*;
+    use crate::Node;
+
+    fn chain() -> (TempDir, State) {
+        let dbdir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let state = State::open(dbdir.path()).unwrap();
+
+        (dbdir, state)
+    }
+
+    fn node() -> (TempDir, SharedState) {
+        let (dbdir, state) = chain();
+
+        (dbdir, Arc::new(Node::new(state)))
+    }
+
+    /// Start a node listening for peers and taking part in discovery.
+    async fn peer() -> (TempDir, SharedState) {
+        let (dbdir, state) = chain();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peers = KnownPeers::open(dbdir.path()).unwrap();
+        let node = Arc::new(Node::new(state).with_peers(peers, addr));
+
+        tokio::spawn(listen(node.clone(), listener));
+
+        (dbdir, node)
+    }
+
+    async fn wait_for_peer(node: &SharedState, peer: &SharedState) {
+        let addr = peer.listen_addr().unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !node.known_peers().contains(&addr) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("peer never discovered");
+    }
+
+    async fn generate(node: &SharedState) {
+        let tx = Tx::Generate {
+            to: Account::new("alice").unwrap(),
+            value: 1,
+            denom: None,
+        };
+
+        node.submit(&mut *node.write().await, tx.into())
+            .await
+            .unwrap();
+    }
+
+    async fn wait_for_height(node: &SharedState, height: u64) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while node.read().await.height() < height {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("peer never caught up");
+    }
+
+    #[tokio::test]
+    async fn peers_follow_each_other() {
+        let (_ahead_dir, ahead) = node();
+        let (behind_dir, behind) = node();
+
+        for _ in 0..3 {
+            generate(&ahead).await;
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(listen(ahead.clone(), listener));
+        tokio::spawn(connect(behind.clone(), addr));
+
+        wait_for_height(&behind, 3).await;
+        generate(&ahead).await;
+        wait_for_height(&behind, 4).await;
+
+        assert_eq!(behind.read().await.blocks(), ahead.read().await.blocks());
+        assert_eq!(State::open(behind_dir.path()).unwrap().height(), 4);
+    }
+
+    #[tokio::test]
+    async fn negotiates_protobuf() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dialer = TcpStream::connect(listener.local_addr().unwrap());
+        let (dialer, accepted) = tokio::join!(dialer, listener.accept());
+        let (dialer_read, mut dialer_write) = dialer.unwrap().into_split();
+        let (listener_read, mut listener_write) = accepted.unwrap().0.into_split();
+        let mut dialer_read = BufReader::new(dialer_read);
+        let mut listener_read = BufReader::new(listener_read);
+
+        let (dialed, accepted) = tokio::join!(
+            negotiate(&mut dialer_read, &mut dialer_write, Some(Encoding::Proto)),
+            negotiate(&mut listener_read, &mut listener_write, None),
+        );
+
+        assert_eq!(dialed.unwrap(), Some(Encoding::Proto));
+        assert_eq!(accepted.unwrap(), Some(Encoding::Proto));
+
+        let hello = Message::Hello {
+            height: 3,
+            state_root: None,
+            listen: None,
+        };
+
+        send(&mut dialer_write, Encoding::Proto, &hello)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            receive(&mut listener_read, Encoding::Proto).await.unwrap(),
+            Some(hello)
+        );
+    }
+
+    #[tokio::test]
+    async fn speaks_json_with_older_peers() {
+        let (_ahead_dir, ahead) = node();
+        let (_behind_dir, behind) = node();
+        let (_json_dir, json) = node();
+
+        generate(&ahead).await;
+
+        // A listener that only speaks JSON, like nodes predating protobuf.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn({
+            let ahead = ahead.clone();
+
+            async move {
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+
+                    tokio::spawn(session(ahead.clone(), stream, Some(Encoding::Json)));
+                }
+            }
+        });
+        tokio::spawn(connect(behind.clone(), addr));
+
+        wait_for_height(&behind, 1).await;
+
+        // A dialer that only speaks JSON.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap());
+
+        tokio::spawn(listen(behind.clone(), listener));
+        tokio::spawn(session(
+            json.clone(),
+            stream.await.unwrap(),
+            Some(Encoding::Json),
+        ));
+
+        wait_for_height(&json, 1).await;
+
+        assert_eq!(json.read().await.blocks(), ahead.read().await.blocks());
+    }
+
+    #[tokio::test]
+    async fn accepted_peer_blocks_cancel_mining() {
+        let (_ahead_dir, ahead) = node();
+        let (_behind_dir, behind) = node();
+        let mining = behind.mining_handle();
+
+        generate(&ahead).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(listen(ahead.clone(), listener));
+        tokio::spawn(connect(behind.clone(), addr));
+
+        wait_for_height(&behind, 1).await;
+
+        assert!(mining.is_cancelled());
+        assert!(!behind.mining_handle().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn discovers_peers_from_a_bootstrap_node() {
+        let (_bootstrap_dir, bootstrap) = peer().await;
+        let (_first_dir, first) = peer().await;
+        let (second_dir, second) = peer().await;
+
+        dial(first.clone(), bootstrap.listen_addr().unwrap());
+        wait_for_peer(&bootstrap, &first).await;
+
+        dial(second.clone(), bootstrap.listen_addr().unwrap());
+        wait_for_peer(&second, &first).await;
+        wait_for_peer(&first, &second).await;
+
+        generate(&first).await;
+        wait_for_height(&second, 1).await;
+
+        let persisted = KnownPeers::open(second_dir.path()).unwrap();
+        assert!(persisted.contains(&first.listen_addr().unwrap()));
+    }
+}
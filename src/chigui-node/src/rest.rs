@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+
+use axum::extract::{Path, Query, State as AxumState};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use chigui_core::query::TxFilter;
+use chigui_core::signed::SignedTx;
+use chigui_core::statement::StatementEntry;
+use chigui_core::{Account, ChiguiError, Hash, TxKind};
+
+use crate::SharedState;
+use crate::error::ApiError;
+
+pub fn router() -> Router<SharedState> {
+    Router::new()
+        .route("/balances", get(balances))
+        .route("/balances/{account}", get(balance))
+        .route("/balances/{account}/statement", get(statement))
+        .route("/txs", get(txs).post(submit_tx))
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BalanceResponse {
+    pub account: Account,
+    pub balance: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TxsQuery {
+    pub account: Option<Account>,
+    #[serde(rename = "type")]
+    pub kind: Option<TxKind>,
+    pub min_value: Option<u64>,
+    pub memo: Option<String>,
+    /// Only transactions of blocks from this height on.
+    pub from_height: Option<u64>,
+    /// Only transactions of blocks up to this height, included.
+    pub to_height: Option<u64>,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxResponse {
+    pub block: u64,
+    pub hash: Hash,
+    #[serde(flatten)]
+    pub tx: SignedTx,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubmitResponse {
+    pub hash: Hash,
+    pub block: u64,
+}
+
+/// Selects the asset whose balances are returned, the native coin by default.
+#[derive(Debug, Default, Deserialize)]
+pub struct DenomQuery {
+    pub denom: Option<String>,
+}
+
+#[tracing::instrument(skip_all, fields(denom = ?query.denom))]
+async fn balances(
+    AxumState(state): AxumState<SharedState>,
+    Query(query): Query<DenomQuery>,
+) -> Result<Json<BTreeMap<String, u64>>, ApiError> {
+    let state = state.read().await;
+    let balances = match &query.denom {
+        Some(denom) => state
+            .asset_balances(denom)
+            .ok_or_else(|| ChiguiError::UnknownAsset {
+                denom: denom.clone(),
+            })?,
+        None => state.balances(),
+    };
+
+    Ok(Json(
+        balances
+            .iter()
+            .map(|(account, balance)| (account.to_string(), *balance))
+            .collect(),
+    ))
+}
+
+#[tracing::instrument(skip_all, fields(%account, denom = ?query.denom))]
+async fn balance(
+    AxumState(state): AxumState<SharedState>,
+    Path(account): Path<String>,
+    Query(query): Query<DenomQuery>,
+) -> Result<Json<BalanceResponse>, ApiError> {
+    let account = Account::new(account)?;
+    let state = state.read().await;
+
+    if let Some(denom) = query
+        .denom
+        .as_ref()
+        .filter(|d| state.asset_balances(d).is_none())
+    {
+        return Err(ChiguiError::UnknownAsset {
+            denom: denom.clone(),
+        }
+        .into());
+    }
+
+    let balance = state
+        .get_asset_balance(&account, query.denom.as_deref())
+        .ok_or_else(|| ChiguiError::AccountNotFound {
+            account: account.clone(),
+        })?;
+
+    Ok(Json(BalanceResponse { account, balance }))
+}
+
+#[tracing::instrument(skip_all, fields(%account))]
+async fn statement(
+    AxumState(state): AxumState<SharedState>,
+    Path(account): Path<String>,
+) -> Result<Json<Vec<StatementEntry>>, ApiError> {
+    let account = Account::new(account)?;
+    let statement = state.read().await.statement(&account)?;
+
+    Ok(Json(statement))
+}
+
+#[tracing::instrument(skip_all)]
+async fn txs(
+    AxumState(state): AxumState<SharedState>,
+    Query(query): Query<TxsQuery>,
+) -> Json<Vec<TxResponse>> {
+    let state = state.read().await;
+    let filter = TxFilter {
+        account: query.account,
+        kind: query.kind,
+        min_value: query.min_value,
+        memo: query.memo,
+    };
+    let txs = state
+        .find_txs(
+            &filter,
+            query.from_height.unwrap_or(0),
+            query.to_height.unwrap_or(u64::MAX),
+        )
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .map(|(block, tx)| TxResponse {
+            block,
+            hash: tx.hash(),
+            tx: tx.clone(),
+        })
+        .collect();
+
+    Json(txs)
+}
+
+#[tracing::instrument(skip_all, fields(hash = %tx.hash()))]
+async fn submit_tx(
+    AxumState(state): AxumState<SharedState>,
+    Json(tx): Json<SignedTx>,
+) -> Result<(StatusCode, Json<SubmitResponse>), ApiError> {
+    let mut chain = state.write().await;
+    let response = state.submit(&mut chain, tx).await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{Request, header};
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use chigui_core::state::State;
+    use chigui_core::statement::Direction;
+
+    use super::*;
+    use crate::Node;
+
+    fn state() -> (TempDir, SharedState) {
+        let dbdir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":1000,"bob":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let state = State::open(dbdir.path()).unwrap();
+
+        (dbdir, Arc::new(Node::new(state)))
+    }
+
+    async fn body<T: for<'de> Deserialize<'de>>(response: axum::response::Response) -> T {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn submit_and_query() {
+        let (_dbdir, state) = state();
+        let app = crate::router(state);
+        let request = Request::post("/txs")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"tx":{"type":"transfer","from":"alice","to":"bob","value":10,"nonce":0}}"#,
+            ))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/balances/bob").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let balance = body::<BalanceResponse>(response).await;
+
+        assert_eq!(balance.balance, 10);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/txs?account=bob&type=transfer")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let txs = body::<Vec<TxResponse>>(response).await;
+
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].block, 1);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/balances/alice/statement")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let statement = body::<Vec<StatementEntry>>(response).await;
+
+        assert_eq!(statement.len(), 1);
+        assert_eq!(statement[0].amount, 10);
+        assert_eq!(statement[0].direction, Direction::Debit);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_tx() {
+        let (_dbdir, state) = state();
+        let app = crate::router(state);
+        let request = Request::post("/txs")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"tx":{"type":"transfer","from":"bob","to":"alice","value":10,"nonce":0}}"#,
+            ))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = app
+            .oneshot(Request::get("/balances/carol").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,542 @@
+use std::time::Instant;
+
+use axum::body::Bytes;
+use axum::extract::State as AxumState;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::Instrument;
+
+use chigui_core::merkle::MerkleProof;
+use chigui_core::signed::SignedTx;
+use chigui_core::state::State;
+use chigui_core::{Account, ChiguiError, Hash, Tx};
+
+use crate::Node;
+use crate::SharedState;
+use crate::admin;
+use crate::rest::TxResponse;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Server error code used for rejected transactions and missing entities.
+const SERVER_ERROR: i64 = -32000;
+
+/// Most transactions `chigui_getTxs` and `chigui_getTxsBetween` return at once.
+const MAX_TXS: usize = 1000;
+
+pub fn router() -> Router<SharedState> {
+    Router::new().route("/rpc", post(rpc))
+}
+
+/// Which methods a JSON-RPC endpoint dispatches to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Namespace {
+    /// `chigui_*` queries and transaction submissions, open to anyone.
+    Public,
+    /// `admin_*` node operations, see [`admin`](crate::admin).
+    Admin,
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    /// Absent for notifications, which get no response.
+    id: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub(crate) fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn method_not_found(method: &str) -> Self {
+        Self::new(
+            METHOD_NOT_FOUND,
+            format!("Method \"{}\" not found.", method),
+        )
+    }
+}
+
+impl From<ChiguiError> for RpcError {
+    fn from(err: ChiguiError) -> Self {
+        Self::new(SERVER_ERROR, err.to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+impl RpcResponse {
+    fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        Self {
+            jsonrpc: String::from("2.0"),
+            result,
+            error,
+            id,
+        }
+    }
+}
+
+/// A queued transaction as listed by `chigui_getMempool`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PendingTx {
+    pub hash: Hash,
+    #[serde(flatten)]
+    pub tx: SignedTx,
+}
+
+/// Proof that a block includes a transaction, as returned by `chigui_getTxProof`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxProof {
+    pub block: u64,
+    pub tx_root: Hash,
+    pub proof: MerkleProof,
+}
+
+async fn rpc(AxumState(state): AxumState<SharedState>, body: Bytes) -> Response {
+    serve(state, body, Namespace::Public).await
+}
+
+/// Handle a single JSON-RPC 2.0 call or a batch of them, with the methods of the namespace.
+pub(crate) async fn serve(state: SharedState, body: Bytes, namespace: Namespace) -> Response {
+    let payload = match serde_json::from_slice::<Value>(&body) {
+        Ok(payload) => payload,
+        Err(err) => {
+            let error = RpcError::new(PARSE_ERROR, err.to_string());
+
+            return Json(RpcResponse::new(Value::Null, Err(error))).into_response();
+        }
+    };
+    let mut chain = state.write().await;
+
+    match payload {
+        Value::Array(calls) if calls.is_empty() => {
+            let error = RpcError::new(INVALID_REQUEST, "Empty batch.");
+
+            Json(RpcResponse::new(Value::Null, Err(error))).into_response()
+        }
+        Value::Array(calls) => {
+            let mut responses = Vec::new();
+
+            for call in calls {
+                responses.extend(handle(&state, &mut chain, call, namespace).await);
+            }
+
+            if responses.is_empty() {
+                StatusCode::NO_CONTENT.into_response()
+            } else {
+                Json(responses).into_response()
+            }
+        }
+        call => match handle(&state, &mut chain, call, namespace).await {
+            Some(response) => Json(response).into_response(),
+            None => StatusCode::NO_CONTENT.into_response(),
+        },
+    }
+}
+
+/// Dispatch one call, returning `None` for notifications.
+async fn handle(
+    node: &SharedState,
+    state: &mut State,
+    call: Value,
+    namespace: Namespace,
+) -> Option<RpcResponse> {
+    let request = match serde_json::from_value::<Request>(call) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        Ok(request) => {
+            let error = RpcError::new(INVALID_REQUEST, "Unsupported JSON-RPC version.");
+
+            return Some(RpcResponse::new(request.id.unwrap_or_default(), Err(error)));
+        }
+        Err(err) => {
+            let error = RpcError::new(INVALID_REQUEST, err.to_string());
+
+            return Some(RpcResponse::new(Value::Null, Err(error)));
+        }
+    };
+    let span = tracing::info_span!("rpc", method = %request.method);
+    let started = Instant::now();
+    let outcome = match namespace {
+        Namespace::Public => {
+            call_method(node, state, &request.method, request.params)
+                .instrument(span.clone())
+                .await
+        }
+        Namespace::Admin => {
+            admin::call_method(node, state, &request.method, request.params)
+                .instrument(span.clone())
+                .await
+        }
+    };
+
+    let label = match &outcome {
+        Err(err) if err.code == METHOD_NOT_FOUND => "unknown",
+        _ => request.method.as_str(),
+    };
+
+    node.metrics().rpc_call(label, started.elapsed());
+    span.in_scope(|| match &outcome {
+        Ok(_) => tracing::debug!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "handled call"
+        ),
+        Err(err) => tracing::info!(code = err.code, message = %err.message, "failed call"),
+    });
+
+    request.id.map(|id| RpcResponse::new(id, outcome))
+}
+
+async fn call_method(
+    node: &Node,
+    state: &mut State,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    match method {
+        "chigui_getBalance" => {
+            let BalanceParams(account, denom) = parse_params(params)?;
+
+            if let Some(denom) = denom.as_ref().filter(|d| state.asset_balances(d).is_none()) {
+                return Err(ChiguiError::UnknownAsset {
+                    denom: denom.clone(),
+                }
+                .into());
+            }
+
+            let balance = state
+                .get_asset_balance(&account, denom.as_deref())
+                .ok_or(ChiguiError::AccountNotFound { account })?;
+
+            Ok(json!(balance))
+        }
+        "chigui_getBalanceProof" => {
+            let (account,) = parse_params::<(Account,)>(params)?;
+
+            Ok(json!(state.prove_balance(&account)?))
+        }
+        "chigui_getBalanceAt" => {
+            let (account, height) = parse_params::<(Account, u64)>(params)?;
+
+            Ok(json!(state.balance_at_async(&account, height).await?))
+        }
+        "chigui_sendTransaction" => {
+            let (tx,) = parse_params::<(SignedTx,)>(params)?;
+
+            Ok(json!(node.submit(state, tx).await?))
+        }
+        "chigui_queueTransaction" => {
+            let (tx,) = parse_params::<(SignedTx,)>(params)?;
+
+            Ok(json!(node.queue(state, tx)?))
+        }
+        "chigui_getMempool" => {
+            let pending = node
+                .pending()
+                .into_iter()
+                .map(|tx| PendingTx {
+                    hash: tx.hash(),
+                    tx,
+                })
+                .collect::<Vec<PendingTx>>();
+
+            Ok(json!(pending))
+        }
+        "chigui_estimateFee" => {
+            let (tx,) = parse_params::<(Tx,)>(params)?;
+
+            Ok(json!(node.estimate_fee(state, &tx)))
+        }
+        "chigui_resolveAlias" => {
+            let (name,) = parse_params::<(String,)>(params)?;
+            let account = state
+                .resolve_alias(&name)
+                .ok_or(ChiguiError::UnknownAlias { name })?;
+
+            Ok(json!(account))
+        }
+        "chigui_getStateRoot" => Ok(json!({
+            "height": state.height(),
+            "stateRoot": state.state_root(),
+        })),
+        "chigui_getTx" => {
+            let (hash,) = parse_params::<(Hash,)>(params)?;
+            let found = state.blocks().iter().find_map(|block| {
+                block
+                    .txs
+                    .iter()
+                    .find(|tx| tx.hash() == hash)
+                    .map(|tx| TxResponse {
+                        block: block.header.number,
+                        hash,
+                        tx: tx.clone(),
+                    })
+            });
+
+            Ok(json!(found))
+        }
+        "chigui_getTxs" => {
+            let TxsParams(offset, limit) = parse_params(params)?;
+            let txs = state.txs_range(offset, limit.unwrap_or(MAX_TXS).min(MAX_TXS));
+
+            Ok(json!(tx_responses(txs)))
+        }
+        "chigui_getTxsBetween" => {
+            let (from, to) = parse_params::<(u64, u64)>(params)?;
+            let txs = state.txs_between(from, to).take(MAX_TXS);
+
+            Ok(json!(tx_responses(txs)))
+        }
+        "chigui_getTxProof" => {
+            let (hash,) = parse_params::<(Hash,)>(params)?;
+            let found = state.blocks().iter().find_map(|block| {
+                block.prove_tx(&hash).map(|proof| TxProof {
+                    block: block.header.number,
+                    tx_root: block.header.tx_root,
+                    proof,
+                })
+            });
+
+            Ok(json!(found))
+        }
+        _ => Err(RpcError::method_not_found(method)),
+    }
+}
+
+/// Deserialize positional params.
+/// Parameters of `chigui_getBalance`: the account, then optionally the asset denomination.
+#[derive(Debug, Deserialize)]
+struct BalanceParams(Account, #[serde(default)] Option<String>);
+
+/// Parameters of `chigui_getTxs`: the number of transactions to skip, then optionally how many to
+/// return.
+#[derive(Debug, Deserialize)]
+struct TxsParams(usize, #[serde(default)] Option<usize>);
+
+fn tx_responses<'a>(txs: impl Iterator<Item = (u64, &'a SignedTx)>) -> Vec<TxResponse> {
+    txs.map(|(block, tx)| TxResponse {
+        block,
+        hash: tx.hash(),
+        tx: tx.clone(),
+    })
+    .collect()
+}
+
+pub(crate) fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{Request as HttpRequest, header};
+    use http_body_util::BodyExt;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    use chigui_core::light::BalanceProof;
+
+    use super::*;
+
+    fn state() -> (TempDir, SharedState) {
+        let dbdir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":1000,"bob":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let state = State::open(dbdir.path()).unwrap();
+
+        (dbdir, Arc::new(Node::new(state)))
+    }
+
+    async fn call(app: &Router, body: &str) -> Value {
+        let request = HttpRequest::post("/rpc")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn batch_calls() {
+        let (_dbdir, state) = state();
+        let app = crate::router(state.clone());
+        let responses = call(
+            &app,
+            r#"[
+                {"jsonrpc":"2.0","method":"chigui_sendTransaction","params":[{"tx":{"type":"transfer","from":"alice","to":"bob","value":10,"nonce":0}}],"id":1},
+                {"jsonrpc":"2.0","method":"chigui_getBalance","params":["bob"],"id":2},
+                {"jsonrpc":"2.0","method":"chigui_getBalance","params":["bob"]},
+                {"jsonrpc":"2.0","method":"chigui_nope","id":3}
+            ]"#,
+        )
+        .await;
+        let responses = serde_json::from_value::<Vec<RpcResponse>>(responses).unwrap();
+
+        assert_eq!(responses.len(), 3);
+
+        let response = call(
+            &app,
+            r#"{"jsonrpc":"2.0","method":"chigui_getStateRoot","id":1}"#,
+        )
+        .await;
+        assert_eq!(response["result"]["height"], json!(1));
+
+        let response = call(
+            &app,
+            r#"{"jsonrpc":"2.0","method":"chigui_getBalanceAt","params":["bob",0],"id":1}"#,
+        )
+        .await;
+        assert_eq!(response["result"], json!(0));
+        assert_eq!(responses[1].result, Some(json!(10)));
+        assert_eq!(responses[2].error.as_ref().unwrap().code, METHOD_NOT_FOUND);
+
+        let hash = responses[0].result.as_ref().unwrap()["hash"].clone();
+        let response = call(
+            &app,
+            &json!({"jsonrpc":"2.0","method":"chigui_getTx","params":[hash],"id":"tx"}).to_string(),
+        )
+        .await;
+
+        assert_eq!(response["result"]["block"], json!(1));
+        assert_eq!(response["id"], json!("tx"));
+
+        let response = call(
+            &app,
+            &json!({"jsonrpc":"2.0","method":"chigui_getTxProof","params":[hash],"id":1})
+                .to_string(),
+        )
+        .await;
+        let proof = serde_json::from_value::<TxProof>(response["result"].clone()).unwrap();
+
+        assert!(proof.proof.verify(&proof.tx_root));
+
+        let response = call(
+            &app,
+            r#"{"jsonrpc":"2.0","method":"chigui_getBalanceProof","params":["bob"],"id":1}"#,
+        )
+        .await;
+        let proof = serde_json::from_value::<BalanceProof>(response["result"].clone()).unwrap();
+
+        assert!(proof.verify(&state.read().await.state_root()));
+
+        let response = call(
+            &app,
+            r#"{"jsonrpc":"2.0","method":"chigui_getTxs","params":[0,1],"id":1}"#,
+        )
+        .await;
+        let txs = serde_json::from_value::<Vec<TxResponse>>(response["result"].clone()).unwrap();
+
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].hash, serde_json::from_value::<Hash>(hash).unwrap());
+
+        let response = call(
+            &app,
+            r#"{"jsonrpc":"2.0","method":"chigui_getTxsBetween","params":[2,5],"id":1}"#,
+        )
+        .await;
+        assert_eq!(response["result"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn queues_transactions_by_fee() {
+        let (_dbdir, state) = state();
+        let app = crate::router(state.clone());
+        let responses = call(
+            &app,
+            r#"[
+                {"jsonrpc":"2.0","method":"chigui_queueTransaction","params":[{"tx":{"type":"transfer","from":"alice","to":"bob","value":10,"fee":1,"nonce":0}}],"id":1},
+                {"jsonrpc":"2.0","method":"chigui_queueTransaction","params":[{"tx":{"type":"generate","to":"bob","value":10}}],"id":2},
+                {"jsonrpc":"2.0","method":"chigui_queueTransaction","params":[{"tx":{"type":"transfer","from":"alice","to":"bob","value":20,"fee":2,"nonce":0}}],"id":3},
+                {"jsonrpc":"2.0","method":"chigui_getMempool","id":4},
+                {"jsonrpc":"2.0","method":"chigui_estimateFee","params":[{"type":"transfer","from":"alice","to":"bob","value":5,"nonce":1}],"id":5}
+            ]"#,
+        )
+        .await;
+        let responses = serde_json::from_value::<Vec<RpcResponse>>(responses).unwrap();
+
+        assert!(responses[2].error.is_some());
+
+        let pending =
+            serde_json::from_value::<Vec<PendingTx>>(responses[3].result.clone().unwrap()).unwrap();
+
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].tx.tx.fee(), 1);
+        assert_eq!(responses[4].result, Some(json!(0)));
+
+        assert!(state.produce_block().await.unwrap().is_some());
+        assert!(state.pending().is_empty());
+        assert_eq!(
+            state
+                .read()
+                .await
+                .get_balance(&Account::new("bob").unwrap()),
+            Some(20)
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_errors() {
+        let (_dbdir, state) = state();
+        let app = crate::router(state);
+
+        let response = call(&app, "{not json").await;
+        assert_eq!(response["error"]["code"], json!(PARSE_ERROR));
+
+        let response = call(&app, "[]").await;
+        assert_eq!(response["error"]["code"], json!(INVALID_REQUEST));
+
+        let response = call(
+            &app,
+            r#"{"jsonrpc":"2.0","method":"chigui_getBalance","params":[],"id":1}"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], json!(INVALID_PARAMS));
+
+        let response = call(
+            &app,
+            r#"{"jsonrpc":"2.0","method":"chigui_getBalance","params":["carol"],"id":1}"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], json!(SERVER_ERROR));
+    }
+}
@@ -0,0 +1,97 @@
+use std::io;
+use std::path::PathBuf;
+
+use axum_server::tls_rustls::RustlsConfig;
+
+/// PEM files the HTTP API is served over HTTPS with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsFiles {
+    /// Certificate chain, leaf certificate first.
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsFiles {
+    /// Read the certificate chain and private key, failing if either is missing or invalid.
+    pub async fn load(&self) -> io::Result<RustlsConfig> {
+        // Fails when a provider is already installed, which is just as good.
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        RustlsConfig::from_pem_file(&self.cert, &self.key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::sync::Arc;
+
+    use rustls::pki_types::ServerName;
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+
+    use chigui_core::state::State;
+
+    use super::*;
+    use crate::Node;
+
+    #[tokio::test]
+    async fn serves_https() {
+        let dbdir = TempDir::new().unwrap();
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let files = TlsFiles {
+            cert: dbdir.path().join("cert.pem"),
+            key: dbdir.path().join("key.pem"),
+        };
+
+        std::fs::write(&files.cert, certified.cert.pem()).unwrap();
+        std::fs::write(&files.key, "not a key").unwrap();
+        assert!(files.load().await.is_err());
+
+        std::fs::write(&files.key, certified.signing_key.serialize_pem()).unwrap();
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":100}}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let node = Arc::new(Node::new(State::open(dbdir.path()).unwrap()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = files.load().await.unwrap();
+
+        tokio::spawn(crate::serve_router(listener, node, Some(config)));
+
+        let mut roots = RootCertStore::empty();
+
+        roots.add(certified.cert.der().clone()).unwrap();
+
+        let client = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let response = tokio::task::spawn_blocking(move || get(addr, client))
+            .await
+            .unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("ok"));
+    }
+
+    /// Request `/healthz` over TLS, reading the response until the server closes the connection.
+    fn get(addr: SocketAddr, client: ClientConfig) -> String {
+        let name = ServerName::try_from("localhost").unwrap();
+        let connection = ClientConnection::new(Arc::new(client), name).unwrap();
+        let mut stream = StreamOwned::new(connection, TcpStream::connect(addr).unwrap());
+        let mut response = String::new();
+
+        stream
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        stream.read_to_string(&mut response).ok();
+
+        response
+    }
+}
@@ -0,0 +1,163 @@
+use axum::Router;
+use axum::extract::State as AxumState;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::Response;
+use axum::routing::get;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
+
+use chigui_core::Account;
+
+use crate::SharedState;
+use crate::events::Event;
+
+pub fn router() -> Router<SharedState> {
+    Router::new().route("/ws", get(ws))
+}
+
+/// A subscription request sent by the client, e.g. `{"subscribe":"balanceChanged","account":"bob"}`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "subscribe", rename_all = "camelCase")]
+pub enum Subscription {
+    NewTx,
+    TxReorged,
+    NewBlock,
+    BalanceChanged { account: Account },
+}
+
+impl Subscription {
+    pub fn matches(&self, event: &Event) -> bool {
+        match (self, event) {
+            (Subscription::NewTx, Event::NewTx { .. })
+            | (Subscription::TxReorged, Event::TxReorged { .. })
+            | (Subscription::NewBlock, Event::NewBlock { .. }) => true,
+            (
+                Subscription::BalanceChanged { account },
+                Event::BalanceChanged {
+                    account: changed, ..
+                },
+            ) => account == changed,
+            _ => false,
+        }
+    }
+}
+
+async fn ws(AxumState(state): AxumState<SharedState>, upgrade: WebSocketUpgrade) -> Response {
+    let events = state.subscribe();
+
+    upgrade.on_upgrade(move |socket| session(socket, events))
+}
+
+/// Acknowledge subscriptions and push matching events until either side hangs up.
+async fn session(mut socket: WebSocket, mut events: Receiver<Event>) {
+    let mut subscriptions = Vec::<Subscription>::new();
+
+    loop {
+        let frame = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<Subscription>(&text) {
+                    Ok(subscription) => {
+                        let ack = json!({ "subscribed": subscription });
+
+                        subscriptions.push(subscription);
+                        ack
+                    }
+                    Err(err) => json!({ "error": err.to_string() }),
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            event = events.recv() => match event {
+                Ok(event) if subscriptions.iter().any(|s| s.matches(&event)) => json!(event),
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if socket
+            .send(Message::Text(frame.to_string().into()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::Value;
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::connect_async;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    use chigui_core::Tx;
+    use chigui_core::state::State;
+
+    use super::*;
+    use crate::Node;
+
+    #[tokio::test]
+    async fn pushes_subscribed_events() {
+        let dbdir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dbdir.path().join("genesis.json"),
+            r#"{"genesis_time":"2021-01-01T00:00:00Z","chain_id":"testnet","balances":{"alice":1000,"bob":0},"permissive":true}"#,
+        )
+        .unwrap();
+        std::fs::write(dbdir.path().join("block.db"), "").unwrap();
+
+        let node = Arc::new(Node::new(State::open(dbdir.path()).unwrap()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(axum::serve(listener, crate::router(node.clone())).into_future());
+
+        let (socket, _) = connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+        let (mut sink, mut stream) = socket.split();
+        let mut next = async || -> Value {
+            loop {
+                if let WsMessage::Text(text) = stream.next().await.unwrap().unwrap() {
+                    return serde_json::from_str(&text).unwrap();
+                }
+            }
+        };
+
+        let subscription = r#"{"subscribe":"balanceChanged","account":"bob"}"#;
+        sink.send(WsMessage::Text(subscription.into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            next().await["subscribed"],
+            serde_json::from_str::<Value>(subscription).unwrap()
+        );
+
+        let tx = Tx::Transfer {
+            from: Account::new("alice").unwrap(),
+            to: Account::new("bob").unwrap(),
+            value: 10,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        };
+        node.submit(&mut *node.write().await, tx.into())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            serde_json::from_value::<Event>(next().await).unwrap(),
+            Event::BalanceChanged {
+                account: Account::new("bob").unwrap(),
+                balance: 10,
+            }
+        );
+    }
+}
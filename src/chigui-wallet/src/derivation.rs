@@ -0,0 +1,148 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+use crate::error::{Result, WalletError};
+
+/// HMAC key used to derive the SLIP-10 ed25519 master key from a seed.
+const ED25519_SEED_KEY: &[u8] = b"ed25519 seed";
+
+/// Offset marking a hardened derivation index.
+const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// SLIP-44 style coin type used in chigui derivation paths.
+pub const COIN_TYPE: u32 = 8888;
+
+/// A SLIP-10 extended private key: the ed25519 secret plus its chain code.
+#[derive(Clone)]
+pub struct ExtendedKey {
+    pub secret: [u8; 32],
+    pub chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    /// Derive the master key from a BIP-39 seed.
+    pub fn master(seed: &[u8]) -> Self {
+        Self::from_hmac(ED25519_SEED_KEY, &[seed])
+    }
+
+    /// Derive the hardened child at the given index. ed25519 only supports hardened derivation.
+    pub fn child(&self, index: u32) -> Self {
+        let index = (index | HARDENED_OFFSET).to_be_bytes();
+
+        Self::from_hmac(&self.chain_code, &[&[0], &self.secret, &index])
+    }
+
+    /// Derive the key at the end of the given path.
+    pub fn derive(&self, path: &DerivationPath) -> Self {
+        path.0
+            .iter()
+            .fold(self.clone(), |key, index| key.child(*index))
+    }
+
+    fn from_hmac(key: &[u8], data: &[&[u8]]) -> Self {
+        let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+
+        for chunk in data {
+            mac.update(chunk);
+        }
+
+        let output = mac.finalize().into_bytes();
+        let mut secret = [0; 32];
+        let mut chain_code = [0; 32];
+
+        secret.copy_from_slice(&output[..32]);
+        chain_code.copy_from_slice(&output[32..]);
+
+        Self { secret, chain_code }
+    }
+}
+
+/// A path of hardened derivation indices, written `m/44'/8888'/0'`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// The path of the account at the given index: `m/44'/8888'/<index>'`.
+    pub fn account(index: u32) -> Self {
+        Self(vec![44, COIN_TYPE, index])
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "m")?;
+
+        for index in self.0.iter() {
+            write!(f, "/{}'", index)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = WalletError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || WalletError::InvalidDerivationPath {
+            path: s.to_string(),
+        };
+        let mut segments = s.split('/');
+
+        if segments.next() != Some("m") {
+            return Err(invalid());
+        }
+
+        segments
+            .map(|segment| {
+                segment
+                    .strip_suffix('\'')
+                    .and_then(|index| index.parse::<u32>().ok())
+                    .filter(|index| *index < HARDENED_OFFSET)
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<u32>>>()
+            .map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SLIP-10 ed25519 test vector 1.
+    #[test]
+    fn slip10_test_vector() -> Result<()> {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedKey::master(&seed);
+
+        assert_eq!(
+            hex::encode(master.secret),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+
+        let path = "m/0'/1'/2'/2'/1000000000'".parse::<DerivationPath>()?;
+        let child = master.derive(&path);
+
+        assert_eq!(
+            hex::encode(child.secret),
+            "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793"
+        );
+        assert_eq!(path.to_string(), "m/0'/1'/2'/2'/1000000000'");
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_non_hardened_paths() {
+        assert!("m/0".parse::<DerivationPath>().is_err());
+        assert!("44'/0'".parse::<DerivationPath>().is_err());
+        assert_eq!(
+            "m".parse::<DerivationPath>().unwrap(),
+            DerivationPath::default()
+        );
+    }
+}
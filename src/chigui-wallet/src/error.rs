@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use chigui_core::{Account, ChiguiError};
+
+pub type Result<T> = std::result::Result<T, WalletError>;
+
+/// Errors produced while creating, storing or using a [`Wallet`](crate::Wallet).
+#[derive(Debug, Error)]
+pub enum WalletError {
+    #[error("Failed to gather randomness.")]
+    Randomness(#[source] getrandom::Error),
+    #[error("Invalid mnemonic phrase.")]
+    InvalidMnemonic(#[source] bip39::Error),
+    #[error("Mnemonics must have 12 or 24 words, got {word_count}.")]
+    InvalidWordCount { word_count: usize },
+    #[error("Invalid derivation path \"{path}\"; only hardened indices are supported.")]
+    InvalidDerivationPath { path: String },
+    #[error("Only mnemonic wallets can derive child keys.")]
+    NotDerivable,
+    #[error("No key stored for account \"{account}\".")]
+    KeyNotFound { account: Account },
+    #[error("Wrong password or corrupted key file \"{}\".", path.display())]
+    Decryption { path: PathBuf },
+    #[error("Failed to derive the encryption key.")]
+    KeyDerivation,
+    #[error("Failed to parse key file \"{}\".", path.display())]
+    ParseError {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("I/O error on \"{}\".", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Core(#[from] ChiguiError),
+}
@@ -0,0 +1,234 @@
+use std::fs::{self, read_to_string};
+use std::path::{Path, PathBuf};
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+use chigui_core::Account;
+use chigui_core::signed::PublicKey;
+
+use crate::Wallet;
+use crate::derivation::DerivationPath;
+use crate::error::{Result, WalletError};
+
+/// Name of the keystore directory inside the database directory.
+const KEYSTORE_DIR: &str = "keystore";
+
+/// On-disk representation of a password-encrypted wallet key.
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    account: Account,
+    public_key: PublicKey,
+    /// Argon2 salt, hex encoded.
+    salt: String,
+    /// ChaCha20-Poly1305 nonce, hex encoded.
+    nonce: String,
+    /// Encrypted [`Secret`], hex encoded.
+    ciphertext: String,
+}
+
+/// Plaintext protected by a [`KeyFile`].
+#[derive(Serialize, Deserialize)]
+struct Secret {
+    /// Secret key, hex encoded.
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mnemonic: Option<String>,
+    /// Derivation path of the key below the mnemonic master key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
+/// Directory of password-encrypted wallet keys, one JSON file per account.
+#[derive(Debug)]
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl Keystore {
+    /// Open the keystore living under the given database directory.
+    pub fn open<P: AsRef<Path>>(dbdir: P) -> Self {
+        Self {
+            dir: dbdir.as_ref().join(KEYSTORE_DIR),
+        }
+    }
+
+    /// Encrypt the wallet key with the given password and write it to the keystore.
+    pub fn save(&self, wallet: &Wallet, password: &str) -> Result<PathBuf> {
+        let mut salt = [0; 16];
+        let mut nonce = [0; 12];
+
+        getrandom::getrandom(&mut salt).map_err(WalletError::Randomness)?;
+        getrandom::getrandom(&mut nonce).map_err(WalletError::Randomness)?;
+
+        let secret = Secret {
+            key: hex::encode(wallet.secret()),
+            mnemonic: wallet.mnemonic(),
+            path: wallet.mnemonic().map(|_| wallet.path().to_string()),
+        };
+        let plaintext = serde_json::to_vec(&secret).expect("secrets always serialize to JSON");
+        let cipher = Self::cipher(password, &salt)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| WalletError::KeyDerivation)?;
+        let key_file = KeyFile {
+            account: wallet.account(),
+            public_key: wallet.public_key(),
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+        let path = self.path(&key_file.account);
+        let json =
+            serde_json::to_string_pretty(&key_file).map_err(|source| WalletError::ParseError {
+                path: path.clone(),
+                source,
+            })?;
+
+        fs::create_dir_all(&self.dir).map_err(|source| WalletError::Io {
+            path: self.dir.clone(),
+            source,
+        })?;
+        fs::write(&path, json).map_err(|source| WalletError::Io {
+            path: path.clone(),
+            source,
+        })?;
+
+        Ok(path)
+    }
+
+    /// Load and decrypt the wallet controlling the given account.
+    pub fn load(&self, account: &Account, password: &str) -> Result<Wallet> {
+        let path = self.path(account);
+
+        if !path.exists() {
+            return Err(WalletError::KeyNotFound {
+                account: account.clone(),
+            });
+        }
+
+        let json = read_to_string(&path).map_err(|source| WalletError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let key_file =
+            serde_json::from_str::<KeyFile>(&json).map_err(|source| WalletError::ParseError {
+                path: path.clone(),
+                source,
+            })?;
+        let decryption_error = || WalletError::Decryption { path: path.clone() };
+        let salt = hex::decode(&key_file.salt).map_err(|_| decryption_error())?;
+        let nonce = hex::decode(&key_file.nonce).map_err(|_| decryption_error())?;
+        let ciphertext = hex::decode(&key_file.ciphertext).map_err(|_| decryption_error())?;
+
+        if nonce.len() != 12 {
+            return Err(decryption_error());
+        }
+
+        let plaintext = Self::cipher(password, &salt)?
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| decryption_error())?;
+        let secret =
+            serde_json::from_slice::<Secret>(&plaintext).map_err(|_| decryption_error())?;
+        let wallet = match &secret.mnemonic {
+            Some(phrase) => {
+                let path = secret
+                    .path
+                    .as_deref()
+                    .unwrap_or("m")
+                    .parse::<DerivationPath>()?;
+
+                Wallet::from_mnemonic(phrase)?.derive_path(&path)?
+            }
+            None => {
+                let mut key = [0; 32];
+
+                hex::decode_to_slice(&secret.key, &mut key).map_err(|_| decryption_error())?;
+
+                Wallet::from_secret(&key)
+            }
+        };
+
+        if wallet.public_key() != key_file.public_key {
+            return Err(decryption_error());
+        }
+
+        Ok(wallet)
+    }
+
+    /// List the accounts which have a key in this keystore.
+    pub fn accounts(&self) -> Result<Vec<Account>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(&self.dir).map_err(|source| WalletError::Io {
+            path: self.dir.clone(),
+            source,
+        })?;
+        let mut accounts = Vec::new();
+
+        for entry in entries {
+            let entry = entry.map_err(|source| WalletError::Io {
+                path: self.dir.clone(),
+                source,
+            })?;
+            let path = entry.path();
+
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    accounts.push(Account::new(stem)?);
+                }
+            }
+        }
+
+        accounts.sort_by_key(|account| account.to_string());
+
+        Ok(accounts)
+    }
+
+    fn path(&self, account: &Account) -> PathBuf {
+        self.dir.join(format!("{}.json", account))
+    }
+
+    /// Derive the key file cipher from the password with Argon2.
+    fn cipher(password: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+        let mut key = [0; 32];
+
+        Argon2::default()
+            .hash_password_into(password.as_bytes(), salt, &mut key)
+            .map_err(|_| WalletError::KeyDerivation)?;
+
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_roundtrip() -> Result<()> {
+        let dbdir = std::env::temp_dir().join(format!("chigui-keystore-{}", std::process::id()));
+        let keystore = Keystore::open(&dbdir);
+        let wallet = Wallet::create()?.derive(2)?;
+
+        keystore.save(&wallet, "hunter2")?;
+
+        let loaded = keystore.load(&wallet.account(), "hunter2")?;
+        assert_eq!(loaded.public_key(), wallet.public_key());
+        assert_eq!(loaded.mnemonic(), wallet.mnemonic());
+        assert_eq!(loaded.path(), wallet.path());
+        assert_eq!(keystore.accounts()?, vec![wallet.account()]);
+        assert!(matches!(
+            keystore.load(&wallet.account(), "wrong"),
+            Err(WalletError::Decryption { .. })
+        ));
+
+        fs::remove_dir_all(&dbdir).unwrap();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+pub mod derivation;
+pub mod error;
+pub mod keystore;
+pub mod mnemonic;
+
+use bip39::Mnemonic;
+use ed25519_dalek::SigningKey;
+
+use chigui_core::Account;
+use chigui_core::Tx;
+use chigui_core::block::Block;
+use chigui_core::signed::{PublicKey, SignedTx, TxSignature};
+
+use derivation::{DerivationPath, ExtendedKey};
+pub use error::{Result, WalletError};
+pub use keystore::Keystore;
+
+/// An ed25519 key pair controlling a derived [`Account`].
+///
+/// Wallets created from a BIP-39 mnemonic keep it around so it can be exported for backup, along
+/// with the derivation path of their key, so that a single phrase backs many accounts.
+pub struct Wallet {
+    key: SigningKey,
+    mnemonic: Option<Mnemonic>,
+    path: DerivationPath,
+}
+
+impl Wallet {
+    /// Generate a new wallet from a fresh 12-word mnemonic.
+    pub fn create() -> Result<Self> {
+        Self::create_with_words(12)
+    }
+
+    /// Generate a new wallet from a fresh 12 or 24-word mnemonic.
+    pub fn create_with_words(word_count: usize) -> Result<Self> {
+        Ok(Self::from_parsed_mnemonic(mnemonic::generate(word_count)?))
+    }
+
+    /// Recover a wallet from its 12 or 24-word mnemonic phrase.
+    pub fn from_mnemonic(phrase: &str) -> Result<Self> {
+        Ok(Self::from_parsed_mnemonic(mnemonic::parse(phrase)?))
+    }
+
+    pub fn from_secret(secret: &[u8; 32]) -> Self {
+        Self {
+            key: SigningKey::from_bytes(secret),
+            mnemonic: None,
+            path: DerivationPath::default(),
+        }
+    }
+
+    fn from_parsed_mnemonic(mnemonic: Mnemonic) -> Self {
+        let master = ExtendedKey::master(&mnemonic.to_seed(""));
+
+        Self {
+            key: SigningKey::from_bytes(&master.secret),
+            mnemonic: Some(mnemonic),
+            path: DerivationPath::default(),
+        }
+    }
+
+    /// Derive the wallet at the given path from this wallet's mnemonic.
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self> {
+        let mnemonic = self.mnemonic.clone().ok_or(WalletError::NotDerivable)?;
+        let key = ExtendedKey::master(&mnemonic.to_seed("")).derive(path);
+
+        Ok(Self {
+            key: SigningKey::from_bytes(&key.secret),
+            mnemonic: Some(mnemonic),
+            path: path.clone(),
+        })
+    }
+
+    /// Derive the account wallet at the given index, see [`DerivationPath::account`].
+    pub fn derive(&self, index: u32) -> Result<Self> {
+        self.derive_path(&DerivationPath::account(index))
+    }
+
+    /// The derivation path of this wallet's key, `m` for the master key.
+    pub fn path(&self) -> &DerivationPath {
+        &self.path
+    }
+
+    /// The mnemonic phrase backing this wallet, if it was created from one.
+    pub fn mnemonic(&self) -> Option<String> {
+        self.mnemonic.as_ref().map(Mnemonic::to_string)
+    }
+
+    pub fn secret(&self) -> &[u8; 32] {
+        self.key.as_bytes()
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from(&self.key)
+    }
+
+    /// The account derived from this wallet's public key.
+    pub fn account(&self) -> Account {
+        Account::from_public_key(&self.public_key())
+    }
+
+    /// Sign the transaction for the given chain.
+    pub fn sign(&self, tx: &Tx, chain_id: &str) -> SignedTx {
+        SignedTx::sign(tx.clone(), chain_id, &self.key)
+    }
+
+    /// Sign the block header hash as the validator producing it.
+    pub fn seal(&self, block: &mut Block) {
+        block.seal = Some(TxSignature::sign(&block.hash(), &self.key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_for_derived_account() -> Result<()> {
+        let wallet = Wallet::create()?;
+        let tx = Tx::Transfer {
+            from: wallet.account(),
+            to: Account::new("bob")?,
+            value: 1,
+            fee: 0,
+            nonce: 0,
+            memo: None,
+            denom: None,
+        };
+        let signed = wallet.sign(&tx, "testnet");
+
+        assert_eq!(signed.verify()?, Some(&wallet.public_key()));
+        assert_eq!(
+            Account::from_public_key(&wallet.public_key()),
+            wallet.account()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mnemonic_roundtrip() -> Result<()> {
+        let wallet = Wallet::create_with_words(24)?;
+        let phrase = wallet.mnemonic().unwrap();
+
+        assert_eq!(phrase.split_whitespace().count(), 24);
+        assert_eq!(Wallet::from_mnemonic(&phrase)?.account(), wallet.account());
+        assert!(matches!(
+            Wallet::create_with_words(15),
+            Err(WalletError::InvalidWordCount { word_count: 15 })
+        ));
+        assert!(Wallet::from_mnemonic("not a valid phrase").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn known_mnemonic_vector() -> Result<()> {
+        let wallet = Wallet::from_mnemonic(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )?;
+
+        assert_eq!(
+            hex::encode(wallet.secret()),
+            "560f9f3c94558b6551928bb781cf6092c6b8800b4fc544af2c9444ed126d51aa"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn derives_distinct_accounts_from_one_phrase() -> Result<()> {
+        let root = Wallet::create()?;
+        let first = root.derive(0)?;
+        let third = root.derive(3)?;
+        let recovered = Wallet::from_mnemonic(&root.mnemonic().unwrap())?.derive(3)?;
+
+        assert_ne!(first.account(), third.account());
+        assert_ne!(root.account(), first.account());
+        assert_eq!(recovered.account(), third.account());
+        assert_eq!(third.path().to_string(), "m/44'/8888'/3'");
+        assert!(matches!(
+            Wallet::from_secret(&[0; 32]).derive(1),
+            Err(WalletError::NotDerivable)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use bip39::Mnemonic;
+
+use crate::error::{Result, WalletError};
+
+/// Generate a fresh BIP-39 mnemonic of 12 or 24 words.
+pub fn generate(word_count: usize) -> Result<Mnemonic> {
+    let mut entropy = [0; 32];
+    let entropy_len = match word_count {
+        12 => 16,
+        24 => 32,
+        _ => return Err(WalletError::InvalidWordCount { word_count }),
+    };
+
+    getrandom::getrandom(&mut entropy[..entropy_len]).map_err(WalletError::Randomness)?;
+
+    Mnemonic::from_entropy(&entropy[..entropy_len]).map_err(WalletError::InvalidMnemonic)
+}
+
+/// Parse a 12 or 24-word BIP-39 phrase.
+pub fn parse(phrase: &str) -> Result<Mnemonic> {
+    let mnemonic = Mnemonic::parse_normalized(phrase).map_err(WalletError::InvalidMnemonic)?;
+    let word_count = mnemonic.word_count();
+
+    if word_count != 12 && word_count != 24 {
+        return Err(WalletError::InvalidWordCount { word_count });
+    }
+
+    Ok(mnemonic)
+}